@@ -0,0 +1,59 @@
+//! Feeds arbitrary register contents through the status decoders.
+//!
+//! Run with `cargo +nightly fuzz run status_decoders` from the crate root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use max7797x_driver::{ChargerInterrupts, ChargerStatus, Details, FullStatus, TopInterrupts};
+
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() || data.len() > 6 {
+        return;
+    }
+    let mut buf = [0; 6];
+    buf[..data.len()].copy_from_slice(data);
+
+    let details = Details::from_bytes([buf[0], buf[1], buf[2]]);
+    let _ = (
+        details.sense(),
+        details.chgin(),
+        details.charger(),
+        details.battery(),
+        details.temp(),
+        details.bypass(),
+        details.thermistor(),
+    );
+    let _ = format!("{details} {details:?}");
+    assert_eq!(details.into_bytes(), [buf[0], buf[1], buf[2]]);
+
+    let status = ChargerStatus::from_bytes([buf[0]]);
+    let _ = format!("{status} {status:?}");
+    assert_eq!(status.into_bytes(), [buf[0]]);
+
+    let flags = ChargerInterrupts::from_bytes([buf[0]]);
+    let _ = format!("{flags} {flags:?}");
+    assert_eq!(flags.into_bytes(), [buf[0]]);
+    assert_eq!(
+        ChargerInterrupts::from_bits(buf[0]).bits(),
+        buf[0] & ChargerInterrupts::ALL.bits()
+    );
+
+    let top = TopInterrupts::from_bytes([buf[0]]);
+    let _ = (
+        top.thermal_shutdown(),
+        top.sys_overvoltage(),
+        top.sys_undervoltage(),
+    );
+    assert_eq!(top.into_bytes(), [buf[0]]);
+    assert_eq!(
+        TopInterrupts::from_bits(buf[0]).bits(),
+        buf[0] & TopInterrupts::ALL.bits()
+    );
+
+    let full = FullStatus::from_bytes(buf);
+    let _ = format!("{full:?}");
+    assert_eq!(full.flags.into_bytes(), [buf[0]]);
+    assert_eq!(full.status.into_bytes(), [buf[2]]);
+    assert_eq!(full.details.into_bytes(), [buf[3], buf[4], buf[5]]);
+});
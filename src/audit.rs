@@ -0,0 +1,301 @@
+//! A record of the last register writes, for post-mortem analysis.
+//!
+//! [`Charger::with_audit_log`] wraps the bus in an [`Audit`], which records every register
+//! written by a successful transaction in a fixed-capacity ring of `N` entries. The capacity is
+//! part of the type, so the RAM cost is explicit:
+//!
+//! ```no_run
+//! # use embedded_hal_async::i2c::I2c;
+//! # use max7797x_driver::{Charger, Error};
+//! # async fn example<D: I2c>(
+//! #     i2c: D,
+//! #     log: impl Fn(u32, u8, u8),
+//! # ) -> Result<(), Error<D::Error>> {
+//! let mut charger = Charger::new(i2c).with_audit_log::<32>();
+//! charger.set_chgin_ilim(1500).await?;
+//! for entry in charger.audit_log() {
+//!     log(entry.seq, entry.reg, entry.value);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Writes are recorded at the bus, so every path is covered, including the CHGPROT unlock and
+//! lock around protected writes and the bursts of [`Charger::apply_config`]. Once the ring is
+//! full, the oldest entry is evicted for each new one.
+
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+
+use crate::Charger;
+
+/// A register write recorded by [`Audit`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditEntry {
+    /// The sequence number of the write, counting every write since the log was created
+    pub seq: u32,
+    /// The register address
+    pub reg: u8,
+    /// The value written
+    pub value: u8,
+}
+
+/// An I2C bus recording the last `N` register writes, see [`Charger::with_audit_log`]
+pub struct Audit<D, const N: usize> {
+    i2c_dev: D,
+    entries: [AuditEntry; N],
+    len: usize,
+    next_seq: u32,
+}
+
+impl<D, const N: usize> Audit<D, N> {
+    /// Wrap `i2c_dev` with an empty log.
+    pub fn new(i2c_dev: D) -> Self {
+        Audit {
+            i2c_dev,
+            entries: [AuditEntry::default(); N],
+            len: 0,
+            next_seq: 0,
+        }
+    }
+
+    /// The recorded writes, oldest first
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries[..self.len]
+    }
+
+    /// Forget the recorded writes. Sequence numbers continue from the last write.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Release the bus.
+    pub fn release(self) -> D {
+        self.i2c_dev
+    }
+
+    fn record(&mut self, reg: u8, value: u8) {
+        if N == 0 {
+            return;
+        }
+        if self.len == N {
+            self.entries.copy_within(1.., 0);
+            self.len = self.len.saturating_sub(1);
+        }
+        if let Some(entry) = self.entries.get_mut(self.len) {
+            *entry = AuditEntry {
+                seq: self.next_seq,
+                reg,
+                value,
+            };
+            self.len = self.len.saturating_add(1);
+        }
+        self.next_seq = self.next_seq.wrapping_add(1);
+    }
+
+    /// Record the register writes of a completed transaction.
+    ///
+    /// The first byte written is the register pointer and each further byte is written to the
+    /// register it points to, incrementing the pointer. A read ends the write.
+    fn record_transaction(&mut self, operations: &[Operation<'_>]) {
+        let mut pointer = None;
+        for op in operations {
+            match op {
+                Operation::Write(bytes) => {
+                    for &byte in bytes.iter() {
+                        match pointer {
+                            None => pointer = Some(byte),
+                            Some(reg) => {
+                                self.record(reg, byte);
+                                pointer = Some(reg.wrapping_add(1));
+                            }
+                        }
+                    }
+                }
+                Operation::Read(_) => return,
+            }
+        }
+    }
+}
+
+impl<D: ErrorType, const N: usize> ErrorType for Audit<D, N> {
+    type Error = D::Error;
+}
+
+impl<D: I2c, const N: usize> I2c for Audit<D, N> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.i2c_dev.transaction(address, operations).await?;
+        self.record_transaction(operations);
+        Ok(())
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Record the last `N` register writes, see [`audit_log`](Charger::audit_log).
+    pub fn with_audit_log<const N: usize>(self) -> Charger<Audit<D, N>> {
+        Charger {
+            i2c_dev: Audit::new(self.i2c_dev),
+            bus_error: self.bus_error,
+            address: self.address,
+            variant: self.variant,
+            bypass: self.bypass,
+            suspended_mode: self.suspended_mode,
+            derating: self.derating,
+            read_voting: self.read_voting,
+            verify_writes: self.verify_writes,
+            cache: self.cache,
+            safety_policy: self.safety_policy,
+            #[cfg(feature = "trace")]
+            trace_op: self.trace_op,
+        }
+    }
+}
+
+impl<D: I2c, const N: usize> Charger<Audit<D, N>> {
+    /// The last register writes, oldest first
+    pub fn audit_log(&self) -> &[AuditEntry] {
+        self.i2c_dev.entries()
+    }
+
+    /// Forget the recorded register writes.
+    pub fn clear_audit_log(&mut self) {
+        self.i2c_dev.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+
+    use super::*;
+    use crate::testing::{block_on, FailingBursts};
+    use crate::{ChargerConfig0, Mode, Reg, DEFAULT_ADDRESS};
+
+    /// A register read, as [`Audit`] forwards it through `transaction`
+    fn read(reg: Reg, val: u8) -> [Transaction; 4] {
+        [
+            Transaction::transaction_start(DEFAULT_ADDRESS),
+            Transaction::write(DEFAULT_ADDRESS, vec![reg.address()]),
+            Transaction::read(DEFAULT_ADDRESS, vec![val]),
+            Transaction::transaction_end(DEFAULT_ADDRESS),
+        ]
+    }
+
+    /// A register write, as [`Audit`] forwards it through `transaction`
+    fn write(reg: Reg, val: u8) -> [Transaction; 3] {
+        [
+            Transaction::transaction_start(DEFAULT_ADDRESS),
+            Transaction::write(DEFAULT_ADDRESS, vec![reg.address(), val]),
+            Transaction::transaction_end(DEFAULT_ADDRESS),
+        ]
+    }
+
+    fn entry(seq: u32, reg: Reg, value: u8) -> AuditEntry {
+        AuditEntry {
+            seq,
+            reg: reg.address(),
+            value,
+        }
+    }
+
+    #[test]
+    fn writes_are_recorded_and_reads_are_not() {
+        let mut expectations = Vec::new();
+        expectations.extend(read(Reg::CHARGER_CONFIG_9, 0x00));
+        expectations.extend(write(Reg::CHARGER_CONFIG_9, 0x09));
+        expectations.extend(read(Reg::CHARGER_CONFIG_0, 0x04));
+        expectations.extend(write(Reg::CHARGER_CONFIG_0, 0x05));
+        let mock = Mock::new(&expectations);
+        let mut charger = Charger::new(mock).with_audit_log::<4>();
+        block_on(charger.set_chgin_ilim(500)).unwrap();
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        assert_eq!(
+            charger.audit_log(),
+            [
+                entry(0, Reg::CHARGER_CONFIG_9, 0x09),
+                entry(1, Reg::CHARGER_CONFIG_0, 0x05),
+            ]
+        );
+        charger.release().release().done();
+    }
+
+    #[test]
+    fn protected_writes_record_the_unlock_and_lock() {
+        let mut expectations = Vec::new();
+        expectations.extend(write(Reg::CHARGER_CONFIG_6, 0x0c));
+        expectations.extend(read(Reg::CHARGER_CONFIG_2, 0x00));
+        expectations.extend(write(Reg::CHARGER_CONFIG_2, 0x14));
+        expectations.extend(write(Reg::CHARGER_CONFIG_6, 0x00));
+        let mock = Mock::new(&expectations);
+        let mut charger = Charger::new(mock).with_audit_log::<4>();
+        block_on(charger.protected(async |session| session.set_fast_charge_current(1000).await))
+            .unwrap();
+        assert_eq!(
+            charger.audit_log(),
+            [
+                entry(0, Reg::CHARGER_CONFIG_6, 0x0c),
+                entry(1, Reg::CHARGER_CONFIG_2, 0x14),
+                entry(2, Reg::CHARGER_CONFIG_6, 0x00),
+            ]
+        );
+        charger.release().release().done();
+    }
+
+    #[test]
+    fn bursts_record_each_register() {
+        let mut audit = Audit::<(), 4>::new(());
+        audit.record_transaction(&[Operation::Write(&[0x16, 0x05, 0x00, 0x14])]);
+        // A write ended by a read only sets the pointer
+        audit.record_transaction(&[Operation::Write(&[0x13]), Operation::Read(&mut [0; 3])]);
+        assert_eq!(
+            audit.entries(),
+            [
+                entry(0, Reg::CHARGER_CONFIG_0, 0x05),
+                entry(1, Reg::CHARGER_CONFIG_1, 0x00),
+                entry(2, Reg::CHARGER_CONFIG_2, 0x14),
+            ]
+        );
+    }
+
+    #[test]
+    fn full_log_evicts_the_oldest_entry() {
+        let mut audit = Audit::<(), 2>::new(());
+        for value in 0..3 {
+            audit.record_transaction(&[Operation::Write(&[0x16, value])]);
+        }
+        assert_eq!(
+            audit.entries(),
+            [
+                entry(1, Reg::CHARGER_CONFIG_0, 1),
+                entry(2, Reg::CHARGER_CONFIG_0, 2),
+            ]
+        );
+
+        // Sequence numbers continue after a clear
+        audit.clear();
+        assert!(audit.entries().is_empty());
+        audit.record_transaction(&[Operation::Write(&[0x16, 3])]);
+        assert_eq!(audit.entries(), [entry(3, Reg::CHARGER_CONFIG_0, 3)]);
+
+        let mut audit = Audit::<(), 0>::new(());
+        audit.record_transaction(&[Operation::Write(&[0x16, 0])]);
+        assert!(audit.entries().is_empty());
+    }
+
+    #[test]
+    fn failed_writes_are_not_recorded() {
+        let mut charger = Charger::new(FailingBursts(Mock::new(&[]))).with_audit_log::<4>();
+        let config0 = ChargerConfig0::new().with_mode(Mode::Charge);
+        assert!(block_on(charger.set_charger_config0(config0)).is_err());
+        assert!(charger.audit_log().is_empty());
+        charger.release().release().0.done();
+    }
+}
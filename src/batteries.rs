@@ -0,0 +1,334 @@
+//! The charger traits of `embedded-batteries`, for power-management code that is generic over
+//! the charger IC.
+//!
+//! [`Charger`] implements the async [`charger::Charger`] trait of `embedded-batteries-async`:
+//!
+//! - [`charging_current`](charger::Charger::charging_current) programs the fast-charge current,
+//!   clamped to the range of the [`Variant`](crate::Variant) and rounded down to a step, and
+//!   returns the programmed current.
+//! - [`charging_voltage`](charger::Charger::charging_voltage) programs the termination voltage
+//!   (CHG_CV_PRM), clamped to 3400 mV to 4670 mV and rounded down to a 10 mV step, and returns
+//!   the programmed voltage.
+//! - A request of 0 turns charging off with [`Charger::suspend_charging`] and returns 0. The
+//!   next non-zero request programs its value and resumes charging with
+//!   [`Charger::resume_charging`], which fails with [`Error::NoInput`] while no valid input is
+//!   attached.
+//!
+//! ```no_run
+//! # use embedded_hal_async::i2c::I2c;
+//! # use max7797x_driver::Charger;
+//! use embedded_batteries_async::charger::{Charger as _, MilliAmps};
+//!
+//! # async fn example<D: I2c>(
+//! #     charger: &mut Charger<D>,
+//! # ) -> Result<MilliAmps, max7797x_driver::Error<D::Error>> {
+//! let acknowledged = charger.charging_current(1234).await?;
+//! # Ok(acknowledged)
+//! # }
+//! ```
+//!
+//! Bus errors map to [`ErrorKind::CommError`](charger::ErrorKind::CommError) and every other
+//! [`Error`] to [`ErrorKind::Other`](charger::ErrorKind::Other).
+//!
+//! The traits do not report status. [`ChgIn::power_source`] and [`Details::battery_state`] map
+//! the status of the charger onto the ACPI `_PSR` and `_BST` models of `embedded-batteries`:
+//!
+//! | Charger status | ACPI status |
+//! |---|---|
+//! | CHGIN [`ChgIn::Valid`] | [`PowerSource::Online`] |
+//! | any other CHGIN state | [`PowerSource::Offline`] |
+//! | CHG_DTLS classified as [`ChargeStateClass::Charging`] | [`BatteryState::CHARGING`] |
+//! | CHGIN not [`ChgIn::Valid`] | [`BatteryState::DISCHARGING`] |
+//! | BAT_DTLS classified as [`BatteryClass::Low`] | [`BatteryState::CRITICAL`] |
+//! | CHG_DTLS [`ChargerDetails::Jeita`], or charging above the REGTEMP threshold | [`BatteryState::CHARGE_LIMITING`] |
+//!
+//! States the ACPI model cannot express set no flag: a finished charge
+//! ([`ChargerDetails::Done`]), the charger being off while the input powers the system, and the
+//! suspensions and faults of [`ChargeStateClass`]. Read [`Details`] for those. Without a valid
+//! input the charger does not monitor the battery, so [`BatteryState::CRITICAL`] is not reported
+//! on battery power and has to come from a fuel gauge.
+
+use embedded_batteries::acpi::{BatteryState, PowerSource};
+use embedded_batteries_async::charger::{self, MilliAmps, MilliVolts};
+use embedded_hal_async::i2c::I2c;
+
+use crate::{
+    conv, BatteryClass, ChargeStateClass, Charger, ChargerConfig4, ChargerDetails, ChgIn, Details,
+    Error, TemperatureRegulation,
+};
+
+/// The lowest termination voltage in mV
+const MIN_TERMINATION_MV: u16 = conv::chg_cv_prm_mv(0);
+/// The highest termination voltage in mV
+const MAX_TERMINATION_MV: u16 = conv::chg_cv_prm_mv(u8::MAX);
+
+impl<E: core::fmt::Debug> charger::Error for Error<E> {
+    fn kind(&self) -> charger::ErrorKind {
+        match self {
+            Error::I2c(_) => charger::ErrorKind::CommError,
+            _ => charger::ErrorKind::Other,
+        }
+    }
+}
+
+impl<D: I2c> charger::ErrorType for Charger<D> {
+    type Error = Error<D::Error>;
+}
+
+impl<D: I2c> charger::Charger for Charger<D> {
+    async fn charging_current(&mut self, current: MilliAmps) -> Result<MilliAmps, Self::Error> {
+        traced!(self, "charging_current", {
+            if current == 0 {
+                self.suspend_charging().await?;
+                return Ok(0);
+            }
+            let milliamps = current.clamp(
+                self.variant.min_fast_charge_current(),
+                self.variant.max_fast_charge_current(),
+            );
+            self.set_fast_charge_current(milliamps).await?;
+            self.resume_charging().await?;
+            self.fast_charge_current().await
+        })
+    }
+
+    async fn charging_voltage(&mut self, voltage: MilliVolts) -> Result<MilliVolts, Self::Error> {
+        traced!(self, "charging_voltage", {
+            if voltage == 0 {
+                self.suspend_charging().await?;
+                return Ok(0);
+            }
+            let chg_cv_prm =
+                conv::chg_cv_prm_code(voltage.clamp(MIN_TERMINATION_MV, MAX_TERMINATION_MV))
+                    .ok_or(Error::InvalidValue)?;
+            self.modify(|val: ChargerConfig4| val.with_chg_cv_prm(chg_cv_prm))
+                .await?;
+            self.resume_charging().await?;
+            let chg_cv_prm = self.read::<ChargerConfig4>().await?.chg_cv_prm();
+            Ok(conv::chg_cv_prm_mv(chg_cv_prm))
+        })
+    }
+}
+
+impl ChgIn {
+    /// The ACPI `_PSR` status of the input, see the [module documentation](self).
+    pub fn power_source(self) -> PowerSource {
+        match self {
+            ChgIn::Valid => PowerSource::Online,
+            ChgIn::Undervoltage | ChgIn::BelowBatt | ChgIn::Overvoltage => PowerSource::Offline,
+        }
+    }
+}
+
+impl Details {
+    /// The ACPI `_BST` battery state, see the [module documentation](self).
+    pub fn battery_state(&self) -> BatteryState {
+        let charging = self.charger().classify() == ChargeStateClass::Charging;
+        let limiting = self.charger() == ChargerDetails::Jeita
+            || (charging && self.temp() == TemperatureRegulation::AboveThreshold);
+        let mut state = BatteryState::empty();
+        state.set(BatteryState::CHARGING, charging);
+        state.set(BatteryState::DISCHARGING, self.chgin() != ChgIn::Valid);
+        state.set(
+            BatteryState::CRITICAL,
+            self.battery().classify() == BatteryClass::Low,
+        );
+        state.set(BatteryState::CHARGE_LIMITING, limiting);
+        state
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use embedded_batteries_async::charger::Charger as _;
+    use embedded_hal_async::i2c::ErrorKind;
+    use modular_bitfield::Specifier;
+
+    use super::*;
+    use crate::sim::Simulator;
+    use crate::testing::block_on;
+    use crate::{BatteryDetails, Mode, Variant};
+
+    /// The battery state of every CHG_DTLS code with a valid input and a regular battery
+    const BATTERY_STATES: [(ChargerDetails, BatteryState); 16] = [
+        (ChargerDetails::Prequalification, BatteryState::CHARGING),
+        (ChargerDetails::ConstantCurrent, BatteryState::CHARGING),
+        (ChargerDetails::ConstantVoltage, BatteryState::CHARGING),
+        (ChargerDetails::TopOff, BatteryState::CHARGING),
+        (ChargerDetails::Done, BatteryState::empty()),
+        (ChargerDetails::Reserved05, BatteryState::empty()),
+        (ChargerDetails::TimerFault, BatteryState::empty()),
+        (ChargerDetails::QBattDisabled, BatteryState::empty()),
+        (ChargerDetails::Off, BatteryState::empty()),
+        (ChargerDetails::Reserved09, BatteryState::empty()),
+        (ChargerDetails::HighTemperature, BatteryState::empty()),
+        (ChargerDetails::WatchdogTimer, BatteryState::empty()),
+        (ChargerDetails::Jeita, BatteryState::CHARGE_LIMITING),
+        (ChargerDetails::ThermistorRemoval, BatteryState::empty()),
+        (ChargerDetails::SuspendPin, BatteryState::empty()),
+        (ChargerDetails::Reserved0F, BatteryState::empty()),
+    ];
+
+    fn details(chgin: ChgIn, charger: ChargerDetails, battery: BatteryDetails) -> Details {
+        Details::new()
+            .with_chgin(chgin)
+            .with_charger(charger)
+            .with_battery(battery)
+    }
+
+    #[test]
+    fn charger_details_map_onto_battery_state() {
+        for (code, (charger, state)) in (0..).zip(BATTERY_STATES) {
+            assert_eq!(ChargerDetails::from_bytes(code), Ok(charger));
+            let valid = details(ChgIn::Valid, charger, BatteryDetails::RegularVoltage);
+            assert_eq!(valid.battery_state().bits(), state.bits(), "{charger:?}");
+            let invalid = details(ChgIn::Undervoltage, charger, BatteryDetails::BatteryOnly);
+            assert_eq!(
+                invalid.battery_state().bits(),
+                (state | BatteryState::DISCHARGING).bits(),
+                "{charger:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn low_battery_is_critical() {
+        for battery in [
+            BatteryDetails::PrequalificationVoltage,
+            BatteryDetails::LowVoltage,
+        ] {
+            let details = details(ChgIn::Valid, ChargerDetails::Prequalification, battery);
+            assert_eq!(
+                details.battery_state().bits(),
+                (BatteryState::CHARGING | BatteryState::CRITICAL).bits()
+            );
+        }
+        let details = details(
+            ChgIn::Valid,
+            ChargerDetails::Done,
+            BatteryDetails::Overvoltage,
+        );
+        assert!(details.battery_state().is_empty());
+    }
+
+    #[test]
+    fn thermal_regulation_limits_only_while_charging() {
+        let hot = |charger| {
+            details(ChgIn::Valid, charger, BatteryDetails::RegularVoltage)
+                .with_temp(TemperatureRegulation::AboveThreshold)
+                .battery_state()
+        };
+        assert_eq!(
+            hot(ChargerDetails::ConstantCurrent).bits(),
+            (BatteryState::CHARGING | BatteryState::CHARGE_LIMITING).bits()
+        );
+        assert!(hot(ChargerDetails::Done).is_empty());
+    }
+
+    #[test]
+    fn only_a_valid_input_is_online() {
+        for (chgin, source) in [
+            (ChgIn::Undervoltage, PowerSource::Offline),
+            (ChgIn::BelowBatt, PowerSource::Offline),
+            (ChgIn::Overvoltage, PowerSource::Offline),
+            (ChgIn::Valid, PowerSource::Online),
+        ] {
+            assert_eq!(
+                u32::from(chgin.power_source()),
+                u32::from(source),
+                "{chgin:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn bus_errors_are_communication_errors() {
+        use charger::Error as _;
+
+        assert_eq!(
+            Error::I2c(ErrorKind::Bus).kind(),
+            charger::ErrorKind::CommError
+        );
+        assert_eq!(
+            Error::<ErrorKind>::InvalidValue.kind(),
+            charger::ErrorKind::Other
+        );
+        assert_eq!(
+            Error::<ErrorKind>::NoInput.kind(),
+            charger::ErrorKind::Other
+        );
+    }
+
+    /// Request `current` and `voltage` through the trait, as power-management code would.
+    async fn request<C: charger::Charger>(
+        charger: &mut C,
+        current: MilliAmps,
+        voltage: MilliVolts,
+    ) -> Result<(MilliAmps, MilliVolts), C::Error> {
+        Ok((
+            charger.charging_current(current).await?,
+            charger.charging_voltage(voltage).await?,
+        ))
+    }
+
+    #[test]
+    fn requests_are_rounded_and_acknowledged() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        let mut charger = Charger::new(sim.device());
+        // Through the blanket implementation for `&mut C`
+        assert_eq!(
+            block_on(request(&mut &mut charger, 1234, 4321)),
+            Ok((1200, 4320))
+        );
+        assert_eq!(block_on(charger.fast_charge_current()), Ok(1200));
+        let config4 = block_on(charger.read::<ChargerConfig4>()).unwrap();
+        assert_eq!(conv::chg_cv_prm_mv(config4.chg_cv_prm()), 4320);
+    }
+
+    #[test]
+    fn requests_out_of_range_are_clamped() {
+        for variant in [Variant::Max77975, Variant::Max77976] {
+            let sim = Simulator::new(variant);
+            sim.plug_charger();
+            let mut charger = Charger::new_with_variant(sim.device(), variant);
+            assert_eq!(
+                block_on(request(&mut charger, u16::MAX, u16::MAX)),
+                Ok((variant.max_fast_charge_current(), 4670))
+            );
+            assert_eq!(
+                block_on(request(&mut charger, 1, 1)),
+                Ok((variant.min_fast_charge_current(), 3400))
+            );
+        }
+    }
+
+    #[test]
+    fn zero_turns_charging_off_until_the_next_request() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        let mut charger = Charger::new(sim.device());
+        assert_eq!(block_on(charger.charging_current(0)), Ok(0));
+        assert_eq!(sim.mode(), Some(Mode::Buck));
+        assert_eq!(block_on(charger.charging_voltage(0)), Ok(0));
+        assert_eq!(sim.mode(), Some(Mode::Buck));
+
+        assert_eq!(block_on(charger.charging_voltage(4200)), Ok(4200));
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    #[test]
+    fn charging_stays_off_without_an_input() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.charging_current(0)).unwrap();
+        sim.unplug_charger();
+
+        let err = block_on(charger.charging_current(1000)).unwrap_err();
+        assert_eq!(err, Error::NoInput);
+        assert_eq!(charger::Error::kind(&err), charger::ErrorKind::Other);
+        assert_eq!(sim.mode(), Some(Mode::Buck));
+        assert_eq!(block_on(charger.fast_charge_current()), Ok(1000));
+    }
+}
@@ -0,0 +1,38 @@
+//! Conversions between the status types and their raw register bytes.
+//!
+//! The byte layout is the register layout and is stable, so bytes logged on one device can be
+//! decoded with any later version of this crate.
+
+use crate::{ChargerInterrupts, ChargerStatus, Details, TopInterrupts};
+
+macro_rules! byte_conversions {
+    ($ty:ident) => {
+        impl From<u8> for $ty {
+            fn from(byte: u8) -> Self {
+                $ty::from_bytes([byte])
+            }
+        }
+
+        impl From<$ty> for u8 {
+            fn from(value: $ty) -> u8 {
+                value.into_bytes()[0]
+            }
+        }
+    };
+}
+
+byte_conversions!(ChargerInterrupts);
+byte_conversions!(ChargerStatus);
+byte_conversions!(TopInterrupts);
+
+impl From<[u8; 3]> for Details {
+    fn from(bytes: [u8; 3]) -> Self {
+        Details::from_bytes(bytes)
+    }
+}
+
+impl From<Details> for [u8; 3] {
+    fn from(details: Details) -> [u8; 3] {
+        details.into_bytes()
+    }
+}
@@ -0,0 +1,153 @@
+//! A shadow copy of the charger configuration registers.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{Charger, Reg};
+
+/// The first cached register
+const FIRST: Reg = Reg::CHARGER_CONFIG_0;
+/// The number of cached registers, `CHARGER_CONFIG_0` through `CHARGER_CONFIG_13`
+const LEN: usize = 14;
+
+/// The last values written to or read from the configuration registers
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ShadowCache {
+    vals: [u8; LEN],
+    valid: u16,
+}
+
+impl ShadowCache {
+    /// The cache index of `reg`, if it is cached
+    fn index(reg: Reg) -> Option<usize> {
+        // WDTCLR clears itself, so CHARGER_CONFIG_6 never reads back what was written
+        if reg == Reg::CHARGER_CONFIG_6 {
+            return None;
+        }
+        let index = reg.address().checked_sub(FIRST.address())? as usize;
+        (index < LEN).then_some(index)
+    }
+
+    pub(crate) fn get(&self, reg: Reg) -> Option<u8> {
+        let index = Self::index(reg)?;
+        self.vals
+            .get(index)
+            .copied()
+            .filter(|_| self.valid & 1 << index != 0)
+    }
+
+    /// Record that the registers from `base` hold `vals`.
+    pub(crate) fn update(&mut self, base: Reg, vals: &[u8]) {
+        for (offset, &val) in vals.iter().enumerate() {
+            let reg = Reg::new(base.address().wrapping_add(offset as u8));
+            if reg == Reg::SOFTWARE_RESET || reg == Reg::SHIP_MODE_CONTROL {
+                // Both return the registers to their reset values
+                self.valid = 0;
+            } else if let Some((index, slot)) =
+                Self::index(reg).and_then(|index| Some((index, self.vals.get_mut(index)?)))
+            {
+                *slot = val;
+                self.valid |= 1 << index;
+            }
+        }
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Keep a shadow copy of the configuration registers.
+    ///
+    /// With the cache enabled, `CHARGER_CONFIG_0` through `CHARGER_CONFIG_13`, except
+    /// `CHARGER_CONFIG_6`, are read at most once: changing a single field of a register then
+    /// takes one write instead of a read and a write. Writes through the driver keep the cache
+    /// up to date, and writing `SOFTWARE_RESET` or `SHIP_MODE_CONTROL` invalidates it.
+    ///
+    /// Call [`invalidate_cache`](Self::invalidate_cache) when the registers may have changed
+    /// otherwise, e.g. after a power-on reset. Leave the cache disabled if another bus master
+    /// also writes the charger.
+    pub fn with_shadow_cache(mut self, enabled: bool) -> Self {
+        self.cache = enabled.then(ShadowCache::default);
+        self
+    }
+
+    /// Whether the shadow cache is enabled
+    pub fn shadow_cache(&self) -> bool {
+        self.cache.is_some()
+    }
+
+    /// Forget the cached register values, so they are read from the charger again.
+    pub fn invalidate_cache(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            *cache = ShadowCache::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read, run, write};
+
+    #[test]
+    fn warm_cache_skips_the_read() {
+        run(
+            &[
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                write(Reg::CHARGER_CONFIG_9, 0x09),
+                write(Reg::CHARGER_CONFIG_9, 0x13),
+            ],
+            async |charger| {
+                charger.cache = Some(ShadowCache::default());
+                charger.set_chgin_ilim(500).await.unwrap();
+                charger.set_chgin_ilim(1000).await.unwrap();
+            },
+        );
+    }
+
+    #[test]
+    fn disabled_cache_always_reads() {
+        run(
+            &[
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                write(Reg::CHARGER_CONFIG_9, 0x09),
+                read(Reg::CHARGER_CONFIG_9, 0x09),
+                write(Reg::CHARGER_CONFIG_9, 0x13),
+            ],
+            async |charger| {
+                assert!(!charger.shadow_cache());
+                charger.set_chgin_ilim(500).await.unwrap();
+                charger.set_chgin_ilim(1000).await.unwrap();
+            },
+        );
+    }
+
+    #[test]
+    fn invalidate_cache_forces_a_read() {
+        run(
+            &[
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                write(Reg::CHARGER_CONFIG_9, 0x09),
+                read(Reg::CHARGER_CONFIG_9, 0x09),
+                write(Reg::CHARGER_CONFIG_9, 0x13),
+            ],
+            async |charger| {
+                charger.cache = Some(ShadowCache::default());
+                charger.set_chgin_ilim(500).await.unwrap();
+                charger.invalidate_cache();
+                charger.set_chgin_ilim(1000).await.unwrap();
+            },
+        );
+    }
+
+    #[test]
+    fn reset_and_ship_writes_invalidate_everything() {
+        for reg in [Reg::SOFTWARE_RESET, Reg::SHIP_MODE_CONTROL] {
+            let mut cache = ShadowCache::default();
+            cache.update(Reg::CHARGER_CONFIG_0, &[0x05]);
+            assert_eq!(cache.get(Reg::CHARGER_CONFIG_0), Some(0x05));
+            cache.update(reg, &[0x01]);
+            assert_eq!(cache.get(Reg::CHARGER_CONFIG_0), None);
+        }
+        let mut cache = ShadowCache::default();
+        cache.update(Reg::CHARGER_CONFIG_6, &[0x01]);
+        assert_eq!(cache.get(Reg::CHARGER_CONFIG_6), None);
+    }
+}
@@ -0,0 +1,754 @@
+//! Typed views of the charger configuration registers.
+//!
+//! Each struct decodes one register. Reserved bits are kept as read, so a value read from the
+//! charger can be modified and written back without disturbing them. The numeric fields hold
+//! the raw codes; the [`Charger`] setters convert to and from physical units.
+//!
+//! The structs implement [`RegisterSpec`], so any of them can be accessed directly with
+//! [`Charger::read`], [`Charger::write`] and [`Charger::modify`].
+
+use embedded_hal_async::i2c::I2c;
+use modular_bitfield::bitfield;
+use modular_bitfield::specifiers::{B1, B2, B3, B4, B6, B7};
+
+use crate::{
+    Charger, ChargerConfig0, ChargerStatus, ColdThreshold, CoolThreshold, Error, FastChargeTimer,
+    HotThreshold, JeitaCurrent, JeitaVoltage, RechargeThreshold, Reg, WarmThreshold,
+};
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_1` register, CHGPROT protected
+pub struct ChargerConfig1 {
+    /// The fast-charge safety timer (FCHGTIME)
+    pub fast_charge_timer: FastChargeTimer,
+    #[skip]
+    __: B1,
+    /// The recharge threshold (CHG_RSTRT)
+    pub recharge: RechargeThreshold,
+    #[skip]
+    __: B1,
+    /// Enable the low-battery prequalification mode (PQEN)
+    pub prequalification_enable: bool,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_2` register, CHGPROT protected
+pub struct ChargerConfig2 {
+    /// The fast-charge current code (CHG_CC), in steps that depend on the
+    /// [`Variant`](crate::Variant)
+    pub chg_cc: B7,
+    #[skip]
+    __: B1,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_3` register, CHGPROT protected
+pub struct ChargerConfig3 {
+    /// The top-off current threshold code (TO_ITH), 100 mA + 50 mA per step
+    pub to_ith: B3,
+    /// The top-off time code (TO_TIME), 10 minutes per step
+    pub to_time: B3,
+    #[skip]
+    __: B2,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_4` register, CHGPROT protected
+pub struct ChargerConfig4 {
+    /// The termination voltage code (CHG_CV_PRM), 3400 mV + 10 mV per step
+    pub chg_cv_prm: B7,
+    #[skip]
+    __: B1,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_5` register, CHGPROT protected
+pub struct ChargerConfig5 {
+    /// The Vsys current limit code (SYS_ILIM), 2500 mA + 500 mA per step
+    pub sys_ilim: B4,
+    /// Recycle Vsys after a Vsys current limit shutdown (RECYCLE_EN)
+    pub recycle_enable: bool,
+    /// The prequalification current code (ITRICKLE), 100 mA + 100 mA per step
+    pub itrickle: B2,
+    #[skip]
+    __: B1,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_6` register
+pub struct ChargerConfig6 {
+    /// Write 0b01 to restart the watchdog timer (WDTCLR), reads as 0
+    pub wdtclr: B2,
+    /// Write 0b11 to unlock the protected registers (CHGPROT)
+    pub chgprot: B2,
+    #[skip]
+    __: B4,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_7` register, CHGPROT protected
+pub struct ChargerConfig7 {
+    /// The cold/cool boundary (T1)
+    pub cold: ColdThreshold,
+    /// The cool/normal boundary (T2)
+    pub cool: CoolThreshold,
+    /// The normal/warm boundary (T3)
+    pub warm: WarmThreshold,
+    /// The warm/hot boundary (T4)
+    pub hot: HotThreshold,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_8` register
+pub struct ChargerConfig8 {
+    /// The fast-charge current in the cool zone
+    pub cool_current: JeitaCurrent,
+    /// The fast-charge current in the warm zone
+    pub warm_current: JeitaCurrent,
+    /// The termination voltage reduction in the cool zone
+    pub cool_voltage: JeitaVoltage,
+    /// The termination voltage reduction in the warm zone
+    pub warm_voltage: JeitaVoltage,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_9` register
+pub struct ChargerConfig9 {
+    /// The CHGIN input current limit code (CHGIN_ILIM), 50 mA per step from 50 mA
+    pub chgin_ilim: B6,
+    #[skip]
+    __: B2,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_10` register
+pub struct ChargerConfig10 {
+    /// The input voltage regulation code (VCHGIN_REG), 4000 mV + 200 mV per step
+    pub vchgin_reg: B6,
+    /// The junction temperature regulation code (REGTEMP), see
+    /// [`regtemp_code_to_celsius`](crate::regtemp_code_to_celsius)
+    pub regtemp: B2,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_11` register
+pub struct ChargerConfig11 {
+    /// The bypass voltage code in OTG and boost mode (VBYPSET), 3000 mV + 20 mV per step
+    pub vbypset: B7,
+    #[skip]
+    __: B1,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_12` register
+pub struct ChargerConfig12 {
+    #[skip]
+    __: B4,
+    /// The OTG current limit code (OTG_ILIM): 500, 900, 1200, 1500, 2000, 2250, 2500 or
+    /// 3000 mA
+    pub otg_ilim: B3,
+    #[skip]
+    __: B1,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_13` register
+pub struct ChargerConfig13 {
+    /// Enable JEITA control (JEITA_EN)
+    pub jeita_enable: bool,
+    /// Enable thermistor monitoring (THM_EN)
+    pub thermistor_enable: bool,
+    /// The fuel gauge biases the thermistor (FGSRC)
+    pub fuel_gauge_bias: bool,
+    /// Enable SPSN detection (SPSN_DET_EN)
+    pub spsn_detect_enable: bool,
+    #[skip]
+    __: B4,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `STATUS_LED_CONFIG` register
+pub struct StatusLedConfig {
+    /// Drive the STAT LED from the charger state (STAT_EN)
+    pub enable: bool,
+    #[skip]
+    __: B7,
+}
+
+/// A register with a typed view
+///
+/// Implemented by the register structs of this crate for use with [`Charger::read`],
+/// [`Charger::write`] and [`Charger::modify`].
+pub trait RegisterSpec: Copy {
+    /// The register address
+    const ADDR: u8;
+    /// Whether the register can be written
+    const WRITABLE: bool;
+    /// Whether writes must be unlocked with CHGPROT
+    const PROTECTED: bool;
+
+    /// Decode the raw register value.
+    fn from_byte(val: u8) -> Self;
+
+    /// Encode the raw register value.
+    fn to_byte(self) -> u8;
+}
+
+macro_rules! register_spec {
+    ($($ty:ident => $reg:ident),* $(,)?) => {
+        $(
+            impl RegisterSpec for $ty {
+                const ADDR: u8 = Reg::$reg.address();
+                const WRITABLE: bool = !Reg::$reg.is_read_only();
+                const PROTECTED: bool = Reg::$reg.is_protected();
+
+                fn from_byte(val: u8) -> Self {
+                    Self::from_bytes([val])
+                }
+
+                fn to_byte(self) -> u8 {
+                    self.into_bytes()[0]
+                }
+            }
+        )*
+    };
+}
+
+register_spec!(
+    ChargerStatus => CHARGER_INTERRUPT_STATUS,
+    ChargerConfig0 => CHARGER_CONFIG_0,
+    ChargerConfig1 => CHARGER_CONFIG_1,
+    ChargerConfig2 => CHARGER_CONFIG_2,
+    ChargerConfig3 => CHARGER_CONFIG_3,
+    ChargerConfig4 => CHARGER_CONFIG_4,
+    ChargerConfig5 => CHARGER_CONFIG_5,
+    ChargerConfig6 => CHARGER_CONFIG_6,
+    ChargerConfig7 => CHARGER_CONFIG_7,
+    ChargerConfig8 => CHARGER_CONFIG_8,
+    ChargerConfig9 => CHARGER_CONFIG_9,
+    ChargerConfig10 => CHARGER_CONFIG_10,
+    ChargerConfig11 => CHARGER_CONFIG_11,
+    ChargerConfig12 => CHARGER_CONFIG_12,
+    ChargerConfig13 => CHARGER_CONFIG_13,
+    StatusLedConfig => STATUS_LED_CONFIG,
+);
+
+impl<D: I2c> Charger<D> {
+    /// Read a typed register.
+    pub async fn read<R: RegisterSpec>(&mut self) -> Result<R, Error<D::Error>> {
+        traced!(self, "read", {
+            self.read_reg(Reg::new(R::ADDR)).await.map(R::from_byte)
+        })
+    }
+
+    /// Write a typed register.
+    ///
+    /// Protected registers are written within a [`ProtectedSession`](crate::ProtectedSession). Writing a read-only
+    /// register fails to compile.
+    pub async fn write<R: RegisterSpec>(&mut self, val: R) -> Result<(), Error<D::Error>> {
+        traced!(self, "write", {
+            const { assert!(R::WRITABLE, "register is read-only") };
+            if R::PROTECTED {
+                self.protected(async |session| session.charger.write_typed(val).await)
+                    .await
+            } else {
+                self.write_typed(val).await
+            }
+        })
+    }
+
+    /// Read, modify and write back a typed register.
+    ///
+    /// Protected registers are modified within a [`ProtectedSession`](crate::ProtectedSession). Modifying a read-only
+    /// register fails to compile.
+    pub async fn modify<R: RegisterSpec>(
+        &mut self,
+        func: impl FnOnce(R) -> R,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "modify", {
+            const { assert!(R::WRITABLE, "register is read-only") };
+            if R::PROTECTED {
+                self.protected(async |session| session.charger.modify_typed(func).await)
+                    .await
+            } else {
+                self.modify_typed(func).await
+            }
+        })
+    }
+
+    /// Write a typed register without unlocking it.
+    pub(crate) async fn write_typed<R: RegisterSpec>(
+        &mut self,
+        val: R,
+    ) -> Result<(), Error<D::Error>> {
+        self.write_reg(Reg::new(R::ADDR), val.to_byte()).await
+    }
+
+    /// Modify a typed register without unlocking it.
+    pub(crate) async fn modify_typed<R: RegisterSpec>(
+        &mut self,
+        func: impl FnOnce(R) -> R,
+    ) -> Result<(), Error<D::Error>> {
+        self.modify_reg(Reg::new(R::ADDR), |val| func(R::from_byte(val)).to_byte())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use embedded_hal_async::i2c::{ErrorKind, NoAcknowledgeSource};
+    use embedded_hal_mock::eh1::i2c::Transaction;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::testing::{any_field, lock, read, run, unlock, write};
+    use crate::{Mode, WatchdogAction, DEFAULT_ADDRESS};
+
+    /// Check that `built` encodes to `raw` and that `raw` decodes to it.
+    fn assert_encodes<R: RegisterSpec + PartialEq + core::fmt::Debug>(built: R, raw: u8) {
+        assert_eq!(built.to_byte(), raw, "{built:?}");
+        assert_eq!(R::from_byte(raw), built, "0x{raw:02x}");
+    }
+
+    /// Check that the reserved bits of every raw value survive a decode and encode.
+    fn assert_keeps_reserved_bits<R: RegisterSpec>() {
+        for raw in 0..=u8::MAX {
+            assert_eq!(R::from_byte(raw).to_byte(), raw, "0x{raw:02x}");
+        }
+    }
+
+    #[test]
+    fn modify_protected_relocks_after_nak_on_write() {
+        let res = run(
+            &[
+                unlock(),
+                read(Reg::CHARGER_CONFIG_5, 0x00),
+                Transaction::write(DEFAULT_ADDRESS, vec![Reg::CHARGER_CONFIG_5.address(), 0x03])
+                    .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)),
+                lock(),
+            ],
+            async |charger| {
+                charger
+                    .modify(|val: ChargerConfig5| val.with_sys_ilim(3))
+                    .await
+            },
+        );
+        assert!(matches!(res, Err(Error::I2c(ErrorKind::NoAcknowledge(_)))));
+    }
+
+    #[test]
+    fn modify_protected_relocks_after_failed_read() {
+        let res = run(
+            &[
+                unlock(),
+                Transaction::write_read(
+                    DEFAULT_ADDRESS,
+                    vec![Reg::CHARGER_CONFIG_5.address()],
+                    vec![0],
+                )
+                .with_error(ErrorKind::Other),
+                lock(),
+            ],
+            async |charger| {
+                charger
+                    .modify(|val: ChargerConfig5| val.with_sys_ilim(3))
+                    .await
+            },
+        );
+        assert_eq!(res, Err(Error::I2c(ErrorKind::Other)));
+    }
+
+    #[test]
+    fn modify_unprotected_skips_chgprot() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_CONFIG_10, 0x3f),
+                write(Reg::CHARGER_CONFIG_10, 0x7f),
+            ],
+            async |charger| {
+                charger
+                    .modify(|val: ChargerConfig10| val.with_regtemp(1))
+                    .await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn config0_to_config5_encode_known_bytes() {
+        assert_encodes(
+            ChargerConfig0::new()
+                .with_mode(Mode::Charge)
+                .with_watchdog_enable(true)
+                .with_disibs(true)
+                .with_watchdog_action(WatchdogAction::SystemOff),
+            0xd5,
+        );
+        assert_encodes(
+            ChargerConfig1::new()
+                .with_fast_charge_timer(FastChargeTimer::Hours5)
+                .with_recharge(RechargeThreshold::Disabled)
+                .with_prequalification_enable(true),
+            0xb3,
+        );
+        assert_encodes(ChargerConfig2::new().with_chg_cc(0x7f), 0x7f);
+        assert_encodes(ChargerConfig3::new().with_to_ith(5).with_to_time(3), 0x1d);
+        assert_encodes(ChargerConfig4::new().with_chg_cv_prm(0x50), 0x50);
+        assert_encodes(
+            ChargerConfig5::new()
+                .with_sys_ilim(0x0a)
+                .with_recycle_enable(true)
+                .with_itrickle(2),
+            0x5a,
+        );
+    }
+
+    #[test]
+    fn config6_to_config13_encode_known_bytes() {
+        assert_encodes(ChargerConfig6::new().with_wdtclr(1).with_chgprot(3), 0x0d);
+        assert_encodes(
+            ChargerConfig7::new()
+                .with_cold(ColdThreshold::Minus5C)
+                .with_cool(CoolThreshold::Plus15C)
+                .with_warm(WarmThreshold::Plus35C)
+                .with_hot(HotThreshold::Plus55C),
+            0x8d,
+        );
+        assert_encodes(
+            ChargerConfig8::new()
+                .with_cool_current(JeitaCurrent::Percent75)
+                .with_warm_current(JeitaCurrent::Percent20)
+                .with_cool_voltage(JeitaVoltage::Mv200)
+                .with_warm_voltage(JeitaVoltage::Mv100),
+            0x7d,
+        );
+        assert_encodes(ChargerConfig9::new().with_chgin_ilim(0x3f), 0x3f);
+        assert_encodes(
+            ChargerConfig10::new().with_vchgin_reg(5).with_regtemp(2),
+            0x85,
+        );
+        assert_encodes(ChargerConfig11::new().with_vbypset(0x64), 0x64);
+        assert_encodes(ChargerConfig12::new().with_otg_ilim(5), 0x50);
+        assert_encodes(
+            ChargerConfig13::new()
+                .with_jeita_enable(true)
+                .with_fuel_gauge_bias(true)
+                .with_spsn_detect_enable(true),
+            0x0d,
+        );
+        assert_encodes(StatusLedConfig::new().with_enable(true), 0x01);
+    }
+
+    #[test]
+    fn config_registers_keep_reserved_bits() {
+        assert_keeps_reserved_bits::<ChargerConfig0>();
+        assert_keeps_reserved_bits::<ChargerConfig1>();
+        assert_keeps_reserved_bits::<ChargerConfig2>();
+        assert_keeps_reserved_bits::<ChargerConfig3>();
+        assert_keeps_reserved_bits::<ChargerConfig4>();
+        assert_keeps_reserved_bits::<ChargerConfig5>();
+        assert_keeps_reserved_bits::<ChargerConfig6>();
+        assert_keeps_reserved_bits::<ChargerConfig7>();
+        assert_keeps_reserved_bits::<ChargerConfig8>();
+        assert_keeps_reserved_bits::<ChargerConfig9>();
+        assert_keeps_reserved_bits::<ChargerConfig10>();
+        assert_keeps_reserved_bits::<ChargerConfig11>();
+        assert_keeps_reserved_bits::<ChargerConfig12>();
+        assert_keeps_reserved_bits::<ChargerConfig13>();
+        assert_keeps_reserved_bits::<StatusLedConfig>();
+    }
+
+    #[test]
+    fn write_unlocks_protected_registers() {
+        const { assert!(ChargerConfig2::PROTECTED) };
+        let res = run(
+            &[unlock(), write(Reg::CHARGER_CONFIG_2, 0x14), lock()],
+            async |charger| charger.write(ChargerConfig2::new().with_chg_cc(20)).await,
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn write_leaves_unprotected_registers_locked() {
+        const { assert!(!ChargerConfig9::PROTECTED) };
+        let res = run(&[write(Reg::CHARGER_CONFIG_9, 0x1d)], async |charger| {
+            charger
+                .write(ChargerConfig9::new().with_chgin_ilim(29))
+                .await
+        });
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn modify_unlocks_protected_registers() {
+        let res = run(
+            &[
+                unlock(),
+                read(Reg::CHARGER_CONFIG_4, 0xd0),
+                write(Reg::CHARGER_CONFIG_4, 0xc0),
+                lock(),
+            ],
+            async |charger| {
+                charger
+                    .modify(|val: ChargerConfig4| val.with_chg_cv_prm(0x40))
+                    .await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn read_returns_the_typed_register() {
+        let res = run(&[read(Reg::CHARGER_CONFIG_12, 0x50)], async |charger| {
+            charger.read::<ChargerConfig12>().await
+        });
+        assert_eq!(res, Ok(ChargerConfig12::new().with_otg_ilim(5)));
+    }
+
+    #[test]
+    fn register_specs_match_the_register_map() {
+        assert_eq!(ChargerStatus::ADDR, Reg::CHARGER_INTERRUPT_STATUS.address());
+        const { assert!(!ChargerStatus::WRITABLE) };
+        assert_eq!(ChargerConfig0::ADDR, Reg::CHARGER_CONFIG_0.address());
+        const { assert!(ChargerConfig0::WRITABLE && !ChargerConfig0::PROTECTED) };
+        for protected in [
+            ChargerConfig1::PROTECTED,
+            ChargerConfig2::PROTECTED,
+            ChargerConfig3::PROTECTED,
+            ChargerConfig4::PROTECTED,
+            ChargerConfig5::PROTECTED,
+            ChargerConfig7::PROTECTED,
+        ] {
+            assert!(protected);
+        }
+        assert_eq!(StatusLedConfig::ADDR, Reg::STATUS_LED_CONFIG.address());
+    }
+
+    proptest! {
+        #[test]
+        fn config1_round_trips(
+            fast_charge_timer in any_field::<FastChargeTimer>(),
+            recharge in any_field::<RechargeThreshold>(),
+            prequalification_enable: bool,
+        ) {
+            let built = ChargerConfig1::new()
+                .with_fast_charge_timer(fast_charge_timer)
+                .with_recharge(recharge)
+                .with_prequalification_enable(prequalification_enable);
+            let decoded = ChargerConfig1::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(
+                (decoded.fast_charge_timer(), decoded.recharge(), decoded.prequalification_enable()),
+                (fast_charge_timer, recharge, prequalification_enable)
+            );
+        }
+
+        #[test]
+        fn config2_round_trips(chg_cc in any_field::<B7>()) {
+            let built = ChargerConfig2::new().with_chg_cc(chg_cc);
+            let decoded = ChargerConfig2::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(decoded.chg_cc(), chg_cc);
+        }
+
+        #[test]
+        fn config3_round_trips(to_ith in any_field::<B3>(), to_time in any_field::<B3>()) {
+            let built = ChargerConfig3::new().with_to_ith(to_ith).with_to_time(to_time);
+            let decoded = ChargerConfig3::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!((decoded.to_ith(), decoded.to_time()), (to_ith, to_time));
+        }
+
+        #[test]
+        fn config4_round_trips(chg_cv_prm in any_field::<B7>()) {
+            let built = ChargerConfig4::new().with_chg_cv_prm(chg_cv_prm);
+            let decoded = ChargerConfig4::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(decoded.chg_cv_prm(), chg_cv_prm);
+        }
+
+        #[test]
+        fn config5_round_trips(
+            sys_ilim in any_field::<B4>(),
+            recycle_enable: bool,
+            itrickle in any_field::<B2>(),
+        ) {
+            let built = ChargerConfig5::new()
+                .with_sys_ilim(sys_ilim)
+                .with_recycle_enable(recycle_enable)
+                .with_itrickle(itrickle);
+            let decoded = ChargerConfig5::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(
+                (decoded.sys_ilim(), decoded.recycle_enable(), decoded.itrickle()),
+                (sys_ilim, recycle_enable, itrickle)
+            );
+        }
+
+        #[test]
+        fn config6_round_trips(wdtclr in any_field::<B2>(), chgprot in any_field::<B2>()) {
+            let built = ChargerConfig6::new().with_wdtclr(wdtclr).with_chgprot(chgprot);
+            let decoded = ChargerConfig6::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!((decoded.wdtclr(), decoded.chgprot()), (wdtclr, chgprot));
+        }
+
+        #[test]
+        fn config7_round_trips(
+            cold in any_field::<ColdThreshold>(),
+            cool in any_field::<CoolThreshold>(),
+            warm in any_field::<WarmThreshold>(),
+            hot in any_field::<HotThreshold>(),
+        ) {
+            let built = ChargerConfig7::new()
+                .with_cold(cold)
+                .with_cool(cool)
+                .with_warm(warm)
+                .with_hot(hot);
+            let decoded = ChargerConfig7::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(
+                (decoded.cold(), decoded.cool(), decoded.warm(), decoded.hot()),
+                (cold, cool, warm, hot)
+            );
+        }
+
+        #[test]
+        fn config8_round_trips(
+            cool_current in any_field::<JeitaCurrent>(),
+            warm_current in any_field::<JeitaCurrent>(),
+            cool_voltage in any_field::<JeitaVoltage>(),
+            warm_voltage in any_field::<JeitaVoltage>(),
+        ) {
+            let built = ChargerConfig8::new()
+                .with_cool_current(cool_current)
+                .with_warm_current(warm_current)
+                .with_cool_voltage(cool_voltage)
+                .with_warm_voltage(warm_voltage);
+            let decoded = ChargerConfig8::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(
+                (
+                    decoded.cool_current(),
+                    decoded.warm_current(),
+                    decoded.cool_voltage(),
+                    decoded.warm_voltage(),
+                ),
+                (cool_current, warm_current, cool_voltage, warm_voltage)
+            );
+        }
+
+        #[test]
+        fn config9_round_trips(chgin_ilim in any_field::<B6>()) {
+            let built = ChargerConfig9::new().with_chgin_ilim(chgin_ilim);
+            let decoded = ChargerConfig9::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(decoded.chgin_ilim(), chgin_ilim);
+        }
+
+        #[test]
+        fn config10_round_trips(vchgin_reg in any_field::<B6>(), regtemp in any_field::<B2>()) {
+            let built = ChargerConfig10::new().with_vchgin_reg(vchgin_reg).with_regtemp(regtemp);
+            let decoded = ChargerConfig10::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!((decoded.vchgin_reg(), decoded.regtemp()), (vchgin_reg, regtemp));
+        }
+
+        #[test]
+        fn config11_round_trips(vbypset in any_field::<B7>()) {
+            let built = ChargerConfig11::new().with_vbypset(vbypset);
+            let decoded = ChargerConfig11::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(decoded.vbypset(), vbypset);
+        }
+
+        #[test]
+        fn config12_round_trips(otg_ilim in any_field::<B3>()) {
+            let built = ChargerConfig12::new().with_otg_ilim(otg_ilim);
+            let decoded = ChargerConfig12::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(decoded.otg_ilim(), otg_ilim);
+        }
+
+        #[test]
+        fn config13_round_trips(
+            jeita_enable: bool,
+            thermistor_enable: bool,
+            fuel_gauge_bias: bool,
+            spsn_detect_enable: bool,
+        ) {
+            let built = ChargerConfig13::new()
+                .with_jeita_enable(jeita_enable)
+                .with_thermistor_enable(thermistor_enable)
+                .with_fuel_gauge_bias(fuel_gauge_bias)
+                .with_spsn_detect_enable(spsn_detect_enable);
+            let decoded = ChargerConfig13::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(
+                (
+                    decoded.jeita_enable(),
+                    decoded.thermistor_enable(),
+                    decoded.fuel_gauge_bias(),
+                    decoded.spsn_detect_enable(),
+                ),
+                (jeita_enable, thermistor_enable, fuel_gauge_bias, spsn_detect_enable)
+            );
+        }
+
+        #[test]
+        fn status_led_round_trips(enable: bool) {
+            let built = StatusLedConfig::new().with_enable(enable);
+            let decoded = StatusLedConfig::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(decoded.enable(), enable);
+        }
+
+        #[test]
+        fn raw_bytes_round_trip(raw: u8) {
+            prop_assert_eq!(ChargerConfig1::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig2::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig3::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig4::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig5::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig6::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig7::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig8::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig9::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig10::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig11::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig12::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(ChargerConfig13::from_bytes([raw]).into_bytes(), [raw]);
+            prop_assert_eq!(StatusLedConfig::from_bytes([raw]).into_bytes(), [raw]);
+        }
+    }
+}
@@ -0,0 +1,476 @@
+//! Conversions between physical units and register codes.
+//!
+//! Encoders round down to the step of the field. Decoders return the value a code stands for,
+//! ignoring bits outside the field, so `encode(decode(code)) == code` for every code the field
+//! can hold, except for the CHG_CC codes below the minimum current, which all decode to the
+//! minimum. The setters of [`Charger`](crate::Charger) use these functions, so they can be used
+//! to predict what a setter will program:
+//!
+//! ```
+//! use max7797x_driver::conv;
+//!
+//! assert_eq!(conv::chgin_ilim_code(1234), 23);
+//! assert_eq!(conv::chgin_ilim_ma(23), 1200);
+//! ```
+//!
+//! None of the functions can panic or overflow, whatever the input.
+
+use crate::Variant;
+
+/// The SYS_ILIM current of code 0 in mA
+const SYS_ILIM_OFFSET_MA: u16 = 2500;
+/// The SYS_ILIM step in mA
+const SYS_ILIM_STEP_MA: u16 = 500;
+/// The highest SYS_ILIM code
+const SYS_ILIM_MAX_CODE: u8 = 0x0f;
+
+/// The CHGIN_ILIM current of code 0 in mA
+const CHGIN_ILIM_OFFSET_MA: u16 = 50;
+/// The CHGIN_ILIM step in mA
+const CHGIN_ILIM_STEP_MA: u16 = 50;
+/// The highest CHGIN_ILIM code
+const CHGIN_ILIM_MAX_CODE: u8 = 0x3f;
+
+/// The highest CHG_CC code
+const CHG_CC_MAX_CODE: u8 = 0x7f;
+
+/// The CHG_CV_PRM voltage of code 0 in mV
+const CHG_CV_PRM_OFFSET_MV: u16 = 3400;
+/// The CHG_CV_PRM step in mV
+const CHG_CV_PRM_STEP_MV: u16 = 10;
+/// The highest CHG_CV_PRM code
+const CHG_CV_PRM_MAX_CODE: u8 = 0x7f;
+
+/// The highest OTG_ILIM code
+const OTG_ILIM_MAX_CODE: u8 = 0x07;
+
+/// The VBYPSET voltage of code 0 in mV
+const VBYPSET_OFFSET_MV: u16 = 3000;
+/// The VBYPSET step in mV
+const VBYPSET_STEP_MV: u16 = 20;
+/// The highest VBYPSET code
+const VBYPSET_MAX_CODE: u8 = 0x7f;
+
+/// The REGTEMP threshold of code 0 in °C
+const REGTEMP_OFFSET_CELSIUS: u8 = 85;
+/// The REGTEMP step in °C
+const REGTEMP_STEP_CELSIUS: u8 = 15;
+/// The highest REGTEMP code
+const REGTEMP_MAX_CODE: u8 = 3;
+
+/// The SYS_ILIM code for `milliamps`, in 500 mA steps from 2500 mA.
+///
+/// Saturates at the limits of the field, 2500 mA and 10000 mA.
+pub const fn sys_ilim_code(milliamps: u16) -> u8 {
+    let code = milliamps.saturating_sub(SYS_ILIM_OFFSET_MA) / SYS_ILIM_STEP_MA;
+    if code > SYS_ILIM_MAX_CODE as u16 {
+        SYS_ILIM_MAX_CODE
+    } else {
+        code as u8
+    }
+}
+
+/// The Vsys current limit in mA for a SYS_ILIM `code`.
+pub const fn sys_ilim_ma(code: u8) -> u16 {
+    SYS_ILIM_OFFSET_MA
+        .saturating_add(SYS_ILIM_STEP_MA.saturating_mul((code & SYS_ILIM_MAX_CODE) as u16))
+}
+
+/// The CHGIN_ILIM code for `milliamps`, in 50 mA steps from 50 mA.
+///
+/// Saturates at the limits of the field, 50 mA and 3200 mA.
+pub const fn chgin_ilim_code(milliamps: u16) -> u8 {
+    let code = milliamps.saturating_sub(CHGIN_ILIM_OFFSET_MA) / CHGIN_ILIM_STEP_MA;
+    if code > CHGIN_ILIM_MAX_CODE as u16 {
+        CHGIN_ILIM_MAX_CODE
+    } else {
+        code as u8
+    }
+}
+
+/// The CHGIN input current limit in mA for a CHGIN_ILIM `code`.
+pub const fn chgin_ilim_ma(code: u8) -> u16 {
+    CHGIN_ILIM_OFFSET_MA
+        .saturating_add(CHGIN_ILIM_STEP_MA.saturating_mul((code & CHGIN_ILIM_MAX_CODE) as u16))
+}
+
+/// The CHG_CC code for `milliamps` on `variant`, in steps of
+/// [`Variant::fast_charge_current_step`] from 0 mA.
+///
+/// Returns `None` if `milliamps` is below [`Variant::min_fast_charge_current`] or exceeds
+/// [`Variant::max_fast_charge_current`].
+pub const fn chg_cc_code(milliamps: u16, variant: Variant) -> Option<u8> {
+    if milliamps < variant.min_fast_charge_current()
+        || milliamps > variant.max_fast_charge_current()
+    {
+        return None;
+    }
+    match milliamps.checked_div(variant.fast_charge_current_step()) {
+        Some(code) if code <= CHG_CC_MAX_CODE as u16 => Some(code as u8),
+        _ => None,
+    }
+}
+
+/// The fast-charge current in mA for a CHG_CC `code` on `variant`.
+///
+/// Codes below [`Variant::min_fast_charge_current`] decode to the minimum, which the part
+/// selects for them. Codes above [`Variant::max_fast_charge_current`] decode to the current
+/// they would select, although the part does not support it.
+pub const fn chg_cc_ma(code: u8, variant: Variant) -> u16 {
+    let milliamps =
+        ((code & CHG_CC_MAX_CODE) as u16).saturating_mul(variant.fast_charge_current_step());
+    if milliamps < variant.min_fast_charge_current() {
+        variant.min_fast_charge_current()
+    } else {
+        milliamps
+    }
+}
+
+/// The CHG_CV_PRM code for `millivolts`, in 10 mV steps from 3400 mV.
+///
+/// Returns `None` outside the 3400 mV to 4670 mV range.
+pub const fn chg_cv_prm_code(millivolts: u16) -> Option<u8> {
+    if millivolts < CHG_CV_PRM_OFFSET_MV || millivolts > chg_cv_prm_mv(CHG_CV_PRM_MAX_CODE) {
+        None
+    } else {
+        Some((millivolts.saturating_sub(CHG_CV_PRM_OFFSET_MV) / CHG_CV_PRM_STEP_MV) as u8)
+    }
+}
+
+/// The termination voltage in mV for a CHG_CV_PRM `code`.
+pub const fn chg_cv_prm_mv(code: u8) -> u16 {
+    CHG_CV_PRM_OFFSET_MV
+        .saturating_add(CHG_CV_PRM_STEP_MV.saturating_mul((code & CHG_CV_PRM_MAX_CODE) as u16))
+}
+
+/// The OTG_ILIM code for the largest setting not above `milliamps`.
+///
+/// The settings are 500, 900, 1200, 1500, 2000, 2250, 2500 and 3000 mA. Returns `None` below
+/// 500 mA.
+pub const fn otg_ilim_code(milliamps: u16) -> Option<u8> {
+    let mut code = OTG_ILIM_MAX_CODE;
+    loop {
+        if otg_ilim_ma(code) <= milliamps {
+            return Some(code);
+        }
+        if code == 0 {
+            return None;
+        }
+        code = code.saturating_sub(1);
+    }
+}
+
+/// The OTG current limit in mA for an OTG_ILIM `code`.
+pub const fn otg_ilim_ma(code: u8) -> u16 {
+    match code & OTG_ILIM_MAX_CODE {
+        0 => 500,
+        1 => 900,
+        2 => 1200,
+        3 => 1500,
+        4 => 2000,
+        5 => 2250,
+        6 => 2500,
+        _ => 3000,
+    }
+}
+
+/// The VBYPSET code for `millivolts`, in 20 mV steps from 3000 mV.
+///
+/// Returns `None` below 3000 mV or above 5540 mV after rounding.
+pub const fn vbypset_code(millivolts: u16) -> Option<u8> {
+    if millivolts < VBYPSET_OFFSET_MV {
+        return None;
+    }
+    let code = millivolts.saturating_sub(VBYPSET_OFFSET_MV) / VBYPSET_STEP_MV;
+    if code > VBYPSET_MAX_CODE as u16 {
+        None
+    } else {
+        Some(code as u8)
+    }
+}
+
+/// The OTG bypass voltage in mV for a VBYPSET `code`.
+pub const fn vbypset_mv(code: u8) -> u16 {
+    VBYPSET_OFFSET_MV
+        .saturating_add(VBYPSET_STEP_MV.saturating_mul((code & VBYPSET_MAX_CODE) as u16))
+}
+
+/// The junction temperature regulation threshold in °C for a REGTEMP `code`.
+///
+/// Only the two low bits of `code` are used.
+pub const fn regtemp_code_to_celsius(code: u8) -> u8 {
+    REGTEMP_OFFSET_CELSIUS
+        .saturating_add(REGTEMP_STEP_CELSIUS.saturating_mul(code & REGTEMP_MAX_CODE))
+}
+
+/// The REGTEMP code for a regulation threshold of `celsius`, rounded down to a 15 °C step.
+///
+/// Returns `None` outside the 85 °C to 130 °C range.
+pub const fn celsius_to_regtemp_code(celsius: u8) -> Option<u8> {
+    if celsius < REGTEMP_OFFSET_CELSIUS || celsius > regtemp_code_to_celsius(REGTEMP_MAX_CODE) {
+        None
+    } else {
+        Some(celsius.saturating_sub(REGTEMP_OFFSET_CELSIUS) / REGTEMP_STEP_CELSIUS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    use super::*;
+
+    #[test]
+    fn chg_cc_code_scales_per_variant() {
+        for (variant, code) in [
+            (Variant::Max77975, 20),
+            (Variant::Max77976, 20),
+            (Variant::Max77985, 40),
+            (Variant::Max77986, 20),
+        ] {
+            assert_eq!(chg_cc_code(1000, variant), Some(code), "{variant:?}");
+        }
+    }
+
+    #[test]
+    fn chg_cc_code_checks_variant_limits() {
+        assert_eq!(chg_cc_code(5000, Variant::Max77975), None);
+        assert_eq!(chg_cc_code(5000, Variant::Max77976), Some(100));
+        assert_eq!(chg_cc_code(5500, Variant::Max77976), Some(110));
+        assert_eq!(chg_cc_code(5550, Variant::Max77976), None);
+        assert_eq!(chg_cc_code(99, Variant::Max77976), None);
+        assert_eq!(chg_cc_code(100, Variant::Max77976), Some(2));
+        assert_eq!(chg_cc_code(149, Variant::Max77976), Some(2));
+    }
+
+    #[test]
+    fn chg_cc_codes_below_minimum_decode_to_it() {
+        for code in 0..=2 {
+            assert_eq!(chg_cc_ma(code, Variant::Max77976), 100);
+        }
+        assert_eq!(chg_cc_ma(3, Variant::Max77976), 150);
+        assert_eq!(chg_cc_ma(110, Variant::Max77976), 5500);
+    }
+
+    #[test]
+    fn regtemp_round_trips_every_code() {
+        for (code, celsius) in [(0, 85), (1, 100), (2, 115), (3, 130)] {
+            assert_eq!(regtemp_code_to_celsius(code), celsius);
+            assert_eq!(celsius_to_regtemp_code(celsius), Some(code));
+            assert_eq!(
+                celsius_to_regtemp_code(regtemp_code_to_celsius(code)),
+                Some(code)
+            );
+        }
+    }
+
+    #[test]
+    fn regtemp_rounds_down_within_the_range() {
+        assert_eq!(celsius_to_regtemp_code(84), None);
+        assert_eq!(celsius_to_regtemp_code(99), Some(0));
+        assert_eq!(celsius_to_regtemp_code(129), Some(2));
+        assert_eq!(celsius_to_regtemp_code(131), None);
+        assert_eq!(celsius_to_regtemp_code(u8::MAX), None);
+        // Only the two low bits are used
+        assert_eq!(regtemp_code_to_celsius(0xfd), 100);
+    }
+
+    #[test]
+    fn sys_ilim_round_trips_every_code() {
+        // 2500 mA + 500 mA per step
+        for code in 0..=SYS_ILIM_MAX_CODE {
+            assert_eq!(sys_ilim_ma(code), 2500 + 500 * code as u16);
+            assert_eq!(sys_ilim_code(sys_ilim_ma(code)), code);
+            assert_eq!(sys_ilim_code(sys_ilim_ma(code) + 499), code);
+        }
+        assert_eq!(sys_ilim_code(0), 0);
+        assert_eq!(sys_ilim_code(u16::MAX), SYS_ILIM_MAX_CODE);
+    }
+
+    #[test]
+    fn chgin_ilim_round_trips_every_code() {
+        // 50 mA + 50 mA per step
+        for code in 0..=CHGIN_ILIM_MAX_CODE {
+            assert_eq!(chgin_ilim_ma(code), 50 + 50 * code as u16);
+            assert_eq!(chgin_ilim_code(chgin_ilim_ma(code)), code);
+            assert_eq!(chgin_ilim_code(chgin_ilim_ma(code) + 49), code);
+        }
+        assert_eq!(chgin_ilim_code(0), 0);
+        assert_eq!(chgin_ilim_code(u16::MAX), CHGIN_ILIM_MAX_CODE);
+    }
+
+    #[test]
+    fn chg_cc_round_trips_every_supported_code() {
+        // Variant step per code from 0 mA, between the variant limits
+        for variant in [
+            Variant::Max77975,
+            Variant::Max77976,
+            Variant::Max77985,
+            Variant::Max77986,
+        ] {
+            let step = variant.fast_charge_current_step();
+            for code in 0..=CHG_CC_MAX_CODE {
+                let milliamps = chg_cc_ma(code, variant);
+                if milliamps != code as u16 * step {
+                    // Below the minimum, see chg_cc_codes_below_minimum_decode_to_it
+                    assert_eq!(milliamps, variant.min_fast_charge_current());
+                } else if milliamps <= variant.max_fast_charge_current() {
+                    assert_eq!(chg_cc_code(milliamps, variant), Some(code), "{variant:?}");
+                } else {
+                    assert_eq!(chg_cc_code(milliamps, variant), None, "{variant:?}");
+                }
+            }
+            assert_eq!(chg_cc_code(u16::MAX, variant), None);
+        }
+    }
+
+    #[test]
+    fn chg_cv_prm_round_trips_every_code() {
+        // 3400 mV + 10 mV per step
+        for code in 0..=CHG_CV_PRM_MAX_CODE {
+            assert_eq!(chg_cv_prm_mv(code), 3400 + 10 * code as u16);
+            assert_eq!(chg_cv_prm_code(chg_cv_prm_mv(code)), Some(code));
+            assert_eq!(
+                chg_cv_prm_code(chg_cv_prm_mv(code) + 9),
+                Some(code).filter(|&c| c < CHG_CV_PRM_MAX_CODE)
+            );
+        }
+        assert_eq!(chg_cv_prm_code(3399), None);
+        assert_eq!(chg_cv_prm_code(4671), None);
+    }
+
+    #[test]
+    fn otg_ilim_round_trips_every_code() {
+        let settings = [500, 900, 1200, 1500, 2000, 2250, 2500, 3000];
+        for (code, milliamps) in (0..=OTG_ILIM_MAX_CODE).zip(settings) {
+            assert_eq!(otg_ilim_ma(code), milliamps);
+            assert_eq!(otg_ilim_code(milliamps), Some(code));
+            assert_eq!(otg_ilim_code(milliamps + 1), Some(code));
+        }
+        assert_eq!(otg_ilim_code(499), None);
+        assert_eq!(otg_ilim_code(u16::MAX), Some(OTG_ILIM_MAX_CODE));
+    }
+
+    #[test]
+    fn vbypset_round_trips_every_code() {
+        // 3000 mV + 20 mV per step
+        for code in 0..=VBYPSET_MAX_CODE {
+            assert_eq!(vbypset_mv(code), 3000 + 20 * code as u16);
+            assert_eq!(vbypset_code(vbypset_mv(code)), Some(code));
+            assert_eq!(vbypset_code(vbypset_mv(code) + 19), Some(code));
+        }
+        assert_eq!(vbypset_code(2999), None);
+        assert_eq!(vbypset_code(5560), None);
+    }
+
+    #[test]
+    fn decoders_ignore_bits_outside_the_field() {
+        for code in 0..=u8::MAX {
+            assert_eq!(sys_ilim_ma(code), sys_ilim_ma(code & SYS_ILIM_MAX_CODE));
+            assert_eq!(
+                chgin_ilim_ma(code),
+                chgin_ilim_ma(code & CHGIN_ILIM_MAX_CODE)
+            );
+            assert_eq!(
+                chg_cv_prm_mv(code),
+                chg_cv_prm_mv(code & CHG_CV_PRM_MAX_CODE)
+            );
+            assert_eq!(otg_ilim_ma(code), otg_ilim_ma(code & OTG_ILIM_MAX_CODE));
+            assert_eq!(vbypset_mv(code), vbypset_mv(code & VBYPSET_MAX_CODE));
+            for variant in [Variant::Max77975, Variant::Max77985] {
+                assert_eq!(
+                    chg_cc_ma(code, variant),
+                    chg_cc_ma(code & CHG_CC_MAX_CODE, variant)
+                );
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn sys_ilim_decodes_within_a_step(milliamps in 2500u16..=10000) {
+            let decoded = sys_ilim_ma(sys_ilim_code(milliamps));
+            prop_assert!(decoded <= milliamps && milliamps.abs_diff(decoded) < SYS_ILIM_STEP_MA);
+        }
+
+        #[test]
+        fn chgin_ilim_decodes_within_a_step(milliamps in 50u16..=3200) {
+            let decoded = chgin_ilim_ma(chgin_ilim_code(milliamps));
+            prop_assert!(decoded <= milliamps && milliamps.abs_diff(decoded) < CHGIN_ILIM_STEP_MA);
+        }
+
+        #[test]
+        fn chg_cc_decodes_within_a_step(
+            (variant, milliamps) in select(&[
+                Variant::Max77975,
+                Variant::Max77976,
+                Variant::Max77985,
+                Variant::Max77986,
+            ][..])
+            .prop_flat_map(|variant| {
+                let milliamps =
+                    variant.min_fast_charge_current()..=variant.max_fast_charge_current();
+                (Just(variant), milliamps)
+            }),
+        ) {
+            let code = chg_cc_code(milliamps, variant);
+            prop_assert!(code.is_some());
+            let decoded = chg_cc_ma(code.unwrap_or_default(), variant);
+            prop_assert!(
+                decoded <= milliamps && milliamps.abs_diff(decoded) < variant.fast_charge_current_step()
+            );
+        }
+
+        #[test]
+        fn chg_cv_prm_decodes_within_a_step(millivolts in 3400u16..=4670) {
+            let code = chg_cv_prm_code(millivolts);
+            prop_assert!(code.is_some());
+            let decoded = chg_cv_prm_mv(code.unwrap_or_default());
+            prop_assert!(decoded <= millivolts && millivolts.abs_diff(decoded) < CHG_CV_PRM_STEP_MV);
+        }
+
+        #[test]
+        fn otg_ilim_decodes_within_a_step(milliamps in 500u16..=3000) {
+            let code = otg_ilim_code(milliamps);
+            prop_assert!(code.is_some());
+            let code = code.unwrap_or_default();
+            prop_assert!(otg_ilim_ma(code) <= milliamps);
+            prop_assert!(code == OTG_ILIM_MAX_CODE || otg_ilim_ma(code.saturating_add(1)) > milliamps);
+        }
+
+        #[test]
+        fn vbypset_decodes_within_a_step(millivolts in 3000u16..=5540) {
+            let code = vbypset_code(millivolts);
+            prop_assert!(code.is_some());
+            let decoded = vbypset_mv(code.unwrap_or_default());
+            prop_assert!(decoded <= millivolts && millivolts.abs_diff(decoded) < VBYPSET_STEP_MV);
+        }
+
+        #[test]
+        fn regtemp_decodes_within_a_step(celsius in 85u8..=130) {
+            let code = celsius_to_regtemp_code(celsius);
+            prop_assert!(code.is_some());
+            let decoded = regtemp_code_to_celsius(code.unwrap_or_default());
+            prop_assert!(decoded <= celsius && celsius.abs_diff(decoded) < REGTEMP_STEP_CELSIUS);
+        }
+
+        #[test]
+        fn encoders_never_panic(value in any::<u16>(), celsius in any::<u8>()) {
+            sys_ilim_code(value);
+            chgin_ilim_code(value);
+            chg_cv_prm_code(value);
+            otg_ilim_code(value);
+            vbypset_code(value);
+            celsius_to_regtemp_code(celsius);
+            for variant in [
+                Variant::Max77975,
+                Variant::Max77976,
+                Variant::Max77985,
+                Variant::Max77986,
+            ] {
+                chg_cc_code(value, variant);
+            }
+        }
+    }
+}
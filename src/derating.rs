@@ -0,0 +1,295 @@
+//! Charge current and voltage derating on an external temperature.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{conv, ChargeProfile, Charger, ChargerConfig4, Error};
+
+/// The charge limits for one temperature band, see [`DeratingTable`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeratingBand {
+    /// The highest temperature of this band, in 0.1 °C
+    pub max_deci_c: i16,
+    /// The highest fast-charge current in this band, in mA
+    pub max_fast_charge_ma: u16,
+    /// The highest termination voltage in this band, in mV
+    pub max_termination_mv: u16,
+}
+
+/// Charge limits by temperature, for [`Charger::apply_derating`]
+///
+/// The bands are ordered by increasing `max_deci_c`. A temperature falls into the first band
+/// whose `max_deci_c` it does not exceed; temperatures above the last band use the last band.
+/// Moving to a hotter band takes effect immediately, while moving back to a cooler band
+/// requires the temperature to be `hysteresis_deci_c` below the boundary.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct DeratingTable<'a> {
+    /// The temperature bands
+    pub bands: &'a [DeratingBand],
+    /// The hysteresis when moving to a cooler band, in 0.1 °C
+    pub hysteresis_deci_c: i16,
+}
+
+impl DeratingTable<'_> {
+    /// The index of the band `temp_deci_c` falls into, ignoring hysteresis.
+    pub fn band_for(&self, temp_deci_c: i16) -> usize {
+        self.bands
+            .iter()
+            .position(|band| temp_deci_c <= band.max_deci_c)
+            .unwrap_or(self.bands.len().saturating_sub(1))
+    }
+
+    /// The index of the band for `temp_deci_c` when `previous` was selected before.
+    pub fn band_with_hysteresis(&self, temp_deci_c: i16, previous: Option<usize>) -> usize {
+        let band = self.band_for(temp_deci_c);
+        match previous {
+            Some(previous) if band < previous => self
+                .band_for(temp_deci_c.saturating_add(self.hysteresis_deci_c))
+                .clamp(band, previous),
+            _ => band,
+        }
+    }
+}
+
+/// The derated settings written by [`Charger::apply_derating`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Derating {
+    /// The index of the selected band
+    pub band: usize,
+    /// The fast-charge current in mA
+    pub fast_charge_current_ma: u16,
+    /// The termination voltage in mV
+    pub termination_mv: u16,
+}
+
+impl<D: I2c> Charger<D> {
+    /// Limit the fast-charge current and termination voltage of `profile` for `temp_deci_c`.
+    ///
+    /// The lower of the profile and band values are written to `CHARGER_CONFIG_2` and
+    /// `CHARGER_CONFIG_4` and returned. The last result is remembered, so the registers are only
+    /// written when it changes and calling this every few seconds costs nothing but the
+    /// comparison. Call [`forget_derating`](Self::forget_derating) after changing these
+    /// settings by other means.
+    ///
+    /// Fails with [`Error::InvalidValue`] if the table is empty, the band's termination voltage
+    /// is below 3400 mV or the current exceeds the maximum of this part.
+    pub async fn apply_derating(
+        &mut self,
+        temp_deci_c: i16,
+        table: &DeratingTable<'_>,
+        profile: &ChargeProfile,
+    ) -> Result<Derating, Error<D::Error>> {
+        traced!(self, "apply_derating", {
+            let previous = self.derating.map(|derating| derating.band);
+            let band = table.band_with_hysteresis(temp_deci_c, previous);
+            let limits = table.bands.get(band).ok_or(Error::InvalidValue)?;
+            let termination_mv = profile.termination_mv().min(limits.max_termination_mv);
+            let chg_cv_prm = conv::chg_cv_prm_code(termination_mv).ok_or(Error::InvalidValue)?;
+            let derating = Derating {
+                band,
+                fast_charge_current_ma: profile
+                    .fast_charge_current_ma()
+                    .min(limits.max_fast_charge_ma),
+                termination_mv: conv::chg_cv_prm_mv(chg_cv_prm),
+            };
+            if self.derating == Some(derating) {
+                return Ok(derating);
+            }
+            self.derating = None;
+            self.protected(async |session| {
+                session
+                    .set_fast_charge_current(derating.fast_charge_current_ma)
+                    .await?;
+                session
+                    .charger
+                    .modify_typed(|val: ChargerConfig4| val.with_chg_cv_prm(chg_cv_prm))
+                    .await
+            })
+            .await?;
+            self.derating = Some(derating);
+            Ok(derating)
+        })
+    }
+
+    /// Forget the last [`Derating`], so the next [`apply_derating`](Self::apply_derating)
+    /// writes the registers and selects a band without hysteresis.
+    pub fn forget_derating(&mut self) {
+        self.derating = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::testing::{lock, read, run_variant, unlock, write};
+    use crate::{charge_profile, Reg, Variant};
+
+    const BANDS: [DeratingBand; 3] = [
+        DeratingBand {
+            max_deci_c: 0,
+            max_fast_charge_ma: 500,
+            max_termination_mv: 4350,
+        },
+        DeratingBand {
+            max_deci_c: 450,
+            max_fast_charge_ma: 2000,
+            max_termination_mv: 4350,
+        },
+        DeratingBand {
+            max_deci_c: 600,
+            max_fast_charge_ma: 1000,
+            max_termination_mv: 4100,
+        },
+    ];
+
+    const TABLE: DeratingTable<'static> = DeratingTable {
+        bands: &BANDS,
+        hysteresis_deci_c: 20,
+    };
+
+    const PROFILE: ChargeProfile = charge_profile!(Variant::Max77975, 1500, 4200, 150, 30);
+
+    #[test]
+    fn bands_include_their_upper_breakpoint() {
+        for (temp, band) in [
+            (i16::MIN, 0),
+            (-1, 0),
+            (0, 0),
+            (1, 1),
+            (449, 1),
+            (450, 1),
+            (451, 2),
+            (600, 2),
+            // Hotter than the last band still uses it
+            (601, 2),
+            (i16::MAX, 2),
+        ] {
+            assert_eq!(TABLE.band_for(temp), band, "{temp}");
+        }
+    }
+
+    #[test]
+    fn cooling_down_needs_the_hysteresis() {
+        for (temp, previous, band) in [
+            // Heating up switches at the breakpoint
+            (451, Some(1), 2),
+            (1, Some(0), 1),
+            // Cooling down switches only the hysteresis below it
+            (450, Some(2), 2),
+            (431, Some(2), 2),
+            (430, Some(2), 1),
+            (-19, Some(1), 1),
+            (-20, Some(1), 0),
+            // Never further than the band the temperature is in
+            (-100, Some(2), 0),
+            // Without a previous band there is no hysteresis
+            (450, None, 1),
+        ] {
+            assert_eq!(
+                TABLE.band_with_hysteresis(temp, previous),
+                band,
+                "{temp} from {previous:?}"
+            );
+        }
+    }
+
+    /// The transactions writing CHG_CC `chg_cc` and CHG_CV_PRM `chg_cv_prm` over zeroed
+    /// registers
+    fn derating_writes(
+        chg_cc: u8,
+        chg_cv_prm: u8,
+    ) -> Vec<embedded_hal_mock::eh1::i2c::Transaction> {
+        Vec::from([
+            unlock(),
+            read(Reg::CHARGER_CONFIG_2, 0x00),
+            write(Reg::CHARGER_CONFIG_2, chg_cc),
+            read(Reg::CHARGER_CONFIG_4, 0x00),
+            write(Reg::CHARGER_CONFIG_4, chg_cv_prm),
+            lock(),
+        ])
+    }
+
+    #[test]
+    fn apply_derating_writes_the_lower_limits() {
+        for (temp, band, chg_cc, chg_cv_prm, fast_charge_current_ma, termination_mv) in [
+            // Cold: the band current, the profile voltage
+            (0, 0, 0x0a, 0x50, 500, 4200),
+            // Normal: the profile current and voltage
+            (250, 1, 0x1e, 0x50, 1500, 4200),
+            // Hot: the band current and voltage
+            (451, 2, 0x14, 0x46, 1000, 4100),
+        ] {
+            let res = run_variant(
+                Variant::Max77975,
+                &derating_writes(chg_cc, chg_cv_prm),
+                async |charger| charger.apply_derating(temp, &TABLE, &PROFILE).await,
+            );
+            assert_eq!(
+                res,
+                Ok(Derating {
+                    band,
+                    fast_charge_current_ma,
+                    termination_mv,
+                }),
+                "{temp}"
+            );
+        }
+    }
+
+    #[test]
+    fn apply_derating_writes_only_changes() {
+        let mut expectations = derating_writes(0x1e, 0x50);
+        expectations.extend(derating_writes(0x14, 0x46));
+        expectations.extend(derating_writes(0x1e, 0x50));
+        let bands = run_variant(Variant::Max77975, &expectations, async |charger| {
+            let mut bands = Vec::new();
+            // Back below the breakpoint but within the hysteresis stays hot, without writes
+            for temp in [250, 300, 451, 460, 440, 430] {
+                bands.push(charger.apply_derating(temp, &TABLE, &PROFILE).await?.band);
+            }
+            Ok::<_, Error<embedded_hal_async::i2c::ErrorKind>>(bands)
+        });
+        assert_eq!(bands, Ok(Vec::from([1, 1, 2, 2, 2, 1])));
+    }
+
+    #[test]
+    fn forget_derating_writes_again() {
+        let mut expectations = derating_writes(0x1e, 0x50);
+        expectations.extend(derating_writes(0x1e, 0x50));
+        let res = run_variant(Variant::Max77975, &expectations, async |charger| {
+            charger.apply_derating(250, &TABLE, &PROFILE).await?;
+            charger.forget_derating();
+            charger.apply_derating(250, &TABLE, &PROFILE).await
+        });
+        assert_eq!(res.map(|derating| derating.band), Ok(1));
+    }
+
+    #[test]
+    fn apply_derating_rejects_bad_tables() {
+        let empty = DeratingTable {
+            bands: &[],
+            hysteresis_deci_c: 0,
+        };
+        let low = [DeratingBand {
+            max_deci_c: 0,
+            max_fast_charge_ma: 1000,
+            max_termination_mv: 3000,
+        }];
+        let low = DeratingTable {
+            bands: &low,
+            hysteresis_deci_c: 0,
+        };
+        for table in [empty, low] {
+            let res = run_variant(Variant::Max77975, &[], async |charger| {
+                charger.apply_derating(250, &table, &PROFILE).await
+            });
+            assert_eq!(res, Err(Error::InvalidValue));
+        }
+    }
+}
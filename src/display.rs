@@ -0,0 +1,160 @@
+//! Human-readable [`Display`] implementations for the status types.
+
+use core::fmt::{self, Debug, Display, Formatter};
+
+use crate::{
+    BatteryDetails, BypassNodeDetails, ChargerDetails, ChargerInterrupts, ChargerStatus, ChgIn,
+    Details, TemperatureRegulation, ThermistorDetails,
+};
+
+/// Write the names of the set flags as a bracketed, comma-separated list.
+fn write_flags(f: &mut Formatter<'_>, flags: &[(bool, &str)]) -> fmt::Result {
+    f.write_str("[")?;
+    let mut first = true;
+    for &(set, name) in flags {
+        if set {
+            if !first {
+                f.write_str(",")?;
+            }
+            f.write_str(name)?;
+            first = false;
+        }
+    }
+    f.write_str("]")
+}
+
+impl Display for ChgIn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl Display for ChargerDetails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl Display for BatteryDetails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl Display for ThermistorDetails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl Display for TemperatureRegulation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TemperatureRegulation::BelowThreshold => "OK",
+            TemperatureRegulation::AboveThreshold => "Regulating",
+        })
+    }
+}
+
+impl Display for BypassNodeDetails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_flags(
+            f,
+            &[
+                (self.otg_current_limit(), "otg_ocp"),
+                (self.boost_current_limit(), "boost_ocp"),
+                (self.buck_current_limit(), "buck_ocp"),
+                (self.boost_on(), "boost_on"),
+            ],
+        )
+    }
+}
+
+impl Display for ChargerInterrupts {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_flags(
+            f,
+            &[
+                (self.bypass_node(), "byp"),
+                (self.disqbat(), "disqbat"),
+                (self.battery(), "bat"),
+                (self.charger(), "chg"),
+                (self.input_current_limit(), "inlim"),
+                (self.chgin(), "chgin"),
+                (self.adaptive_input_current_loop(), "aicl"),
+            ],
+        )
+    }
+}
+
+impl Display for ChargerStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_flags(
+            f,
+            &[
+                (self.bypass_ok(), "byp"),
+                (self.disqbat_ok(), "disqbat"),
+                (self.battery_ok(), "bat"),
+                (self.charger_ok(), "chg"),
+                (self.input_current_limit_ok(), "inlim"),
+                (self.chgin_ok(), "chgin"),
+                (self.aicl_ok(), "aicl"),
+            ],
+        )
+    }
+}
+
+impl Display for Details {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "chgin={} chg={} bat={} thm={} temp={} byp={}",
+            self.chgin(),
+            self.charger(),
+            self.battery(),
+            self.thermistor(),
+            self.temp(),
+            self.bypass()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::format;
+
+    use super::*;
+
+    #[test]
+    fn details_from_raw_bytes() {
+        for (bytes, expected) in [
+            (
+                [0x60, 0x31, 0x21],
+                "chgin=Valid chg=ConstantCurrent bat=RegularVoltage thm=Normal temp=OK byp=[otg_ocp]",
+            ),
+            (
+                [0x00, 0x87, 0x5e],
+                "chgin=Undervoltage chg=QBattDisabled bat=BatteryRemoved thm=Removed temp=Regulating byp=[boost_ocp,buck_ocp,boost_on]",
+            ),
+            (
+                [0x40, 0x74, 0x70],
+                "chgin=Overvoltage chg=Done bat=BatteryOnly thm=Reserved temp=OK byp=[]",
+            ),
+        ] {
+            assert_eq!(format!("{}", Details::from_bytes(bytes)), expected);
+        }
+    }
+
+    #[test]
+    fn flags_from_raw_bytes() {
+        assert_eq!(
+            format!("{}", ChargerInterrupts::from_bytes([0x41])),
+            "[byp,chgin]"
+        );
+        assert_eq!(format!("{}", ChargerInterrupts::from_bytes([0x00])), "[]");
+        assert_eq!(
+            format!("{}", ChargerStatus::from_bytes([0xff])),
+            "[byp,disqbat,bat,chg,inlim,chgin,aicl]"
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::{ErrorType, I2c};
+
+use crate::{
+    BatteryDetails, BypassNodeDetails, Charger, ChargerDetails, ChargerEvent, ChgIn, Error,
+    FaultKind,
+};
+
+/// The most events a single interrupt pass can produce
+const CAPACITY: usize = 10;
+
+/// A typed charger event, as produced by [`ChargerEvents`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Event {
+    /// A valid input source was attached to CHGIN
+    InputInserted,
+    /// The CHGIN input is no longer valid
+    InputRemoved,
+    /// Charging completed
+    ChargeDone,
+    /// The charger entered a new non-fault state
+    ChargerState(ChargerDetails),
+    /// The battery status changed
+    Battery(BatteryDetails),
+    /// The bypass node status changed
+    Bypass(BypassNodeDetails),
+    /// The input current limit status changed
+    InputCurrentLimit,
+    /// The adaptive input current loop status changed
+    AdaptiveInputCurrentLoop,
+    /// The QBATT switch status changed
+    QBattSwitch,
+    /// A fault was reported
+    Fault(FaultKind),
+}
+
+impl ChargerEvent {
+    /// Decode the events reported by this interrupt pass, in a fixed order: TOP faults, CHGIN,
+    /// charger, battery, bypass, QBATT, input current limit and finally AICL.
+    pub fn events(&self) -> impl Iterator<Item = Event> {
+        let mut queue = Queue::new();
+        let top = self.top;
+        if top.thermal_shutdown() {
+            queue.push(Event::Fault(FaultKind::ThermalShutdown));
+        }
+        if top.sys_overvoltage() {
+            queue.push(Event::Fault(FaultKind::SysOvervoltage));
+        }
+        if top.sys_undervoltage() {
+            queue.push(Event::Fault(FaultKind::SysUndervoltage));
+        }
+
+        let irqs = self.charger;
+        let details = self.details;
+        if irqs.chgin() {
+            queue.push(match details.chgin() {
+                ChgIn::Valid => Event::InputInserted,
+                ChgIn::Overvoltage => Event::Fault(FaultKind::InputOvervoltage),
+                ChgIn::Undervoltage | ChgIn::BelowBatt => Event::InputRemoved,
+            });
+        }
+        if irqs.charger() {
+            queue.push(match details.charger() {
+                ChargerDetails::Done => Event::ChargeDone,
+                ChargerDetails::TimerFault => Event::Fault(FaultKind::Timer),
+                ChargerDetails::WatchdogTimer => Event::Fault(FaultKind::Watchdog),
+                ChargerDetails::HighTemperature => Event::Fault(FaultKind::OverTemperature),
+                ChargerDetails::ThermistorRemoval => Event::Fault(FaultKind::ThermistorRemoved),
+                state => Event::ChargerState(state),
+            });
+        }
+        if irqs.battery() {
+            queue.push(match details.battery() {
+                BatteryDetails::Overvoltage => Event::Fault(FaultKind::BatteryOvervoltage),
+                battery => Event::Battery(battery),
+            });
+        }
+        if irqs.bypass_node() {
+            queue.push(Event::Bypass(details.bypass()));
+        }
+        if irqs.disqbat() {
+            queue.push(Event::QBattSwitch);
+        }
+        if irqs.input_current_limit() {
+            queue.push(Event::InputCurrentLimit);
+        }
+        if irqs.adaptive_input_current_loop() {
+            queue.push(Event::AdaptiveInputCurrentLoop);
+        }
+        queue
+    }
+}
+
+/// A fixed-capacity FIFO of decoded events
+struct Queue {
+    events: [Option<Event>; CAPACITY],
+    head: usize,
+    len: usize,
+}
+
+impl Queue {
+    const fn new() -> Self {
+        Queue {
+            events: [None; CAPACITY],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, event: Event) {
+        if self.len < CAPACITY {
+            if let Some(slot) = self
+                .events
+                .get_mut(self.head.wrapping_add(self.len) % CAPACITY)
+            {
+                *slot = Some(event);
+                self.len = self.len.saturating_add(1);
+            }
+        }
+    }
+}
+
+impl Iterator for Queue {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events.get_mut(self.head).and_then(Option::take);
+        self.head = self.head.wrapping_add(1) % CAPACITY;
+        self.len = self.len.saturating_sub(1);
+        event
+    }
+}
+
+/// A source of typed charger events driven by the nIRQ pin.
+///
+/// Owns the [`Charger`] and its interrupt pin. Every interrupt pass reads and clears all pending
+/// flags and may produce several events; these are buffered and returned one at a time by
+/// [`ChargerEvents::next_event`], so no event is lost when several sources fire together.
+pub struct ChargerEvents<D: ErrorType, P> {
+    charger: Charger<D>,
+    irq_pin: P,
+    pending: Queue,
+}
+
+impl<D: I2c, P: Wait> ChargerEvents<D, P> {
+    /// Create a new event source from a charger and its nIRQ pin.
+    ///
+    /// The interrupt sources of interest must be enabled with [`Charger::set_charger_irq_mask`]
+    /// and [`Charger::set_top_irq_mask`].
+    pub fn new(charger: Charger<D>, irq_pin: P) -> Self {
+        ChargerEvents {
+            charger,
+            irq_pin,
+            pending: Queue::new(),
+        }
+    }
+
+    /// Wait for the next charger event.
+    pub async fn next_event(&mut self) -> Result<Event, Error<D::Error>> {
+        loop {
+            if let Some(event) = self.pending.next() {
+                return Ok(event);
+            }
+            let event = self.charger.wait_for_event(&mut self.irq_pin).await?;
+            for event in event.events() {
+                self.pending.push(event);
+            }
+        }
+    }
+
+    /// Access the underlying charger.
+    pub fn charger(&mut self) -> &mut Charger<D> {
+        &mut self.charger
+    }
+
+    /// Release the charger and the interrupt pin.
+    ///
+    /// Any buffered events are discarded.
+    pub fn release(self) -> (Charger<D>, P) {
+        (self.charger, self.irq_pin)
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use core::time::Duration;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::sim::{SimIrqPin, SimulatedCharger, Simulator};
+    use crate::testing::block_on;
+    use crate::{ChargerConfig3, ChargerInterrupts, Reg, TopInterrupts, Variant};
+
+    type SimEvents<'a> = ChargerEvents<SimulatedCharger<'a>, SimIrqPin<'a>>;
+
+    /// Events from a simulated charger with every interrupt source enabled
+    fn events(sim: &Simulator) -> SimEvents<'_> {
+        let mut charger = Charger::new(sim.device());
+        block_on(async {
+            charger
+                .set_charger_irq_mask(ChargerInterrupts::from_bytes([0xff]))
+                .await?;
+            charger
+                .set_top_irq_mask(TopInterrupts::from_bytes([0xff]))
+                .await?;
+            charger.irq_summary().await
+        })
+        .expect("simulated bus failed");
+        ChargerEvents::new(charger, sim.irq_pin())
+    }
+
+    /// Take the events of every interrupt pass pending now
+    fn drain(sim: &Simulator, events: &mut SimEvents<'_>) -> Vec<Event> {
+        let mut out = Vec::new();
+        while sim.irq_asserted() || events.pending.len > 0 {
+            out.push(block_on(events.next_event()).expect("simulated bus failed"));
+        }
+        out
+    }
+
+    #[test]
+    fn insertion_yields_coalesced_events_in_order() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut events = events(&sim);
+        sim.plug_charger();
+        assert_eq!(
+            drain(&sim, &mut events),
+            [
+                Event::InputInserted,
+                Event::ChargerState(ChargerDetails::ConstantCurrent),
+                Event::Battery(BatteryDetails::RegularVoltage),
+            ]
+        );
+
+        sim.unplug_charger();
+        let removed = drain(&sim, &mut events);
+        assert_eq!(removed.first(), Some(&Event::InputRemoved));
+    }
+
+    #[test]
+    fn charge_cycle_ends_with_charge_done() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut events = events(&sim);
+        // A 10 minute top-off, so the top-off state outlasts a step
+        block_on(
+            events
+                .charger()
+                .modify(|val: ChargerConfig3| val.with_to_time(1)),
+        )
+        .unwrap();
+        sim.plug_charger();
+        let mut seen = drain(&sim, &mut events);
+        for _ in 0..24 * 60 {
+            if seen.contains(&Event::ChargeDone) {
+                break;
+            }
+            sim.step(Duration::from_secs(60));
+            seen.extend(drain(&sim, &mut events));
+        }
+        let states: Vec<Event> = seen
+            .into_iter()
+            .filter(|event| matches!(event, Event::ChargerState(_) | Event::ChargeDone))
+            .collect();
+        assert_eq!(
+            states,
+            [
+                Event::ChargerState(ChargerDetails::ConstantCurrent),
+                Event::ChargerState(ChargerDetails::ConstantVoltage),
+                Event::ChargerState(ChargerDetails::TopOff),
+                Event::ChargeDone,
+            ]
+        );
+    }
+
+    #[test]
+    fn canned_flags_decode_in_fixed_order() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut events = events(&sim);
+        sim.set_register(Reg::CHARGER_DETAILS_0.address(), 0x60);
+        sim.set_register(
+            Reg::CHARGER_DETAILS_1.address(),
+            ChargerDetails::Done.code(),
+        );
+        sim.set_register(
+            Reg::TOP_INTERRUPT.address(),
+            TopInterrupts::new()
+                .with_thermal_shutdown(true)
+                .into_bytes()[0],
+        );
+        sim.set_register(
+            Reg::CHARGER_INTERRUPT.address(),
+            ChargerInterrupts::new()
+                .with_adaptive_input_current_loop(true)
+                .with_input_current_limit(true)
+                .with_charger(true)
+                .with_chgin(true)
+                .into_bytes()[0],
+        );
+        assert_eq!(
+            drain(&sim, &mut events),
+            [
+                Event::Fault(FaultKind::ThermalShutdown),
+                Event::InputInserted,
+                Event::ChargeDone,
+                Event::InputCurrentLimit,
+                Event::AdaptiveInputCurrentLoop,
+            ]
+        );
+    }
+
+    #[test]
+    fn timer_fault_is_reported() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut events = events(&sim);
+        sim.plug_charger();
+        drain(&sim, &mut events);
+        sim.trigger_timer_fault();
+        assert!(drain(&sim, &mut events).contains(&Event::Fault(FaultKind::Timer)));
+    }
+}
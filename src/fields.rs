@@ -0,0 +1,543 @@
+//! Field layouts of the registers, for rendering register dumps.
+
+use core::ops::Range;
+
+use crate::Reg;
+
+/// A register field as `(mnemonic, first bit, bit count)`
+type Field = (&'static str, u8, u8);
+
+const TOP_INT: &[Field] = &[("TSHDN_I", 2, 1), ("SYSOVLO_I", 3, 1), ("SYSUVLO_I", 4, 1)];
+const TOP_INT_MASK: &[Field] = &[("TSHDN_M", 2, 1), ("SYSOVLO_M", 3, 1), ("SYSUVLO_M", 4, 1)];
+const CHG_INT: &[Field] = &[
+    ("BYP_I", 0, 1),
+    ("DISQBAT_I", 1, 1),
+    ("BAT_I", 3, 1),
+    ("CHG_I", 4, 1),
+    ("INLIM_I", 5, 1),
+    ("CHGIN_I", 6, 1),
+    ("AICL_I", 7, 1),
+];
+const CHG_INT_MASK: &[Field] = &[
+    ("BYP_M", 0, 1),
+    ("DISQBAT_M", 1, 1),
+    ("BAT_M", 3, 1),
+    ("CHG_M", 4, 1),
+    ("INLIM_M", 5, 1),
+    ("CHGIN_M", 6, 1),
+    ("AICL_M", 7, 1),
+];
+const CHG_INT_OK: &[Field] = &[
+    ("BYP_OK", 0, 1),
+    ("DISQBAT_OK", 1, 1),
+    ("BAT_OK", 3, 1),
+    ("CHG_OK", 4, 1),
+    ("INLIM_OK", 5, 1),
+    ("CHGIN_OK", 6, 1),
+    ("AICL_OK", 7, 1),
+];
+const CHG_DETAILS_00: &[Field] = &[("BATSNS_DTLS", 1, 2), ("CHGIN_DTLS", 5, 2)];
+const CHG_DETAILS_01: &[Field] = &[("CHG_DTLS", 0, 4), ("BAT_DTLS", 4, 3), ("TREG", 7, 1)];
+const CHG_DETAILS_02: &[Field] = &[("BYP_DTLS", 0, 4), ("THM_DTLS", 4, 3)];
+const CHG_CNFG_00: &[Field] = &[
+    ("MODE", 0, 4),
+    ("WDTEN", 4, 1),
+    ("STBY_EN", 5, 1),
+    ("DISIBS", 6, 1),
+    ("WDT_ACT", 7, 1),
+];
+const CHG_CNFG_01: &[Field] = &[("FCHGTIME", 0, 3), ("CHG_RSTRT", 4, 2), ("PQEN", 7, 1)];
+const CHG_CNFG_02: &[Field] = &[("CHG_CC", 0, 7)];
+const CHG_CNFG_03: &[Field] = &[("TO_ITH", 0, 3), ("TO_TIME", 3, 3)];
+const CHG_CNFG_04: &[Field] = &[("CHG_CV_PRM", 0, 7)];
+const CHG_CNFG_05: &[Field] = &[("SYS_ILIM", 0, 4), ("RECYCLE_EN", 4, 1), ("ITRICKLE", 5, 2)];
+const CHG_CNFG_06: &[Field] = &[("WDTCLR", 0, 2), ("CHGPROT", 2, 2)];
+const CHG_CNFG_07: &[Field] = &[("T1", 0, 2), ("T2", 2, 2), ("T3", 4, 2), ("T4", 6, 2)];
+const CHG_CNFG_08: &[Field] = &[
+    ("CHG_CC_COOL", 0, 2),
+    ("CHG_CC_WARM", 2, 2),
+    ("CHG_CV_COOL", 4, 2),
+    ("CHG_CV_WARM", 6, 2),
+];
+const CHG_CNFG_09: &[Field] = &[("CHGIN_ILIM", 0, 6)];
+const CHG_CNFG_10: &[Field] = &[("VCHGIN_REG", 0, 6), ("REGTEMP", 6, 2)];
+const CHG_CNFG_11: &[Field] = &[("VBYPSET", 0, 7)];
+const CHG_CNFG_12: &[Field] = &[("OTG_ILIM", 4, 3)];
+const CHG_CNFG_13: &[Field] = &[
+    ("JEITA_EN", 0, 1),
+    ("THM_EN", 1, 1),
+    ("FGSRC", 2, 1),
+    ("SPSN_DET_EN", 3, 1),
+];
+const STAT_CNFG: &[Field] = &[("STAT_EN", 0, 1)];
+
+impl Reg {
+    /// The documented fields of the register as `(mnemonic, bit range)` pairs, from the least
+    /// significant bit up.
+    ///
+    /// The ID, reset and control registers have no documented fields.
+    pub fn fields(self) -> impl Iterator<Item = (&'static str, Range<u8>)> {
+        self.field_table()
+            .iter()
+            .map(|&(name, first, len)| (name, first..first.saturating_add(len)))
+    }
+
+    const fn field_table(self) -> &'static [Field] {
+        match self {
+            Reg::TOP_INTERRUPT => TOP_INT,
+            Reg::TOP_INTERRUPT_MASK => TOP_INT_MASK,
+            Reg::CHARGER_INTERRUPT => CHG_INT,
+            Reg::CHARGER_INTERRUPT_MASK => CHG_INT_MASK,
+            Reg::CHARGER_INTERRUPT_STATUS => CHG_INT_OK,
+            Reg::CHARGER_DETAILS_0 => CHG_DETAILS_00,
+            Reg::CHARGER_DETAILS_1 => CHG_DETAILS_01,
+            Reg::CHARGER_DETAILS_2 => CHG_DETAILS_02,
+            Reg::CHARGER_CONFIG_0 => CHG_CNFG_00,
+            Reg::CHARGER_CONFIG_1 => CHG_CNFG_01,
+            Reg::CHARGER_CONFIG_2 => CHG_CNFG_02,
+            Reg::CHARGER_CONFIG_3 => CHG_CNFG_03,
+            Reg::CHARGER_CONFIG_4 => CHG_CNFG_04,
+            Reg::CHARGER_CONFIG_5 => CHG_CNFG_05,
+            Reg::CHARGER_CONFIG_6 => CHG_CNFG_06,
+            Reg::CHARGER_CONFIG_7 => CHG_CNFG_07,
+            Reg::CHARGER_CONFIG_8 => CHG_CNFG_08,
+            Reg::CHARGER_CONFIG_9 => CHG_CNFG_09,
+            Reg::CHARGER_CONFIG_10 => CHG_CNFG_10,
+            Reg::CHARGER_CONFIG_11 => CHG_CNFG_11,
+            Reg::CHARGER_CONFIG_12 => CHG_CNFG_12,
+            Reg::CHARGER_CONFIG_13 => CHG_CNFG_13,
+            Reg::STATUS_LED_CONFIG => STAT_CNFG,
+            _ => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BatteryDetails, BatterySense, BypassNodeDetails, ChargerConfig0, ChargerConfig1,
+        ChargerConfig10, ChargerConfig11, ChargerConfig12, ChargerConfig13, ChargerConfig2,
+        ChargerConfig3, ChargerConfig4, ChargerConfig5, ChargerConfig6, ChargerConfig7,
+        ChargerConfig8, ChargerConfig9, ChargerDetails, ChargerInterrupts, ChargerStatus, ChgIn,
+        ColdThreshold, CoolThreshold, Details, FastChargeTimer, HotThreshold, JeitaCurrent,
+        JeitaVoltage, RechargeThreshold, StatusLedConfig, TemperatureRegulation, ThermistorDetails,
+        TopInterrupts, WarmThreshold, WatchdogAction,
+    };
+
+    /// The bits of the field `name` of `reg`
+    fn mask(reg: Reg, name: &str) -> Option<u8> {
+        let (_, bits) = reg.fields().find(|(field, _)| *field == name)?;
+        Some(bits.fold(0, |mask, bit| {
+            mask | 1u8.checked_shl(bit.into()).unwrap_or(0)
+        }))
+    }
+
+    #[test]
+    fn fields_match_the_register_structs() {
+        // Each struct with a single field set to all ones
+        let details = |details: Details| details.into_bytes();
+        let top = TopInterrupts::new();
+        let chg = ChargerInterrupts::new();
+        let ok = ChargerStatus::new();
+        let cases = [
+            (
+                Reg::TOP_INTERRUPT,
+                "TSHDN_I",
+                top.with_thermal_shutdown(true).into_bytes()[0],
+            ),
+            (
+                Reg::TOP_INTERRUPT,
+                "SYSOVLO_I",
+                top.with_sys_overvoltage(true).into_bytes()[0],
+            ),
+            (
+                Reg::TOP_INTERRUPT,
+                "SYSUVLO_I",
+                top.with_sys_undervoltage(true).into_bytes()[0],
+            ),
+            (
+                Reg::TOP_INTERRUPT_MASK,
+                "TSHDN_M",
+                top.with_thermal_shutdown(true).into_bytes()[0],
+            ),
+            (
+                Reg::TOP_INTERRUPT_MASK,
+                "SYSOVLO_M",
+                top.with_sys_overvoltage(true).into_bytes()[0],
+            ),
+            (
+                Reg::TOP_INTERRUPT_MASK,
+                "SYSUVLO_M",
+                top.with_sys_undervoltage(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT,
+                "BYP_I",
+                chg.with_bypass_node(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT,
+                "DISQBAT_I",
+                chg.with_disqbat(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT,
+                "BAT_I",
+                chg.with_battery(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT,
+                "CHG_I",
+                chg.with_charger(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT,
+                "INLIM_I",
+                chg.with_input_current_limit(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT,
+                "CHGIN_I",
+                chg.with_chgin(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT,
+                "AICL_I",
+                chg.with_adaptive_input_current_loop(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_MASK,
+                "BYP_M",
+                chg.with_bypass_node(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_MASK,
+                "DISQBAT_M",
+                chg.with_disqbat(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_MASK,
+                "BAT_M",
+                chg.with_battery(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_MASK,
+                "CHG_M",
+                chg.with_charger(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_MASK,
+                "INLIM_M",
+                chg.with_input_current_limit(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_MASK,
+                "CHGIN_M",
+                chg.with_chgin(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_MASK,
+                "AICL_M",
+                chg.with_adaptive_input_current_loop(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_STATUS,
+                "BYP_OK",
+                ok.with_bypass_ok(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_STATUS,
+                "DISQBAT_OK",
+                ok.with_disqbat_ok(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_STATUS,
+                "BAT_OK",
+                ok.with_battery_ok(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_STATUS,
+                "CHG_OK",
+                ok.with_charger_ok(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_STATUS,
+                "INLIM_OK",
+                ok.with_input_current_limit_ok(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_STATUS,
+                "CHGIN_OK",
+                ok.with_chgin_ok(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_INTERRUPT_STATUS,
+                "AICL_OK",
+                ok.with_aicl_ok(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_DETAILS_0,
+                "BATSNS_DTLS",
+                details(Details::new().with_sense(BatterySense::BothOpen))[0],
+            ),
+            (
+                Reg::CHARGER_DETAILS_0,
+                "CHGIN_DTLS",
+                details(Details::new().with_chgin(ChgIn::Valid))[0],
+            ),
+            (
+                Reg::CHARGER_DETAILS_1,
+                "CHG_DTLS",
+                details(Details::new().with_charger(ChargerDetails::Reserved0F))[1],
+            ),
+            (
+                Reg::CHARGER_DETAILS_1,
+                "BAT_DTLS",
+                details(Details::new().with_battery(BatteryDetails::BatteryOnly))[1],
+            ),
+            (
+                Reg::CHARGER_DETAILS_1,
+                "TREG",
+                details(Details::new().with_temp(TemperatureRegulation::AboveThreshold))[1],
+            ),
+            (
+                Reg::CHARGER_DETAILS_2,
+                "BYP_DTLS",
+                details(
+                    Details::new().with_bypass(
+                        BypassNodeDetails::new()
+                            .with_otg_current_limit(true)
+                            .with_boost_current_limit(true)
+                            .with_buck_current_limit(true)
+                            .with_boost_on(true),
+                    ),
+                )[2],
+            ),
+            (
+                Reg::CHARGER_DETAILS_2,
+                "THM_DTLS",
+                details(Details::new().with_thermistor(ThermistorDetails::Reserved))[2],
+            ),
+            (
+                Reg::CHARGER_CONFIG_0,
+                "MODE",
+                ChargerConfig0::new().with_mode_code(0x0f).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_0,
+                "WDTEN",
+                ChargerConfig0::new()
+                    .with_watchdog_enable(true)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_0,
+                "STBY_EN",
+                ChargerConfig0::new().with_standby_enable(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_0,
+                "DISIBS",
+                ChargerConfig0::new().with_disibs(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_0,
+                "WDT_ACT",
+                ChargerConfig0::new()
+                    .with_watchdog_action(WatchdogAction::SystemOff)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_1,
+                "FCHGTIME",
+                ChargerConfig1::new()
+                    .with_fast_charge_timer(FastChargeTimer::Hours10)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_1,
+                "CHG_RSTRT",
+                ChargerConfig1::new()
+                    .with_recharge(RechargeThreshold::Disabled)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_1,
+                "PQEN",
+                ChargerConfig1::new()
+                    .with_prequalification_enable(true)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_2,
+                "CHG_CC",
+                ChargerConfig2::new().with_chg_cc(0x7f).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_3,
+                "TO_ITH",
+                ChargerConfig3::new().with_to_ith(0x07).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_3,
+                "TO_TIME",
+                ChargerConfig3::new().with_to_time(0x07).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_4,
+                "CHG_CV_PRM",
+                ChargerConfig4::new().with_chg_cv_prm(0x7f).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_5,
+                "SYS_ILIM",
+                ChargerConfig5::new().with_sys_ilim(0x0f).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_5,
+                "RECYCLE_EN",
+                ChargerConfig5::new().with_recycle_enable(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_5,
+                "ITRICKLE",
+                ChargerConfig5::new().with_itrickle(0x03).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_6,
+                "WDTCLR",
+                ChargerConfig6::new().with_wdtclr(0x03).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_6,
+                "CHGPROT",
+                ChargerConfig6::new().with_chgprot(0x03).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_7,
+                "T1",
+                ChargerConfig7::new()
+                    .with_cold(ColdThreshold::Plus5C)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_7,
+                "T2",
+                ChargerConfig7::new()
+                    .with_cool(CoolThreshold::Plus15C)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_7,
+                "T3",
+                ChargerConfig7::new()
+                    .with_warm(WarmThreshold::Plus50C)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_7,
+                "T4",
+                ChargerConfig7::new()
+                    .with_hot(HotThreshold::Plus60C)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_8,
+                "CHG_CC_COOL",
+                ChargerConfig8::new()
+                    .with_cool_current(JeitaCurrent::Percent20)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_8,
+                "CHG_CC_WARM",
+                ChargerConfig8::new()
+                    .with_warm_current(JeitaCurrent::Percent20)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_8,
+                "CHG_CV_COOL",
+                ChargerConfig8::new()
+                    .with_cool_voltage(JeitaVoltage::Mv200)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_8,
+                "CHG_CV_WARM",
+                ChargerConfig8::new()
+                    .with_warm_voltage(JeitaVoltage::Mv200)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_9,
+                "CHGIN_ILIM",
+                ChargerConfig9::new().with_chgin_ilim(0x3f).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_10,
+                "VCHGIN_REG",
+                ChargerConfig10::new().with_vchgin_reg(0x3f).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_10,
+                "REGTEMP",
+                ChargerConfig10::new().with_regtemp(0x03).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_11,
+                "VBYPSET",
+                ChargerConfig11::new().with_vbypset(0x7f).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_12,
+                "OTG_ILIM",
+                ChargerConfig12::new().with_otg_ilim(0x07).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_13,
+                "JEITA_EN",
+                ChargerConfig13::new().with_jeita_enable(true).into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_13,
+                "THM_EN",
+                ChargerConfig13::new()
+                    .with_thermistor_enable(true)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_13,
+                "FGSRC",
+                ChargerConfig13::new()
+                    .with_fuel_gauge_bias(true)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::CHARGER_CONFIG_13,
+                "SPSN_DET_EN",
+                ChargerConfig13::new()
+                    .with_spsn_detect_enable(true)
+                    .into_bytes()[0],
+            ),
+            (
+                Reg::STATUS_LED_CONFIG,
+                "STAT_EN",
+                StatusLedConfig::new().with_enable(true).into_bytes()[0],
+            ),
+        ];
+        for (reg, name, bits) in cases {
+            assert_eq!(mask(reg, name), Some(bits), "{reg:?} {name}");
+        }
+        // Every documented field is covered
+        for reg in Reg::ALL {
+            for (name, _) in reg.fields() {
+                assert!(
+                    cases.iter().any(|&(r, n, _)| r == reg && n == name),
+                    "{reg:?} {name}"
+                );
+            }
+        }
+    }
+}
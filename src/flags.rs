@@ -0,0 +1,319 @@
+//! Bit operations and named sources for the interrupt flag types.
+
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+use crate::{ChargerInterrupts, TopInterrupts};
+
+macro_rules! flag_ops {
+    ($ty:ident, $valid:expr) => {
+        impl $ty {
+            /// All sources set
+            pub const ALL: $ty = $ty::from_bits($valid);
+            /// No sources set
+            pub const NONE: $ty = $ty::from_bits(0);
+
+            /// Create the flags from the raw register value, dropping reserved bits.
+            pub const fn from_bits(bits: u8) -> Self {
+                Self::from_bytes([bits & $valid])
+            }
+
+            /// The raw register value
+            pub const fn bits(self) -> u8 {
+                self.into_bytes()[0]
+            }
+
+            /// The sources set in either `self` or `other`
+            pub const fn union(self, other: Self) -> Self {
+                Self::from_bits(self.bits() | other.bits())
+            }
+
+            /// The sources set in both `self` and `other`
+            pub const fn intersection(self, other: Self) -> Self {
+                Self::from_bits(self.bits() & other.bits())
+            }
+
+            /// Whether no source is set
+            pub const fn is_empty(self) -> bool {
+                self.bits() == 0
+            }
+        }
+
+        impl BitOr for $ty {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                self.union(rhs)
+            }
+        }
+
+        impl BitOrAssign for $ty {
+            fn bitor_assign(&mut self, rhs: Self) {
+                *self = self.union(rhs);
+            }
+        }
+
+        impl BitAnd for $ty {
+            type Output = Self;
+
+            fn bitand(self, rhs: Self) -> Self {
+                self.intersection(rhs)
+            }
+        }
+
+        impl BitAndAssign for $ty {
+            fn bitand_assign(&mut self, rhs: Self) {
+                *self = self.intersection(rhs);
+            }
+        }
+
+        impl Not for $ty {
+            type Output = Self;
+
+            fn not(self) -> Self {
+                Self::from_bits(!self.bits())
+            }
+        }
+    };
+}
+
+flag_ops!(ChargerInterrupts, 0xfb);
+flag_ops!(TopInterrupts, 0x1c);
+
+macro_rules! flag_sources {
+    ($ty:ident, $source:ident) => {
+        impl $source {
+            /// The register bit of this source
+            pub const fn bit(self) -> u8 {
+                1 << self as u8
+            }
+        }
+
+        impl $ty {
+            /// Create the flags with `sources` set.
+            pub const fn from_sources(sources: &[$source]) -> Self {
+                let mut bits = 0;
+                let mut rest = sources;
+                while let [source, tail @ ..] = rest {
+                    bits |= source.bit();
+                    rest = tail;
+                }
+                Self::from_bits(bits)
+            }
+
+            /// Whether `source` is set
+            pub const fn contains(self, source: $source) -> bool {
+                self.bits() & source.bit() != 0
+            }
+
+            /// The sources that are set, in register bit order
+            pub fn iter_set(self) -> impl Iterator<Item = $source> {
+                $source::ALL
+                    .into_iter()
+                    .filter(move |&source| self.contains(source))
+            }
+        }
+    };
+}
+
+/// A charger interrupt source, named by its bit in `CHARGER_INTERRUPT`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChargerIrqSource {
+    /// [`ChargerInterrupts::bypass_node`]
+    BypassNode = 0,
+    /// [`ChargerInterrupts::disqbat`]
+    Disqbat = 1,
+    /// [`ChargerInterrupts::battery`]
+    Battery = 3,
+    /// [`ChargerInterrupts::charger`]
+    Charger = 4,
+    /// [`ChargerInterrupts::input_current_limit`]
+    InputCurrentLimit = 5,
+    /// [`ChargerInterrupts::chgin`]
+    Chgin = 6,
+    /// [`ChargerInterrupts::adaptive_input_current_loop`]
+    AdaptiveInputCurrentLoop = 7,
+}
+
+impl ChargerIrqSource {
+    /// All sources, in register bit order
+    pub const ALL: [ChargerIrqSource; 7] = [
+        ChargerIrqSource::BypassNode,
+        ChargerIrqSource::Disqbat,
+        ChargerIrqSource::Battery,
+        ChargerIrqSource::Charger,
+        ChargerIrqSource::InputCurrentLimit,
+        ChargerIrqSource::Chgin,
+        ChargerIrqSource::AdaptiveInputCurrentLoop,
+    ];
+
+    /// The datasheet name of the interrupt bit
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ChargerIrqSource::BypassNode => "BYP_I",
+            ChargerIrqSource::Disqbat => "DISQBAT_I",
+            ChargerIrqSource::Battery => "BAT_I",
+            ChargerIrqSource::Charger => "CHG_I",
+            ChargerIrqSource::InputCurrentLimit => "INLIM_I",
+            ChargerIrqSource::Chgin => "CHGIN_I",
+            ChargerIrqSource::AdaptiveInputCurrentLoop => "AICL_I",
+        }
+    }
+}
+
+/// A TOP interrupt source, named by its bit in `TOP_INTERRUPT`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TopIrqSource {
+    /// [`TopInterrupts::thermal_shutdown`]
+    ThermalShutdown = 2,
+    /// [`TopInterrupts::sys_overvoltage`]
+    SysOvervoltage = 3,
+    /// [`TopInterrupts::sys_undervoltage`]
+    SysUndervoltage = 4,
+}
+
+impl TopIrqSource {
+    /// All sources, in register bit order
+    pub const ALL: [TopIrqSource; 3] = [
+        TopIrqSource::ThermalShutdown,
+        TopIrqSource::SysOvervoltage,
+        TopIrqSource::SysUndervoltage,
+    ];
+
+    /// The datasheet name of the interrupt bit
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            TopIrqSource::ThermalShutdown => "TSHDN_I",
+            TopIrqSource::SysOvervoltage => "SYSOVLO_I",
+            TopIrqSource::SysUndervoltage => "SYSUVLO_I",
+        }
+    }
+}
+
+flag_sources!(ChargerInterrupts, ChargerIrqSource);
+flag_sources!(TopInterrupts, TopIrqSource);
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn constants_cover_exactly_the_sources() {
+        assert_eq!(ChargerInterrupts::ALL.bits(), 0xfb);
+        assert_eq!(ChargerInterrupts::NONE.bits(), 0x00);
+        assert_eq!(TopInterrupts::ALL.bits(), 0x1c);
+        assert_eq!(TopInterrupts::NONE.bits(), 0x00);
+        assert!(ChargerInterrupts::NONE.is_empty());
+        assert!(!TopInterrupts::ALL.is_empty());
+    }
+
+    #[test]
+    fn not_never_sets_a_reserved_bit() {
+        assert_eq!((!ChargerInterrupts::NONE).bits(), 0xfb);
+        assert_eq!((!ChargerInterrupts::ALL).bits(), 0x00);
+        assert_eq!((!TopInterrupts::NONE).bits(), 0x1c);
+        assert_eq!((!TopInterrupts::from_bits(0x04)).bits(), 0x18);
+    }
+
+    #[test]
+    fn operators_combine_sources() {
+        let chgin = ChargerInterrupts::from_bits(0x40);
+        let battery = ChargerInterrupts::from_bits(0x08);
+        let mut irqs = chgin | battery;
+        assert_eq!(irqs.bits(), 0x48);
+        assert_eq!((irqs & chgin).bits(), 0x40);
+        irqs &= !chgin;
+        assert_eq!(irqs, battery);
+        irqs |= chgin;
+        assert_eq!(irqs.bits(), 0x48);
+    }
+
+    #[test]
+    fn iter_set_names_the_charger_sources() {
+        // BYP_I, BAT_I and CHGIN_I, plus the reserved bit 2
+        let irqs = ChargerInterrupts::from_bits(0x4d);
+        let sources: std::vec::Vec<_> = irqs.iter_set().collect();
+        assert_eq!(
+            sources,
+            [
+                ChargerIrqSource::BypassNode,
+                ChargerIrqSource::Battery,
+                ChargerIrqSource::Chgin,
+            ]
+        );
+        let names: std::vec::Vec<_> = irqs.iter_set().map(ChargerIrqSource::as_str).collect();
+        assert_eq!(names, ["BYP_I", "BAT_I", "CHGIN_I"]);
+        assert!(irqs.contains(ChargerIrqSource::Battery));
+        assert!(!irqs.contains(ChargerIrqSource::Charger));
+        assert_eq!(ChargerInterrupts::from_sources(&sources), irqs);
+    }
+
+    #[test]
+    fn iter_set_names_the_top_sources() {
+        let irqs = TopInterrupts::from_bits(0xff);
+        let names: std::vec::Vec<_> = irqs.iter_set().map(TopIrqSource::as_str).collect();
+        assert_eq!(names, ["TSHDN_I", "SYSOVLO_I", "SYSUVLO_I"]);
+        assert_eq!(TopInterrupts::NONE.iter_set().count(), 0);
+        assert_eq!(
+            TopInterrupts::from_sources(&[TopIrqSource::SysUndervoltage]).bits(),
+            0x10
+        );
+    }
+
+    #[test]
+    fn sources_match_the_bitfield_accessors() {
+        for (i, source) in ChargerIrqSource::ALL.into_iter().enumerate() {
+            let irqs = ChargerInterrupts::from_sources(&[source]);
+            let set = [
+                irqs.bypass_node(),
+                irqs.disqbat(),
+                irqs.battery(),
+                irqs.charger(),
+                irqs.input_current_limit(),
+                irqs.chgin(),
+                irqs.adaptive_input_current_loop(),
+            ];
+            let expected: [bool; 7] = core::array::from_fn(|j| j == i);
+            assert_eq!(set, expected, "{source:?}");
+            assert_eq!(irqs.iter_set().collect::<std::vec::Vec<_>>(), [source]);
+        }
+        for (i, source) in TopIrqSource::ALL.into_iter().enumerate() {
+            let irqs = TopInterrupts::from_sources(&[source]);
+            let set = [
+                irqs.thermal_shutdown(),
+                irqs.sys_overvoltage(),
+                irqs.sys_undervoltage(),
+            ];
+            let expected: [bool; 3] = core::array::from_fn(|j| j == i);
+            assert_eq!(set, expected, "{source:?}");
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn reserved_bits_never_leak(a: u8, b: u8) {
+            for irqs in [
+                ChargerInterrupts::from_bits(a),
+                ChargerInterrupts::from_bits(a) | ChargerInterrupts::from_bits(b),
+                ChargerInterrupts::from_bits(a) & ChargerInterrupts::from_bits(b),
+                !ChargerInterrupts::from_bits(a),
+            ] {
+                prop_assert_eq!(irqs.bits() & !0xfb, 0);
+            }
+            for irqs in [
+                TopInterrupts::from_bits(a),
+                TopInterrupts::from_bits(a) | TopInterrupts::from_bits(b),
+                TopInterrupts::from_bits(a) & TopInterrupts::from_bits(b),
+                !TopInterrupts::from_bits(a),
+            ] {
+                prop_assert_eq!(irqs.bits() & !0x1c, 0);
+            }
+            prop_assert_eq!(ChargerInterrupts::from_bits(a).bits(), a & 0xfb);
+        }
+    }
+}
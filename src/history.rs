@@ -0,0 +1,256 @@
+//! A timestamped history of the charger state for fault analysis.
+//!
+//! A [`StatusHistory`] owned by the application is fed by [`Charger::record_status`] and keeps
+//! the last `N` distinct states in the [packed](Details::pack) format:
+//!
+//! ```no_run
+//! # use embedded_hal_async::delay::DelayNs;
+//! # use embedded_hal_async::i2c::I2c;
+//! # use max7797x_driver::{Charger, ChargerStatus, Details, Error, StatusHistory};
+//! # async fn example<D: I2c>(
+//! #     charger: &mut Charger<D>,
+//! #     delay: &mut impl DelayNs,
+//! #     now_ms: impl Fn() -> u64,
+//! #     fault_detected: impl Fn() -> bool,
+//! #     log: impl Fn(u64, Details, ChargerStatus),
+//! # ) -> Result<(), Error<D::Error>> {
+//! let mut history = StatusHistory::<64>::new();
+//! loop {
+//!     charger.record_status(&mut history, now_ms()).await?;
+//!     if fault_detected() {
+//!         for entry in history.iter() {
+//!             let (details, status) = entry.unpack();
+//!             log(entry.at_ms, details, status);
+//!         }
+//!     }
+//!     delay.delay_ms(500).await;
+//! }
+//! # }
+//! ```
+//!
+//! A state is only appended when it differs from the last entry, so a quiet period takes a
+//! single entry and the history spans as far back as the last `N` changes.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{Charger, ChargerStatus, Details, Error, Reg};
+
+/// A state recorded by [`StatusHistory`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatusEntry {
+    /// The timestamp of the poll that first observed the state in ms
+    pub at_ms: u64,
+    /// The state, packed with [`Details::pack`]
+    pub word: u32,
+}
+
+impl StatusEntry {
+    /// The details and status bits of the entry, see [`Details::unpack`]
+    pub fn unpack(&self) -> (Details, ChargerStatus) {
+        Details::unpack(self.word)
+    }
+}
+
+/// The last `N` distinct charger states recorded by [`Charger::record_status`]
+#[derive(Debug, Clone)]
+pub struct StatusHistory<const N: usize> {
+    entries: [StatusEntry; N],
+    /// The index of the oldest entry
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> Default for StatusHistory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> StatusHistory<N> {
+    /// An empty history.
+    pub const fn new() -> Self {
+        StatusHistory {
+            entries: [StatusEntry { at_ms: 0, word: 0 }; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// The number of recorded entries
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no entry is recorded
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The most recent entry
+    pub fn last(&self) -> Option<&StatusEntry> {
+        self.iter().next_back()
+    }
+
+    /// The recorded entries, oldest first
+    #[allow(clippy::indexing_slicing)] // slot() is always below N
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &StatusEntry> + ExactSizeIterator {
+        (0..self.len).map(move |i| &self.entries[self.slot(i)])
+    }
+
+    /// The index in `entries` of the `i`th oldest entry
+    fn slot(&self, i: usize) -> usize {
+        self.head.wrapping_add(i).checked_rem(N).unwrap_or(0)
+    }
+
+    /// Forget the recorded entries.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Append `word` observed at `now_ms` unless it equals the last entry, evicting the oldest
+    /// entry when full. Returns whether it was appended.
+    pub fn push(&mut self, word: u32, now_ms: u64) -> bool {
+        if N == 0 || self.last().is_some_and(|last| last.word == word) {
+            return false;
+        }
+        let entry = StatusEntry {
+            at_ms: now_ms,
+            word,
+        };
+        let full = self.len == N;
+        let slot = self.slot(self.len);
+        if let Some(slot) = self.entries.get_mut(slot) {
+            *slot = entry;
+        }
+        if full {
+            self.head = self.slot(1);
+        } else {
+            self.len = self.len.saturating_add(1);
+        }
+        true
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Sample the charger state and append it to `history`, stamped with `now_ms`, if it
+    /// changed since the last entry. Returns whether it was appended.
+    ///
+    /// `CHARGER_INTERRUPT_STATUS` through `CHARGER_DETAILS_2` are read in a single transaction.
+    /// The interrupt flags are not cleared.
+    pub async fn record_status<const N: usize>(
+        &mut self,
+        history: &mut StatusHistory<N>,
+        now_ms: u64,
+    ) -> Result<bool, Error<D::Error>> {
+        traced!(self, "record_status", {
+            let mut buf = [0; 4];
+            self.read_buf(Reg::CHARGER_INTERRUPT_STATUS, &mut buf)
+                .await?;
+            let status = ChargerStatus::from_bytes([buf[0]]);
+            let details = Details::from_bytes([buf[1], buf[2], buf[3]]);
+            Ok(history.push(details.pack(&status), now_ms))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read_burst, run};
+    use embedded_hal_async::i2c::ErrorKind;
+
+    fn words<const N: usize>(history: &StatusHistory<N>) -> std::vec::Vec<u32> {
+        history.iter().map(|entry| entry.word).collect()
+    }
+
+    #[test]
+    fn unchanged_words_are_not_appended() {
+        let mut history = StatusHistory::<4>::new();
+        assert!(history.push(1, 0));
+        assert!(!history.push(1, 100));
+        assert!(history.push(2, 200));
+        assert!(!history.push(2, 300));
+        // Only consecutive duplicates are dropped
+        assert!(history.push(1, 400));
+        assert_eq!(words(&history), [1, 2, 1]);
+        let stamps: std::vec::Vec<u64> = history.iter().map(|entry| entry.at_ms).collect();
+        assert_eq!(stamps, [0, 200, 400]);
+    }
+
+    #[test]
+    fn wraparound_keeps_the_newest_entries_oldest_first() {
+        let mut history = StatusHistory::<3>::new();
+        for word in 1..=10 {
+            assert!(history.push(word, u64::from(word)));
+            assert_eq!(history.len(), usize::try_from(word).unwrap().min(3));
+        }
+        assert_eq!(words(&history), [8, 9, 10]);
+        let newest_first: std::vec::Vec<u32> = history.iter().rev().map(|e| e.word).collect();
+        assert_eq!(newest_first, [10, 9, 8]);
+        assert_eq!(history.iter().len(), 3);
+        // Dedup still compares against the newest entry after wrapping
+        assert!(!history.push(10, 11));
+        assert!(history.push(8, 12));
+        assert_eq!(words(&history), [9, 10, 8]);
+    }
+
+    #[test]
+    fn cleared_history_starts_over() {
+        let mut history = StatusHistory::<2>::new();
+        for word in 1..=3 {
+            history.push(word, 0);
+        }
+        history.clear();
+        assert!(history.is_empty());
+        assert!(history.last().is_none());
+        assert!(history.push(3, 0));
+        assert_eq!(words(&history), [3]);
+    }
+
+    #[test]
+    fn record_status_appends_only_changes() {
+        let mut history = StatusHistory::<4>::new();
+        let res = run(
+            &[
+                read_burst(Reg::CHARGER_INTERRUPT_STATUS, &[0x40, 0x60, 0x01, 0x00]),
+                read_burst(Reg::CHARGER_INTERRUPT_STATUS, &[0x40, 0x60, 0x01, 0x00]),
+                read_burst(Reg::CHARGER_INTERRUPT_STATUS, &[0x40, 0x60, 0x04, 0x00]),
+            ],
+            async |charger| {
+                let mut appended = [false; 3];
+                for (at_ms, out) in (0..).zip(&mut appended) {
+                    *out = charger.record_status(&mut history, at_ms).await?;
+                }
+                Ok::<_, Error<ErrorKind>>(appended)
+            },
+        );
+        assert_eq!(res, Ok([true, false, true]));
+        let status = ChargerStatus::from_bytes([0x40]);
+        let (details, unpacked) = history.iter().next().unwrap().unpack();
+        assert_eq!(details, Details::from_bytes([0x60, 0x01, 0x00]));
+        assert_eq!(unpacked.into_bytes(), status.into_bytes());
+        assert_eq!(history.last().map(|entry| entry.at_ms), Some(2));
+    }
+
+    #[test]
+    fn full_history_evicts_the_oldest_entry() {
+        let mut history = StatusHistory::<2>::new();
+        for (word, at_ms) in [(1, 0), (2, u64::MAX), (3, 5)] {
+            assert!(history.push(word, at_ms));
+        }
+        let words: [u32; 2] = core::array::from_fn(|i| history.iter().nth(i).unwrap().word);
+        assert_eq!(words, [2, 3]);
+        assert_eq!(history.last().map(|entry| entry.word), Some(3));
+    }
+
+    #[test]
+    fn zero_capacity_records_nothing() {
+        let mut history = StatusHistory::<0>::new();
+        assert!(!history.push(1, 0));
+        assert!(history.is_empty());
+        assert_eq!(history.iter().count(), 0);
+    }
+}
@@ -0,0 +1,704 @@
+//! Input source handling: USB-PD contracts, USB presets, adapter profiles and input current
+//! limits.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::{conv, ChargeProfile, Charger, ChargerConfig10, ChgIn, Error, Event};
+
+/// The lowest accepted USB-PD contract voltage
+const MIN_CONTRACT_MV: u16 = 5000;
+/// The CHGIN input voltage rating
+const MAX_CONTRACT_MV: u16 = 19000;
+/// The lowest VCHGIN_REG setting
+const MIN_VCHGIN_REG_MV: u16 = 4000;
+/// The VCHGIN_REG step
+const VCHGIN_REG_STEP_MV: u16 = 200;
+/// The battery voltage assumed when deriving a charge current from the input power
+const CHARGE_VOLTAGE_MV: u64 = 4200;
+/// The converter efficiency assumed when deriving a charge current from the input power
+const EFFICIENCY_PERCENT: u64 = 90;
+/// How long [`Charger::probe_input_current`] waits for the input to settle after each step
+const PROBE_SETTLE_MS: u32 = 50;
+
+/// How [`Charger::apply_pd_contract_with`] derives the charger limits from a contract
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PdOptions {
+    /// How far below the contract current to set the input current limit, in percent
+    pub margin_percent: u8,
+    /// If set, the fast-charge current is set to what the contract can supply, up to this cap
+    pub fast_charge_cap_ma: Option<u16>,
+}
+
+impl PdOptions {
+    /// A 10% input current margin, leaving the fast-charge current unchanged
+    pub const DEFAULT: PdOptions = PdOptions {
+        margin_percent: 10,
+        fast_charge_cap_ma: None,
+    };
+}
+
+impl Default for PdOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Apply a negotiated USB-PD contract of `mv` and `ma` with [`PdOptions::DEFAULT`].
+    ///
+    /// See [`apply_pd_contract_with`](Self::apply_pd_contract_with).
+    pub async fn apply_pd_contract(&mut self, mv: u16, ma: u16) -> Result<(), Error<D::Error>> {
+        traced!(self, "apply_pd_contract", {
+            self.apply_pd_contract_with(mv, ma, &PdOptions::DEFAULT)
+                .await
+        })
+    }
+
+    /// Apply a negotiated USB-PD contract of `mv` and `ma`.
+    ///
+    /// The input current limit is set `options.margin_percent` below the contract current and
+    /// the input voltage regulation threshold (VCHGIN_REG) to 85% of the contract voltage, so the
+    /// charger backs off before the source collapses. If `options.fast_charge_cap_ma` is set,
+    /// the fast-charge current is set to the current the input can supply at a 4.2 V battery
+    /// and 90% efficiency, limited to the cap and the maximum of the part.
+    ///
+    /// Fails with [`Error::InvalidValue`] if `mv` is below 5 V or above the 19 V input rating.
+    pub async fn apply_pd_contract_with(
+        &mut self,
+        mv: u16,
+        ma: u16,
+        options: &PdOptions,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "apply_pd_contract_with", {
+            if !(MIN_CONTRACT_MV..=MAX_CONTRACT_MV).contains(&mv) {
+                return Err(Error::InvalidValue);
+            }
+            let margin = options.margin_percent.min(100) as u32;
+            let input_ma = ((ma as u32).saturating_mul(100u32.saturating_sub(margin)) / 100) as u16;
+            // `mv` is at most 19 V, so 85% of it fits a u16
+            let vchgin_reg = (((mv as u32).saturating_mul(85) / 100) as u16)
+                .saturating_sub(MIN_VCHGIN_REG_MV)
+                / VCHGIN_REG_STEP_MV;
+
+            self.set_chgin_ilim(input_ma).await?;
+            self.modify_typed(|val: ChargerConfig10| {
+                val.with_vchgin_reg(vchgin_reg.min(0x3f) as u8)
+            })
+            .await?;
+            if let Some(cap) = options.fast_charge_cap_ma {
+                let available = (input_ma as u64)
+                    .saturating_mul(mv as u64)
+                    .saturating_mul(EFFICIENCY_PERCENT)
+                    / 100
+                    / CHARGE_VOLTAGE_MV;
+                let fast_charge = (available.min(u16::MAX as u64) as u16)
+                    .min(cap)
+                    .min(self.variant.max_fast_charge_current());
+                self.set_fast_charge_current(fast_charge).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A USB input source type, see [`Charger::apply_usb_preset`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UsbPreset {
+    /// Standard downstream port
+    Sdp,
+    /// Charging downstream port
+    Cdp,
+    /// Dedicated charging port
+    Dcp,
+    /// Type-C at default USB power
+    TypeCDefault,
+    /// Type-C at 1.5 A
+    TypeC1A5,
+    /// Type-C at 3 A
+    TypeC3A,
+}
+
+/// The limits programmed for a [`UsbPreset`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct UsbPresetLimits {
+    /// The preset these limits apply to
+    pub preset: UsbPreset,
+    /// The CHGIN input current limit in mA
+    pub input_limit_ma: u16,
+    /// The fast-charge current cap in mA, further limited by the part maximum
+    pub fast_charge_ma: u16,
+}
+
+/// The limits applied by [`Charger::apply_usb_preset`], indexed by [`UsbPreset`]
+///
+/// The input limits are the currents the source is allowed to supply at 5 V. The fast-charge
+/// cap equals the input limit: at battery voltages below 5 V the buck converter delivers more
+/// current than it draws, so the charge current never makes the input exceed its limit.
+pub const USB_PRESET_LIMITS: [UsbPresetLimits; 6] = [
+    UsbPresetLimits {
+        preset: UsbPreset::Sdp,
+        input_limit_ma: 500,
+        fast_charge_ma: 500,
+    },
+    UsbPresetLimits {
+        preset: UsbPreset::Cdp,
+        input_limit_ma: 1500,
+        fast_charge_ma: 1500,
+    },
+    UsbPresetLimits {
+        preset: UsbPreset::Dcp,
+        input_limit_ma: 1500,
+        fast_charge_ma: 1500,
+    },
+    UsbPresetLimits {
+        preset: UsbPreset::TypeCDefault,
+        input_limit_ma: 900,
+        fast_charge_ma: 900,
+    },
+    UsbPresetLimits {
+        preset: UsbPreset::TypeC1A5,
+        input_limit_ma: 1500,
+        fast_charge_ma: 1500,
+    },
+    UsbPresetLimits {
+        preset: UsbPreset::TypeC3A,
+        input_limit_ma: 3000,
+        fast_charge_ma: 3000,
+    },
+];
+
+impl UsbPreset {
+    /// The limits applied for this preset, from [`USB_PRESET_LIMITS`]
+    #[allow(clippy::indexing_slicing)] // the table has an entry for every preset, in order
+    pub const fn limits(self) -> UsbPresetLimits {
+        USB_PRESET_LIMITS[self as usize]
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Apply the input current limit and fast-charge cap of a USB `preset`.
+    ///
+    /// The limits are taken from [`USB_PRESET_LIMITS`]. Returns the input current limit that was
+    /// programmed, after rounding down to the CHGIN_ILIM step.
+    pub async fn apply_usb_preset(&mut self, preset: UsbPreset) -> Result<u16, Error<D::Error>> {
+        traced!(self, "apply_usb_preset", {
+            let limits = preset.limits();
+            self.set_chgin_ilim(limits.input_limit_ma).await?;
+            let fast_charge = limits
+                .fast_charge_ma
+                .min(self.variant.max_fast_charge_current());
+            self.set_fast_charge_current(fast_charge).await?;
+            Ok(conv::chgin_ilim_ma(conv::chgin_ilim_code(
+                limits.input_limit_ma,
+            )))
+        })
+    }
+}
+
+/// The settings for one class of wall adapter, see [`AdapterProfiles`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdapterProfile {
+    /// The lowest input voltage of this class in mV
+    pub min_mv: u16,
+    /// The highest input voltage of this class in mV
+    pub max_mv: u16,
+    /// The CHGIN input current limit in mA
+    pub chgin_ilim_ma: u16,
+    /// The charge profile to apply
+    pub profile: ChargeProfile,
+}
+
+/// A table of [`AdapterProfile`]s keyed by input voltage, with a fallback
+///
+/// The charger only reports whether CHGIN is valid, not its voltage, so the input voltage
+/// comes from the application, e.g. from a USB-PD stack or an ADC. When the voltage is unknown
+/// or no entry matches, the conservative `fallback` is used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct AdapterProfiles<'a> {
+    /// The profiles, searched in order
+    pub entries: &'a [AdapterProfile],
+    /// The profile used when no entry matches
+    pub fallback: AdapterProfile,
+}
+
+impl AdapterProfiles<'_> {
+    /// The first entry whose voltage range contains `input_mv`, or the fallback.
+    pub fn select(&self, input_mv: Option<u16>) -> &AdapterProfile {
+        input_mv
+            .and_then(|mv| {
+                self.entries
+                    .iter()
+                    .find(|entry| (entry.min_mv..=entry.max_mv).contains(&mv))
+            })
+            .unwrap_or(&self.fallback)
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Apply the adapter profile matching the attached input.
+    ///
+    /// Does nothing and returns `None` unless CHGIN reports a valid input. Otherwise the profile
+    /// selected for `input_mv` is applied, input current limit first, and returned.
+    pub async fn apply_for_current_input<'p>(
+        &mut self,
+        profiles: &'p AdapterProfiles<'_>,
+        input_mv: Option<u16>,
+    ) -> Result<Option<&'p AdapterProfile>, Error<D::Error>> {
+        traced!(self, "apply_for_current_input", {
+            if self.chgin_status().await? != ChgIn::Valid {
+                return Ok(None);
+            }
+            let selected = profiles.select(input_mv);
+            self.set_chgin_ilim(selected.chgin_ilim_ma).await?;
+            self.apply_charge_profile(&selected.profile).await?;
+            Ok(Some(selected))
+        })
+    }
+
+    /// Apply the matching adapter profile when `event` is [`Event::InputInserted`].
+    ///
+    /// Intended to be called for every event from [`ChargerEvents`](crate::ChargerEvents):
+    ///
+    /// ```no_run
+    /// # use embedded_hal_async::digital::Wait;
+    /// # use embedded_hal_async::i2c::I2c;
+    /// # use max7797x_driver::{AdapterProfiles, ChargerEvents, Error};
+    /// # async fn example<D: I2c, P: Wait>(
+    /// #     events: &mut ChargerEvents<D, P>,
+    /// #     profiles: &AdapterProfiles<'_>,
+    /// #     pd_voltage_mv: Option<u16>,
+    /// # ) -> Result<(), Error<D::Error>> {
+    /// let event = events.next_event().await?;
+    /// events.charger().on_adapter_event(profiles, &event, pd_voltage_mv).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn on_adapter_event<'p>(
+        &mut self,
+        profiles: &'p AdapterProfiles<'_>,
+        event: &Event,
+        input_mv: Option<u16>,
+    ) -> Result<Option<&'p AdapterProfile>, Error<D::Error>> {
+        traced!(self, "on_adapter_event", {
+            match event {
+                Event::InputInserted => self.apply_for_current_input(profiles, input_mv).await,
+                _ => Ok(None),
+            }
+        })
+    }
+}
+
+/// The result of [`Charger::handle_aicl_event`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AiclAction {
+    /// AICL was not active, the input current limit was left unchanged
+    Inactive,
+    /// The input current limit was reduced to the contained value in mA
+    Reduced(u16),
+    /// The input current limit is at the floor, in mA, and was not reduced further
+    AtFloor(u16),
+}
+
+impl<D: I2c> Charger<D> {
+    /// Step the CHGIN input current limit down while the adaptive input current loop is active.
+    ///
+    /// AICL activating means the input voltage is sagging under the load, so the limit is
+    /// lowered by `step_ma`, but not below `floor_ma`. The status is read with
+    /// [`charger_status_peek`](Self::charger_status_peek), so pending interrupt flags are left
+    /// alone. Call it for every [`Event::AdaptiveInputCurrentLoop`] until it stops returning
+    /// [`AiclAction::Reduced`]:
+    ///
+    /// ```no_run
+    /// # use embedded_hal_async::digital::Wait;
+    /// # use embedded_hal_async::i2c::I2c;
+    /// # use max7797x_driver::{ChargerEvents, Error, Event};
+    /// # async fn example<D: I2c, P: Wait>(
+    /// #     events: &mut ChargerEvents<D, P>,
+    /// # ) -> Result<(), Error<D::Error>> {
+    /// if let Event::AdaptiveInputCurrentLoop = events.next_event().await? {
+    ///     events.charger().handle_aicl_event(100, 500).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn handle_aicl_event(
+        &mut self,
+        step_ma: u16,
+        floor_ma: u16,
+    ) -> Result<AiclAction, Error<D::Error>> {
+        traced!(self, "handle_aicl_event", {
+            if self.charger_status_peek().await?.aicl_ok() {
+                return Ok(AiclAction::Inactive);
+            }
+            let current = self.chgin_ilim().await?;
+            let reduced = current.saturating_sub(step_ma).max(floor_ma);
+            if reduced >= current {
+                return Ok(AiclAction::AtFloor(current));
+            }
+            self.set_chgin_ilim(reduced).await?;
+            Ok(AiclAction::Reduced(self.chgin_ilim().await?))
+        })
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Find the highest input current limit the attached adapter can supply.
+    ///
+    /// CHGIN_ILIM is raised from `start_ma` towards `max_ma` in steps of `step_ma`, waiting
+    /// 50 ms on `settle` after each step. Once the adaptive input current loop engages or the
+    /// input becomes invalid, the limit is set one step below the level that triggered it.
+    /// Returns the limit that was programmed, after rounding down to the CHGIN_ILIM step.
+    ///
+    /// `start_ma` should be a limit any adapter can supply: if the bus fails mid-probe, an
+    /// attempt is made to restore it before the error is returned. Fails with
+    /// [`Error::InvalidValue`] if `step_ma` is zero or `start_ma` exceeds `max_ma`.
+    pub async fn probe_input_current(
+        &mut self,
+        start_ma: u16,
+        max_ma: u16,
+        step_ma: u16,
+        mut settle: impl DelayNs,
+    ) -> Result<u16, Error<D::Error>> {
+        traced!(self, "probe_input_current", {
+            if step_ma == 0 || start_ma > max_ma {
+                return Err(Error::InvalidValue);
+            }
+            let result = self
+                .probe_input_current_steps(start_ma, max_ma, step_ma, &mut settle)
+                .await;
+            if result.is_err() {
+                let _ = self.set_chgin_ilim(start_ma).await;
+            }
+            result
+        })
+    }
+
+    async fn probe_input_current_steps(
+        &mut self,
+        start_ma: u16,
+        max_ma: u16,
+        step_ma: u16,
+        settle: &mut impl DelayNs,
+    ) -> Result<u16, Error<D::Error>> {
+        let mut limit = start_ma;
+        loop {
+            self.set_chgin_ilim(limit).await?;
+            settle.delay_ms(PROBE_SETTLE_MS).await;
+            let status = self.charger_status_peek().await?;
+            if !status.aicl_ok() || !status.chgin_ok() {
+                self.set_chgin_ilim(limit.saturating_sub(step_ma)).await?;
+                break;
+            }
+            if limit >= max_ma {
+                break;
+            }
+            limit = limit.saturating_add(step_ma).min(max_ma);
+        }
+        self.chgin_ilim().await
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use super::*;
+    use crate::sim::Simulator;
+    use crate::testing::{block_on, lock, read, run_variant, unlock, write, SimDelay};
+    use crate::{ChargerEvents, Reg, Variant};
+
+    const FIVE_VOLT: AdapterProfile = AdapterProfile {
+        min_mv: 4500,
+        max_mv: 5500,
+        chgin_ilim_ma: 2000,
+        profile: crate::charge_profile!(Variant::Max77975, 1500, 4200, 150, 10),
+    };
+    const NINE_VOLT: AdapterProfile = AdapterProfile {
+        min_mv: 8500,
+        max_mv: 9500,
+        chgin_ilim_ma: 3000,
+        profile: crate::charge_profile!(Variant::Max77975, 3000, 4200, 150, 10),
+    };
+    const FALLBACK: AdapterProfile = AdapterProfile {
+        min_mv: 0,
+        max_mv: 0,
+        chgin_ilim_ma: 500,
+        profile: crate::charge_profile!(Variant::Max77975, 500, 4100, 100, 10),
+    };
+    const ENTRIES: [AdapterProfile; 2] = [FIVE_VOLT, NINE_VOLT];
+    const PROFILES: AdapterProfiles<'static> = AdapterProfiles {
+        entries: &ENTRIES,
+        fallback: FALLBACK,
+    };
+
+    /// The input current limit and fast-charge current programmed in the simulator
+    fn limits(charger: &mut Charger<crate::sim::SimulatedCharger<'_>>) -> (u16, u16) {
+        let chgin_ilim = block_on(charger.chgin_ilim()).expect("simulated bus failed");
+        let fast_charge = block_on(charger.fast_charge_current()).expect("simulated bus failed");
+        (chgin_ilim, fast_charge)
+    }
+
+    #[test]
+    fn input_voltage_selects_the_profile() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        let mut charger = Charger::new(sim.device());
+        let cases = [
+            (Some(5000), FIVE_VOLT),
+            (Some(9000), NINE_VOLT),
+            (Some(12000), FALLBACK),
+            (None, FALLBACK),
+        ];
+        for (input_mv, expected) in cases {
+            let applied = block_on(charger.apply_for_current_input(&PROFILES, input_mv)).unwrap();
+            assert_eq!(applied, Some(&expected), "{input_mv:?}");
+            assert_eq!(
+                limits(&mut charger),
+                (
+                    expected.chgin_ilim_ma,
+                    expected.profile.fast_charge_current_ma()
+                ),
+                "{input_mv:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn nothing_is_applied_without_a_valid_input() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        let before = limits(&mut charger);
+        let applied = block_on(charger.apply_for_current_input(&PROFILES, Some(9000))).unwrap();
+        assert_eq!(applied, None);
+        assert_eq!(limits(&mut charger), before);
+    }
+
+    #[test]
+    fn insertion_event_applies_the_profile() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_charger_irq_mask(crate::ChargerInterrupts::from_bytes([0xff])))
+            .unwrap();
+        let mut events = ChargerEvents::new(charger, sim.irq_pin());
+        sim.plug_charger();
+
+        let mut applied = None;
+        while applied.is_none() {
+            let event = block_on(events.next_event()).unwrap();
+            applied = block_on(
+                events
+                    .charger()
+                    .on_adapter_event(&PROFILES, &event, Some(9000)),
+            )
+            .unwrap();
+        }
+        assert_eq!(applied, Some(&NINE_VOLT));
+        assert_eq!(limits(events.charger()), (3000, 3000));
+    }
+
+    #[test]
+    fn pd_contract_extremes_saturate() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        let uncapped = PdOptions {
+            margin_percent: 0,
+            fast_charge_cap_ma: Some(u16::MAX),
+        };
+        block_on(charger.apply_pd_contract_with(MAX_CONTRACT_MV, u16::MAX, &uncapped)).unwrap();
+        assert_eq!(limits(&mut charger), (3200, 3500));
+
+        let all_margin = PdOptions {
+            margin_percent: u8::MAX,
+            fast_charge_cap_ma: None,
+        };
+        block_on(charger.apply_pd_contract_with(MIN_CONTRACT_MV, u16::MAX, &all_margin)).unwrap();
+        assert_eq!(limits(&mut charger), (50, 3500));
+
+        for mv in [0, MIN_CONTRACT_MV - 1, MAX_CONTRACT_MV + 1, u16::MAX] {
+            let res = block_on(charger.apply_pd_contract_with(mv, 3000, &PdOptions::DEFAULT));
+            assert_eq!(res, Err(Error::InvalidValue), "{mv}");
+        }
+    }
+
+    #[test]
+    fn usb_presets_write_their_limits() {
+        for (preset, chgin_ilim, chg_cc, input_ma) in [
+            (UsbPreset::Sdp, 0x09, 0x0a, 500),
+            (UsbPreset::Cdp, 0x1d, 0x1e, 1500),
+            (UsbPreset::Dcp, 0x1d, 0x1e, 1500),
+            (UsbPreset::TypeCDefault, 0x11, 0x12, 900),
+            (UsbPreset::TypeC1A5, 0x1d, 0x1e, 1500),
+            (UsbPreset::TypeC3A, 0x3b, 0x3c, 3000),
+        ] {
+            let res = run_variant(
+                Variant::Max77975,
+                &[
+                    read(Reg::CHARGER_CONFIG_9, 0x00),
+                    write(Reg::CHARGER_CONFIG_9, chgin_ilim),
+                    unlock(),
+                    read(Reg::CHARGER_CONFIG_2, 0x00),
+                    write(Reg::CHARGER_CONFIG_2, chg_cc),
+                    lock(),
+                ],
+                async |charger| charger.apply_usb_preset(preset).await,
+            );
+            assert_eq!(res, Ok(input_ma), "{preset:?}");
+        }
+    }
+
+    #[test]
+    fn aicl_steps_the_limit_down() {
+        // CHGIN_OK without AICL_OK: the loop is active
+        let res = run_variant(
+            Variant::Max77975,
+            &[
+                read(Reg::CHARGER_INTERRUPT_STATUS, 0x40),
+                read(Reg::CHARGER_CONFIG_9, 0x1d),
+                read(Reg::CHARGER_CONFIG_9, 0x1d),
+                write(Reg::CHARGER_CONFIG_9, 0x1b),
+                read(Reg::CHARGER_CONFIG_9, 0x1b),
+            ],
+            async |charger| charger.handle_aicl_event(100, 500).await,
+        );
+        assert_eq!(res, Ok(AiclAction::Reduced(1400)));
+    }
+
+    #[test]
+    fn aicl_stops_at_the_floor() {
+        // A step past the floor is cut short
+        let res = run_variant(
+            Variant::Max77975,
+            &[
+                read(Reg::CHARGER_INTERRUPT_STATUS, 0x40),
+                read(Reg::CHARGER_CONFIG_9, 0x0a),
+                read(Reg::CHARGER_CONFIG_9, 0x0a),
+                write(Reg::CHARGER_CONFIG_9, 0x09),
+                read(Reg::CHARGER_CONFIG_9, 0x09),
+            ],
+            async |charger| charger.handle_aicl_event(100, 500).await,
+        );
+        assert_eq!(res, Ok(AiclAction::Reduced(500)));
+
+        let res = run_variant(
+            Variant::Max77975,
+            &[
+                read(Reg::CHARGER_INTERRUPT_STATUS, 0x40),
+                read(Reg::CHARGER_CONFIG_9, 0x09),
+            ],
+            async |charger| charger.handle_aicl_event(100, 500).await,
+        );
+        assert_eq!(res, Ok(AiclAction::AtFloor(500)));
+    }
+
+    #[test]
+    fn aicl_inactive_changes_nothing() {
+        let res = run_variant(
+            Variant::Max77975,
+            &[read(Reg::CHARGER_INTERRUPT_STATUS, 0xc0)],
+            async |charger| charger.handle_aicl_event(100, 500).await,
+        );
+        assert_eq!(res, Ok(AiclAction::Inactive));
+    }
+
+    #[test]
+    fn probe_backs_off_once_aicl_engages() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        sim.set_adapter_limit(Some(1200));
+        let mut charger = Charger::new(sim.device());
+        // 500, 700, 900 and 1100 mA hold, 1300 mA engages the loop
+        let res = block_on(charger.probe_input_current(500, 3000, 200, SimDelay(&sim)));
+        assert_eq!(res, Ok(1100));
+        assert_eq!(limits(&mut charger).0, 1100);
+    }
+
+    #[test]
+    fn probe_stops_at_the_ceiling() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        let mut charger = Charger::new(sim.device());
+        // The last step is cut short at the ceiling
+        let res = block_on(charger.probe_input_current(500, 1500, 400, SimDelay(&sim)));
+        assert_eq!(res, Ok(1500));
+        assert_eq!(limits(&mut charger).0, 1500);
+    }
+
+    #[test]
+    fn probe_rejects_bad_arguments() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        for (start, max, step) in [(500, 1500, 0), (1500, 500, 100)] {
+            let res = block_on(charger.probe_input_current(start, max, step, SimDelay(&sim)));
+            assert_eq!(res, Err(Error::InvalidValue), "{start} {max} {step}");
+        }
+    }
+
+    #[test]
+    fn pd_contracts_write_the_derived_codes() {
+        // 90% of the contract current and 85% of its voltage
+        for (mv, ma, chgin_ilim, vchgin_reg) in [
+            // 2700 mA, 4250 mV
+            (5000, 3000, 0x35, 0x01),
+            // 1800 mA, 7650 mV
+            (9000, 2000, 0x23, 0x12),
+            // 2700 mA, 12750 mV
+            (15000, 3000, 0x35, 0x2b),
+            // 1350 mA, 12750 mV
+            (15000, 1500, 0x1a, 0x2b),
+        ] {
+            let res = run_variant(
+                Variant::Max77975,
+                &[
+                    read(Reg::CHARGER_CONFIG_9, 0x00),
+                    write(Reg::CHARGER_CONFIG_9, chgin_ilim),
+                    // REGTEMP is left alone
+                    read(Reg::CHARGER_CONFIG_10, 0xc0),
+                    write(Reg::CHARGER_CONFIG_10, 0xc0 | vchgin_reg),
+                ],
+                async |charger| charger.apply_pd_contract(mv, ma).await,
+            );
+            assert_eq!(res, Ok(()), "{mv} mV {ma} mA");
+        }
+    }
+
+    #[test]
+    fn pd_contracts_above_the_rating_are_refused() {
+        let res = run_variant(Variant::Max77975, &[], async |charger| {
+            charger.apply_pd_contract(20000, 5000).await
+        });
+        assert_eq!(res, Err(Error::InvalidValue));
+    }
+
+    #[test]
+    fn pd_contract_caps_the_fast_charge_current() {
+        // 1800 mA at 9 V and 90% efficiency is 3471 mA at 4.2 V, capped at 2000 mA
+        let options = PdOptions {
+            margin_percent: 10,
+            fast_charge_cap_ma: Some(2000),
+        };
+        let res = run_variant(
+            Variant::Max77975,
+            &[
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                write(Reg::CHARGER_CONFIG_9, 0x23),
+                read(Reg::CHARGER_CONFIG_10, 0x00),
+                write(Reg::CHARGER_CONFIG_10, 0x12),
+                unlock(),
+                read(Reg::CHARGER_CONFIG_2, 0x00),
+                write(Reg::CHARGER_CONFIG_2, 0x28),
+                lock(),
+            ],
+            async |charger| charger.apply_pd_contract_with(9000, 2000, &options).await,
+        );
+        assert_eq!(res, Ok(()));
+    }
+}
@@ -1,124 +1,1240 @@
 #![no_std]
 #![warn(missing_docs)]
+// modular-bitfield 0.11 wraps field types in parentheses in its generated accessors
+#![allow(unused_parens)]
+#![deny(
+    clippy::arithmetic_side_effects,
+    clippy::indexing_slicing,
+    clippy::unwrap_used,
+    clippy::panic,
+    clippy::todo,
+    clippy::unimplemented,
+    clippy::unreachable
+)]
 
 //! An embedded async driver for the MAX77975/MAX77976 19VIN, 3.5/5.5A 1-Cell Li+ Battery Charger with Smart Power
 //! Selector and OTG for USBC PD
+//!
+//! The register-compatible MAX77985/MAX77986 are also supported; see [`Variant`].
+//!
+//! # Panics
+//!
+//! The driver does not panic on any register contents or argument values. Bytes read from the
+//! charger decode to a value for every bit pattern: reserved codes of the status fields have
+//! their own variants, and reserved [`Mode`] codes are returned as errors by
+//! [`ChargerConfig0::mode`] and [`Charger::mode`]. Out-of-range arguments are rejected with
+//! [`Error::InvalidValue`] or saturate where documented.
+//!
+//! The crate is linted with `clippy::arithmetic_side_effects` and `clippy::indexing_slicing`,
+//! so arithmetic is checked or saturating, and slices are accessed with `get` or iterators.
+//! The host-side `sim` module is exempt.
 
-use embedded_hal_async::i2c::I2c;
-use modular_bitfield::specifiers::{B1, B2};
-use modular_bitfield::{bitfield, BitfieldSpecifier};
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+use modular_bitfield::specifiers::{B1, B2, B3, B4};
+use modular_bitfield::{bitfield, BitfieldSpecifier, Specifier};
 
-const ADDR: u8 = 0x6b;
+use cache::ShadowCache;
 
+#[cfg(test)]
+extern crate std;
+
+// Declared first so that `traced!` is visible in the other modules
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "embedded-batteries")]
+pub mod batteries;
+mod bytes;
+mod cache;
+mod config;
+pub mod conv;
+mod derating;
+mod display;
+mod events;
+mod fields;
+mod flags;
+mod history;
+mod input;
+pub mod ntc;
+mod ops;
+mod pack;
+mod persist;
+mod policy;
+#[cfg(feature = "nb")]
+pub mod poll;
+mod profile;
+mod progress;
+pub mod recommended;
+pub mod retry;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(feature = "embassy-sync")]
+pub mod shared;
+mod ship;
+#[cfg(feature = "sim")]
+pub mod sim;
+mod split;
+mod stats;
+#[cfg(feature = "supervisor")]
+pub mod supervisor;
+mod telemetry;
+#[cfg(test)]
+mod testing;
+mod thermistor;
+pub mod timeout;
+#[cfg(feature = "unsafe-raw")]
+pub mod unsafe_raw;
+mod verify;
+mod voting;
+mod wait;
+mod watchdog;
+
+pub use config::{
+    ChargerConfig1, ChargerConfig10, ChargerConfig11, ChargerConfig12, ChargerConfig13,
+    ChargerConfig2, ChargerConfig3, ChargerConfig4, ChargerConfig5, ChargerConfig6, ChargerConfig7,
+    ChargerConfig8, ChargerConfig9, RegisterSpec, StatusLedConfig,
+};
+pub use conv::{celsius_to_regtemp_code, regtemp_code_to_celsius};
+pub use derating::{Derating, DeratingBand, DeratingTable};
+pub use events::{ChargerEvents, Event};
+pub use flags::{ChargerIrqSource, TopIrqSource};
+pub use history::{StatusEntry, StatusHistory};
+pub use input::{
+    AdapterProfile, AdapterProfiles, AiclAction, PdOptions, UsbPreset, UsbPresetLimits,
+    USB_PRESET_LIMITS,
+};
+pub use ops::ChargerOps;
+pub use persist::DecodeError;
+pub use policy::{PolicyViolation, SafetyPolicy};
+pub use profile::{ChargeProfile, FastChargeTimer, ProfileError, ProfileImage, RechargeThreshold};
+pub use progress::{ChargePhase, PhaseTransition, ProgressTracker};
+pub use ship::ShipModeError;
+pub use split::{ChargerControl, ChargerMonitor};
+pub use stats::ChargeStats;
+pub use telemetry::Telemetry;
+pub use thermistor::{
+    ColdThreshold, CoolThreshold, HotThreshold, JeitaAction, JeitaConfig, JeitaCurrent,
+    JeitaPolicy, JeitaVoltage, ThermistorBiasSource, ThermistorThresholds, WarmThreshold,
+};
+pub use voting::ReadVoting;
+pub use wait::ChargeOutcome;
+pub use watchdog::{WatchdogAction, WATCHDOG_RECOVERY_POLLS, WATCHDOG_TIMEOUT_MS};
+
+/// The default 7-bit I2C address of the charger.
+pub const DEFAULT_ADDRESS: u8 = 0x6b;
+
+/// A charger register, identified by its address
+///
+/// The associated constants name every register the driver knows about; [`Reg::ALL`] lists
+/// them in address order.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
-struct Reg(pub u8);
+pub struct Reg(u8);
 
-#[allow(dead_code)]
 impl Reg {
+    /// Chip identification
     pub const CHIP_ID: Reg = Reg(0x00);
+    /// Chip revision
     pub const CHIP_REVISION: Reg = Reg(0x01);
+    /// OTP revision
     pub const OTP_REVISION: Reg = Reg(0x02);
+    /// TOP interrupt flags, cleared on read
     pub const TOP_INTERRUPT: Reg = Reg(0x03);
+    /// TOP interrupt mask
     pub const TOP_INTERRUPT_MASK: Reg = Reg(0x04);
+    /// TOP control
     pub const TOP_CONTROL: Reg = Reg(0x05);
+    /// Software reset
     pub const SOFTWARE_RESET: Reg = Reg(0x50);
+    /// Ship mode control
     pub const SHIP_MODE_CONTROL: Reg = Reg(0x51);
+    /// I2C configuration
     pub const I2C_CONFIG: Reg = Reg(0x40);
 
+    /// Charger interrupt flags, cleared on read
     pub const CHARGER_INTERRUPT: Reg = Reg(0x10);
+    /// Charger interrupt mask
     pub const CHARGER_INTERRUPT_MASK: Reg = Reg(0x11);
+    /// Charger interrupt status
     pub const CHARGER_INTERRUPT_STATUS: Reg = Reg(0x12);
+    /// Charger details 0
     pub const CHARGER_DETAILS_0: Reg = Reg(0x13);
+    /// Charger details 1
     pub const CHARGER_DETAILS_1: Reg = Reg(0x14);
+    /// Charger details 2
     pub const CHARGER_DETAILS_2: Reg = Reg(0x15);
+    /// Charger configuration 0
     pub const CHARGER_CONFIG_0: Reg = Reg(0x16);
+    /// Charger configuration 1, CHGPROT protected
     pub const CHARGER_CONFIG_1: Reg = Reg(0x17);
+    /// Charger configuration 2, CHGPROT protected
     pub const CHARGER_CONFIG_2: Reg = Reg(0x18);
+    /// Charger configuration 3, CHGPROT protected
     pub const CHARGER_CONFIG_3: Reg = Reg(0x19);
+    /// Charger configuration 4, CHGPROT protected
     pub const CHARGER_CONFIG_4: Reg = Reg(0x1a);
+    /// Charger configuration 5, CHGPROT protected
     pub const CHARGER_CONFIG_5: Reg = Reg(0x1b);
+    /// Charger configuration 6
     pub const CHARGER_CONFIG_6: Reg = Reg(0x1c);
+    /// Charger configuration 7, CHGPROT protected
     pub const CHARGER_CONFIG_7: Reg = Reg(0x1d);
+    /// Charger configuration 8
     pub const CHARGER_CONFIG_8: Reg = Reg(0x1e);
+    /// Charger configuration 9
     pub const CHARGER_CONFIG_9: Reg = Reg(0x1f);
+    /// Charger configuration 10
     pub const CHARGER_CONFIG_10: Reg = Reg(0x20);
+    /// Charger configuration 11
     pub const CHARGER_CONFIG_11: Reg = Reg(0x21);
+    /// Charger configuration 12
     pub const CHARGER_CONFIG_12: Reg = Reg(0x22);
+    /// Charger configuration 13
     pub const CHARGER_CONFIG_13: Reg = Reg(0x23);
+    /// Status LED configuration
     pub const STATUS_LED_CONFIG: Reg = Reg(0x24);
 
-    pub const fn new(val: u8) -> Self {
+    /// All registers, in address order
+    pub const ALL: [Reg; 30] = [
+        Reg::CHIP_ID,
+        Reg::CHIP_REVISION,
+        Reg::OTP_REVISION,
+        Reg::TOP_INTERRUPT,
+        Reg::TOP_INTERRUPT_MASK,
+        Reg::TOP_CONTROL,
+        Reg::CHARGER_INTERRUPT,
+        Reg::CHARGER_INTERRUPT_MASK,
+        Reg::CHARGER_INTERRUPT_STATUS,
+        Reg::CHARGER_DETAILS_0,
+        Reg::CHARGER_DETAILS_1,
+        Reg::CHARGER_DETAILS_2,
+        Reg::CHARGER_CONFIG_0,
+        Reg::CHARGER_CONFIG_1,
+        Reg::CHARGER_CONFIG_2,
+        Reg::CHARGER_CONFIG_3,
+        Reg::CHARGER_CONFIG_4,
+        Reg::CHARGER_CONFIG_5,
+        Reg::CHARGER_CONFIG_6,
+        Reg::CHARGER_CONFIG_7,
+        Reg::CHARGER_CONFIG_8,
+        Reg::CHARGER_CONFIG_9,
+        Reg::CHARGER_CONFIG_10,
+        Reg::CHARGER_CONFIG_11,
+        Reg::CHARGER_CONFIG_12,
+        Reg::CHARGER_CONFIG_13,
+        Reg::STATUS_LED_CONFIG,
+        Reg::I2C_CONFIG,
+        Reg::SOFTWARE_RESET,
+        Reg::SHIP_MODE_CONTROL,
+    ];
+
+    pub(crate) const fn new(val: u8) -> Self {
         Reg(val)
     }
 
-    pub const fn to_u8(self) -> u8 {
+    /// The register with `address`, if it is one of [`Reg::ALL`].
+    pub const fn from_address(address: u8) -> Option<Self> {
+        let mut rest = Self::ALL.as_slice();
+        while let [reg, tail @ ..] = rest {
+            if reg.0 == address {
+                return Some(*reg);
+            }
+            rest = tail;
+        }
+        None
+    }
+
+    /// The register address
+    pub const fn address(self) -> u8 {
         self.0
     }
+
+    /// Whether the register cannot be written.
+    ///
+    /// Status and ID registers are read-only; the interrupt flags clear on read.
+    pub const fn is_read_only(self) -> bool {
+        let mut rest = WRITABLE_RANGES.as_slice();
+        while let [(first, last), tail @ ..] = rest {
+            if self.0 >= first.0 && self.0 <= last.0 {
+                return false;
+            }
+            rest = tail;
+        }
+        true
+    }
+
+    /// The datasheet mnemonic of the register, e.g. `"CHG_CNFG_09"`
+    pub const fn name(self) -> &'static str {
+        match self {
+            Reg::CHIP_ID => "CHIP_ID",
+            Reg::CHIP_REVISION => "CHIP_REVISION",
+            Reg::OTP_REVISION => "OTP_REVISION",
+            Reg::TOP_INTERRUPT => "TOP_INT",
+            Reg::TOP_INTERRUPT_MASK => "TOP_INT_MASK",
+            Reg::TOP_CONTROL => "TOP_CTRL",
+            Reg::CHARGER_INTERRUPT => "CHG_INT",
+            Reg::CHARGER_INTERRUPT_MASK => "CHG_INT_MASK",
+            Reg::CHARGER_INTERRUPT_STATUS => "CHG_INT_OK",
+            Reg::CHARGER_DETAILS_0 => "CHG_DETAILS_00",
+            Reg::CHARGER_DETAILS_1 => "CHG_DETAILS_01",
+            Reg::CHARGER_DETAILS_2 => "CHG_DETAILS_02",
+            Reg::CHARGER_CONFIG_0 => "CHG_CNFG_00",
+            Reg::CHARGER_CONFIG_1 => "CHG_CNFG_01",
+            Reg::CHARGER_CONFIG_2 => "CHG_CNFG_02",
+            Reg::CHARGER_CONFIG_3 => "CHG_CNFG_03",
+            Reg::CHARGER_CONFIG_4 => "CHG_CNFG_04",
+            Reg::CHARGER_CONFIG_5 => "CHG_CNFG_05",
+            Reg::CHARGER_CONFIG_6 => "CHG_CNFG_06",
+            Reg::CHARGER_CONFIG_7 => "CHG_CNFG_07",
+            Reg::CHARGER_CONFIG_8 => "CHG_CNFG_08",
+            Reg::CHARGER_CONFIG_9 => "CHG_CNFG_09",
+            Reg::CHARGER_CONFIG_10 => "CHG_CNFG_10",
+            Reg::CHARGER_CONFIG_11 => "CHG_CNFG_11",
+            Reg::CHARGER_CONFIG_12 => "CHG_CNFG_12",
+            Reg::CHARGER_CONFIG_13 => "CHG_CNFG_13",
+            Reg::STATUS_LED_CONFIG => "STAT_CNFG",
+            Reg::I2C_CONFIG => "I2C_CNFG",
+            Reg::SOFTWARE_RESET => "SW_RESET",
+            Reg::SHIP_MODE_CONTROL => "SHIP_MODE_CTRL",
+            _ => "UNKNOWN",
+        }
+    }
+
+    /// Whether writes to the register must be unlocked with CHGPROT.
+    ///
+    /// See [`Charger::protected`].
+    pub const fn is_protected(self) -> bool {
+        (self.0 >= Reg::CHARGER_CONFIG_1.0 && self.0 <= Reg::CHARGER_CONFIG_5.0)
+            || self.0 == Reg::CHARGER_CONFIG_7.0
+    }
+}
+
+/// A MAX7797x driver error
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Error<E> {
+    /// An error from the underlying I2C bus
+    I2c(E),
+    /// A requested value is out of range for the device
+    InvalidValue,
+    /// The chip ID read from the device does not belong to a supported part
+    UnknownChip(u8),
+    /// The requested setting does not exist on this part
+    Unsupported,
+    /// An error from the interrupt pin
+    Pin,
+    /// The charger was not in, or did not reach, the state the operation requires
+    UnexpectedState(ChargerDetails),
+    /// A valid input is attached, which the operation does not allow
+    InputPresent,
+    /// No valid input is attached, which the operation requires
+    NoInput,
+    /// Repeated reads of a register disagreed, see [`Charger::with_read_voting`]
+    ReadMismatch {
+        /// The register address
+        reg: u8,
+        /// The value of the first read
+        first: u8,
+        /// The value of the second read
+        second: u8,
+    },
+    /// A register did not read back the value written, see [`Charger::with_write_verification`]
+    VerifyFailed {
+        /// The register address
+        reg: u8,
+        /// The value written
+        wrote: u8,
+        /// The value read back
+        read: u8,
+    },
+    /// The charger details did not match the requested mode in time, see
+    /// [`Charger::wait_mode_settled`]. Holds the last details read.
+    ModeNotSettled(Details),
+    /// A [`ChargeProfile`] is invalid for this part, see [`ChargeProfile::validate`]
+    InvalidProfile(ProfileError),
+    /// A configuration or profile breaks the enforced [`SafetyPolicy`], see
+    /// [`Charger::with_safety_policy`]
+    PolicyViolation(PolicyViolation),
+    /// Ship mode was not entered, see [`Charger::try_enter_ship_mode`]
+    ShipMode(ShipModeError),
+    /// A bus transaction did not complete in time, see [`Charger::with_timeout`]
+    Timeout,
+    /// The bypass was enabled in another mode than the one being disabled, e.g.
+    /// [`Charger::disable_boost`] after [`Charger::enable_otg`]. Holds the active mode.
+    BypassModeMismatch(Mode),
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(err: E) -> Self {
+        Error::I2c(err)
+    }
+}
+
+/// The supported charger parts
+///
+/// All parts share the same register map and I2C protocol but differ in chip ID, some field
+/// scalings and the availability of a few fields.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Variant {
+    /// MAX77975, 3.5A fast-charge current
+    #[default]
+    Max77975,
+    /// MAX77976, 5.5A fast-charge current
+    Max77976,
+    /// MAX77985, 3.0A fast-charge current
+    Max77985,
+    /// MAX77986, 6.0A fast-charge current
+    Max77986,
+}
+
+/// Per-part parameters
+struct VariantInfo {
+    chip_id: u8,
+    min_fast_charge_current: u16,
+    max_fast_charge_current: u16,
+    fast_charge_current_step: u16,
+    sys_ilim_recycle: bool,
+}
+
+impl Variant {
+    const ALL: [Variant; 4] = [
+        Variant::Max77975,
+        Variant::Max77976,
+        Variant::Max77985,
+        Variant::Max77986,
+    ];
+
+    const fn info(self) -> &'static VariantInfo {
+        match self {
+            Variant::Max77975 => &VariantInfo {
+                chip_id: 0x75,
+                min_fast_charge_current: 100,
+                max_fast_charge_current: 3500,
+                fast_charge_current_step: 50,
+                sys_ilim_recycle: true,
+            },
+            Variant::Max77976 => &VariantInfo {
+                chip_id: 0x76,
+                min_fast_charge_current: 100,
+                max_fast_charge_current: 5500,
+                fast_charge_current_step: 50,
+                sys_ilim_recycle: true,
+            },
+            Variant::Max77985 => &VariantInfo {
+                chip_id: 0x85,
+                min_fast_charge_current: 0,
+                max_fast_charge_current: 3000,
+                fast_charge_current_step: 25,
+                sys_ilim_recycle: false,
+            },
+            Variant::Max77986 => &VariantInfo {
+                chip_id: 0x86,
+                min_fast_charge_current: 0,
+                max_fast_charge_current: 6000,
+                fast_charge_current_step: 50,
+                sys_ilim_recycle: false,
+            },
+        }
+    }
+
+    /// Get the part with the given `CHIP_ID` register value.
+    pub const fn from_chip_id(chip_id: u8) -> Option<Self> {
+        let mut rest = Self::ALL.as_slice();
+        while let [variant, tail @ ..] = rest {
+            if variant.chip_id() == chip_id {
+                return Some(*variant);
+            }
+            rest = tail;
+        }
+        None
+    }
+
+    /// The value of the `CHIP_ID` register for this part.
+    pub const fn chip_id(self) -> u8 {
+        self.info().chip_id
+    }
+
+    /// The minimum fast-charge current in milliamps.
+    ///
+    /// The CHG_CC codes below it select the minimum as well.
+    pub const fn min_fast_charge_current(self) -> u16 {
+        self.info().min_fast_charge_current
+    }
+
+    /// The maximum fast-charge current in milliamps.
+    pub const fn max_fast_charge_current(self) -> u16 {
+        self.info().max_fast_charge_current
+    }
+
+    /// Whether the part can automatically recycle Vsys after a SYS_ILIM shutdown.
+    pub const fn has_sys_ilim_recycle(self) -> bool {
+        self.info().sys_ilim_recycle
+    }
+
+    /// The fast-charge current (CHG_CC) step in milliamps.
+    pub const fn fast_charge_current_step(self) -> u16 {
+        self.info().fast_charge_current_step
+    }
 }
 
-/// A MAX77975/MAX77976 battery charger.
-pub struct Charger<D> {
+/// The number of times [`Charger::force_recharge`] polls the charger state before giving up
+pub const RECHARGE_POLLS: usize = 10;
+
+/// The number of times [`Charger::clear_timer_fault_and_restart`] polls the charger state
+/// before giving up
+pub const TIMER_FAULT_POLLS: usize = 10;
+
+/// The documented register ranges as `(base, count)` pairs, in address order.
+const REG_RANGES: [(Reg, usize); 4] = [
+    (Reg::CHIP_ID, 6),
+    (Reg::CHARGER_INTERRUPT, 21),
+    (Reg::I2C_CONFIG, 1),
+    (Reg::SOFTWARE_RESET, 2),
+];
+
+/// The writable register ranges as inclusive `(first, last)` pairs, in address order.
+const WRITABLE_RANGES: [(Reg, Reg); 6] = [
+    (Reg::TOP_INTERRUPT_MASK, Reg::TOP_CONTROL),
+    (Reg::CHARGER_INTERRUPT_MASK, Reg::CHARGER_INTERRUPT_MASK),
+    (Reg::CHARGER_CONFIG_0, Reg::STATUS_LED_CONFIG),
+    (Reg::I2C_CONFIG, Reg::I2C_CONFIG),
+    (Reg::SOFTWARE_RESET, Reg::SOFTWARE_RESET),
+    (Reg::SHIP_MODE_CONTROL, Reg::SHIP_MODE_CONTROL),
+];
+
+/// Whether the `len` registers from `base` lie within a single writable range.
+fn is_writable_range(base: Reg, len: usize) -> bool {
+    let first = base.address() as usize;
+    WRITABLE_RANGES.iter().any(|&(start, end)| {
+        (start.address() as usize..=end.address() as usize).contains(&first)
+            && first.saturating_add(len.saturating_sub(1)) <= end.address() as usize
+    })
+}
+
+/// A MAX77975/MAX77976/MAX77985/MAX77986 battery charger.
+pub struct Charger<D: ErrorType> {
     i2c_dev: D,
+    /// Converts the errors of `i2c_dev`, see [`Charger::with_timeout`]
+    bus_error: fn(D::Error) -> Error<D::Error>,
+    address: u8,
+    variant: Variant,
+    /// The bypass mode set by `enable_otg`/`enable_boost` and the mode to restore after it
+    bypass: Option<(Mode, Option<Mode>)>,
+    suspended_mode: Option<Mode>,
+    derating: Option<Derating>,
+    read_voting: ReadVoting,
+    verify_writes: bool,
+    cache: Option<ShadowCache>,
+    safety_policy: Option<SafetyPolicy>,
+    #[cfg(feature = "trace")]
+    trace_op: Option<&'static str>,
 }
 
 impl<D: I2c> Charger<D> {
-    /// Create a new `Charger`
+    /// Create a new `Charger` at the [`DEFAULT_ADDRESS`]
+    ///
+    /// The part is assumed to be a [`Variant::Max77975`]. Use [`Charger::new_with_variant`] or
+    /// [`Charger::new_checked`] for other parts.
     pub fn new(i2c_dev: D) -> Self {
-        Charger { i2c_dev }
+        Self::new_with_address(i2c_dev, DEFAULT_ADDRESS)
+    }
+
+    /// Create a new `Charger` at the 7-bit I2C `address`
+    pub fn new_with_address(i2c_dev: D, address: u8) -> Self {
+        Self::with_parts(i2c_dev, address, Variant::default())
+    }
+
+    /// Create a new `Charger` for a known part at the 7-bit I2C `address`
+    pub fn new_with_address_and_variant(i2c_dev: D, address: u8, variant: Variant) -> Self {
+        Self::with_parts(i2c_dev, address, variant)
+    }
+
+    /// The constructor all others go through
+    fn with_parts(i2c_dev: D, address: u8, variant: Variant) -> Self {
+        Charger {
+            i2c_dev,
+            bus_error: Error::I2c,
+            address,
+            variant,
+            bypass: None,
+            suspended_mode: None,
+            derating: None,
+            read_voting: ReadVoting::Off,
+            verify_writes: false,
+            cache: None,
+            safety_policy: None,
+            #[cfg(feature = "trace")]
+            trace_op: None,
+        }
+    }
+
+    /// Create a new `Charger` for a known part at the [`DEFAULT_ADDRESS`]
+    pub fn new_with_variant(i2c_dev: D, variant: Variant) -> Self {
+        Self::with_parts(i2c_dev, DEFAULT_ADDRESS, variant)
+    }
+
+    /// Create a new `Charger` at the [`DEFAULT_ADDRESS`], detecting the part from its chip ID
+    pub async fn new_checked(i2c_dev: D) -> Result<Self, Error<D::Error>> {
+        Self::new_checked_with_address(i2c_dev, DEFAULT_ADDRESS).await
+    }
+
+    /// Create a new `Charger` at the 7-bit I2C `address`, detecting the part from its chip ID
+    pub async fn new_checked_with_address(
+        i2c_dev: D,
+        address: u8,
+    ) -> Result<Self, Error<D::Error>> {
+        let mut charger = Self::new_with_address(i2c_dev, address);
+        charger.detect_variant().await?;
+        Ok(charger)
+    }
+
+    /// Create a new `Charger` at the [`DEFAULT_ADDRESS`] like [`new_checked`](Self::new_checked),
+    /// then provision it with `config`.
+    ///
+    /// The config is written with [`apply_config`](Self::apply_config) and read back with
+    /// [`verify_config`](Self::verify_config), so the driver is only returned once the charger
+    /// holds the requested settings. On failure, the error is returned together with the I2C
+    /// device, so the caller can retry or fall back.
+    pub async fn with_config(
+        i2c_dev: D,
+        config: &ChargerConfig,
+    ) -> Result<Self, (Error<D::Error>, D)> {
+        Self::with_config_and_address(i2c_dev, DEFAULT_ADDRESS, config).await
+    }
+
+    /// Like [`with_config`](Self::with_config), for a charger at the 7-bit I2C `address`.
+    pub async fn with_config_and_address(
+        i2c_dev: D,
+        address: u8,
+        config: &ChargerConfig,
+    ) -> Result<Self, (Error<D::Error>, D)> {
+        let mut charger = Self::new_with_address(i2c_dev, address);
+        let res = async {
+            charger.detect_variant().await?;
+            charger.apply_config(config).await?;
+            charger.verify_config(config).await
+        }
+        .await;
+        match res {
+            Ok(()) => Ok(charger),
+            Err(err) => Err((err, charger.release())),
+        }
+    }
+
+    /// Read the chip ID and select the matching [`Variant`].
+    ///
+    /// Returns [`Error::UnknownChip`] if the chip ID does not belong to a supported part.
+    pub async fn detect_variant(&mut self) -> Result<Variant, Error<D::Error>> {
+        traced!(self, "detect_variant", {
+            let chip_id = self.read_reg(Reg::CHIP_ID).await?;
+            self.variant = Variant::from_chip_id(chip_id).ok_or(Error::UnknownChip(chip_id))?;
+            Ok(self.variant)
+        })
+    }
+
+    /// The part this driver is configured for.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Release the underlying I2C device.
+    pub fn release(self) -> D {
+        self.i2c_dev
     }
 
     /// Set the current limit for Vsys out.
     ///
     /// If the current limit is exceeded, Vsys will be shut off. If `recycle_en` is false, it will remain
     /// shut off until a valid charger is present. Otherwise, it will attempt to recycle after 150ms.
-    pub async fn set_sys_ilim(&mut self, milliamps: u16, recycle_en: bool) -> Result<(), D::Error> {
-        let sys_ilim = (milliamps / 500).saturating_sub(5).min(0xf) as u8;
-        let recycle_en = if recycle_en { 0x10 } else { 0x00 };
-        self.write_protected_reg(Reg::CHARGER_CONFIG_5, recycle_en | sys_ilim)
-            .await
+    ///
+    /// Returns [`Error::Unsupported`] if `recycle_en` is set on a part without
+    /// [`Variant::has_sys_ilim_recycle`].
+    pub async fn set_sys_ilim(
+        &mut self,
+        milliamps: u16,
+        recycle_en: bool,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_sys_ilim", {
+            let update = update_sys_ilim(self.variant, milliamps, recycle_en)?;
+            self.modify(update).await
+        })
     }
 
     /// Set the current limit for CHGIN.
-    pub async fn set_chgin_ilim(&mut self, milliamps: u16) -> Result<(), D::Error> {
-        let chgin_ilim = (milliamps / 50).saturating_sub(1).min(0x3f) as u8;
-        self.modify_reg(Reg::CHARGER_CONFIG_9, |val| (val & 0xc0) | chgin_ilim)
-            .await
+    pub async fn set_chgin_ilim(&mut self, milliamps: u16) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_chgin_ilim", {
+            let chgin_ilim = conv::chgin_ilim_code(milliamps);
+            self.modify_typed(|val: ChargerConfig9| val.with_chgin_ilim(chgin_ilim))
+                .await
+        })
+    }
+
+    /// Returns the current limit for CHGIN in mA.
+    pub async fn chgin_ilim(&mut self) -> Result<u16, Error<D::Error>> {
+        traced!(self, "chgin_ilim", {
+            let code = self.read::<ChargerConfig9>().await?.chgin_ilim();
+            Ok(conv::chgin_ilim_ma(code))
+        })
+    }
+
+    /// Set the junction temperature above which the charge current folds back (REGTEMP).
+    ///
+    /// `celsius` is rounded down to a 15 °C step. Returns [`Error::InvalidValue`] outside the
+    /// 85 °C to 130 °C range, see [`celsius_to_regtemp_code`].
+    pub async fn set_regulation_temperature(&mut self, celsius: u8) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_regulation_temperature", {
+            let code = celsius_to_regtemp_code(celsius).ok_or(Error::InvalidValue)?;
+            self.modify_typed(|val: ChargerConfig10| val.with_regtemp(code))
+                .await
+        })
+    }
+
+    /// Returns the junction temperature regulation threshold (REGTEMP) in °C.
+    pub async fn regulation_temperature(&mut self) -> Result<u8, Error<D::Error>> {
+        traced!(self, "regulation_temperature", {
+            let val = self.read::<ChargerConfig10>().await?;
+            Ok(regtemp_code_to_celsius(val.regtemp()))
+        })
     }
 
     /// Set the current to use during the [`ChargerDetails::ConstantCurrent`] charging phase.
-    pub async fn set_fast_charge_current(&mut self, milliamps: u16) -> Result<(), D::Error> {
-        let chg_cc = (milliamps / 50).min(0x7f) as u8;
-        self.write_protected_reg(Reg::CHARGER_CONFIG_2, chg_cc)
-            .await
+    ///
+    /// Returns [`Error::InvalidValue`] if `milliamps` is below
+    /// [`Variant::min_fast_charge_current`] or exceeds [`Variant::max_fast_charge_current`].
+    pub async fn set_fast_charge_current(&mut self, milliamps: u16) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_fast_charge_current", {
+            let update = update_chg_cc(self.variant, milliamps)?;
+            self.modify(update).await
+        })
+    }
+
+    /// Returns the programmed fast-charge current in mA.
+    pub async fn fast_charge_current(&mut self) -> Result<u16, Error<D::Error>> {
+        traced!(self, "fast_charge_current", {
+            let code = self.read::<ChargerConfig2>().await?.chg_cc();
+            Ok(conv::chg_cc_ma(code, self.variant))
+        })
+    }
+
+    /// Move the fast-charge current to `target_ma` in steps of at most `step_ma`.
+    ///
+    /// Each step is written in its own [`ProtectedSession`], waiting `step_delay_us` on `delay`
+    /// between steps with protection locked again, to avoid the transient of a single large
+    /// change. Every step is a valid setting, so if the returned future is dropped mid-ramp the
+    /// charger keeps the last step written; protection is only left unlocked if it is dropped
+    /// while a step is being written, as with [`protected`](Self::protected).
+    ///
+    /// Returns [`Error::InvalidValue`] if `step_ma` is zero or `target_ma` exceeds
+    /// [`Variant::max_fast_charge_current`].
+    pub async fn ramp_fast_charge_current(
+        &mut self,
+        target_ma: u16,
+        step_ma: u16,
+        delay: &mut impl DelayNs,
+        step_delay_us: u32,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "ramp_fast_charge_current", {
+            if step_ma == 0 || conv::chg_cc_code(target_ma, self.variant).is_none() {
+                return Err(Error::InvalidValue);
+            }
+            let mut current = self.fast_charge_current().await?;
+            while current != target_ma {
+                current = if target_ma > current {
+                    current.saturating_add(step_ma).min(target_ma)
+                } else {
+                    current.saturating_sub(step_ma).max(target_ma)
+                };
+                self.protected(async |session| session.set_fast_charge_current(current).await)
+                    .await?;
+                if current != target_ma {
+                    delay.delay_us(step_delay_us).await;
+                }
+            }
+            Ok(())
+        })
     }
 
     /// Set the charger [`Mode`].
-    pub async fn set_mode(&mut self, mode: Mode) -> Result<(), D::Error> {
-        self.write_reg(Reg::CHARGER_CONFIG_0, mode as u8).await
+    ///
+    /// The other fields of [`ChargerConfig0`] are left untouched.
+    pub async fn set_mode(&mut self, mode: Mode) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_mode", {
+            self.modify_typed(|val: ChargerConfig0| val.with_mode(mode))
+                .await
+        })
+    }
+
+    /// Read the charger [`Mode`].
+    ///
+    /// Fails with [`Error::InvalidValue`] if the mode bits hold a reserved code.
+    pub async fn mode(&mut self) -> Result<Mode, Error<D::Error>> {
+        traced!(self, "mode", {
+            self.charger_config0()
+                .await?
+                .mode()
+                .map_err(|_| Error::InvalidValue)
+        })
+    }
+
+    /// Open or close the QBATT switch between the battery and the system from software
+    /// (DISIBS, the register equivalent of the DISQBAT pin).
+    ///
+    /// **Disabling QBATT with no valid input attached powers the system down**, including the
+    /// host running this driver: nothing but the battery supplies it, so nothing can enable
+    /// QBATT again until an input is attached. Only disable it with an input attached, e.g. in
+    /// production testing to measure leakage. While it is disabled, charging is suspended and
+    /// the charger reports [`ChargerDetails::QBattDisabled`].
+    ///
+    /// The switch also stays open while the DISQBAT pin is high, whatever is written here.
+    /// The other fields of [`ChargerConfig0`] are left untouched.
+    pub async fn set_qbatt(&mut self, enabled: bool) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_qbatt", {
+            self.modify_typed(|val: ChargerConfig0| val.with_disibs(!enabled))
+                .await
+        })
+    }
+
+    /// Whether the QBATT switch is enabled from software, see [`set_qbatt`](Self::set_qbatt).
+    ///
+    /// The DISQBAT pin is not reflected.
+    pub async fn qbatt_enabled(&mut self) -> Result<bool, Error<D::Error>> {
+        traced!(self, "qbatt_enabled", {
+            Ok(!self.charger_config0().await?.disibs())
+        })
+    }
+
+    /// Park the converter in its low power standby state, or release it (STBY_EN).
+    ///
+    /// In standby the converter stops switching whatever the [`Mode`], so with a valid input
+    /// attached nothing is charged and VSYS is supplied by the battery through QBATT, as
+    /// without an input. This minimizes the quiescent current drawn from the input; the crate
+    /// has no measured figures, see the electrical characteristics of the datasheet. The mode
+    /// is kept and takes effect again when standby is released.
+    ///
+    /// The other fields of [`ChargerConfig0`] are left untouched, so this composes with
+    /// [`set_mode`](Self::set_mode) and the watchdog enable bit.
+    pub async fn set_standby(&mut self, enabled: bool) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_standby", {
+            self.modify_typed(|val: ChargerConfig0| val.with_standby_enable(enabled))
+                .await
+        })
+    }
+
+    /// Whether the converter is parked in standby, see [`set_standby`](Self::set_standby).
+    pub async fn standby(&mut self) -> Result<bool, Error<D::Error>> {
+        traced!(self, "standby", {
+            Ok(self.charger_config0().await?.standby_enable())
+        })
+    }
+
+    /// Enable or disable detection of the SP and SN remote battery sense lines (SPSN_DET_EN).
+    ///
+    /// While enabled, the charger checks the lines and reports them in [`Details::sense`]: an
+    /// open line reads as [`BatterySense::PositiveOpen`], [`BatterySense::NegativeOpen`] or
+    /// [`BatterySense::BothOpen`], and the charger regulates the battery voltage at its BATT
+    /// pin instead of across the lines. While disabled, the lines are not checked and the
+    /// sense field reads [`BatterySense::Connected`] whatever their state, so the regulation
+    /// point is set by the board wiring alone. On boards that do not route remote sense, tie
+    /// SP and SN as the datasheet describes before disabling detection, as an open line is no
+    /// longer caught.
+    ///
+    /// Only the SPSN_DET_EN bit of `CHARGER_CONFIG_13` is modified.
+    pub async fn set_remote_sense(&mut self, enabled: bool) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_remote_sense", {
+            self.modify_typed(|val: ChargerConfig13| val.with_spsn_detect_enable(enabled))
+                .await
+        })
+    }
+
+    /// Whether remote sense line detection is enabled, see
+    /// [`set_remote_sense`](Self::set_remote_sense).
+    pub async fn remote_sense(&mut self) -> Result<bool, Error<D::Error>> {
+        traced!(self, "remote_sense", {
+            Ok(self.read::<ChargerConfig13>().await?.spsn_detect_enable())
+        })
+    }
+
+    /// Pause charging while keeping the system powered.
+    ///
+    /// The current mode is recorded and the charger switches to [`Mode::Buck`]. Suspending again
+    /// while suspended keeps the originally recorded mode.
+    pub async fn suspend_charging(&mut self) -> Result<(), Error<D::Error>> {
+        traced!(self, "suspend_charging", {
+            if self.suspended_mode.is_none() {
+                self.suspended_mode = Some(self.mode().await?);
+            }
+            self.set_mode(Mode::Buck).await
+        })
+    }
+
+    /// Resume charging after [`suspend_charging`](Self::suspend_charging).
+    ///
+    /// Restores the recorded mode if a valid input is present, otherwise fails with
+    /// [`Error::NoInput`] and stays suspended. Does nothing if charging is not suspended.
+    pub async fn resume_charging(&mut self) -> Result<(), Error<D::Error>> {
+        traced!(self, "resume_charging", {
+            let Some(mode) = self.suspended_mode else {
+                return Ok(());
+            };
+            if self.chgin_status().await? != ChgIn::Valid {
+                return Err(Error::NoInput);
+            }
+            self.set_mode(mode).await?;
+            self.suspended_mode = None;
+            Ok(())
+        })
+    }
+
+    /// Read the [`ChargerConfig0`] register.
+    pub async fn charger_config0(&mut self) -> Result<ChargerConfig0, Error<D::Error>> {
+        traced!(self, "charger_config0", { self.read().await })
+    }
+
+    /// Write the [`ChargerConfig0`] register.
+    pub async fn set_charger_config0(
+        &mut self,
+        config: ChargerConfig0,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_charger_config0", {
+            self.write_typed(config).await
+        })
+    }
+
+    /// Clear a charge timer fault and restart charging.
+    ///
+    /// Fails with [`Error::UnexpectedState`] unless the charger or battery details report a timer
+    /// fault. The charger is then switched to [`Mode::Off`] and back to its previous mode, which
+    /// restarts the fast-charge timer. The charger state is polled every `interval_ms`, up to
+    /// [`TIMER_FAULT_POLLS`] times, until it leaves [`ChargerDetails::TimerFault`]; if it never
+    /// does, [`Error::UnexpectedState`] is returned.
+    pub async fn clear_timer_fault_and_restart(
+        &mut self,
+        delay: impl DelayNs,
+        interval_ms: u32,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "clear_timer_fault_and_restart", {
+            let details = self.charger_details().await?;
+            if details.charger() != ChargerDetails::TimerFault
+                && details.battery() != BatteryDetails::TimerFault
+            {
+                return Err(Error::UnexpectedState(details.charger()));
+            }
+            let config0 = self.charger_config0().await?;
+            self.set_charger_config0(config0.with_mode(Mode::Off))
+                .await?;
+            self.set_charger_config0(config0).await?;
+            self.poll_charger_state(delay, interval_ms, TIMER_FAULT_POLLS, |state| {
+                state != ChargerDetails::TimerFault
+            })
+            .await?;
+            Ok(())
+        })
+    }
+
+    /// Start a new charge cycle, even if the current one is [`ChargerDetails::Done`].
+    ///
+    /// The charger is switched to [`Mode::Off`] and back to its previous mode, then the charger
+    /// state is polled every `interval_ms`, up to [`RECHARGE_POLLS`] times, until it reports a
+    /// charging state. If it never does, [`Error::UnexpectedState`] is returned with the last
+    /// state read.
+    pub async fn force_recharge(
+        &mut self,
+        delay: impl DelayNs,
+        interval_ms: u32,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "force_recharge", {
+            let config0 = self.charger_config0().await?;
+            self.set_charger_config0(config0.with_mode(Mode::Off))
+                .await?;
+            self.set_charger_config0(config0).await?;
+            self.poll_charger_state(delay, interval_ms, RECHARGE_POLLS, |state| {
+                state.classify() == ChargeStateClass::Charging
+            })
+            .await?;
+            Ok(())
+        })
+    }
+
+    /// Poll the charger state every `interval_ms`, waiting before each of at most `polls` reads,
+    /// until `done` accepts it, and return that state. Fails with [`Error::UnexpectedState`]
+    /// holding the last state.
+    async fn poll_charger_state(
+        &mut self,
+        mut delay: impl DelayNs,
+        interval_ms: u32,
+        polls: usize,
+        done: impl Fn(ChargerDetails) -> bool,
+    ) -> Result<ChargerDetails, Error<D::Error>> {
+        let mut state = ChargerDetails::Off;
+        for _ in 0..polls {
+            delay.delay_ms(interval_ms).await;
+            state = self.charger_state().await?;
+            if done(state) {
+                return Ok(state);
+            }
+        }
+        Err(Error::UnexpectedState(state))
+    }
+
+    /// Start sourcing VBYP/CHGIN from the battery in [`Mode::Otg`].
+    ///
+    /// The OTG current limit is rounded down to the nearest supported setting (500 mA to
+    /// 3000 mA) and the bypass voltage to a 20 mV step between 3000 mV and 5540 mV. The bypass
+    /// interrupt is masked while the limits and the mode are written, and the previous charger
+    /// interrupt mask is restored afterwards, also if a write fails. Fails with
+    /// [`Error::InputPresent`] if CHGIN has a valid input, since the charger cannot source into
+    /// an adapter.
+    ///
+    /// The current mode is restored by [`disable_otg`](Self::disable_otg).
+    pub async fn enable_otg(
+        &mut self,
+        current_limit_ma: u16,
+        vbus_mv: u16,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "enable_otg", {
+            let ilim = conv::otg_ilim_code(current_limit_ma).ok_or(Error::InvalidValue)?;
+            let vbypset = conv::vbypset_code(vbus_mv).ok_or(Error::InvalidValue)?;
+            if self.chgin_status().await? == ChgIn::Valid {
+                return Err(Error::InputPresent);
+            }
+            let bypass = ChargerInterrupts::from_sources(&[ChargerIrqSource::BypassNode]);
+            let mode = self.charger_config0().await?.mode().ok();
+            let irq_mask = self.read_reg(Reg::CHARGER_INTERRUPT_MASK).await?;
+            self.write_reg(Reg::CHARGER_INTERRUPT_MASK, irq_mask | bypass.bits())
+                .await?;
+            let res = self.start_otg(ilim, vbypset).await;
+            let restored = self.write_reg(Reg::CHARGER_INTERRUPT_MASK, irq_mask).await;
+            res?;
+            self.enter_bypass(Mode::Otg, mode);
+            restored
+        })
+    }
+
+    /// Write the OTG limits and switch to [`Mode::Otg`], see [`enable_otg`](Self::enable_otg).
+    async fn start_otg(&mut self, ilim: u8, vbypset: u8) -> Result<(), Error<D::Error>> {
+        self.modify_typed(|val: ChargerConfig12| val.with_otg_ilim(ilim))
+            .await?;
+        self.modify_typed(|val: ChargerConfig11| val.with_vbypset(vbypset))
+            .await?;
+        self.set_mode(Mode::Otg).await
+    }
+
+    /// Record that the bypass runs in `active`, keeping the mode to restore if switching
+    /// between OTG and boost.
+    fn enter_bypass(&mut self, active: Mode, previous: Option<Mode>) {
+        let restore = self.bypass.map_or(previous, |(_, restore)| restore);
+        self.bypass = Some((active, restore));
+    }
+
+    /// Leave the bypass mode `active`, restoring the mode that was active before it was enabled,
+    /// or [`Mode::Off`] if it was not enabled through this driver.
+    async fn leave_bypass(&mut self, active: Mode) -> Result<(), Error<D::Error>> {
+        let restore = match self.bypass {
+            Some((mode, _)) if mode != active => return Err(Error::BypassModeMismatch(mode)),
+            Some((_, restore)) => restore,
+            None => None,
+        };
+        self.set_mode(restore.unwrap_or(Mode::Off)).await?;
+        self.bypass = None;
+        Ok(())
+    }
+
+    /// Stop OTG, returning to the mode that was active before [`enable_otg`](Self::enable_otg).
+    ///
+    /// Switches to [`Mode::Off`] if OTG was not enabled through `enable_otg`. Fails with
+    /// [`Error::BypassModeMismatch`] without writing anything if the boost was enabled through
+    /// [`enable_boost`](Self::enable_boost) instead.
+    pub async fn disable_otg(&mut self) -> Result<(), Error<D::Error>> {
+        traced!(self, "disable_otg", { self.leave_bypass(Mode::Otg).await })
+    }
+
+    /// Start regulating VBYP from the battery in [`Mode::Boost`], to power a load on BYP.
+    ///
+    /// Unlike [`enable_otg`](Self::enable_otg), CHGIN is not sourced. The bypass voltage is
+    /// rounded down to a 20 mV step between 3000 mV and 5540 mV. Fails with
+    /// [`Error::InputPresent`] if CHGIN has a valid input, since the boost does not run with an
+    /// adapter attached.
+    ///
+    /// The current mode is restored by [`disable_boost`](Self::disable_boost). See
+    /// [`enable_boost_settled`](Self::enable_boost_settled) to also wait for the boost to start.
+    pub async fn enable_boost(&mut self, vbyp_mv: u16) -> Result<(), Error<D::Error>> {
+        traced!(self, "enable_boost", {
+            let vbypset = conv::vbypset_code(vbyp_mv).ok_or(Error::InvalidValue)?;
+            if self.chgin_status().await? == ChgIn::Valid {
+                return Err(Error::InputPresent);
+            }
+            let mode = self.charger_config0().await?.mode().ok();
+            self.modify_typed(|val: ChargerConfig11| val.with_vbypset(vbypset))
+                .await?;
+            self.set_mode(Mode::Boost).await?;
+            self.enter_bypass(Mode::Boost, mode);
+            Ok(())
+        })
+    }
+
+    /// Like [`enable_boost`](Self::enable_boost), then wait up to `timeout_ms` for the bypass
+    /// node to report the boost on, see [`wait_mode_settled`](Self::wait_mode_settled).
+    ///
+    /// If the boost does not start, e.g. because the battery is too low, the previous mode is
+    /// restored and [`Error::ModeNotSettled`] is returned with the last details read.
+    pub async fn enable_boost_settled(
+        &mut self,
+        vbyp_mv: u16,
+        delay: impl DelayNs,
+        timeout_ms: u32,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "enable_boost_settled", {
+            self.enable_boost(vbyp_mv).await?;
+            match self.wait_mode_settled(Mode::Boost, delay, timeout_ms).await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    self.disable_boost().await?;
+                    Err(err)
+                }
+            }
+        })
+    }
+
+    /// Stop the boost, returning to the mode that was active before
+    /// [`enable_boost`](Self::enable_boost).
+    ///
+    /// Switches to [`Mode::Off`] if the boost was not enabled through `enable_boost`. Fails with
+    /// [`Error::BypassModeMismatch`] without writing anything if OTG was enabled through
+    /// [`enable_otg`](Self::enable_otg) instead.
+    pub async fn disable_boost(&mut self) -> Result<(), Error<D::Error>> {
+        traced!(self, "disable_boost", {
+            self.leave_bypass(Mode::Boost).await
+        })
     }
 
     /// Enter ship mode.
     ///
     /// All power will be shut down and remain off until a valid charger is present. Ship mode
-    /// can not be enetered when a valid charger is present.
-    pub async fn enter_ship_mode(&mut self) -> Result<(), D::Error> {
-        self.write_reg(Reg::SHIP_MODE_CONTROL, 0x01).await
+    /// can not be enetered when a valid charger is present, in which case the request is
+    /// silently ignored; use [`try_enter_ship_mode`](Self::try_enter_ship_mode) to detect that.
+    pub async fn enter_ship_mode(&mut self) -> Result<(), Error<D::Error>> {
+        traced!(self, "enter_ship_mode", {
+            self.write_reg(Reg::SHIP_MODE_CONTROL, 0x01).await
+        })
     }
 
     /// Enable charger interrupts.
     ///
     /// Fields set to `true` in `irqs` will have their interrupts enabled.
-    pub async fn set_charger_irq_mask(&mut self, irqs: ChargerInterrupts) -> Result<(), D::Error> {
-        self.write_reg(Reg::CHARGER_INTERRUPT_MASK, !irqs.into_bytes()[0])
-            .await
+    pub async fn set_charger_irq_mask(
+        &mut self,
+        irqs: ChargerInterrupts,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_charger_irq_mask", {
+            self.write_reg(Reg::CHARGER_INTERRUPT_MASK, !irqs.into_bytes()[0])
+                .await
+        })
+    }
+
+    /// Enable the charger interrupts set in `irqs`, leaving the others unchanged.
+    pub async fn enable_charger_irqs(
+        &mut self,
+        irqs: ChargerInterrupts,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "enable_charger_irqs", {
+            self.modify_reg(Reg::CHARGER_INTERRUPT_MASK, |mask| mask & !irqs.bits())
+                .await
+        })
+    }
+
+    /// Disable the charger interrupts set in `irqs`, leaving the others unchanged.
+    pub async fn disable_charger_irqs(
+        &mut self,
+        irqs: ChargerInterrupts,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "disable_charger_irqs", {
+            self.modify_reg(Reg::CHARGER_INTERRUPT_MASK, |mask| mask | irqs.bits())
+                .await
+        })
+    }
+
+    /// Enable TOP interrupts.
+    ///
+    /// Fields set to `true` in `irqs` will have their interrupts enabled.
+    pub async fn set_top_irq_mask(&mut self, irqs: TopInterrupts) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_top_irq_mask", {
+            self.write_reg(Reg::TOP_INTERRUPT_MASK, !irqs.into_bytes()[0])
+                .await
+        })
+    }
+
+    /// Enable the TOP interrupts set in `irqs`, leaving the others unchanged.
+    pub async fn enable_top_irqs(&mut self, irqs: TopInterrupts) -> Result<(), Error<D::Error>> {
+        traced!(self, "enable_top_irqs", {
+            self.modify_reg(Reg::TOP_INTERRUPT_MASK, |mask| mask & !irqs.bits())
+                .await
+        })
+    }
+
+    /// Disable the TOP interrupts set in `irqs`, leaving the others unchanged.
+    pub async fn disable_top_irqs(&mut self, irqs: TopInterrupts) -> Result<(), Error<D::Error>> {
+        traced!(self, "disable_top_irqs", {
+            self.modify_reg(Reg::TOP_INTERRUPT_MASK, |mask| mask | irqs.bits())
+                .await
+        })
+    }
+
+    /// Reads and clears the current TOP interrupt flags
+    pub async fn top_irq_flags(&mut self) -> Result<TopInterrupts, Error<D::Error>> {
+        traced!(self, "top_irq_flags", {
+            self.read_reg(Reg::TOP_INTERRUPT)
+                .await
+                .map(|x| TopInterrupts::from_bytes([x]))
+        })
+    }
+
+    /// Wait for the nIRQ pin to assert and collect the pending events.
+    ///
+    /// Returns immediately if `irq_pin` is already low. The TOP and charger interrupt flags are
+    /// read and cleared, so every source that latched while the pin was asserted is reported in
+    /// the returned [`ChargerEvent`], along with a snapshot of the charger [`Details`] taken
+    /// afterwards. If a new event latches after the flags are read the pin stays asserted and the
+    /// next call returns immediately.
+    pub async fn wait_for_event<P: Wait>(
+        &mut self,
+        irq_pin: &mut P,
+    ) -> Result<ChargerEvent, Error<D::Error>> {
+        traced!(self, "wait_for_event", {
+            irq_pin.wait_for_low().await.map_err(|_| Error::Pin)?;
+            let IrqSummary { top, charger } = self.irq_summary().await?;
+            let details = self.charger_details().await?;
+            Ok(ChargerEvent {
+                top,
+                charger,
+                details,
+            })
+        })
     }
 
     /// Reads and clears the current charger interrupt flags
-    pub async fn charger_irq_flags(&mut self) -> Result<ChargerInterrupts, D::Error> {
-        self.read_reg(Reg::CHARGER_INTERRUPT)
-            .await
-            .map(|x| ChargerInterrupts::from_bytes([x]))
+    pub async fn charger_irq_flags(&mut self) -> Result<ChargerInterrupts, Error<D::Error>> {
+        traced!(self, "charger_irq_flags", {
+            self.read_reg(Reg::CHARGER_INTERRUPT)
+                .await
+                .map(|x| ChargerInterrupts::from_bytes([x]))
+        })
+    }
+
+    /// Reads and clears both the TOP and the charger interrupt flags
+    ///
+    /// An interrupt handler can call this until [`IrqSummary::any`] returns `false` to make sure
+    /// no source is left latched.
+    pub async fn irq_summary(&mut self) -> Result<IrqSummary, Error<D::Error>> {
+        traced!(self, "irq_summary", {
+            let top = self.top_irq_flags().await?;
+            let charger = self.charger_irq_flags().await?;
+            Ok(IrqSummary { top, charger })
+        })
     }
 
     /// Clears the charger interrupt flags and returns the current status bits
@@ -126,75 +1242,746 @@ impl<D: I2c> Charger<D> {
     /// This method reads from `Reg::CHARGER_INTERRUPT` through `Reg::CHARGER_INTERRUPT_STATUS`
     /// in a single transaction to atomically clear the interrupt flags and return the current
     /// status bits.
-    pub async fn charger_status(&mut self) -> Result<ChargerInterrupts, D::Error> {
-        let mut buf = [0; 3];
-        self.read_buf(Reg::CHARGER_INTERRUPT, &mut buf)
-            .await
-            .map(|_| ChargerInterrupts::from_bytes([buf[2]]))
+    ///
+    /// Use [`charger_status_peek`](Self::charger_status_peek) to read the status bits without
+    /// clearing the flags, e.g. from a polling task running alongside an interrupt handler.
+    pub async fn charger_status(&mut self) -> Result<ChargerStatus, Error<D::Error>> {
+        traced!(self, "charger_status", {
+            let mut buf = [0; 3];
+            self.read_buf(Reg::CHARGER_INTERRUPT, &mut buf)
+                .await
+                .map(|_| ChargerStatus::from_bytes([buf[2]]))
+        })
+    }
+
+    /// Returns the current status bits without clearing the charger interrupt flags
+    ///
+    /// Only `Reg::CHARGER_INTERRUPT_STATUS` is read, so flags latched in
+    /// `Reg::CHARGER_INTERRUPT` remain pending for [`charger_irq_flags`](Self::charger_irq_flags)
+    /// or [`wait_for_event`](Self::wait_for_event).
+    pub async fn charger_status_peek(&mut self) -> Result<ChargerStatus, Error<D::Error>> {
+        traced!(self, "charger_status_peek", {
+            self.read_reg(Reg::CHARGER_INTERRUPT_STATUS)
+                .await
+                .map(|x| ChargerStatus::from_bytes([x]))
+        })
+    }
+
+    /// Clears the charger interrupt flags and returns them with the status bits and details
+    ///
+    /// `Reg::CHARGER_INTERRUPT` through `Reg::CHARGER_DETAILS_2` are read in a single
+    /// transaction, so all three parts of the returned [`FullStatus`] are a consistent snapshot.
+    pub async fn full_status(&mut self) -> Result<FullStatus, Error<D::Error>> {
+        traced!(self, "full_status", {
+            let mut buf = [0; 6];
+            self.read_buf(Reg::CHARGER_INTERRUPT, &mut buf).await?;
+            Ok(FullStatus::from_bytes(buf))
+        })
     }
 
     /// Get the detailed status of the charger.
-    pub async fn charger_details(&mut self) -> Result<Details, D::Error> {
-        let mut buf = [0; 3];
-        self.read_buf(Reg::CHARGER_DETAILS_0, &mut buf).await?;
-        Ok(Details::from_bytes(buf))
+    pub async fn charger_details(&mut self) -> Result<Details, Error<D::Error>> {
+        traced!(self, "charger_details", {
+            let mut buf = [0; 3];
+            self.read_buf(Reg::CHARGER_DETAILS_0, &mut buf).await?;
+            Ok(Details::from_bytes(buf))
+        })
     }
 
-    async fn read_reg(&mut self, reg: Reg) -> Result<u8, D::Error> {
-        let mut val = 0u8;
-        self.i2c_dev
-            .write_read(
-                ADDR,
-                core::slice::from_ref(&reg.to_u8()),
-                core::slice::from_mut(&mut val),
+    /// Get the CHGIN input status.
+    ///
+    /// Only `Reg::CHARGER_DETAILS_0` is read, making this cheaper than
+    /// [`charger_details`](Self::charger_details) for polling whether an input is attached.
+    pub async fn chgin_status(&mut self) -> Result<ChgIn, Error<D::Error>> {
+        traced!(self, "chgin_status", {
+            self.read_details_reg(Reg::CHARGER_DETAILS_0)
+                .await
+                .map(|details| details.chgin())
+        })
+    }
+
+    /// Get the remote battery sense status.
+    ///
+    /// Only `Reg::CHARGER_DETAILS_0` is read.
+    pub async fn battery_sense(&mut self) -> Result<BatterySense, Error<D::Error>> {
+        traced!(self, "battery_sense", {
+            self.read_details_reg(Reg::CHARGER_DETAILS_0)
+                .await
+                .map(|details| details.sense())
+        })
+    }
+
+    /// Get the battery status and temperature regulation state.
+    ///
+    /// Only `Reg::CHARGER_DETAILS_1` is read.
+    pub async fn battery_status(&mut self) -> Result<BatteryStatus, Error<D::Error>> {
+        traced!(self, "battery_status", {
+            self.read_details_reg(Reg::CHARGER_DETAILS_1)
+                .await
+                .map(|details| BatteryStatus {
+                    battery: details.battery(),
+                    temp: details.temp(),
+                })
+        })
+    }
+
+    /// Get the charger state.
+    ///
+    /// Only `Reg::CHARGER_DETAILS_1` is read. Every 4-bit code decodes, reserved codes included.
+    pub async fn charger_state(&mut self) -> Result<ChargerDetails, Error<D::Error>> {
+        traced!(self, "charger_state", {
+            self.read_details_reg(Reg::CHARGER_DETAILS_1)
+                .await
+                .map(|details| details.charger())
+        })
+    }
+
+    /// Get the thermistor status.
+    ///
+    /// Only `Reg::CHARGER_DETAILS_2` is read. [`ThermistorDetails::Removed`] and
+    /// [`ThermistorDetails::Disabled`] are not temperature zones: they persist until the battery
+    /// is reattached or monitoring is re-enabled, and no temperature information is available
+    /// while they are reported.
+    pub async fn thermistor_status(&mut self) -> Result<ThermistorDetails, Error<D::Error>> {
+        traced!(self, "thermistor_status", {
+            self.read_details_reg(Reg::CHARGER_DETAILS_2)
+                .await
+                .map(|details| details.thermistor())
+        })
+    }
+
+    /// Get the bypass node status.
+    ///
+    /// Only `Reg::CHARGER_DETAILS_2` is read.
+    pub async fn bypass_status(&mut self) -> Result<BypassNodeDetails, Error<D::Error>> {
+        traced!(self, "bypass_status", {
+            self.read_details_reg(Reg::CHARGER_DETAILS_2)
+                .await
+                .map(|details| details.bypass())
+        })
+    }
+
+    /// Apply a [`ChargerConfig`].
+    ///
+    /// The protected settings are written in a single [`ProtectedSession`], reading and writing
+    /// `CHARGER_CONFIG_2` through `CHARGER_CONFIG_5` in one burst each, and the mode is written
+    /// last, once the limits it depends on are in place. Fails with [`Error::PolicyViolation`]
+    /// before writing anything if the config breaks the [safety policy](Self::with_safety_policy).
+    pub async fn apply_config(&mut self, config: &ChargerConfig) -> Result<(), Error<D::Error>> {
+        traced!(self, "apply_config", {
+            if let Some(policy) = &self.safety_policy {
+                config
+                    .check_policy(policy)
+                    .map_err(Error::PolicyViolation)?;
+            }
+            let update_cc = update_chg_cc(self.variant, config.fast_charge_current_ma)?;
+            let update_sys_ilim =
+                update_sys_ilim(self.variant, config.sys_ilim_ma, config.sys_ilim_recycle)?;
+            self.protected(async |session| {
+                let mut regs = [0; 4];
+                session
+                    .charger
+                    .read_buf(Reg::CHARGER_CONFIG_2, &mut regs)
+                    .await?;
+                regs[0] = update_cc(ChargerConfig2::from_byte(regs[0])).to_byte();
+                regs[3] = update_sys_ilim(ChargerConfig5::from_byte(regs[3])).to_byte();
+                session
+                    .charger
+                    .write_buf(Reg::CHARGER_CONFIG_2, &regs)
+                    .await
+            })
+            .await?;
+            self.set_chgin_ilim(config.chgin_ilim_ma).await?;
+            self.set_charger_irq_mask(config.charger_irqs).await?;
+            self.set_mode(config.mode).await
+        })
+    }
+
+    /// Check that the charger holds the settings of a [`ChargerConfig`].
+    ///
+    /// The registers written by [`apply_config`](Self::apply_config) are read back and only
+    /// the fields it sets are compared. Fails with [`Error::VerifyFailed`] for the first
+    /// register that differs, where `wrote` is the value `apply_config` would leave in it.
+    pub async fn verify_config(&mut self, config: &ChargerConfig) -> Result<(), Error<D::Error>> {
+        traced!(self, "verify_config", {
+            let update_cc = update_chg_cc(self.variant, config.fast_charge_current_ma)?;
+            let update_sys_ilim =
+                update_sys_ilim(self.variant, config.sys_ilim_ma, config.sys_ilim_recycle)?;
+            let mut regs = [0; 4];
+            self.read_buf(Reg::CHARGER_CONFIG_2, &mut regs).await?;
+            let config2 = update_cc(ChargerConfig2::from_byte(regs[0])).to_byte();
+            check_config(Reg::CHARGER_CONFIG_2, config2, regs[0])?;
+            let config5 = update_sys_ilim(ChargerConfig5::from_byte(regs[3])).to_byte();
+            check_config(Reg::CHARGER_CONFIG_5, config5, regs[3])?;
+
+            let read = self.read_reg(Reg::CHARGER_CONFIG_9).await?;
+            let chgin_ilim = conv::chgin_ilim_code(config.chgin_ilim_ma);
+            let config9 = ChargerConfig9::from_byte(read).with_chgin_ilim(chgin_ilim);
+            check_config(Reg::CHARGER_CONFIG_9, config9.to_byte(), read)?;
+
+            let read = self.read_reg(Reg::CHARGER_INTERRUPT_MASK).await?;
+            check_config(
+                Reg::CHARGER_INTERRUPT_MASK,
+                !config.charger_irqs.into_bytes()[0],
+                read,
+            )?;
+
+            let read = self.read_reg(Reg::CHARGER_CONFIG_0).await?;
+            let config0 = ChargerConfig0::from_byte(read).with_mode(config.mode);
+            check_config(Reg::CHARGER_CONFIG_0, config0.to_byte(), read)
+        })
+    }
+
+    /// Unlock the CHGPROT protected charger settings for the duration of `f`.
+    ///
+    /// Protection is unlocked once before `f` runs and locked again after it returns, including
+    /// when `f` fails, so several protected settings can be applied without toggling the
+    /// protection around each write. Sessions can not be nested. If `f` fails, its error is
+    /// returned even if locking fails as well.
+    ///
+    /// If the returned future is dropped while `f` is running, protection is left unlocked.
+    pub async fn protected<T, F>(&mut self, f: F) -> Result<T, Error<D::Error>>
+    where
+        F: AsyncFnOnce(&mut ProtectedSession<'_, D>) -> Result<T, Error<D::Error>>,
+    {
+        traced!(self, "protected", {
+            self.write_typed(ChargerConfig6::new().with_chgprot(CHGPROT_UNLOCK))
+                .await?;
+            let res = f(&mut ProtectedSession { charger: self }).await;
+            let locked = self.write_typed(ChargerConfig6::new()).await;
+            let out = res?;
+            locked?;
+            Ok(out)
+        })
+    }
+
+    /// Read every documented register into `buf`.
+    ///
+    /// This is intended for attaching a full register snapshot to bug reports. Note that reading
+    /// the interrupt registers clears any latched interrupt flags.
+    pub async fn dump_registers<'a>(
+        &mut self,
+        buf: &'a mut [u8; RegisterDump::LEN],
+    ) -> Result<RegisterDump<'a>, Error<D::Error>> {
+        traced!(self, "dump_registers", {
+            let mut rest = buf.as_mut_slice();
+            for (base, count) in REG_RANGES {
+                // The ranges add up to RegisterDump::LEN, so the buffer never runs out
+                let Some((regs, tail)) = core::mem::take(&mut rest).split_at_mut_checked(count)
+                else {
+                    break;
+                };
+                self.read_buf(base, regs).await?;
+                rest = tail;
+            }
+            Ok(RegisterDump { buf })
+        })
+    }
+
+    /// Write a previously captured [`RegisterDump`] back to the charger.
+    ///
+    /// Only the writable configuration registers are restored; identification, interrupt, status
+    /// and detail registers are skipped, as are `SOFTWARE_RESET`, `SHIP_MODE_CONTROL` and the
+    /// CHGPROT/WDTCLR register. The protected registers are written while CHGPROT is unlocked and
+    /// protection is re-enabled afterwards, even if one of the writes fails. The charger mode is
+    /// restored after the limits it depends on.
+    pub async fn restore_registers(
+        &mut self,
+        dump: &RegisterDump<'_>,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "restore_registers", {
+            self.protected(async |session| session.restore(dump).await)
+                .await?;
+
+            self.write_reg(Reg::CHARGER_CONFIG_0, dump.value(Reg::CHARGER_CONFIG_0))
+                .await?;
+            self.write_reg(
+                Reg::CHARGER_INTERRUPT_MASK,
+                dump.value(Reg::CHARGER_INTERRUPT_MASK),
             )
             .await?;
+            self.write_buf(
+                Reg::TOP_INTERRUPT_MASK,
+                dump.range(Reg::TOP_INTERRUPT_MASK, Reg::TOP_CONTROL),
+            )
+            .await?;
+            self.write_reg(Reg::I2C_CONFIG, dump.value(Reg::I2C_CONFIG))
+                .await
+        })
+    }
+
+    async fn read_reg(&mut self, reg: Reg) -> Result<u8, Error<D::Error>> {
+        let mut val = 0u8;
+        self.read_buf(reg, core::slice::from_mut(&mut val)).await?;
         Ok(val)
     }
 
-    async fn read_buf(&mut self, base: Reg, buf: &mut [u8]) -> Result<(), D::Error> {
+    /// Read a single `CHARGER_DETAILS_n` register into [`Details`], leaving the fields of the
+    /// other registers zeroed, so the bit positions are shared with the full read.
+    async fn read_details_reg(&mut self, reg: Reg) -> Result<Details, Error<D::Error>> {
+        let mut bytes = [0; 3];
+        let index = reg.address().wrapping_sub(Reg::CHARGER_DETAILS_0.address());
+        if let Some(byte) = bytes.get_mut(index as usize) {
+            *byte = self.read_reg(reg).await?;
+        }
+        Ok(Details::from_bytes(bytes))
+    }
+
+    async fn read_buf(&mut self, base: Reg, buf: &mut [u8]) -> Result<(), Error<D::Error>> {
+        self.read_buf_once(base, buf).await?;
+        self.vote_read(base, buf).await
+    }
+
+    async fn read_buf_once(&mut self, base: Reg, buf: &mut [u8]) -> Result<(), Error<D::Error>> {
         self.i2c_dev
-            .write_read(ADDR, core::slice::from_ref(&base.to_u8()), buf)
+            .write_read(self.address, core::slice::from_ref(&base.address()), buf)
             .await
+            .map_err(self.bus_error)?;
+        self.trace_transfer(false, base, buf);
+        Ok(())
     }
 
-    async fn write_reg(&mut self, reg: Reg, val: u8) -> Result<(), D::Error> {
-        let buf = [reg.to_u8(), val];
-        self.i2c_dev.write(ADDR, &buf).await
+    async fn write_reg(&mut self, reg: Reg, val: u8) -> Result<(), Error<D::Error>> {
+        let buf = [reg.address(), val];
+        self.i2c_dev
+            .write(self.address, &buf)
+            .await
+            .map_err(self.bus_error)?;
+        self.trace_transfer(true, reg, &buf[1..]);
+        if let Some(cache) = &mut self.cache {
+            cache.update(reg, &buf[1..]);
+        }
+        self.verify_write(reg, &buf[1..]).await
     }
 
-    async fn write_protected_reg(&mut self, reg: Reg, val: u8) -> Result<(), D::Error> {
-        self.write_reg(Reg::CHARGER_CONFIG_6, 0x0c).await?;
-        let res = self.write_reg(reg, val).await;
-        self.write_reg(Reg::CHARGER_CONFIG_6, 0x00).await?;
-        res
+    /// Write `vals` to consecutive registers from `base` in a single transaction.
+    ///
+    /// Fails with [`Error::InvalidValue`] without touching the bus if the range is not entirely
+    /// writable.
+    async fn write_buf(&mut self, base: Reg, vals: &[u8]) -> Result<(), Error<D::Error>> {
+        if !is_writable_range(base, vals.len()) {
+            return Err(Error::InvalidValue);
+        }
+        self.i2c_dev
+            .transaction(
+                self.address,
+                &mut [
+                    Operation::Write(core::slice::from_ref(&base.address())),
+                    Operation::Write(vals),
+                ],
+            )
+            .await
+            .map_err(self.bus_error)?;
+        self.trace_transfer(true, base, vals);
+        if let Some(cache) = &mut self.cache {
+            cache.update(base, vals);
+        }
+        self.verify_write(base, vals).await
     }
 
-    async fn modify_reg<F: FnOnce(u8) -> u8>(&mut self, reg: Reg, func: F) -> Result<(), D::Error> {
-        let val = self.read_reg(reg).await?;
+    async fn modify_reg<F: FnOnce(u8) -> u8>(
+        &mut self,
+        reg: Reg,
+        func: F,
+    ) -> Result<(), Error<D::Error>> {
+        let val = match self.cache.and_then(|cache| cache.get(reg)) {
+            Some(val) => val,
+            None => {
+                let val = self.read_reg(reg).await?;
+                if let Some(cache) = &mut self.cache {
+                    cache.update(reg, &[val]);
+                }
+                val
+            }
+        };
         let val = func(val);
         self.write_reg(reg, val).await
     }
 }
 
-#[bitfield(bits = 8)]
-#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
-/// The charger interrupt flags
-pub struct ChargerInterrupts {
-    pub bypass_node: bool,
-    pub disqbat: bool,
-    #[skip]
-    __: B1,
-    pub battery: bool,
-    pub charger: bool,
-    pub input_current_limit: bool,
-    pub chgin: bool,
-    pub adaptive_input_current_loop: bool,
+/// The CHGPROT value that unlocks the protected registers
+const CHGPROT_UNLOCK: u8 = 0b11;
+
+/// Returns an update of `CHARGER_CONFIG_5` setting SYS_ILIM to `milliamps` and the recycle bit,
+/// leaving the other fields untouched.
+fn update_sys_ilim<E>(
+    variant: Variant,
+    milliamps: u16,
+    recycle_en: bool,
+) -> Result<impl FnOnce(ChargerConfig5) -> ChargerConfig5, Error<E>> {
+    if recycle_en && !variant.has_sys_ilim_recycle() {
+        return Err(Error::Unsupported);
+    }
+    let sys_ilim = conv::sys_ilim_code(milliamps);
+    Ok(move |val: ChargerConfig5| val.with_sys_ilim(sys_ilim).with_recycle_enable(recycle_en))
+}
+
+/// Fails with [`Error::VerifyFailed`] if `reg` reads `read` instead of `expected`.
+fn check_config<E>(reg: Reg, expected: u8, read: u8) -> Result<(), Error<E>> {
+    if read == expected {
+        Ok(())
+    } else {
+        Err(Error::VerifyFailed {
+            reg: reg.address(),
+            wrote: expected,
+            read,
+        })
+    }
+}
+
+/// Returns an update of `CHARGER_CONFIG_2` setting CHG_CC to `milliamps`.
+fn update_chg_cc<E>(
+    variant: Variant,
+    milliamps: u16,
+) -> Result<impl FnOnce(ChargerConfig2) -> ChargerConfig2, Error<E>> {
+    let chg_cc = conv::chg_cc_code(milliamps, variant).ok_or(Error::InvalidValue)?;
+    Ok(move |val: ChargerConfig2| val.with_chg_cc(chg_cc))
+}
+
+/// A charger configuration, applied as a unit by [`Charger::apply_config`]
+///
+/// The fields are public and [`ChargerInterrupts::from_sources`] is `const`, so configurations
+/// can be kept in constant tables:
+///
+/// ```
+/// use max7797x_driver::{ChargerConfig, ChargerInterrupts, ChargerIrqSource, Mode};
+///
+/// static USB_CONFIG: ChargerConfig = ChargerConfig {
+///     chgin_ilim_ma: 500,
+///     fast_charge_current_ma: 400,
+///     sys_ilim_ma: 3000,
+///     sys_ilim_recycle: false,
+///     charger_irqs: ChargerInterrupts::from_sources(&[ChargerIrqSource::Chgin]),
+///     mode: Mode::Charge,
+/// };
+/// # assert_eq!(USB_CONFIG.mode, Mode::Charge);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChargerConfig {
+    /// The CHGIN input current limit, see [`Charger::set_chgin_ilim`]
+    pub chgin_ilim_ma: u16,
+    /// The fast-charge current, see [`Charger::set_fast_charge_current`]
+    pub fast_charge_current_ma: u16,
+    /// The Vsys current limit, see [`Charger::set_sys_ilim`]
+    pub sys_ilim_ma: u16,
+    /// Whether Vsys is recycled after a Vsys current limit shutdown
+    pub sys_ilim_recycle: bool,
+    /// The charger interrupts to enable
+    pub charger_irqs: ChargerInterrupts,
+    /// The charger mode
+    pub mode: Mode,
+}
+
+/// A scope in which the CHGPROT protected charger settings are unlocked.
+///
+/// Created by [`Charger::protected`].
+pub struct ProtectedSession<'a, D: ErrorType> {
+    charger: &'a mut Charger<D>,
+}
+
+impl<D: I2c> ProtectedSession<'_, D> {
+    /// Set the current limit for Vsys out.
+    ///
+    /// See [`Charger::set_sys_ilim`].
+    pub async fn set_sys_ilim(
+        &mut self,
+        milliamps: u16,
+        recycle_en: bool,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self.charger, "set_sys_ilim", {
+            let update = update_sys_ilim(self.charger.variant, milliamps, recycle_en)?;
+            self.charger.modify_typed(update).await
+        })
+    }
+
+    /// Set the current to use during the [`ChargerDetails::ConstantCurrent`] charging phase.
+    ///
+    /// See [`Charger::set_fast_charge_current`].
+    pub async fn set_fast_charge_current(&mut self, milliamps: u16) -> Result<(), Error<D::Error>> {
+        traced!(self.charger, "set_fast_charge_current", {
+            let update = update_chg_cc(self.charger.variant, milliamps)?;
+            self.charger.modify_typed(update).await
+        })
+    }
+
+    async fn restore(&mut self, dump: &RegisterDump<'_>) -> Result<(), Error<D::Error>> {
+        self.charger
+            .write_buf(
+                Reg::CHARGER_CONFIG_1,
+                dump.range(Reg::CHARGER_CONFIG_1, Reg::CHARGER_CONFIG_5),
+            )
+            .await?;
+        self.charger
+            .write_buf(
+                Reg::CHARGER_CONFIG_7,
+                dump.range(Reg::CHARGER_CONFIG_7, Reg::STATUS_LED_CONFIG),
+            )
+            .await
+    }
+}
+
+/// A snapshot of the documented registers, as captured by [`Charger::dump_registers`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RegisterDump<'a> {
+    buf: &'a [u8; RegisterDump::LEN],
+}
+
+impl RegisterDump<'_> {
+    /// The number of registers captured in a dump.
+    pub const LEN: usize = Reg::ALL.len();
+
+    /// Get the captured value of the register at address `reg`.
+    ///
+    /// Returns `None` if `reg` is not part of the dump.
+    pub fn get(&self, reg: u8) -> Option<u8> {
+        self.offset(Reg::new(reg))
+            .and_then(|offset| self.buf.get(offset).copied())
+    }
+
+    /// Iterate over the `(address, value)` pairs of the dump in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        REG_RANGES
+            .iter()
+            .flat_map(|&(base, count)| base.address()..base.address().saturating_add(count as u8))
+            .zip(self.buf.iter().copied())
+    }
+
+    /// The raw register values in dump order.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buf
+    }
+
+    fn value(&self, reg: Reg) -> u8 {
+        self.get(reg.address()).unwrap_or(0)
+    }
+
+    /// The captured values of the registers `first..=last`, which must lie in a single range.
+    fn range(&self, first: Reg, last: Reg) -> &[u8] {
+        match (self.offset(first), self.offset(last)) {
+            (Some(start), Some(end)) => self.buf.get(start..=end).unwrap_or_default(),
+            _ => &[],
+        }
+    }
+
+    fn offset(&self, reg: Reg) -> Option<usize> {
+        let mut offset = 0usize;
+        for (base, count) in REG_RANGES {
+            let index = reg.address().wrapping_sub(base.address()) as usize;
+            if index < count {
+                return Some(offset.saturating_add(index));
+            }
+            offset = offset.saturating_add(count);
+        }
+        None
+    }
+}
+
+#[cfg(feature = "defmt-03")]
+impl defmt::Format for RegisterDump<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        for (reg, val) in self.iter() {
+            defmt::write!(f, "reg=0x{=u8:02x} val=0x{=u8:02x}\n", reg, val);
+        }
+    }
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The TOP interrupt flags
+///
+/// `from_bytes` and `into_bytes` use the raw `TOP_INTERRUPT` byte, reserved bits included.
+pub struct TopInterrupts {
+    #[skip]
+    __: B2,
+    pub thermal_shutdown: bool,
+    pub sys_overvoltage: bool,
+    pub sys_undervoltage: bool,
+    #[skip]
+    __: B3,
+}
+
+/// The kind of a fault reported by the charger
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum FaultKind {
+    /// The fast-charge timer expired ([`ChargerDetails::TimerFault`])
+    Timer,
+    /// The watchdog timer expired ([`ChargerDetails::WatchdogTimer`])
+    Watchdog,
+    /// The junction temperature exceeded TSHDN ([`ChargerDetails::HighTemperature`])
+    OverTemperature,
+    /// Battery removal was detected on the THM pin ([`ChargerDetails::ThermistorRemoval`])
+    ThermistorRemoved,
+    /// The battery voltage exceeded the overvoltage threshold ([`BatteryDetails::Overvoltage`])
+    BatteryOvervoltage,
+    /// The CHGIN voltage exceeded VCHGIN_OVLO ([`ChgIn::Overvoltage`])
+    InputOvervoltage,
+    /// The device entered thermal shutdown ([`TopInterrupts::thermal_shutdown`])
+    ThermalShutdown,
+    /// The system voltage exceeded its overvoltage lockout ([`TopInterrupts::sys_overvoltage`])
+    SysOvervoltage,
+    /// The system voltage fell below its undervoltage lockout
+    /// ([`TopInterrupts::sys_undervoltage`])
+    SysUndervoltage,
+}
+
+/// The fields of `CHARGER_DETAILS_1` returned by [`Charger::battery_status`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatteryStatus {
+    /// The battery status
+    pub battery: BatteryDetails,
+    /// Whether the junction temperature regulation loop is active
+    pub temp: TemperatureRegulation,
+}
+
+/// The interrupt flags returned by [`Charger::irq_summary`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct IrqSummary {
+    /// The TOP interrupt flags that were pending
+    pub top: TopInterrupts,
+    /// The charger interrupt flags that were pending
+    pub charger: ChargerInterrupts,
+}
+
+impl IrqSummary {
+    /// Whether any interrupt flag was pending
+    pub fn any(&self) -> bool {
+        !self.top.is_empty() || !self.charger.is_empty()
+    }
+}
+
+/// A snapshot of the charger interrupt block returned by [`Charger::full_status`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct FullStatus {
+    /// The charger interrupt flags that were pending, now cleared
+    pub flags: ChargerInterrupts,
+    /// The charger status bits
+    pub status: ChargerStatus,
+    /// The charger details
+    pub details: Details,
+}
+
+impl FullStatus {
+    /// Decode the registers from `CHARGER_INTERRUPT` through `CHARGER_DETAILS_2`.
+    ///
+    /// The `CHARGER_INTERRUPT_MASK` byte is ignored. Every field decodes for any input, so this
+    /// cannot panic on a corrupted read.
+    pub const fn from_bytes(buf: [u8; 6]) -> Self {
+        FullStatus {
+            flags: ChargerInterrupts::from_bytes([buf[0]]),
+            status: ChargerStatus::from_bytes([buf[2]]),
+            details: Details::from_bytes([buf[3], buf[4], buf[5]]),
+        }
+    }
+}
+
+/// The interrupt flags and charger state collected by [`Charger::wait_for_event`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct ChargerEvent {
+    /// The TOP interrupt flags that were pending
+    pub top: TopInterrupts,
+    /// The charger interrupt flags that were pending
+    pub charger: ChargerInterrupts,
+    /// The charger details after the flags were cleared
+    pub details: Details,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The charger interrupt flags
+///
+/// Each flag latches a change of the corresponding [`ChargerStatus`] source and is cleared
+/// when `CHARGER_INTERRUPT` is read. The same layout is used for the interrupt mask.
+///
+/// `from_bytes` and `into_bytes` use the raw register byte, reserved bits included.
+pub struct ChargerInterrupts {
+    pub bypass_node: bool,
+    pub disqbat: bool,
+    #[skip]
+    __: B1,
+    pub battery: bool,
+    pub charger: bool,
+    pub input_current_limit: bool,
+    pub chgin: bool,
+    pub adaptive_input_current_loop: bool,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The live charger status bits of `CHARGER_INTERRUPT_STATUS`
+///
+/// Unlike [`ChargerInterrupts`], which latches that a source *changed*, these bits report the
+/// current level of each source and are not cleared by reading. A set bit means the source is
+/// OK; a cleared bit means the condition described on the field is present.
+///
+/// `from_bytes` and `into_bytes` use the raw register byte, reserved bits included.
+pub struct ChargerStatus {
+    /// The bypass node is OK: no OTG, boost or buck overcurrent
+    pub bypass_ok: bool,
+    /// DISQBAT is low and QBAT is enabled
+    pub disqbat_ok: bool,
+    #[skip]
+    __: B1,
+    /// The battery is OK: present, above prequalification and below overvoltage
+    pub battery_ok: bool,
+    /// The charger is OK or off: no fault, suspend or done condition
+    pub charger_ok: bool,
+    /// The input current is below the CHGIN_ILIM limit
+    pub input_current_limit_ok: bool,
+    /// The CHGIN input is valid
+    pub chgin_ok: bool,
+    /// The charger is not regulating the input voltage (AICL inactive)
+    pub aicl_ok: bool,
+}
+
+#[bitfield(bits = 8)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// The `CHARGER_CONFIG_0` register
+pub struct ChargerConfig0 {
+    /// The raw MODE code, see [`mode`](Self::mode)
+    pub mode_code: B4,
+    /// Enable the charger watchdog timer
+    pub watchdog_enable: bool,
+    /// Place the charger in its low power standby state
+    pub standby_enable: bool,
+    /// Disable the QBATT switch
+    pub disibs: bool,
+    /// What happens when the watchdog expires
+    pub watchdog_action: WatchdogAction,
+}
+
+impl ChargerConfig0 {
+    /// The charger [`Mode`], or the raw MODE code if it is reserved
+    pub fn mode(&self) -> Result<Mode, u8> {
+        <Mode as Specifier>::from_bytes(self.mode_code()).map_err(|err| err.invalid_bytes())
+    }
+
+    /// Returns a copy with the MODE code of `mode`
+    #[must_use]
+    pub fn with_mode(self, mode: Mode) -> Self {
+        self.with_mode_code(mode as u8)
+    }
+
+    /// Set the MODE code to that of `mode`
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.set_mode_code(mode as u8);
+    }
 }
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[bits = 4]
 /// Charging mode
 pub enum Mode {
@@ -222,6 +2009,7 @@ pub enum Mode {
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[bits = 2]
 /// CHGIN status
 pub enum ChgIn {
@@ -237,6 +2025,7 @@ pub enum ChgIn {
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[bits = 2]
 /// Battery sense status
 pub enum BatterySense {
@@ -252,6 +2041,7 @@ pub enum BatterySense {
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[bits = 1]
 /// Temperature regulation status
 pub enum TemperatureRegulation {
@@ -263,8 +2053,15 @@ pub enum TemperatureRegulation {
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[bits = 3]
+#[non_exhaustive]
 /// Battery status
+///
+/// Every 3-bit code decodes: the reserved code is [`BatteryDetails::Reserved`], and [`code`]
+/// recovers the raw value for logging.
+///
+/// [`code`]: BatteryDetails::code
 pub enum BatteryDetails {
     /// Battery Removal A valid adpater is present and the battery is detached, detected on THM pin.
     BatteryRemoved,
@@ -284,7 +2081,7 @@ pub enum BatteryDetails {
     ///
     /// *Note:* This flag is only generated when there is a valid input.
     Overvoltage,
-    #[doc(hidden)]
+    /// A reserved code, possibly reported by a silicon revision this crate does not know
     Reserved,
     /// Battery Only No valid adapter is present The battery voltage and battery removal monitoring are not available.
     ///
@@ -292,10 +2089,62 @@ pub enum BatteryDetails {
     BatteryOnly = 7,
 }
 
+impl BatteryDetails {
+    /// The raw BAT_DTLS code
+    pub const fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether the code is reserved
+    pub const fn is_reserved(self) -> bool {
+        matches!(self, BatteryDetails::Reserved)
+    }
+
+    /// Classify the battery status coarsely.
+    pub fn classify(self) -> BatteryClass {
+        match self {
+            BatteryDetails::RegularVoltage => BatteryClass::Ok,
+            BatteryDetails::PrequalificationVoltage | BatteryDetails::LowVoltage => {
+                BatteryClass::Low
+            }
+            BatteryDetails::BatteryRemoved => BatteryClass::Removed,
+            BatteryDetails::TimerFault => BatteryClass::Fault(FaultKind::Timer),
+            BatteryDetails::Overvoltage => BatteryClass::Fault(FaultKind::BatteryOvervoltage),
+            BatteryDetails::BatteryOnly | BatteryDetails::Reserved => BatteryClass::Unmonitored,
+        }
+    }
+}
+
+/// The coarse class of a [`BatteryDetails`] value
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum BatteryClass {
+    /// The battery voltage is in the regular range ([`BatteryDetails::RegularVoltage`])
+    Ok,
+    /// The battery voltage is below VSYSMIN ([`BatteryDetails::PrequalificationVoltage`] or
+    /// [`BatteryDetails::LowVoltage`])
+    Low,
+    /// The battery is detached ([`BatteryDetails::BatteryRemoved`])
+    Removed,
+    /// No valid adapter is present so the battery is not monitored
+    /// ([`BatteryDetails::BatteryOnly`], or a reserved code)
+    Unmonitored,
+    /// The battery timer expired ([`FaultKind::Timer`]) or the battery is overvoltage
+    /// ([`FaultKind::BatteryOvervoltage`])
+    Fault(FaultKind),
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[bits = 4]
+#[non_exhaustive]
 /// Charger status
+///
+/// Every 4-bit code decodes: reserved codes are the `Reserved` variants named after their
+/// value, and [`code`] recovers the raw value for logging.
+///
+/// [`code`]: ChargerDetails::code
 pub enum ChargerDetails {
     /// Charger is in dead-battery prequalification or low-battery prequalification mode. CHG_OK = 1 and VBATT < VPQLB and TJ < TSHDN
     Prequalification,
@@ -307,7 +2156,7 @@ pub enum ChargerDetails {
     TopOff,
     /// Charger is in done mode. CHG_OK = 0 and VBATT > VBATTREG - VRSTRT and TJ < TSHDN
     Done,
-    #[doc(hidden)]
+    /// Reserved code 0x05, possibly reported by a silicon revision this crate does not know
     Reserved05,
     /// Charger is in timer-fault mode. CHG_OK = 0 and if BAT_DTLS=0b001 then VBATT < VPQLB or VBATT < VPQDB and TJ < TSHDN
     TimerFault = 6,
@@ -315,7 +2164,7 @@ pub enum ChargerDetails {
     QBattDisabled,
     /// Charger is off, charger input invalid and/or charger is disabled. CHG_OK = 1
     Off,
-    #[doc(hidden)]
+    /// Reserved code 0x09, possibly reported by a silicon revision this crate does not know
     Reserved09,
     /// Charger is off and the junction temperature is > TSHDN. CHG_OK = 0
     HighTemperature = 0x0a,
@@ -327,14 +2176,96 @@ pub enum ChargerDetails {
     ThermistorRemoval,
     /// Charger is suspended because SUSPEND pin is high. CHG_OK = 0
     SuspendPin,
-    #[doc(hidden)]
+    /// Reserved code 0x0f, possibly reported by a silicon revision this crate does not know
     Reserved0F,
 }
 
+impl ChargerDetails {
+    /// The raw CHG_DTLS code
+    pub const fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether the code is reserved
+    pub const fn is_reserved(self) -> bool {
+        matches!(
+            self,
+            ChargerDetails::Reserved05 | ChargerDetails::Reserved09 | ChargerDetails::Reserved0F
+        )
+    }
+
+    /// Classify the charger state coarsely.
+    ///
+    /// Reserved codes are classified as [`ChargeStateClass::Idle`].
+    pub fn classify(self) -> ChargeStateClass {
+        match self {
+            ChargerDetails::Prequalification
+            | ChargerDetails::ConstantCurrent
+            | ChargerDetails::ConstantVoltage
+            | ChargerDetails::TopOff => ChargeStateClass::Charging,
+            ChargerDetails::Done => ChargeStateClass::Done,
+            ChargerDetails::Off
+            | ChargerDetails::Reserved05
+            | ChargerDetails::Reserved09
+            | ChargerDetails::Reserved0F => ChargeStateClass::Idle,
+            ChargerDetails::QBattDisabled => {
+                ChargeStateClass::Suspended(SuspendReason::QBattDisabled)
+            }
+            ChargerDetails::Jeita => ChargeStateClass::Suspended(SuspendReason::Jeita),
+            ChargerDetails::ThermistorRemoval => {
+                ChargeStateClass::Suspended(SuspendReason::ThermistorRemoved)
+            }
+            ChargerDetails::SuspendPin => ChargeStateClass::Suspended(SuspendReason::SuspendPin),
+            ChargerDetails::TimerFault => ChargeStateClass::Fault(FaultKind::Timer),
+            ChargerDetails::WatchdogTimer => ChargeStateClass::Fault(FaultKind::Watchdog),
+            ChargerDetails::HighTemperature => ChargeStateClass::Fault(FaultKind::OverTemperature),
+        }
+    }
+}
+
+/// The coarse class of a [`ChargerDetails`] value
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum ChargeStateClass {
+    /// The charger is delivering current to the battery
+    Charging,
+    /// The charge cycle finished
+    Done,
+    /// The charger is off or the input is invalid
+    Idle,
+    /// Charging is suspended until the condition clears
+    Suspended(SuspendReason),
+    /// Charging stopped because of a fault ([`FaultKind::Timer`], [`FaultKind::Watchdog`] or
+    /// [`FaultKind::OverTemperature`])
+    Fault(FaultKind),
+}
+
+/// Why charging is suspended, see [`ChargeStateClass::Suspended`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SuspendReason {
+    /// JEITA control suspended charging ([`ChargerDetails::Jeita`])
+    Jeita,
+    /// The SUSPEND pin is high ([`ChargerDetails::SuspendPin`])
+    SuspendPin,
+    /// QBATT is disabled by DISQBAT or DISIBS ([`ChargerDetails::QBattDisabled`])
+    QBattDisabled,
+    /// Battery removal was detected on the THM pin ([`ChargerDetails::ThermistorRemoval`])
+    ThermistorRemoved,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[bits = 3]
+#[non_exhaustive]
 /// Thermistor status
+///
+/// Every 3-bit code decodes: the reserved code is [`ThermistorDetails::Reserved`], and
+/// [`code`] recovers the raw value for logging.
+///
+/// [`code`]: ThermistorDetails::code
 pub enum ThermistorDetails {
     /// Low temperature and charging suspended
     Cold,
@@ -350,13 +2281,13 @@ pub enum ThermistorDetails {
     Removed,
     /// Thermistor monitoring is disabled
     Disabled,
-    #[doc(hidden)]
+    /// A reserved code, possibly reported by a silicon revision this crate does not know
     Reserved,
 }
 
 #[repr(C, align(1))]
 #[bitfield(bits = 4)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 /// Bypass node status
 pub struct BypassNodeDetails {
@@ -366,11 +2297,61 @@ pub struct BypassNodeDetails {
     pub boost_on: bool,
 }
 
+impl BypassNodeDetails {
+    /// Whether any of the bypass node current limits is active
+    pub fn any_fault(&self) -> bool {
+        self.faults().next().is_some()
+    }
+
+    /// Whether the current limit `fault` is active
+    pub fn has_fault(&self, fault: BypassFault) -> bool {
+        match fault {
+            BypassFault::OtgCurrentLimit => self.otg_current_limit(),
+            BypassFault::BoostCurrentLimit => self.boost_current_limit(),
+            BypassFault::BuckCurrentLimit => self.buck_current_limit(),
+        }
+    }
+
+    /// The active current limits, in [`BypassFault::ALL`] order
+    pub fn faults(&self) -> impl Iterator<Item = BypassFault> {
+        let details = *self;
+        BypassFault::ALL
+            .into_iter()
+            .filter(move |&fault| details.has_fault(fault))
+    }
+}
+
+/// A bypass node current limit condition reported in [`BypassNodeDetails`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BypassFault {
+    /// The OTG output is in current limit
+    OtgCurrentLimit,
+    /// The boost converter is in current limit
+    BoostCurrentLimit,
+    /// The buck converter is in current limit
+    BuckCurrentLimit,
+}
+
+impl BypassFault {
+    /// All bypass faults, in register bit order
+    pub const ALL: [BypassFault; 3] = [
+        BypassFault::OtgCurrentLimit,
+        BypassFault::BoostCurrentLimit,
+        BypassFault::BuckCurrentLimit,
+    ];
+}
+
 #[repr(C, align(1))]
 #[bitfield(bits = 24)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 /// Detailed status of the charger
+///
+/// `from_bytes` and `into_bytes` take the raw values of `CHARGER_DETAILS_0` through
+/// `CHARGER_DETAILS_2` in register order. The layout is stable, so the three bytes can be logged
+/// and decoded later, on another host.
 pub struct Details {
     #[skip]
     __: B1,
@@ -395,3 +2376,1655 @@ pub struct Details {
     #[skip]
     __: B1,
 }
+
+impl Details {
+    /// Whether the CHGIN input is [`ChgIn::Valid`]
+    pub fn is_input_valid(&self) -> bool {
+        self.chgin() == ChgIn::Valid
+    }
+
+    /// Whether the charger is delivering current to the battery: [`ChargerDetails::Prequalification`],
+    /// [`ChargerDetails::ConstantCurrent`], [`ChargerDetails::ConstantVoltage`] or
+    /// [`ChargerDetails::TopOff`]
+    pub fn is_charging(&self) -> bool {
+        matches!(
+            self.charger(),
+            ChargerDetails::Prequalification
+                | ChargerDetails::ConstantCurrent
+                | ChargerDetails::ConstantVoltage
+                | ChargerDetails::TopOff
+        )
+    }
+
+    /// Whether the charge cycle finished: [`ChargerDetails::Done`]
+    pub fn charge_complete(&self) -> bool {
+        self.charger() == ChargerDetails::Done
+    }
+
+    /// Whether charging stopped because of a fault: [`ChargerDetails::TimerFault`],
+    /// [`ChargerDetails::WatchdogTimer`], [`ChargerDetails::ThermistorRemoval`],
+    /// [`ChargerDetails::HighTemperature`], [`BatteryDetails::TimerFault`] or
+    /// [`BatteryDetails::Overvoltage`]
+    pub fn is_faulted(&self) -> bool {
+        matches!(
+            self.charger(),
+            ChargerDetails::TimerFault
+                | ChargerDetails::WatchdogTimer
+                | ChargerDetails::ThermistorRemoval
+                | ChargerDetails::HighTemperature
+        ) || matches!(
+            self.battery(),
+            BatteryDetails::TimerFault | BatteryDetails::Overvoltage
+        )
+    }
+
+    /// Whether the system runs from the battery without a valid adapter:
+    /// [`BatteryDetails::BatteryOnly`]
+    pub fn on_battery_only(&self) -> bool {
+        self.battery() == BatteryDetails::BatteryOnly
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal_async::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::testing::{
+        any_field, block_on, lock, read, read_burst, run, run_variant, unlock, write, write_burst,
+        FailingBursts,
+    };
+
+    #[test]
+    fn set_chgin_ilim_keeps_reserved_bits() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_CONFIG_9, 0xc0),
+                write(Reg::CHARGER_CONFIG_9, 0xc0 | 23),
+            ],
+            async |charger| charger.set_chgin_ilim(1234).await,
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn set_chgin_ilim_saturates() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                write(Reg::CHARGER_CONFIG_9, 0x00),
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                write(Reg::CHARGER_CONFIG_9, 0x3f),
+            ],
+            async |charger| {
+                charger.set_chgin_ilim(0).await?;
+                charger.set_chgin_ilim(u16::MAX).await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn set_sys_ilim_unlocks_chgprot() {
+        let res = run(
+            &[
+                unlock(),
+                read(Reg::CHARGER_CONFIG_5, 0x60),
+                write(Reg::CHARGER_CONFIG_5, 0x71),
+                lock(),
+            ],
+            async |charger| charger.set_sys_ilim(3000, true).await,
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn set_sys_ilim_keeps_other_config5_fields() {
+        // Sentinel ITRICKLE and reserved bits, with the recycle bit to be cleared
+        let res = run(
+            &[
+                unlock(),
+                read(Reg::CHARGER_CONFIG_5, 0xff),
+                write(Reg::CHARGER_CONFIG_5, 0xe0),
+                lock(),
+            ],
+            async |charger| charger.set_sys_ilim(2500, false).await,
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn protected_locks_after_failed_write() {
+        let res = run(
+            &[
+                unlock(),
+                read(Reg::CHARGER_CONFIG_2, 0x00),
+                Transaction::write(DEFAULT_ADDRESS, vec![Reg::CHARGER_CONFIG_2.address(), 20])
+                    .with_error(ErrorKind::Other),
+                lock(),
+            ],
+            async |charger| charger.set_fast_charge_current(1000).await,
+        );
+        assert_eq!(res, Err(Error::I2c(ErrorKind::Other)));
+    }
+
+    #[test]
+    fn protected_keeps_the_session_error_if_locking_fails() {
+        let res = run(
+            &[
+                unlock(),
+                read(Reg::CHARGER_CONFIG_2, 0x00),
+                Transaction::write(DEFAULT_ADDRESS, vec![Reg::CHARGER_CONFIG_2.address(), 20])
+                    .with_error(ErrorKind::Overrun),
+                lock().with_error(ErrorKind::Other),
+            ],
+            async |charger| charger.set_fast_charge_current(1000).await,
+        );
+        assert_eq!(res, Err(Error::I2c(ErrorKind::Overrun)));
+
+        let res = run(
+            &[unlock(), lock().with_error(ErrorKind::Other)],
+            async |charger| charger.protected(async |_| Ok(())).await,
+        );
+        assert_eq!(res, Err(Error::I2c(ErrorKind::Other)));
+    }
+
+    #[test]
+    fn protected_session_unlocks_once() {
+        let res = run(
+            &[
+                unlock(),
+                read(Reg::CHARGER_CONFIG_2, 0x80),
+                write(Reg::CHARGER_CONFIG_2, 0x80 | 20),
+                read(Reg::CHARGER_CONFIG_5, 0x00),
+                write(Reg::CHARGER_CONFIG_5, 0x02),
+                lock(),
+            ],
+            async |charger| {
+                charger
+                    .protected(async |session| {
+                        session.set_fast_charge_current(1000).await?;
+                        session.set_sys_ilim(3500, false).await
+                    })
+                    .await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn set_fast_charge_current_rejects_without_bus_traffic() {
+        let res = run(&[], async |charger| {
+            charger.set_fast_charge_current(3550).await
+        });
+        assert_eq!(res, Err(Error::InvalidValue));
+    }
+
+    #[test]
+    fn set_fast_charge_current_scales_per_variant() {
+        for (variant, code) in [
+            (Variant::Max77975, 20),
+            (Variant::Max77976, 20),
+            (Variant::Max77985, 40),
+            (Variant::Max77986, 20),
+        ] {
+            let res = run_variant(
+                variant,
+                &[
+                    unlock(),
+                    read(Reg::CHARGER_CONFIG_2, 0x00),
+                    write(Reg::CHARGER_CONFIG_2, code),
+                    lock(),
+                ],
+                async |charger| charger.set_fast_charge_current(1000).await,
+            );
+            assert_eq!(res, Ok(()), "{variant:?}");
+        }
+    }
+
+    #[test]
+    fn set_fast_charge_current_checks_variant_limits() {
+        let res = run_variant(
+            Variant::Max77976,
+            &[
+                unlock(),
+                read(Reg::CHARGER_CONFIG_2, 0x00),
+                write(Reg::CHARGER_CONFIG_2, 100),
+                lock(),
+            ],
+            async |charger| charger.set_fast_charge_current(5000).await,
+        );
+        assert_eq!(res, Ok(()));
+        let res = run(&[], async |charger| {
+            charger.set_fast_charge_current(5000).await
+        });
+        assert_eq!(res, Err(Error::InvalidValue));
+        let res = run_variant(Variant::Max77976, &[], async |charger| {
+            charger.set_fast_charge_current(50).await
+        });
+        assert_eq!(res, Err(Error::InvalidValue));
+    }
+
+    #[test]
+    fn set_regulation_temperature_writes_code() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_CONFIG_10, 0x05),
+                write(Reg::CHARGER_CONFIG_10, 0x85),
+            ],
+            async |charger| charger.set_regulation_temperature(115).await,
+        );
+        assert_eq!(res, Ok(()));
+        let res = run(&[], async |charger| {
+            charger.set_regulation_temperature(84).await
+        });
+        assert_eq!(res, Err(Error::InvalidValue));
+    }
+
+    #[test]
+    fn set_mode_keeps_other_fields() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_CONFIG_0, 0xf0),
+                write(Reg::CHARGER_CONFIG_0, 0xf5),
+            ],
+            async |charger| charger.set_mode(Mode::Charge).await,
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn set_mode_keeps_watchdog_enable() {
+        let config0 = run(
+            &[
+                read(Reg::CHARGER_CONFIG_0, 0x10),
+                write(Reg::CHARGER_CONFIG_0, 0x15),
+                read(Reg::CHARGER_CONFIG_0, 0x15),
+            ],
+            async |charger| {
+                charger.set_mode(Mode::Charge).await?;
+                charger.charger_config0().await
+            },
+        )
+        .unwrap();
+        assert!(config0.watchdog_enable());
+        assert_eq!(config0.mode(), Ok(Mode::Charge));
+    }
+
+    #[test]
+    fn set_charger_config0_writes_byte() {
+        let config = ChargerConfig0::new()
+            .with_mode(Mode::Otg)
+            .with_watchdog_enable(true);
+        let res = run(&[write(Reg::CHARGER_CONFIG_0, 0x1a)], async |charger| {
+            charger.set_charger_config0(config).await
+        });
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn enter_ship_mode_writes_request() {
+        let res = run(&[write(Reg::SHIP_MODE_CONTROL, 0x01)], async |charger| {
+            charger.enter_ship_mode().await
+        });
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn set_charger_irq_mask_inverts() {
+        let res = run(
+            &[
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xbf),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xff),
+            ],
+            async |charger| {
+                charger
+                    .set_charger_irq_mask(ChargerInterrupts::new().with_chgin(true))
+                    .await?;
+                charger.set_charger_irq_mask(ChargerInterrupts::new()).await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn set_top_irq_mask_inverts() {
+        let res = run(&[write(Reg::TOP_INTERRUPT_MASK, 0xfb)], async |charger| {
+            charger
+                .set_top_irq_mask(TopInterrupts::new().with_thermal_shutdown(true))
+                .await
+        });
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn charger_status_reads_three_registers() {
+        let status = run(
+            &[read_burst(Reg::CHARGER_INTERRUPT, &[0xff, 0x00, 0x40])],
+            async |charger| charger.charger_status().await,
+        )
+        .unwrap();
+        assert_eq!(status, ChargerStatus::new().with_chgin_ok(true));
+    }
+
+    #[test]
+    fn charger_details_reads_three_registers() {
+        let details = run(
+            &[read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x31, 0x00])],
+            async |charger| charger.charger_details().await,
+        )
+        .unwrap();
+        assert_eq!(details.chgin(), ChgIn::Valid);
+        assert_eq!(details.charger(), ChargerDetails::ConstantCurrent);
+        assert_eq!(details.battery(), BatteryDetails::RegularVoltage);
+    }
+
+    #[test]
+    fn release_returns_the_device_for_rewrapping() {
+        let mock = Mock::new(&[
+            read(Reg::CHIP_ID, 0x75),
+            write(Reg::SHIP_MODE_CONTROL, 0x01),
+        ]);
+        let mut charger = Charger::new(mock);
+        assert_eq!(block_on(charger.read_reg(Reg::CHIP_ID)), Ok(0x75));
+        let mut charger = Charger::new(charger.release());
+        assert_eq!(block_on(charger.enter_ship_mode()), Ok(()));
+        charger.release().done();
+    }
+
+    #[test]
+    fn new_with_address_uses_address_on_the_wire() {
+        const ALT: u8 = 0x6a;
+        let mock = Mock::new(&[
+            Transaction::write_read(ALT, vec![Reg::CHARGER_CONFIG_0.address()], vec![0x04]),
+            Transaction::write(ALT, vec![Reg::CHARGER_CONFIG_0.address(), 0x05]),
+            Transaction::transaction_start(ALT),
+            Transaction::write(ALT, vec![Reg::TOP_INTERRUPT_MASK.address()]),
+            Transaction::write(ALT, vec![0xff, 0x00]),
+            Transaction::transaction_end(ALT),
+        ]);
+        let mut charger = Charger::new_with_address(mock, ALT);
+        assert_eq!(block_on(charger.set_mode(Mode::Charge)), Ok(()));
+        assert_eq!(
+            block_on(charger.write_buf(Reg::TOP_INTERRUPT_MASK, &[0xff, 0x00])),
+            Ok(())
+        );
+        charger.release().done();
+    }
+
+    #[test]
+    fn new_checked_rejects_an_unknown_chip() {
+        for chip_id in [0x00, 0xff] {
+            let mut mock = Mock::new(&[read(Reg::CHIP_ID, chip_id)]);
+            let res = block_on(Charger::new_checked(mock.clone())).map(|charger| charger.variant());
+            assert_eq!(res, Err(Error::UnknownChip(chip_id)));
+            mock.done();
+        }
+    }
+
+    #[test]
+    fn sys_ilim_recycle_is_unsupported_on_the_max7798x() {
+        for variant in [Variant::Max77985, Variant::Max77986] {
+            let res = run_variant(variant, &[], async |charger| {
+                charger.set_sys_ilim(3000, true).await
+            });
+            assert_eq!(res, Err(Error::Unsupported), "{variant:?}");
+        }
+    }
+
+    #[test]
+    fn enabling_chgin_keeps_battery_enabled() {
+        let chgin = ChargerInterrupts::from_sources(&[ChargerIrqSource::Chgin]);
+        let res = run(
+            &[
+                // Only BAT_I unmasked
+                read(Reg::CHARGER_INTERRUPT_MASK, 0xf7),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xb7),
+                read(Reg::CHARGER_INTERRUPT_MASK, 0xb7),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xf7),
+            ],
+            async |charger| {
+                charger.enable_charger_irqs(chgin).await?;
+                charger.disable_charger_irqs(chgin).await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn irq_mask_updates_keep_the_reserved_bits() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_INTERRUPT_MASK, 0xff),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0x04),
+                read(Reg::CHARGER_INTERRUPT_MASK, 0x00),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xfb),
+                read(Reg::TOP_INTERRUPT_MASK, 0xff),
+                write(Reg::TOP_INTERRUPT_MASK, 0xfb),
+                read(Reg::TOP_INTERRUPT_MASK, 0xe3),
+                write(Reg::TOP_INTERRUPT_MASK, 0xeb),
+            ],
+            async |charger| {
+                charger.enable_charger_irqs(ChargerInterrupts::ALL).await?;
+                charger.disable_charger_irqs(ChargerInterrupts::ALL).await?;
+                charger
+                    .enable_top_irqs(TopInterrupts::from_sources(&[
+                        TopIrqSource::ThermalShutdown,
+                    ]))
+                    .await?;
+                charger
+                    .disable_top_irqs(TopInterrupts::from_sources(&[TopIrqSource::SysOvervoltage]))
+                    .await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn with_config_returns_the_bus_on_a_verify_mismatch() {
+        let config = ChargerConfig::recommended(2000, Variant::Max77975);
+        let mut expectations = vec![
+            read(Reg::CHIP_ID, Variant::Max77975.chip_id()),
+            unlock(),
+            read_burst(Reg::CHARGER_CONFIG_2, &[0x00; 4]),
+        ];
+        expectations.extend(write_burst(
+            Reg::CHARGER_CONFIG_2,
+            &[0x14, 0x00, 0x00, 0x0f],
+        ));
+        expectations.extend([
+            lock(),
+            read(Reg::CHARGER_CONFIG_9, 0x00),
+            write(Reg::CHARGER_CONFIG_9, 0x09),
+            write(Reg::CHARGER_INTERRUPT_MASK, 0xa7),
+            read(Reg::CHARGER_CONFIG_0, 0x00),
+            write(Reg::CHARGER_CONFIG_0, 0x05),
+            read_burst(Reg::CHARGER_CONFIG_2, &[0x14, 0x00, 0x00, 0x0f]),
+            read(Reg::CHARGER_CONFIG_9, 0x09),
+            // The mask write did not take
+            read(Reg::CHARGER_INTERRUPT_MASK, 0xff),
+        ]);
+        let res = block_on(Charger::with_config(Mock::new(&expectations), &config));
+        let Err((err, mut mock)) = res else {
+            panic!("verify mismatch not reported");
+        };
+        assert_eq!(
+            err,
+            Error::VerifyFailed {
+                reg: Reg::CHARGER_INTERRUPT_MASK.address(),
+                wrote: 0xa7,
+                read: 0xff,
+            }
+        );
+        mock.done();
+    }
+
+    #[test]
+    fn resume_without_suspend_touches_nothing() {
+        let res = run(&[], async |charger| charger.resume_charging().await);
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn irq_summary_reads_both_blocks() {
+        for (top, charger, any) in [
+            (0x00, 0x00, false),
+            (0x10, 0x00, true),
+            (0x00, 0x40, true),
+            (0x04, 0x09, true),
+        ] {
+            let res = run(
+                &[
+                    read(Reg::TOP_INTERRUPT, top),
+                    read(Reg::CHARGER_INTERRUPT, charger),
+                ],
+                async |charger| charger.irq_summary().await,
+            );
+            let summary = res.unwrap();
+            assert_eq!(summary.top.bits(), top);
+            assert_eq!(summary.charger.bits(), charger);
+            assert_eq!(summary.any(), any, "0x{top:02x} 0x{charger:02x}");
+        }
+    }
+
+    #[test]
+    fn single_flag_reads_match_the_summary() {
+        let res = run(
+            &[
+                read(Reg::TOP_INTERRUPT, 0x08),
+                read(Reg::CHARGER_INTERRUPT, 0x81),
+            ],
+            async |charger| {
+                let top = charger.top_irq_flags().await?;
+                let irqs = charger.charger_irq_flags().await?;
+                Ok::<_, Error<ErrorKind>>((top, irqs))
+            },
+        );
+        let (top, irqs) = res.unwrap();
+        assert!(top.sys_overvoltage());
+        assert!(irqs.bypass_node() && irqs.adaptive_input_current_loop());
+        assert!(IrqSummary { top, charger: irqs }.any());
+    }
+
+    #[test]
+    fn wait_for_event_collects_every_latched_flag() {
+        use embedded_hal_mock::eh1::digital::{
+            Mock as PinMock, State, Transaction as PinTransaction,
+        };
+
+        // The pin is already asserted, with sources latched in both blocks
+        let mut pin = PinMock::new(&[
+            PinTransaction::wait_for_state(State::Low),
+            PinTransaction::wait_for_state(State::Low),
+        ]);
+        let charger_irqs =
+            ChargerInterrupts::from_sources(&[ChargerIrqSource::Chgin, ChargerIrqSource::Charger]);
+        let res = run(
+            &[
+                read(Reg::TOP_INTERRUPT, 0x04),
+                read(Reg::CHARGER_INTERRUPT, charger_irqs.bits()),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x04, 0x00]),
+                // A source latching after the flags were read keeps the pin asserted
+                read(Reg::TOP_INTERRUPT, 0x00),
+                read(Reg::CHARGER_INTERRUPT, 0x01),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x04, 0x00]),
+            ],
+            async |charger| {
+                let first = charger.wait_for_event(&mut pin).await?;
+                let second = charger.wait_for_event(&mut pin).await?;
+                Ok::<_, Error<ErrorKind>>((first, second))
+            },
+        );
+        let (first, second) = res.unwrap();
+        assert!(first.top.thermal_shutdown());
+        assert_eq!(first.charger.bits(), charger_irqs.bits());
+        assert_eq!(first.details.chgin(), ChgIn::Valid);
+        assert_eq!(second.top.into_bytes(), [0x00]);
+        assert_eq!(second.charger.into_bytes(), [0x01]);
+        pin.done();
+    }
+
+    #[test]
+    fn wait_for_event_reports_pin_errors() {
+        use embedded_hal_mock::eh1::digital::{
+            Mock as PinMock, State, Transaction as PinTransaction,
+        };
+        use embedded_hal_mock::eh1::MockError;
+
+        let mut pin = PinMock::new(&[PinTransaction::wait_for_state(State::Low)
+            .with_error(MockError::Io(std::io::ErrorKind::NotConnected))]);
+        let res = run(&[], async |charger| charger.wait_for_event(&mut pin).await);
+        assert_eq!(res.map(|event| event.details), Err(Error::Pin));
+        pin.done();
+    }
+
+    #[test]
+    fn checked_constructors_use_the_address() {
+        const ALT: u8 = 0x6a;
+        let chip_id = Variant::Max77986.chip_id();
+        let mock = Mock::new(&[Transaction::write_read(
+            ALT,
+            vec![Reg::CHIP_ID.address()],
+            vec![chip_id],
+        )]);
+        let charger = block_on(Charger::new_checked_with_address(mock, ALT)).unwrap();
+        assert_eq!(charger.variant(), Variant::Max77986);
+        charger.release().done();
+
+        let mock = Mock::new(&[Transaction::write(
+            ALT,
+            vec![Reg::CHARGER_CONFIG_0.address(), 0x05],
+        )]);
+        let mut charger = Charger::new_with_address_and_variant(mock, ALT, Variant::Max77986);
+        assert_eq!(charger.variant(), Variant::Max77986);
+        assert_eq!(
+            block_on(charger.set_charger_config0(ChargerConfig0::from_byte(0x05))),
+            Ok(())
+        );
+        charger.release().done();
+    }
+
+    #[test]
+    fn dump_registers_reads_exactly_reg_all() {
+        // One burst per run of consecutive addresses in `Reg::ALL`, each register reading back
+        // a value derived from its address
+        let mut expectations = Vec::new();
+        let mut run_start = 0;
+        for i in 1..=Reg::ALL.len() {
+            if i == Reg::ALL.len() || Reg::ALL[i].address() != Reg::ALL[i - 1].address() + 1 {
+                let vals: Vec<u8> = Reg::ALL[run_start..i]
+                    .iter()
+                    .map(|reg| reg.address() ^ 0xa5)
+                    .collect();
+                expectations.push(read_burst(Reg::ALL[run_start], &vals));
+                run_start = i;
+            }
+        }
+
+        let mut buf = [0; RegisterDump::LEN];
+        let dump = run(&expectations, async |charger| {
+            charger
+                .dump_registers(&mut buf)
+                .await
+                .map(|dump| dump.iter().collect::<Vec<_>>())
+        })
+        .unwrap();
+        let expected: Vec<_> = Reg::ALL
+            .iter()
+            .map(|reg| (reg.address(), reg.address() ^ 0xa5))
+            .collect();
+        assert_eq!(dump, expected);
+    }
+
+    /// A dump holding `0x80 | offset` in every register
+    fn sample_dump_buf() -> [u8; RegisterDump::LEN] {
+        core::array::from_fn(|offset| 0x80 | offset as u8)
+    }
+
+    #[test]
+    fn restore_registers_writes_only_writable_registers() {
+        let buf = sample_dump_buf();
+        let dump = RegisterDump { buf: &buf };
+        let mut expectations = vec![unlock()];
+        expectations.extend(write_burst(
+            Reg::CHARGER_CONFIG_1,
+            dump.range(Reg::CHARGER_CONFIG_1, Reg::CHARGER_CONFIG_5),
+        ));
+        expectations.extend(write_burst(
+            Reg::CHARGER_CONFIG_7,
+            dump.range(Reg::CHARGER_CONFIG_7, Reg::STATUS_LED_CONFIG),
+        ));
+        expectations.push(lock());
+        expectations.push(write(
+            Reg::CHARGER_CONFIG_0,
+            dump.value(Reg::CHARGER_CONFIG_0),
+        ));
+        expectations.push(write(
+            Reg::CHARGER_INTERRUPT_MASK,
+            dump.value(Reg::CHARGER_INTERRUPT_MASK),
+        ));
+        expectations.extend(write_burst(
+            Reg::TOP_INTERRUPT_MASK,
+            dump.range(Reg::TOP_INTERRUPT_MASK, Reg::TOP_CONTROL),
+        ));
+        expectations.push(write(Reg::I2C_CONFIG, dump.value(Reg::I2C_CONFIG)));
+
+        let res = run(&expectations, async |charger| {
+            charger.restore_registers(&dump).await
+        });
+        // The mock fails on any write beyond these, so no read-only register, nor
+        // SOFTWARE_RESET or SHIP_MODE_CONTROL, is written
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn restore_registers_relocks_after_failed_write() {
+        let buf = sample_dump_buf();
+        let dump = RegisterDump { buf: &buf };
+        let mut charger = Charger::new(FailingBursts(Mock::new(&[unlock(), lock()])));
+        let res = block_on(charger.restore_registers(&dump));
+        assert_eq!(res, Err(Error::I2c(ErrorKind::Other)));
+        charger.release().0.done();
+    }
+
+    #[test]
+    fn register_dump_get_skips_undocumented_registers() {
+        let buf = core::array::from_fn(|i| i as u8);
+        let dump = RegisterDump { buf: &buf };
+        for (offset, reg) in Reg::ALL.iter().enumerate() {
+            assert_eq!(dump.get(reg.address()), Some(offset as u8));
+        }
+        for addr in 0..=u8::MAX {
+            if !Reg::ALL.iter().any(|reg| reg.address() == addr) {
+                assert_eq!(dump.get(addr), None, "0x{addr:02x}");
+            }
+        }
+    }
+
+    #[test]
+    fn status_and_interrupts_decode_the_same_byte_differently() {
+        // CHGIN and AICL set, the charger bit clear
+        let interrupts = ChargerInterrupts::from_bytes([0xc1]);
+        assert!(interrupts.chgin());
+        assert!(interrupts.adaptive_input_current_loop());
+        assert!(interrupts.bypass_node());
+        assert!(!interrupts.charger());
+        let status = ChargerStatus::from_bytes([0xc1]);
+        assert!(status.chgin_ok());
+        assert!(status.aicl_ok());
+        assert!(status.bypass_ok());
+        assert!(!status.charger_ok());
+        assert!(!status.battery_ok());
+    }
+
+    #[test]
+    fn status_and_interrupts_map_every_bit_to_the_same_source() {
+        let interrupt_bits: [fn(&ChargerInterrupts) -> bool; 7] = [
+            ChargerInterrupts::bypass_node,
+            ChargerInterrupts::disqbat,
+            ChargerInterrupts::battery,
+            ChargerInterrupts::charger,
+            ChargerInterrupts::input_current_limit,
+            ChargerInterrupts::chgin,
+            ChargerInterrupts::adaptive_input_current_loop,
+        ];
+        let status_bits: [fn(&ChargerStatus) -> bool; 7] = [
+            ChargerStatus::bypass_ok,
+            ChargerStatus::disqbat_ok,
+            ChargerStatus::battery_ok,
+            ChargerStatus::charger_ok,
+            ChargerStatus::input_current_limit_ok,
+            ChargerStatus::chgin_ok,
+            ChargerStatus::aicl_ok,
+        ];
+        for (bit, (interrupt, status)) in [0, 1, 3, 4, 5, 6, 7]
+            .into_iter()
+            .zip(interrupt_bits.iter().zip(status_bits))
+        {
+            let byte = 1u8 << bit;
+            assert!(
+                interrupt(&ChargerInterrupts::from_bytes([byte])),
+                "bit {bit}"
+            );
+            assert!(status(&ChargerStatus::from_bytes([byte])), "bit {bit}");
+            assert!(
+                !interrupt(&ChargerInterrupts::from_bytes([!byte])),
+                "bit {bit}"
+            );
+            assert!(!status(&ChargerStatus::from_bytes([!byte])), "bit {bit}");
+        }
+    }
+
+    #[test]
+    fn charger_status_peek_leaves_interrupt_flags_alone() {
+        // The mock fails on any other transaction, so CHARGER_INTERRUPT is never addressed
+        let status = run(
+            &[read(Reg::CHARGER_INTERRUPT_STATUS, 0x48)],
+            async |charger| charger.charger_status_peek().await,
+        );
+        assert_eq!(
+            status,
+            Ok(ChargerStatus::new()
+                .with_battery_ok(true)
+                .with_chgin_ok(true))
+        );
+    }
+
+    #[test]
+    fn chgin_status_decodes_every_code() {
+        let codes = [
+            (0x00, ChgIn::Undervoltage),
+            (0x20, ChgIn::BelowBatt),
+            (0x40, ChgIn::Overvoltage),
+            (0x60, ChgIn::Valid),
+        ];
+        for (code, chgin) in codes {
+            // The sense and reserved bits around the field must not leak into it
+            for other in [0x00, 0x9f] {
+                let status = run(
+                    &[read(Reg::CHARGER_DETAILS_0, code | other)],
+                    async |charger| charger.chgin_status().await,
+                );
+                assert_eq!(status, Ok(chgin), "0x{:02x}", code | other);
+            }
+        }
+    }
+
+    #[test]
+    fn battery_sense_decodes_every_code() {
+        let codes = [
+            (0x00, BatterySense::Connected),
+            (0x02, BatterySense::PositiveOpen),
+            (0x04, BatterySense::NegativeOpen),
+            (0x06, BatterySense::BothOpen),
+        ];
+        for (code, sense) in codes {
+            let status = run(
+                &[read(Reg::CHARGER_DETAILS_0, code | 0xf9)],
+                async |charger| charger.battery_sense().await,
+            );
+            assert_eq!(status, Ok(sense), "0x{code:02x}");
+        }
+    }
+
+    #[test]
+    fn battery_status_decodes_every_code() {
+        for (code, battery) in (0u8..).zip(BATTERY_DETAILS) {
+            assert_eq!(battery.code(), code);
+            for (temp_bit, temp) in [
+                (0x00, TemperatureRegulation::BelowThreshold),
+                (0x80, TemperatureRegulation::AboveThreshold),
+            ] {
+                // The charger details in the low nibble must not leak into either field
+                let val = temp_bit | code << 4 | 0x0f;
+                let status = run(&[read(Reg::CHARGER_DETAILS_1, val)], async |charger| {
+                    charger.battery_status().await
+                });
+                assert_eq!(status, Ok(BatteryStatus { battery, temp }), "0x{val:02x}");
+                let details = Details::from_bytes([0, val, 0]);
+                assert_eq!((details.battery(), details.temp()), (battery, temp));
+            }
+        }
+    }
+
+    /// Every `ChargerDetails` code, in code order
+    const CHARGER_DETAILS: [ChargerDetails; 16] = [
+        ChargerDetails::Prequalification,
+        ChargerDetails::ConstantCurrent,
+        ChargerDetails::ConstantVoltage,
+        ChargerDetails::TopOff,
+        ChargerDetails::Done,
+        ChargerDetails::Reserved05,
+        ChargerDetails::TimerFault,
+        ChargerDetails::QBattDisabled,
+        ChargerDetails::Off,
+        ChargerDetails::Reserved09,
+        ChargerDetails::HighTemperature,
+        ChargerDetails::WatchdogTimer,
+        ChargerDetails::Jeita,
+        ChargerDetails::ThermistorRemoval,
+        ChargerDetails::SuspendPin,
+        ChargerDetails::Reserved0F,
+    ];
+
+    #[test]
+    fn charger_state_decodes_every_code() {
+        for (code, state) in (0u8..).zip(CHARGER_DETAILS) {
+            assert_eq!(state.code(), code);
+            assert_eq!(
+                state.is_reserved(),
+                matches!(code, 0x05 | 0x09 | 0x0f),
+                "0x{code:02x}"
+            );
+            // The battery and temperature fields in the high nibble must not leak into it
+            for val in [code, code | 0xf0] {
+                let res = run(&[read(Reg::CHARGER_DETAILS_1, val)], async |charger| {
+                    charger.charger_state().await
+                });
+                assert_eq!(res, Ok(state), "0x{val:02x}");
+            }
+        }
+    }
+
+    #[test]
+    fn thermistor_status_decodes_every_code() {
+        let codes = [
+            ThermistorDetails::Cold,
+            ThermistorDetails::Cool,
+            ThermistorDetails::Normal,
+            ThermistorDetails::Warm,
+            ThermistorDetails::Hot,
+            ThermistorDetails::Removed,
+            ThermistorDetails::Disabled,
+            ThermistorDetails::Reserved,
+        ];
+        for (code, thermistor) in (0u8..).zip(codes) {
+            assert_eq!(thermistor.code(), code);
+            // The bypass details and the reserved top bit must not leak into the field
+            for val in [code << 4, code << 4 | 0x8f] {
+                let res = run(&[read(Reg::CHARGER_DETAILS_2, val)], async |charger| {
+                    charger.thermistor_status().await
+                });
+                assert_eq!(res, Ok(thermistor), "0x{val:02x}");
+                assert_eq!(Details::from_bytes([0, 0, val]).thermistor(), thermistor);
+            }
+        }
+    }
+
+    #[test]
+    fn bypass_status_reports_single_faults() {
+        let patterns = [
+            (0x01, BypassFault::OtgCurrentLimit),
+            (0x02, BypassFault::BoostCurrentLimit),
+            (0x04, BypassFault::BuckCurrentLimit),
+        ];
+        for (bits, fault) in patterns {
+            // The thermistor field above must not leak into the bypass details
+            let bypass = run(
+                &[read(Reg::CHARGER_DETAILS_2, bits | 0x70)],
+                async |charger| charger.bypass_status().await,
+            )
+            .unwrap();
+            assert!(bypass.any_fault());
+            assert_eq!(bypass.faults().collect::<Vec<_>>(), [fault]);
+            for other in BypassFault::ALL {
+                assert_eq!(bypass.has_fault(other), other == fault);
+            }
+        }
+    }
+
+    #[test]
+    fn bypass_status_reports_multiple_faults_in_bit_order() {
+        let bypass = run(&[read(Reg::CHARGER_DETAILS_2, 0x0d)], async |charger| {
+            charger.bypass_status().await
+        })
+        .unwrap();
+        assert!(bypass.boost_on());
+        assert!(bypass.any_fault());
+        assert_eq!(
+            bypass.faults().collect::<Vec<_>>(),
+            [BypassFault::OtgCurrentLimit, BypassFault::BuckCurrentLimit]
+        );
+    }
+
+    #[test]
+    fn bypass_status_boost_on_is_not_a_fault() {
+        let bypass = run(&[read(Reg::CHARGER_DETAILS_2, 0x08)], async |charger| {
+            charger.bypass_status().await
+        })
+        .unwrap();
+        assert!(bypass.boost_on());
+        assert!(!bypass.any_fault());
+        assert_eq!(bypass.faults().next(), None);
+    }
+
+    /// Every `BatteryDetails` code, in code order
+    const BATTERY_DETAILS: [BatteryDetails; 8] = [
+        BatteryDetails::BatteryRemoved,
+        BatteryDetails::PrequalificationVoltage,
+        BatteryDetails::TimerFault,
+        BatteryDetails::RegularVoltage,
+        BatteryDetails::LowVoltage,
+        BatteryDetails::Overvoltage,
+        BatteryDetails::Reserved,
+        BatteryDetails::BatteryOnly,
+    ];
+
+    #[test]
+    fn details_predicates_follow_the_charger_state() {
+        use ChargerDetails::*;
+        // (state, is_charging, charge_complete, is_faulted)
+        let table = [
+            (Prequalification, true, false, false),
+            (ConstantCurrent, true, false, false),
+            (ConstantVoltage, true, false, false),
+            (TopOff, true, false, false),
+            (Done, false, true, false),
+            (Reserved05, false, false, false),
+            (TimerFault, false, false, true),
+            (QBattDisabled, false, false, false),
+            (Off, false, false, false),
+            (Reserved09, false, false, false),
+            (HighTemperature, false, false, true),
+            (WatchdogTimer, false, false, true),
+            (Jeita, false, false, false),
+            (ThermistorRemoval, false, false, true),
+            (SuspendPin, false, false, false),
+            (Reserved0F, false, false, false),
+        ];
+        assert_eq!(table.map(|row| row.0), CHARGER_DETAILS);
+        for (state, charging, complete, faulted) in table {
+            let details = Details::new()
+                .with_charger(state)
+                .with_battery(BatteryDetails::RegularVoltage);
+            assert_eq!(details.is_charging(), charging, "{state:?}");
+            assert_eq!(details.charge_complete(), complete, "{state:?}");
+            assert_eq!(details.is_faulted(), faulted, "{state:?}");
+        }
+    }
+
+    #[test]
+    fn details_predicates_follow_the_battery_state() {
+        use BatteryDetails::*;
+        // (battery, is_faulted, on_battery_only)
+        let table = [
+            (BatteryRemoved, false, false),
+            (PrequalificationVoltage, false, false),
+            (TimerFault, true, false),
+            (RegularVoltage, false, false),
+            (LowVoltage, false, false),
+            (Overvoltage, true, false),
+            (Reserved, false, false),
+            (BatteryOnly, false, true),
+        ];
+        assert_eq!(table.map(|row| row.0), BATTERY_DETAILS);
+        for (battery, faulted, battery_only) in table {
+            let details = Details::new()
+                .with_charger(ChargerDetails::Off)
+                .with_battery(battery);
+            assert_eq!(details.is_faulted(), faulted, "{battery:?}");
+            assert_eq!(details.on_battery_only(), battery_only, "{battery:?}");
+        }
+    }
+
+    #[test]
+    fn details_input_valid_only_for_valid_chgin() {
+        let table = [
+            (ChgIn::Undervoltage, false),
+            (ChgIn::BelowBatt, false),
+            (ChgIn::Overvoltage, false),
+            (ChgIn::Valid, true),
+        ];
+        for (chgin, valid) in table {
+            assert_eq!(
+                Details::new().with_chgin(chgin).is_input_valid(),
+                valid,
+                "{chgin:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn charger_details_classify_every_code() {
+        use ChargerDetails::*;
+        let table = [
+            (Prequalification, ChargeStateClass::Charging),
+            (ConstantCurrent, ChargeStateClass::Charging),
+            (ConstantVoltage, ChargeStateClass::Charging),
+            (TopOff, ChargeStateClass::Charging),
+            (Done, ChargeStateClass::Done),
+            (Reserved05, ChargeStateClass::Idle),
+            (TimerFault, ChargeStateClass::Fault(FaultKind::Timer)),
+            (
+                QBattDisabled,
+                ChargeStateClass::Suspended(SuspendReason::QBattDisabled),
+            ),
+            (Off, ChargeStateClass::Idle),
+            (Reserved09, ChargeStateClass::Idle),
+            (
+                HighTemperature,
+                ChargeStateClass::Fault(FaultKind::OverTemperature),
+            ),
+            (WatchdogTimer, ChargeStateClass::Fault(FaultKind::Watchdog)),
+            (Jeita, ChargeStateClass::Suspended(SuspendReason::Jeita)),
+            (
+                ThermistorRemoval,
+                ChargeStateClass::Suspended(SuspendReason::ThermistorRemoved),
+            ),
+            (
+                SuspendPin,
+                ChargeStateClass::Suspended(SuspendReason::SuspendPin),
+            ),
+            (Reserved0F, ChargeStateClass::Idle),
+        ];
+        assert_eq!(table.map(|row| row.0), CHARGER_DETAILS);
+        for (state, class) in table {
+            assert_eq!(state.classify(), class, "{state:?}");
+        }
+    }
+
+    #[test]
+    fn battery_details_classify_every_code() {
+        use BatteryDetails::*;
+        let table = [
+            (BatteryRemoved, BatteryClass::Removed),
+            (PrequalificationVoltage, BatteryClass::Low),
+            (TimerFault, BatteryClass::Fault(FaultKind::Timer)),
+            (RegularVoltage, BatteryClass::Ok),
+            (LowVoltage, BatteryClass::Low),
+            (
+                Overvoltage,
+                BatteryClass::Fault(FaultKind::BatteryOvervoltage),
+            ),
+            (Reserved, BatteryClass::Unmonitored),
+            (BatteryOnly, BatteryClass::Unmonitored),
+        ];
+        assert_eq!(table.map(|row| row.0), BATTERY_DETAILS);
+        for (battery, class) in table {
+            assert_eq!(battery.classify(), class, "{battery:?}");
+        }
+    }
+
+    #[test]
+    fn full_status_reads_six_registers_at_once() {
+        // A single write_read of six bytes; the mock fails on anything else
+        let status = run(
+            &[read_burst(
+                Reg::CHARGER_INTERRUPT,
+                &[0x41, 0xff, 0x48, 0x60, 0x31, 0x21],
+            )],
+            async |charger| charger.full_status().await,
+        )
+        .unwrap();
+        assert_eq!(
+            status.flags,
+            ChargerInterrupts::new()
+                .with_bypass_node(true)
+                .with_chgin(true)
+        );
+        assert_eq!(
+            status.status,
+            ChargerStatus::new()
+                .with_battery_ok(true)
+                .with_chgin_ok(true)
+        );
+        assert_eq!(status.details.chgin(), ChgIn::Valid);
+        assert_eq!(status.details.charger(), ChargerDetails::ConstantCurrent);
+        assert_eq!(status.details.battery(), BatteryDetails::RegularVoltage);
+        assert_eq!(status.details.thermistor(), ThermistorDetails::Normal);
+        assert!(status.details.bypass().otg_current_limit());
+    }
+
+    #[test]
+    fn full_status_ignores_the_interrupt_mask() {
+        let bytes = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let mut masked = bytes;
+        masked[1] = 0xff;
+        assert_eq!(
+            FullStatus::from_bytes(bytes),
+            FullStatus::from_bytes(masked)
+        );
+    }
+
+    #[test]
+    fn enable_otg_writes_in_order_and_restores_the_irq_mask() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_DETAILS_0, 0x00),
+                read(Reg::CHARGER_CONFIG_0, 0x05),
+                read(Reg::CHARGER_INTERRUPT_MASK, 0xbe),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xbf),
+                read(Reg::CHARGER_CONFIG_12, 0x81),
+                write(Reg::CHARGER_CONFIG_12, 0xb1),
+                read(Reg::CHARGER_CONFIG_11, 0x80),
+                write(Reg::CHARGER_CONFIG_11, 0xe4),
+                read(Reg::CHARGER_CONFIG_0, 0x05),
+                write(Reg::CHARGER_CONFIG_0, 0x0a),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xbe),
+            ],
+            async |charger| {
+                let res = charger.enable_otg(1500, 5000).await;
+                assert_eq!(charger.bypass, Some((Mode::Otg, Some(Mode::Charge))));
+                res
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn enable_otg_keeps_a_masked_bypass_irq_masked() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_DETAILS_0, 0x00),
+                read(Reg::CHARGER_CONFIG_0, 0x04),
+                read(Reg::CHARGER_INTERRUPT_MASK, 0xff),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xff),
+                read(Reg::CHARGER_CONFIG_12, 0x00),
+                write(Reg::CHARGER_CONFIG_12, 0x30),
+                read(Reg::CHARGER_CONFIG_11, 0x00),
+                write(Reg::CHARGER_CONFIG_11, 0x64),
+                read(Reg::CHARGER_CONFIG_0, 0x04),
+                write(Reg::CHARGER_CONFIG_0, 0x0a),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xff),
+            ],
+            async |charger| charger.enable_otg(1500, 5000).await,
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn enable_otg_restores_the_irq_mask_after_a_failed_write() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_DETAILS_0, 0x00),
+                read(Reg::CHARGER_CONFIG_0, 0x05),
+                read(Reg::CHARGER_INTERRUPT_MASK, 0xbe),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xbf),
+                read(Reg::CHARGER_CONFIG_12, 0x00),
+                write(Reg::CHARGER_CONFIG_12, 0x30).with_error(ErrorKind::Other),
+                write(Reg::CHARGER_INTERRUPT_MASK, 0xbe),
+            ],
+            async |charger| {
+                let res = charger.enable_otg(1500, 5000).await;
+                assert_eq!(charger.bypass, None);
+                res
+            },
+        );
+        assert_eq!(res, Err(Error::I2c(ErrorKind::Other)));
+    }
+
+    #[test]
+    fn enable_otg_refuses_a_valid_input() {
+        let res = run(&[read(Reg::CHARGER_DETAILS_0, 0x60)], async |charger| {
+            charger.enable_otg(1500, 5000).await
+        });
+        assert_eq!(res, Err(Error::InputPresent));
+    }
+
+    #[test]
+    fn set_qbatt_flips_only_disibs() {
+        for (enabled, before, after) in [
+            (false, 0xbf, 0xff),
+            (false, 0x00, 0x40),
+            (true, 0xff, 0xbf),
+            (true, 0x40, 0x00),
+        ] {
+            let res = run(
+                &[
+                    read(Reg::CHARGER_CONFIG_0, before),
+                    write(Reg::CHARGER_CONFIG_0, after),
+                ],
+                async |charger| charger.set_qbatt(enabled).await,
+            );
+            assert_eq!(res, Ok(()), "{enabled} from 0x{before:02x}");
+        }
+    }
+
+    #[test]
+    fn set_standby_flips_only_stby_en() {
+        // STBY_EN is bit 5, next to WDTEN (bit 4) and the mode nibble
+        for (enabled, before, after) in [
+            (true, 0xdf, 0xff),
+            (true, 0x15, 0x35),
+            (false, 0xff, 0xdf),
+            (false, 0x35, 0x15),
+        ] {
+            let res = run(
+                &[
+                    read(Reg::CHARGER_CONFIG_0, before),
+                    write(Reg::CHARGER_CONFIG_0, after),
+                ],
+                async |charger| charger.set_standby(enabled).await,
+            );
+            assert_eq!(res, Ok(()), "{enabled} from 0x{before:02x}");
+        }
+    }
+
+    #[test]
+    fn standby_reads_stby_en() {
+        for (val, enabled) in [(0x00, false), (0xdf, false), (0x20, true), (0x35, true)] {
+            let res = run(&[read(Reg::CHARGER_CONFIG_0, val)], async |charger| {
+                charger.standby().await
+            });
+            assert_eq!(res, Ok(enabled), "0x{val:02x}");
+        }
+    }
+
+    #[test]
+    fn qbatt_enabled_reads_disibs() {
+        for (val, enabled) in [(0x00, true), (0xbf, true), (0x40, false), (0xff, false)] {
+            let res = run(&[read(Reg::CHARGER_CONFIG_0, val)], async |charger| {
+                charger.qbatt_enabled().await
+            });
+            assert_eq!(res, Ok(enabled), "0x{val:02x}");
+        }
+    }
+
+    #[test]
+    fn set_remote_sense_flips_only_spsn_det_en() {
+        for (enabled, before, after) in [
+            (true, 0xf7, 0xff),
+            (true, 0x00, 0x08),
+            (false, 0xff, 0xf7),
+            (false, 0x08, 0x00),
+        ] {
+            let res = run(
+                &[
+                    read(Reg::CHARGER_CONFIG_13, before),
+                    write(Reg::CHARGER_CONFIG_13, after),
+                ],
+                async |charger| charger.set_remote_sense(enabled).await,
+            );
+            assert_eq!(res, Ok(()), "{enabled} from 0x{before:02x}");
+        }
+    }
+
+    #[test]
+    fn remote_sense_reads_spsn_det_en() {
+        for (val, enabled) in [(0x00, false), (0xf7, false), (0x08, true), (0xff, true)] {
+            let res = run(&[read(Reg::CHARGER_CONFIG_13, val)], async |charger| {
+                charger.remote_sense().await
+            });
+            assert_eq!(res, Ok(enabled), "0x{val:02x}");
+        }
+    }
+
+    #[test]
+    fn regulation_temperature_reads_regtemp() {
+        for (val, celsius) in [(0x3f, 85), (0x40, 100), (0x80, 115), (0xff, 130)] {
+            let res = run(&[read(Reg::CHARGER_CONFIG_10, val)], async |charger| {
+                charger.regulation_temperature().await
+            });
+            assert_eq!(res, Ok(celsius), "0x{val:02x}");
+        }
+    }
+
+    #[test]
+    fn write_buf_emits_a_single_burst() {
+        let vals = [0x11, 0x22, 0x33, 0x44];
+        let res = run(
+            &write_burst(Reg::CHARGER_CONFIG_1, &vals),
+            async |charger| charger.write_buf(Reg::CHARGER_CONFIG_1, &vals).await,
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn write_buf_rejects_read_only_registers() {
+        let cases: [(Reg, usize); 3] = [
+            // Starts in the read-only details
+            (Reg::CHARGER_DETAILS_2, 2),
+            // Runs past the last writable register
+            (Reg::STATUS_LED_CONFIG, 2),
+            (Reg::CHIP_ID, 1),
+        ];
+        for (base, len) in cases {
+            // No bus traffic is expected
+            let res = run(&[], async |charger| {
+                charger.write_buf(base, &[0; 4][..len]).await
+            });
+            assert_eq!(res, Err(Error::InvalidValue), "{base:?}");
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn charger_interrupts_round_trip(flags: [bool; 7]) {
+            let [bypass_node, disqbat, battery, charger, input_current_limit, chgin, aicl] = flags;
+            let built = ChargerInterrupts::new()
+                .with_bypass_node(bypass_node)
+                .with_disqbat(disqbat)
+                .with_battery(battery)
+                .with_charger(charger)
+                .with_input_current_limit(input_current_limit)
+                .with_chgin(chgin)
+                .with_adaptive_input_current_loop(aicl);
+            let decoded = ChargerInterrupts::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(
+                [
+                    decoded.bypass_node(),
+                    decoded.disqbat(),
+                    decoded.battery(),
+                    decoded.charger(),
+                    decoded.input_current_limit(),
+                    decoded.chgin(),
+                    decoded.adaptive_input_current_loop(),
+                ],
+                flags
+            );
+        }
+
+        #[test]
+        fn charger_config0_round_trips(
+            mode in any_field::<Mode>(),
+            watchdog_enable: bool,
+            standby_enable: bool,
+            disibs: bool,
+            watchdog_action in any_field::<WatchdogAction>(),
+        ) {
+            let built = ChargerConfig0::new()
+                .with_mode(mode)
+                .with_watchdog_enable(watchdog_enable)
+                .with_standby_enable(standby_enable)
+                .with_disibs(disibs)
+                .with_watchdog_action(watchdog_action);
+            let decoded = ChargerConfig0::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(
+                (
+                    decoded.mode(),
+                    decoded.watchdog_enable(),
+                    decoded.standby_enable(),
+                    decoded.disibs(),
+                    decoded.watchdog_action(),
+                ),
+                (Ok(mode), watchdog_enable, standby_enable, disibs, watchdog_action)
+            );
+        }
+
+        #[test]
+        fn details_round_trip(
+            sense in any_field::<BatterySense>(),
+            chgin in any_field::<ChgIn>(),
+            charger in any_field::<ChargerDetails>(),
+            battery in any_field::<BatteryDetails>(),
+            temp in any_field::<TemperatureRegulation>(),
+            bypass in any_field::<BypassNodeDetails>(),
+            thermistor in any_field::<ThermistorDetails>(),
+        ) {
+            let built = Details::new()
+                .with_sense(sense)
+                .with_chgin(chgin)
+                .with_charger(charger)
+                .with_battery(battery)
+                .with_temp(temp)
+                .with_bypass(bypass)
+                .with_thermistor(thermistor);
+            let decoded = Details::from_bytes(built.into_bytes());
+            prop_assert_eq!(decoded, built);
+            prop_assert_eq!(
+                (
+                    decoded.sense(),
+                    decoded.chgin(),
+                    decoded.charger(),
+                    decoded.battery(),
+                    decoded.temp(),
+                    decoded.bypass(),
+                    decoded.thermistor(),
+                ),
+                (sense, chgin, charger, battery, temp, bypass, thermistor)
+            );
+        }
+
+        #[test]
+        fn details_decode_any_bytes(raw: [u8; 3]) {
+            let details = Details::from_bytes(raw);
+            prop_assert_eq!(details.into_bytes(), raw);
+            let _ = (
+                details.sense(),
+                details.chgin(),
+                details.charger().classify(),
+                details.battery().classify(),
+                details.temp(),
+                details.bypass().faults().count(),
+                details.thermistor(),
+            );
+            let _ = (
+                details.is_input_valid(),
+                details.is_charging(),
+                details.charge_complete(),
+                details.is_faulted(),
+                details.on_battery_only(),
+            );
+        }
+    }
+
+    #[test]
+    fn config0_mode_reports_reserved_codes() {
+        for raw in 0..=u8::MAX {
+            let expected = match raw & 0x0f {
+                0x0 => Ok(Mode::Off),
+                0x4 => Ok(Mode::Buck),
+                0x5 => Ok(Mode::Charge),
+                0x9 => Ok(Mode::Boost),
+                0xa => Ok(Mode::Otg),
+                code => Err(code),
+            };
+            assert_eq!(
+                ChargerConfig0::from_bytes([raw]).mode(),
+                expected,
+                "{raw:#04x}"
+            );
+        }
+    }
+
+    #[test]
+    fn mode_rejects_reserved_codes() {
+        for code in [0x01, 0x0f] {
+            let res = run(
+                &[read(Reg::CHARGER_CONFIG_0, 0xf0 | code)],
+                async |charger| charger.mode().await,
+            );
+            assert_eq!(res, Err(Error::InvalidValue), "{code:#04x}");
+        }
+    }
+
+    #[test]
+    fn setters_reject_extreme_values_without_bus_traffic() {
+        for (variant, milliamps) in [
+            (Variant::Max77975, 0),
+            (Variant::Max77976, 0),
+            (Variant::Max77975, u16::MAX),
+            (Variant::Max77976, u16::MAX),
+            (Variant::Max77985, u16::MAX),
+            (Variant::Max77986, u16::MAX),
+        ] {
+            let res = run_variant(variant, &[], async |charger| {
+                charger.set_fast_charge_current(milliamps).await
+            });
+            assert_eq!(res, Err(Error::InvalidValue), "{variant:?} {milliamps}");
+        }
+        for celsius in [0, u8::MAX] {
+            let res = run(&[], async |charger| {
+                charger.set_regulation_temperature(celsius).await
+            });
+            assert_eq!(res, Err(Error::InvalidValue), "{celsius}");
+        }
+        let res = run(&[], async |charger| {
+            charger
+                .ramp_fast_charge_current(1000, 0, &mut NoopDelay, 0)
+                .await
+        });
+        assert_eq!(res, Err(Error::InvalidValue));
+    }
+
+    #[test]
+    fn reserved_detail_codes_decode() {
+        let details = Details::from_bytes([0xff; 3]);
+        assert_eq!(details.charger(), ChargerDetails::Reserved0F);
+        assert_eq!(details.charger().classify(), ChargeStateClass::Idle);
+        assert_eq!(details.battery(), BatteryDetails::BatteryOnly);
+        assert_eq!(details.thermistor(), ThermistorDetails::Reserved);
+        assert!(!details.is_charging() && !details.is_faulted());
+        let details = Details::from_bytes([0x00, 0x65, 0x00]);
+        assert_eq!(details.charger(), ChargerDetails::Reserved05);
+        assert_eq!(details.battery(), BatteryDetails::Reserved);
+        assert_eq!(details.battery().classify(), BatteryClass::Unmonitored);
+    }
+
+    #[test]
+    fn register_dump_ignores_unknown_registers() {
+        let buf = sample_dump_buf();
+        let dump = RegisterDump { buf: &buf };
+        for reg in [0x30, 0x52, u8::MAX] {
+            assert_eq!(dump.get(reg), None, "{reg:#04x}");
+        }
+        assert!(dump
+            .range(Reg::CHARGER_CONFIG_5, Reg::CHARGER_CONFIG_1)
+            .is_empty());
+        assert_eq!(dump.iter().count(), RegisterDump::LEN);
+    }
+
+    #[test]
+    fn charger_details_decode_every_register_value() {
+        for index in 0..3 {
+            for val in 0..=u8::MAX {
+                let mut bytes = [0; 3];
+                bytes[index] = val;
+                let details = run(
+                    &[read_burst(Reg::CHARGER_DETAILS_0, &bytes)],
+                    async |charger| charger.charger_details().await,
+                )
+                .unwrap();
+                let [d0, d1, d2] = bytes;
+                assert_eq!(details.sense() as u8, d0 >> 1 & 0x03, "{bytes:02x?}");
+                assert_eq!(details.chgin() as u8, d0 >> 5 & 0x03, "{bytes:02x?}");
+                assert_eq!(details.charger().code(), d1 & 0x0f, "{bytes:02x?}");
+                assert_eq!(details.battery().code(), d1 >> 4 & 0x07, "{bytes:02x?}");
+                assert_eq!(details.temp() as u8, d1 >> 7, "{bytes:02x?}");
+                assert_eq!(details.bypass().into_bytes()[0], d2 & 0x0f, "{bytes:02x?}");
+                assert_eq!(details.thermistor().code(), d2 >> 4 & 0x07, "{bytes:02x?}");
+            }
+        }
+    }
+
+    #[test]
+    fn single_byte_status_types_round_trip_every_byte() {
+        for raw in 0..=u8::MAX {
+            assert_eq!(ChargerInterrupts::from_bytes([raw]).into_bytes(), [raw]);
+            assert_eq!(ChargerStatus::from_bytes([raw]).into_bytes(), [raw]);
+            assert_eq!(TopInterrupts::from_bytes([raw]).into_bytes(), [raw]);
+        }
+    }
+
+    #[test]
+    fn recorded_details_bytes_keep_their_meaning() {
+        // Bytes as logged from CHARGER_DETAILS_0..2; the layout must never change
+        let details = Details::from_bytes([0x62, 0x31, 0x25]);
+        assert_eq!(details.sense(), BatterySense::PositiveOpen);
+        assert_eq!(details.chgin(), ChgIn::Valid);
+        assert_eq!(details.charger(), ChargerDetails::ConstantCurrent);
+        assert_eq!(details.battery(), BatteryDetails::RegularVoltage);
+        assert_eq!(details.temp(), TemperatureRegulation::BelowThreshold);
+        assert_eq!(
+            details.bypass().faults().collect::<Vec<_>>(),
+            [BypassFault::OtgCurrentLimit, BypassFault::BuckCurrentLimit]
+        );
+        assert_eq!(details.thermistor(), ThermistorDetails::Normal);
+        assert_eq!(details.into_bytes(), [0x62, 0x31, 0x25]);
+    }
+
+    #[test]
+    fn recorded_flag_bytes_keep_their_meaning() {
+        let top = TopInterrupts::from_bytes([0x1c]);
+        assert!(top.thermal_shutdown() && top.sys_overvoltage() && top.sys_undervoltage());
+        // Only reserved bits
+        let top = TopInterrupts::from_bytes([0xe3]);
+        assert!(!top.thermal_shutdown() && !top.sys_overvoltage() && !top.sys_undervoltage());
+
+        let irqs = ChargerInterrupts::from_bytes([0x41]);
+        assert!(irqs.chgin() && irqs.bypass_node());
+        assert!(!irqs.charger() && !irqs.battery());
+        let status = ChargerStatus::from_bytes([0x41]);
+        assert!(status.chgin_ok() && status.bypass_ok());
+        assert!(!status.charger_ok() && !status.aicl_ok());
+    }
+
+    /// The transactions of one protected CHG_CC step from `from` to `to`
+    fn chg_cc_step(from: u8, to: u8) -> [Transaction; 4] {
+        [
+            unlock(),
+            read(Reg::CHARGER_CONFIG_2, from),
+            write(Reg::CHARGER_CONFIG_2, to),
+            lock(),
+        ]
+    }
+
+    #[test]
+    fn ramp_fast_charge_current_steps_up() {
+        let mut expectations = vec![read(Reg::CHARGER_CONFIG_2, 0x14)];
+        expectations.extend(chg_cc_step(0x14, 0x16));
+        expectations.extend(chg_cc_step(0x16, 0x18));
+        expectations.extend(chg_cc_step(0x18, 0x19));
+        let res = run_variant(Variant::Max77975, &expectations, async |charger| {
+            charger
+                .ramp_fast_charge_current(1250, 100, &mut NoopDelay, 0)
+                .await
+        });
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn ramp_fast_charge_current_steps_down() {
+        let mut expectations = vec![read(Reg::CHARGER_CONFIG_2, 0x28)];
+        expectations.extend(chg_cc_step(0x28, 0x1e));
+        expectations.extend(chg_cc_step(0x1e, 0x14));
+        let res = run_variant(Variant::Max77975, &expectations, async |charger| {
+            charger
+                .ramp_fast_charge_current(1000, 500, &mut NoopDelay, 0)
+                .await
+        });
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn ramp_fast_charge_current_at_target_writes_nothing() {
+        let res = run_variant(
+            Variant::Max77975,
+            &[read(Reg::CHARGER_CONFIG_2, 0x14)],
+            async |charger| {
+                charger
+                    .ramp_fast_charge_current(1000, 100, &mut NoopDelay, 0)
+                    .await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+}
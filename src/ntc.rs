@@ -0,0 +1,378 @@
+//! NTC thermistor calculations for choosing [`ThermistorThresholds`].
+//!
+//! The thermistor thresholds compare the THM pin voltage against fixed fractions of the bias
+//! voltage. Which temperature a threshold corresponds to therefore depends on the NTC and the
+//! bias resistor. [`Ntc`] converts between temperatures and THM ratios with the β equation and
+//! picks the threshold closest to a target temperature:
+//!
+//! ```
+//! use max7797x_driver::ntc::Ntc;
+//!
+//! let ntc = Ntc {
+//!     nominal_ohms: 10_000,
+//!     beta: 3435,
+//!     bias_ohms: 10_000,
+//! };
+//! let warm = ntc.warm_threshold(450);
+//! assert_eq!(warm.decidegrees, 450);
+//! ```
+//!
+//! All arithmetic is fixed point. Temperatures are in tenths of a degree Celsius.
+
+use crate::{ColdThreshold, CoolThreshold, HotThreshold, ThermistorThresholds, WarmThreshold};
+
+/// The number of fractional bits of the fixed-point values
+const FRAC_BITS: u32 = 30;
+/// 1.0 in fixed point
+const ONE: i128 = 1 << FRAC_BITS;
+/// ln(2) in fixed point
+const LN_2: i128 = 744_261_118;
+/// 0 °C in centikelvin
+const ZERO_CELSIUS_CK: i128 = 27_315;
+/// The nominal temperature of the NTC, 25 °C, in centikelvin
+const NOMINAL_CK: i128 = ZERO_CELSIUS_CK + 2_500;
+/// The inverse of the nominal temperature, in units of 2^-60 per centikelvin
+const INV_NOMINAL: i128 = ONE * ONE / NOMINAL_CK;
+/// A ratio of one in parts per million
+const PPM: u32 = 1_000_000;
+
+/// The THM ratios of the threshold codes, in parts per million of the bias voltage
+const COLD_RATIOS: [(ColdThreshold, u32); 4] = [
+    (ColdThreshold::Minus10C, 822_349),
+    (ColdThreshold::Minus5C, 783_969),
+    (ColdThreshold::Plus0C, 741_631),
+    (ColdThreshold::Plus5C, 696_018),
+];
+const COOL_RATIOS: [(CoolThreshold, u32); 4] = [
+    (CoolThreshold::Plus0C, 741_631),
+    (CoolThreshold::Plus5C, 696_018),
+    (CoolThreshold::Plus10C, 648_017),
+    (CoolThreshold::Plus15C, 598_646),
+];
+const WARM_RATIOS: [(WarmThreshold, u32); 4] = [
+    (WarmThreshold::Plus35C, 407_604),
+    (WarmThreshold::Plus40C, 365_432),
+    (WarmThreshold::Plus45C, 326_457),
+    (WarmThreshold::Plus50C, 290_840),
+];
+const HOT_RATIOS: [(HotThreshold, u32); 4] = [
+    (HotThreshold::Plus45C, 326_457),
+    (HotThreshold::Plus50C, 290_840),
+    (HotThreshold::Plus55C, 258_597),
+    (HotThreshold::Plus60C, 229_635),
+];
+
+/// An NTC thermistor on the THM pin, biased through a series resistor
+///
+/// The nominal resistance is specified at 25 °C.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ntc {
+    /// The resistance at 25 °C in Ω
+    pub nominal_ohms: u32,
+    /// The β coefficient in K
+    pub beta: u16,
+    /// The bias resistor in Ω
+    pub bias_ohms: u32,
+}
+
+/// The threshold closest to a target temperature
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Nearest<T> {
+    /// The chosen threshold
+    pub threshold: T,
+    /// The temperature the threshold corresponds to with this NTC, in 0.1 °C
+    pub decidegrees: i16,
+}
+
+impl Ntc {
+    /// The THM ratio at `decidegrees`, in parts per million of the bias voltage.
+    pub fn ratio_ppm(&self, decidegrees: i16) -> u32 {
+        let temp_ck = ZERO_CELSIUS_CK.saturating_add(i128::from(decidegrees).saturating_mul(10));
+        // R / R0 = exp(β (1/T - 1/T0)), with T in centikelvin
+        let exponent = i128::from(self.beta)
+            .saturating_mul(100)
+            .saturating_mul(NOMINAL_CK.saturating_sub(temp_ck))
+            .saturating_mul(ONE)
+            .checked_div(temp_ck.saturating_mul(NOMINAL_CK))
+            .unwrap_or(0);
+        // Far below the nominal temperature the resistance saturates rather than overflowing
+        let resistance = i128::from(self.nominal_ohms).saturating_mul(exp(exponent));
+        let bias = i128::from(self.bias_ohms).saturating_mul(ONE);
+        let total = resistance.saturating_add(bias).max(1);
+        let bias_ppm = bias
+            .saturating_mul(i128::from(PPM))
+            .checked_div(total)
+            .unwrap_or(0)
+            .clamp(0, i128::from(PPM));
+        PPM.saturating_sub(bias_ppm as u32)
+    }
+
+    /// The temperature at which the THM ratio is `ratio_ppm`, in 0.1 °C.
+    ///
+    /// The result saturates at the limits of `i16`.
+    pub fn decidegrees(&self, ratio_ppm: u32) -> i16 {
+        let ratio_ppm = i128::from(ratio_ppm.clamp(1, PPM.saturating_sub(1)));
+        // R / R0 = Rb p / ((1 - p) R0)
+        let numerator = i128::from(self.bias_ohms)
+            .saturating_mul(ratio_ppm)
+            .saturating_mul(ONE);
+        let denominator = i128::from(PPM)
+            .saturating_sub(ratio_ppm)
+            .saturating_mul(i128::from(self.nominal_ohms).max(1));
+        let ln_ratio = ln(numerator.checked_div(denominator).unwrap_or(0));
+        // 1/T = 1/T0 + ln(R / R0) / β, in units of 2^-60 per centikelvin
+        let inv_temp = INV_NOMINAL.saturating_add(
+            ln_ratio
+                .saturating_mul(ONE)
+                .checked_div(i128::from(self.beta).max(1).saturating_mul(100))
+                .unwrap_or(0),
+        );
+        if inv_temp <= 0 {
+            return i16::MAX;
+        }
+        let temp_ck = (ONE * ONE)
+            .saturating_add(inv_temp / 2)
+            .checked_div(inv_temp)
+            .unwrap_or(0);
+        let decidegrees = temp_ck
+            .saturating_sub(ZERO_CELSIUS_CK)
+            .saturating_add(5)
+            .div_euclid(10);
+        decidegrees.clamp(i16::MIN as i128, i16::MAX as i128) as i16
+    }
+
+    /// The cold/cool threshold closest to `decidegrees`.
+    pub fn cold_threshold(&self, decidegrees: i16) -> Nearest<ColdThreshold> {
+        self.nearest(&COLD_RATIOS, decidegrees)
+    }
+
+    /// The cool/normal threshold closest to `decidegrees`.
+    pub fn cool_threshold(&self, decidegrees: i16) -> Nearest<CoolThreshold> {
+        self.nearest(&COOL_RATIOS, decidegrees)
+    }
+
+    /// The normal/warm threshold closest to `decidegrees`.
+    pub fn warm_threshold(&self, decidegrees: i16) -> Nearest<WarmThreshold> {
+        self.nearest(&WARM_RATIOS, decidegrees)
+    }
+
+    /// The warm/hot threshold closest to `decidegrees`.
+    pub fn hot_threshold(&self, decidegrees: i16) -> Nearest<HotThreshold> {
+        self.nearest(&HOT_RATIOS, decidegrees)
+    }
+
+    /// The thresholds closest to the four zone boundaries, in 0.1 °C.
+    ///
+    /// The result is not checked for ordering; see [`ThermistorThresholds::is_ordered`].
+    pub fn thresholds(&self, cold: i16, cool: i16, warm: i16, hot: i16) -> ThermistorThresholds {
+        ThermistorThresholds {
+            cold: self.cold_threshold(cold).threshold,
+            cool: self.cool_threshold(cool).threshold,
+            warm: self.warm_threshold(warm).threshold,
+            hot: self.hot_threshold(hot).threshold,
+        }
+    }
+
+    fn nearest<T: Copy>(&self, ratios: &[(T, u32); 4], decidegrees: i16) -> Nearest<T> {
+        let target = self.ratio_ppm(decidegrees);
+        let (threshold, ratio) = ratios
+            .iter()
+            .copied()
+            .min_by_key(|&(_, ratio)| ratio.abs_diff(target))
+            .unwrap_or(ratios[0]);
+        Nearest {
+            threshold,
+            decidegrees: self.decidegrees(ratio),
+        }
+    }
+}
+
+/// e^x in fixed point.
+fn exp(x: i128) -> i128 {
+    // e^x = 2^k e^r with 0 <= r < ln(2)
+    let k = x.div_euclid(LN_2);
+    let r = x.rem_euclid(LN_2);
+    let mut sum = ONE;
+    let mut term = ONE;
+    for n in 1..16 {
+        term = term
+            .saturating_mul(r)
+            .checked_div(ONE.saturating_mul(n))
+            .unwrap_or(0);
+        sum = sum.saturating_add(term);
+    }
+    let shift = k.unsigned_abs().min(127) as u32;
+    if k >= 0 {
+        sum.checked_shl(shift.min(64)).unwrap_or(0)
+    } else {
+        sum.checked_shr(shift).unwrap_or(0)
+    }
+}
+
+/// ln(x) in fixed point, for x > 0.
+fn ln(x: i128) -> i128 {
+    let x = x.max(1);
+    // ln(x) = k ln(2) + ln(m) with 1 <= m < 2
+    let k =
+        i128::from(127u32.saturating_sub(x.leading_zeros())).saturating_sub(i128::from(FRAC_BITS));
+    let shift = k.unsigned_abs().min(127) as u32;
+    let m = if k >= 0 {
+        x.checked_shr(shift)
+    } else {
+        x.checked_shl(shift)
+    }
+    .unwrap_or(ONE);
+    // ln(m) = 2 atanh(s) with s = (m - 1) / (m + 1)
+    let s = m
+        .saturating_sub(ONE)
+        .saturating_mul(ONE)
+        .checked_div(m.saturating_add(ONE))
+        .unwrap_or(0);
+    let s2 = s.saturating_mul(s) / ONE;
+    let mut sum = s;
+    let mut term = s;
+    for n in (3..24).step_by(2) {
+        term = term.saturating_mul(s2) / ONE;
+        sum = sum.saturating_add(term.checked_div(n).unwrap_or(0));
+    }
+    sum.saturating_mul(2).saturating_add(k.saturating_mul(LN_2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NTC_3435: Ntc = Ntc {
+        nominal_ohms: 10_000,
+        beta: 3435,
+        bias_ohms: 10_000,
+    };
+    const NTC_3950: Ntc = Ntc {
+        nominal_ohms: 10_000,
+        beta: 3950,
+        bias_ohms: 10_000,
+    };
+    const NTC_100K: Ntc = Ntc {
+        nominal_ohms: 100_000,
+        beta: 4250,
+        bias_ohms: 100_000,
+    };
+
+    fn assert_close(actual: i16, expected: i16) {
+        assert!(
+            actual.abs_diff(expected) <= 1,
+            "{actual} is not within 0.1 °C of {expected}"
+        );
+    }
+
+    #[test]
+    fn ratio_matches_hand_computed_points() {
+        // (NTC, temperature, ratio from the β equation in ppm)
+        let points = [
+            (NTC_3435, 250, 500_000),
+            (NTC_3435, 450, 326_457),
+            (NTC_3435, -100, 822_349),
+            (NTC_3950, 450, 303_045),
+            (NTC_100K, 570, 200_747),
+        ];
+        for (ntc, decidegrees, ratio) in points {
+            let actual = ntc.ratio_ppm(decidegrees);
+            assert!(
+                actual.abs_diff(ratio) <= 20,
+                "{decidegrees}: {actual} != {ratio}"
+            );
+            assert_close(ntc.decidegrees(ratio), decidegrees);
+        }
+    }
+
+    #[test]
+    fn nominal_thresholds_round_trip() {
+        for (threshold, ratio) in WARM_RATIOS {
+            let nearest = NTC_3435.warm_threshold(threshold.nominal_celsius() as i16 * 10);
+            assert_eq!(nearest.threshold, threshold);
+            assert_close(nearest.decidegrees, threshold.nominal_celsius() as i16 * 10);
+            assert_close(
+                NTC_3435.decidegrees(ratio),
+                threshold.nominal_celsius() as i16 * 10,
+            );
+        }
+    }
+
+    #[test]
+    fn nearest_thresholds_match_hand_computed_points() {
+        // (NTC, target, nearest threshold and its actual temperature) per zone boundary
+        let cold = [
+            (NTC_3435, -50, ColdThreshold::Minus5C, -50),
+            (NTC_3950, -50, ColdThreshold::Minus10C, -59),
+            (NTC_100K, -50, ColdThreshold::Minus10C, -39),
+        ];
+        for (ntc, target, threshold, decidegrees) in cold {
+            let nearest = ntc.cold_threshold(target);
+            assert_eq!(nearest.threshold, threshold, "{ntc:?}");
+            assert_close(nearest.decidegrees, decidegrees);
+        }
+        let cool = [
+            (NTC_3435, 120, CoolThreshold::Plus10C, 100),
+            (NTC_3950, 120, CoolThreshold::Plus10C, 119),
+            (NTC_100K, 120, CoolThreshold::Plus10C, 128),
+        ];
+        for (ntc, target, threshold, decidegrees) in cool {
+            let nearest = ntc.cool_threshold(target);
+            assert_eq!(nearest.threshold, threshold, "{ntc:?}");
+            assert_close(nearest.decidegrees, decidegrees);
+        }
+        let warm = [
+            (NTC_3435, 450, WarmThreshold::Plus45C, 450),
+            (NTC_3950, 450, WarmThreshold::Plus50C, 465),
+            (NTC_100K, 450, WarmThreshold::Plus50C, 449),
+        ];
+        for (ntc, target, threshold, decidegrees) in warm {
+            let nearest = ntc.warm_threshold(target);
+            assert_eq!(nearest.threshold, threshold, "{ntc:?}");
+            assert_close(nearest.decidegrees, decidegrees);
+        }
+        let hot = [
+            (NTC_3435, 570, HotThreshold::Plus55C, 550),
+            (NTC_3950, 570, HotThreshold::Plus60C, 550),
+            (NTC_100K, 570, HotThreshold::Plus60C, 527),
+        ];
+        for (ntc, target, threshold, decidegrees) in hot {
+            let nearest = ntc.hot_threshold(target);
+            assert_eq!(nearest.threshold, threshold, "{ntc:?}");
+            assert_close(nearest.decidegrees, decidegrees);
+        }
+    }
+
+    #[test]
+    fn thresholds_plug_into_the_setter() {
+        let thresholds = NTC_3950.thresholds(-50, 120, 450, 570);
+        assert_eq!(
+            thresholds,
+            ThermistorThresholds {
+                cold: ColdThreshold::Minus10C,
+                cool: CoolThreshold::Plus10C,
+                warm: WarmThreshold::Plus50C,
+                hot: HotThreshold::Plus60C,
+            }
+        );
+        assert!(thresholds.is_ordered());
+    }
+
+    #[test]
+    fn extreme_inputs_saturate() {
+        let ntc = Ntc {
+            nominal_ohms: u32::MAX,
+            beta: u16::MAX,
+            bias_ohms: 0,
+        };
+        assert!(ntc.ratio_ppm(i16::MIN) <= PPM);
+        assert!(ntc.ratio_ppm(i16::MAX) <= PPM);
+        for ratio in [0, 1, PPM / 2, PPM, u32::MAX] {
+            NTC_3435.decidegrees(ratio);
+            ntc.decidegrees(ratio);
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! A trait over the charger operations used by application code.
+//!
+//! Application logic written against [`ChargerOps`] instead of [`Charger`] can be tested on the
+//! host with a hand-rolled fake, such as the
+//! [`FakeCharger`](crate::sim::FakeCharger) of the `sim` feature, without an I2C mock:
+//!
+//! ```
+//! use max7797x_driver::{ChargerOps, ChgIn, Error};
+//!
+//! async fn limit_input<C: ChargerOps>(charger: &mut C) -> Result<(), Error<C::Error>> {
+//!     if charger.charger_details().await?.chgin() == ChgIn::Valid {
+//!         charger.set_chgin_ilim(500).await?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The methods return `impl Future` rather than being `async fn`, so implementations may return
+//! `Send` futures. See the methods of [`Charger`] for their behavior.
+
+use core::future::Future;
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{
+    Charger, ChargerInterrupts, ChargerStatus, Details, Error, IrqSummary, Mode, TopInterrupts,
+};
+
+/// The charger operations used by application code, implemented by [`Charger`]
+pub trait ChargerOps {
+    /// The bus error type
+    type Error;
+
+    /// Set the charger [`Mode`].
+    fn set_mode(&mut self, mode: Mode) -> impl Future<Output = Result<(), Error<Self::Error>>>;
+
+    /// Read the charger [`Mode`].
+    fn mode(&mut self) -> impl Future<Output = Result<Mode, Error<Self::Error>>>;
+
+    /// Set the current limit for CHGIN.
+    fn set_chgin_ilim(
+        &mut self,
+        milliamps: u16,
+    ) -> impl Future<Output = Result<(), Error<Self::Error>>>;
+
+    /// Returns the current limit for CHGIN in mA.
+    fn chgin_ilim(&mut self) -> impl Future<Output = Result<u16, Error<Self::Error>>>;
+
+    /// Set the fast-charge current.
+    fn set_fast_charge_current(
+        &mut self,
+        milliamps: u16,
+    ) -> impl Future<Output = Result<(), Error<Self::Error>>>;
+
+    /// Returns the programmed fast-charge current in mA.
+    fn fast_charge_current(&mut self) -> impl Future<Output = Result<u16, Error<Self::Error>>>;
+
+    /// Set the current limit for Vsys out.
+    fn set_sys_ilim(
+        &mut self,
+        milliamps: u16,
+        recycle_en: bool,
+    ) -> impl Future<Output = Result<(), Error<Self::Error>>>;
+
+    /// Clear the charger interrupt flags and return the current status bits.
+    fn charger_status(&mut self)
+        -> impl Future<Output = Result<ChargerStatus, Error<Self::Error>>>;
+
+    /// Get the detailed status of the charger.
+    fn charger_details(&mut self) -> impl Future<Output = Result<Details, Error<Self::Error>>>;
+
+    /// Set the charger interrupt mask.
+    fn set_charger_irq_mask(
+        &mut self,
+        irqs: ChargerInterrupts,
+    ) -> impl Future<Output = Result<(), Error<Self::Error>>>;
+
+    /// Set the TOP interrupt mask.
+    fn set_top_irq_mask(
+        &mut self,
+        irqs: TopInterrupts,
+    ) -> impl Future<Output = Result<(), Error<Self::Error>>>;
+
+    /// Read and clear both the TOP and the charger interrupt flags.
+    fn irq_summary(&mut self) -> impl Future<Output = Result<IrqSummary, Error<Self::Error>>>;
+}
+
+impl<D: I2c> ChargerOps for Charger<D> {
+    type Error = D::Error;
+
+    async fn set_mode(&mut self, mode: Mode) -> Result<(), Error<D::Error>> {
+        Charger::set_mode(self, mode).await
+    }
+
+    async fn mode(&mut self) -> Result<Mode, Error<D::Error>> {
+        Charger::mode(self).await
+    }
+
+    async fn set_chgin_ilim(&mut self, milliamps: u16) -> Result<(), Error<D::Error>> {
+        Charger::set_chgin_ilim(self, milliamps).await
+    }
+
+    async fn chgin_ilim(&mut self) -> Result<u16, Error<D::Error>> {
+        Charger::chgin_ilim(self).await
+    }
+
+    async fn set_fast_charge_current(&mut self, milliamps: u16) -> Result<(), Error<D::Error>> {
+        Charger::set_fast_charge_current(self, milliamps).await
+    }
+
+    async fn fast_charge_current(&mut self) -> Result<u16, Error<D::Error>> {
+        Charger::fast_charge_current(self).await
+    }
+
+    async fn set_sys_ilim(
+        &mut self,
+        milliamps: u16,
+        recycle_en: bool,
+    ) -> Result<(), Error<D::Error>> {
+        Charger::set_sys_ilim(self, milliamps, recycle_en).await
+    }
+
+    async fn charger_status(&mut self) -> Result<ChargerStatus, Error<D::Error>> {
+        Charger::charger_status(self).await
+    }
+
+    async fn charger_details(&mut self) -> Result<Details, Error<D::Error>> {
+        Charger::charger_details(self).await
+    }
+
+    async fn set_charger_irq_mask(
+        &mut self,
+        irqs: ChargerInterrupts,
+    ) -> Result<(), Error<D::Error>> {
+        Charger::set_charger_irq_mask(self, irqs).await
+    }
+
+    async fn set_top_irq_mask(&mut self, irqs: TopInterrupts) -> Result<(), Error<D::Error>> {
+        Charger::set_top_irq_mask(self, irqs).await
+    }
+
+    async fn irq_summary(&mut self) -> Result<IrqSummary, Error<D::Error>> {
+        Charger::irq_summary(self).await
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use embedded_hal_async::i2c::ErrorKind;
+
+    use super::*;
+    use crate::sim::{FakeCharger, Simulator};
+    use crate::testing::block_on;
+    use crate::{ChgIn, Variant};
+
+    const VARIANTS: [Variant; 4] = [
+        Variant::Max77975,
+        Variant::Max77976,
+        Variant::Max77985,
+        Variant::Max77986,
+    ];
+
+    /// The application logic from the module documentation
+    async fn limit_input<C: ChargerOps>(charger: &mut C) -> Result<(), Error<C::Error>> {
+        if charger.charger_details().await?.chgin() == ChgIn::Valid {
+            charger.set_chgin_ilim(500).await?;
+        }
+        Ok(())
+    }
+
+    /// Program `charger` and read back what it reports.
+    async fn program<C: ChargerOps>(
+        charger: &mut C,
+        chgin_ma: u16,
+        cc_ma: u16,
+    ) -> Result<(Mode, u16, u16), Error<C::Error>> {
+        charger.set_mode(Mode::Buck).await?;
+        charger.set_chgin_ilim(chgin_ma).await?;
+        charger.set_fast_charge_current(cc_ma).await?;
+        charger.set_sys_ilim(3300, false).await?;
+        Ok((
+            charger.mode().await?,
+            charger.chgin_ilim().await?,
+            charger.fast_charge_current().await?,
+        ))
+    }
+
+    #[test]
+    fn fake_reports_what_the_driver_reports() {
+        for variant in VARIANTS {
+            for (chgin_ma, cc_ma) in [(100, 100), (1234, 1234), (3200, 3000)] {
+                let sim = Simulator::new(variant);
+                let mut charger = Charger::new_with_variant(sim.device(), variant);
+                let mut fake = FakeCharger::new(variant);
+                assert_eq!(
+                    block_on(program(&mut fake, chgin_ma, cc_ma)),
+                    block_on(program(&mut charger, chgin_ma, cc_ma)),
+                    "{variant:?} at {chgin_ma} mA in, {cc_ma} mA charge"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fake_rejects_what_the_driver_rejects() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        let mut fake = FakeCharger::new(Variant::Max77975);
+        assert_eq!(
+            block_on(ChargerOps::set_fast_charge_current(&mut charger, 6000)),
+            Err(Error::InvalidValue)
+        );
+        assert_eq!(
+            block_on(fake.set_fast_charge_current(6000)),
+            Err(Error::InvalidValue)
+        );
+        assert_eq!(
+            fake.fast_charge_current_ma,
+            FakeCharger::new(Variant::Max77975).fast_charge_current_ma
+        );
+    }
+
+    #[test]
+    fn application_logic_runs_against_the_fake() {
+        let mut fake = FakeCharger::new(Variant::Max77975);
+        block_on(limit_input(&mut fake)).unwrap();
+        assert_eq!(
+            fake.chgin_ilim_ma,
+            FakeCharger::new(Variant::Max77975).chgin_ilim_ma
+        );
+
+        fake.details = fake.details.with_chgin(ChgIn::Valid);
+        block_on(limit_input(&mut fake)).unwrap();
+        assert_eq!(fake.chgin_ilim_ma, 500);
+
+        fake.fail_next = Some(ErrorKind::Bus);
+        assert_eq!(
+            block_on(limit_input(&mut fake)),
+            Err(Error::I2c(ErrorKind::Bus))
+        );
+        assert_eq!(fake.fail_next, None);
+    }
+
+    #[test]
+    fn fake_clears_pending_interrupts_when_read() {
+        let mut fake = FakeCharger::new(Variant::Max77975);
+        fake.pending.top = TopInterrupts::from_bytes([0x01]);
+        fake.pending.charger = ChargerInterrupts::from_bytes([0x40]);
+        let summary = block_on(fake.irq_summary()).unwrap();
+        assert_eq!(summary.charger, ChargerInterrupts::from_bytes([0x40]));
+        assert_eq!(summary.top, TopInterrupts::from_bytes([0x01]));
+        assert_eq!(
+            block_on(fake.irq_summary()).unwrap(),
+            IrqSummary {
+                top: TopInterrupts::new(),
+                charger: ChargerInterrupts::new(),
+            }
+        );
+
+        fake.pending.charger = ChargerInterrupts::from_bytes([0x40]);
+        block_on(fake.charger_status()).unwrap();
+        assert_eq!(fake.pending.charger, ChargerInterrupts::new());
+    }
+}
@@ -0,0 +1,158 @@
+//! A 32-bit encoding of the charger state for constrained links.
+
+use crate::{ChargerStatus, Details};
+
+/// Where each packed field comes from, as `(source byte, first bit, bit count, packed bit)`.
+///
+/// The source bytes are `CHARGER_DETAILS_0` through `CHARGER_DETAILS_2` followed by
+/// `CHARGER_INTERRUPT_STATUS`.
+const PACK_MAP: [(usize, u8, u8, u8); 14] = [
+    (0, 5, 2, 0),  // CHGIN_DTLS
+    (1, 0, 4, 2),  // CHG_DTLS
+    (1, 4, 3, 6),  // BAT_DTLS
+    (2, 4, 3, 9),  // THM_DTLS
+    (1, 7, 1, 12), // TREG
+    (2, 0, 4, 13), // BYP_DTLS
+    (3, 0, 1, 17), // BYP_OK
+    (3, 1, 1, 18), // DISQBAT_OK
+    (3, 3, 1, 19), // BAT_OK
+    (3, 4, 1, 20), // CHG_OK
+    (3, 5, 1, 21), // INLIM_OK
+    (3, 6, 1, 22), // CHGIN_OK
+    (3, 7, 1, 23), // AICL_OK
+    (0, 1, 2, 24), // BATSNS_DTLS
+];
+
+impl Details {
+    /// Pack the details and `status` into 32 bits.
+    ///
+    /// The layout is fixed and independent of the register layout. The multi-bit fields hold
+    /// the datasheet codes of the corresponding enums, see e.g. [`ChargerDetails::code`]:
+    ///
+    /// | Bits  | Field                                   |
+    /// |-------|-----------------------------------------|
+    /// | 0–1   | CHGIN details, [`ChgIn`]                |
+    /// | 2–5   | Charger details, [`ChargerDetails`]     |
+    /// | 6–8   | Battery details, [`BatteryDetails`]     |
+    /// | 9–11  | Thermistor details, [`ThermistorDetails`] |
+    /// | 12    | Junction temperature regulation active  |
+    /// | 13    | OTG current limit                       |
+    /// | 14    | Boost current limit                     |
+    /// | 15    | Buck current limit                      |
+    /// | 16    | Boost on                                |
+    /// | 17    | BYP_OK                                  |
+    /// | 18    | DISQBAT_OK                              |
+    /// | 19    | BAT_OK                                  |
+    /// | 20    | CHG_OK                                  |
+    /// | 21    | INLIM_OK                                |
+    /// | 22    | CHGIN_OK                                |
+    /// | 23    | AICL_OK                                 |
+    /// | 24–25 | Battery sense, [`BatterySense`]         |
+    /// | 26–31 | Reserved, zero                          |
+    ///
+    /// [`ChgIn`]: crate::ChgIn
+    /// [`ChargerDetails`]: crate::ChargerDetails
+    /// [`ChargerDetails::code`]: crate::ChargerDetails::code
+    /// [`BatteryDetails`]: crate::BatteryDetails
+    /// [`ThermistorDetails`]: crate::ThermistorDetails
+    /// [`BatterySense`]: crate::BatterySense
+    pub fn pack(&self, status: &ChargerStatus) -> u32 {
+        let [d0, d1, d2] = self.into_bytes();
+        let src = [d0, d1, d2, status.into_bytes()[0]];
+        PACK_MAP
+            .iter()
+            .fold(0, |word, &(byte, first, len, packed)| {
+                let raw = src.get(byte).copied().unwrap_or(0);
+                let field = u32::from(raw >> first) & (1u32 << len).wrapping_sub(1);
+                word | field << packed
+            })
+    }
+
+    /// Unpack a word produced by [`pack`](Self::pack) into the details and the status bits.
+    ///
+    /// The reserved bits are ignored.
+    pub fn unpack(word: u32) -> (Details, ChargerStatus) {
+        let mut src = [0u8; 4];
+        for &(byte, first, len, packed) in &PACK_MAP {
+            let field = (word >> packed) as u8 & (1u8 << len).wrapping_sub(1);
+            if let Some(raw) = src.get_mut(byte) {
+                *raw |= field << first;
+            }
+        }
+        (
+            Details::from_bytes([src[0], src[1], src[2]]),
+            ChargerStatus::from_bytes([src[3]]),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{BatteryDetails, BatterySense, ChargerDetails, ChgIn};
+
+    /// The register bits that carry a field, so the others read back as zero
+    const DETAILS_MASK: [u8; 3] = [0x66, 0xff, 0x7f];
+    const STATUS_MASK: u8 = 0xfb;
+
+    #[test]
+    fn fields_land_on_the_documented_bits() {
+        let none = Details::new();
+        let status = ChargerStatus::new();
+        assert_eq!(none.pack(&status), 0);
+        let cases = [
+            (none.with_chgin(ChgIn::Valid), 0b11),
+            (none.with_charger(ChargerDetails::Reserved0F), 0xf << 2),
+            (none.with_battery(BatteryDetails::BatteryOnly), 0b111 << 6),
+            (Details::from_bytes([0, 0, 0x70]), 0b111 << 9),
+            (Details::from_bytes([0, 0x80, 0]), 1 << 12),
+            (Details::from_bytes([0, 0, 0x0f]), 0xf << 13),
+            (none.with_sense(BatterySense::BothOpen), 0b11 << 24),
+        ];
+        for (details, word) in cases {
+            assert_eq!(details.pack(&status), word, "{details:?}");
+            assert_eq!(Details::unpack(word), (details, status));
+        }
+        for (bit, packed) in [
+            (0, 17),
+            (1, 18),
+            (3, 19),
+            (4, 20),
+            (5, 21),
+            (6, 22),
+            (7, 23),
+        ] {
+            let status = ChargerStatus::from_bytes([1 << bit]);
+            assert_eq!(none.pack(&status), 1 << packed, "status bit {bit}");
+            assert_eq!(Details::unpack(1 << packed), (none, status));
+        }
+    }
+
+    #[test]
+    fn unpack_ignores_the_reserved_bits() {
+        assert_eq!(
+            Details::unpack(0xfc00_0000),
+            (Details::new(), ChargerStatus::new())
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn pack_round_trips(d0: u8, d1: u8, d2: u8, status: u8) {
+            let [m0, m1, m2] = DETAILS_MASK;
+            let details = Details::from_bytes([d0 & m0, d1 & m1, d2 & m2]);
+            let status = ChargerStatus::from_bytes([status & STATUS_MASK]);
+            let word = details.pack(&status);
+            prop_assert_eq!(word >> 26, 0);
+            prop_assert_eq!(Details::unpack(word), (details, status));
+        }
+
+        #[test]
+        fn unpack_pack_round_trips(word in 0..1u32 << 26) {
+            let (details, status) = Details::unpack(word);
+            prop_assert_eq!(details.pack(&status), word);
+        }
+    }
+}
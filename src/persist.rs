@@ -0,0 +1,242 @@
+//! A fixed-size binary encoding of [`ChargerConfig`] for EEPROM persistence.
+//!
+//! The encoding needs no serde and is versioned, so a config written by one release can be read
+//! back by any later one:
+//!
+//! ```
+//! use max7797x_driver::{ChargerConfig, DecodeError, Variant};
+//!
+//! // Written by the first release with version 1 of the encoding
+//! const V1_BLOB: [u8; 11] = [1, 0xf4, 0x01, 0xe8, 0x03, 0x10, 0x27, 0, 0x58, 0x05, 0xe0];
+//!
+//! let config = ChargerConfig::recommended(2000, Variant::Max77975);
+//! assert_eq!(ChargerConfig::from_bytes(&V1_BLOB), Ok(config));
+//! assert_eq!(config.to_bytes(), V1_BLOB);
+//!
+//! assert_eq!(ChargerConfig::from_bytes(&V1_BLOB[..7]), Err(DecodeError::Truncated));
+//! let mut corrupt = V1_BLOB;
+//! corrupt[3] ^= 0x01;
+//! assert_eq!(ChargerConfig::from_bytes(&corrupt), Err(DecodeError::Checksum));
+//! ```
+
+use crate::{ChargerConfig, ChargerConfig0, ChargerInterrupts};
+
+/// Why bytes could not be decoded by [`ChargerConfig::from_bytes`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum DecodeError {
+    /// Fewer bytes than the encoding of the version
+    Truncated,
+    /// The version byte is not one this release can decode
+    UnsupportedVersion(u8),
+    /// The checksum does not match the contents
+    Checksum,
+    /// A field holds a value the encoding never produces, e.g. a reserved mode code
+    InvalidField,
+}
+
+/// The CRC-8 of `bytes`, with polynomial 0x07 and initial value 0 (CRC-8/SMBUS).
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                crc << 1 ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+impl ChargerConfig {
+    /// The version written by [`to_bytes`](Self::to_bytes)
+    pub const ENCODING_VERSION: u8 = 1;
+
+    /// The length of the encoding written by [`to_bytes`](Self::to_bytes)
+    pub const ENCODED_LEN: usize = 11;
+
+    /// Encode the config for persistence.
+    ///
+    /// The layout of version 1 is fixed and independent of the struct layout; multi-byte fields
+    /// are little-endian:
+    ///
+    /// | Offset | Size | Field                                                        |
+    /// |--------|------|--------------------------------------------------------------|
+    /// | 0      | 1    | Version, 1                                                   |
+    /// | 1      | 2    | `chgin_ilim_ma`                                              |
+    /// | 3      | 2    | `fast_charge_current_ma`                                     |
+    /// | 5      | 2    | `sys_ilim_ma`                                                |
+    /// | 7      | 1    | Flags: bit 0 is `sys_ilim_recycle`, the others are zero      |
+    /// | 8      | 1    | `charger_irqs`, the raw [`ChargerInterrupts`] byte           |
+    /// | 9      | 1    | `mode`, the MODE code of [`Mode`](crate::Mode)               |
+    /// | 10     | 1    | CRC-8/SMBUS (polynomial 0x07, initial value 0) of bytes 0–9  |
+    ///
+    /// Later releases can decode every version they ever wrote.
+    pub fn to_bytes(&self) -> [u8; Self::ENCODED_LEN] {
+        let [ilim_lo, ilim_hi] = self.chgin_ilim_ma.to_le_bytes();
+        let [cc_lo, cc_hi] = self.fast_charge_current_ma.to_le_bytes();
+        let [sys_lo, sys_hi] = self.sys_ilim_ma.to_le_bytes();
+        let mut bytes = [
+            Self::ENCODING_VERSION,
+            ilim_lo,
+            ilim_hi,
+            cc_lo,
+            cc_hi,
+            sys_lo,
+            sys_hi,
+            u8::from(self.sys_ilim_recycle),
+            self.charger_irqs.into_bytes()[0],
+            ChargerConfig0::new().with_mode(self.mode).into_bytes()[0],
+            0,
+        ];
+        bytes[10] = crc8(&bytes[..10]);
+        bytes
+    }
+
+    /// Decode a config encoded by [`to_bytes`](Self::to_bytes) of this or an earlier release.
+    ///
+    /// Bytes after the encoding are ignored, so a whole EEPROM page can be passed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        match bytes.first() {
+            None => Err(DecodeError::Truncated),
+            Some(&Self::ENCODING_VERSION) => Self::decode_v1(bytes),
+            Some(&version) => Err(DecodeError::UnsupportedVersion(version)),
+        }
+    }
+
+    fn decode_v1(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let Some(
+            &[version, ilim_lo, ilim_hi, cc_lo, cc_hi, sys_lo, sys_hi, flags, irqs, mode, crc],
+        ) = bytes.get(..Self::ENCODED_LEN)
+        else {
+            return Err(DecodeError::Truncated);
+        };
+        let encoded = [
+            version, ilim_lo, ilim_hi, cc_lo, cc_hi, sys_lo, sys_hi, flags, irqs, mode,
+        ];
+        if crc8(&encoded) != crc {
+            return Err(DecodeError::Checksum);
+        }
+        if flags & !1 != 0 || mode & 0xf0 != 0 {
+            return Err(DecodeError::InvalidField);
+        }
+        Ok(ChargerConfig {
+            chgin_ilim_ma: u16::from_le_bytes([ilim_lo, ilim_hi]),
+            fast_charge_current_ma: u16::from_le_bytes([cc_lo, cc_hi]),
+            sys_ilim_ma: u16::from_le_bytes([sys_lo, sys_hi]),
+            sys_ilim_recycle: flags & 1 != 0,
+            charger_irqs: ChargerInterrupts::from_bytes([irqs]),
+            mode: ChargerConfig0::from_bytes([mode])
+                .mode()
+                .map_err(|_| DecodeError::InvalidField)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::testing::any_field;
+    use crate::{Mode, Variant};
+
+    /// The version 1 encoding of the recommended config for 2000 mAh on a MAX77975
+    const V1_BLOB: [u8; 11] = [1, 0xf4, 0x01, 0xe8, 0x03, 0x10, 0x27, 0, 0x58, 0x05, 0xe0];
+
+    /// Encode `contents` with a valid checksum appended.
+    fn with_crc(contents: [u8; 10]) -> [u8; 11] {
+        let mut bytes = [0; 11];
+        for (byte, &val) in bytes.iter_mut().zip(&contents) {
+            *byte = val;
+        }
+        bytes[10] = crc8(&contents);
+        bytes
+    }
+
+    #[test]
+    fn crc8_is_crc8_smbus() {
+        assert_eq!(crc8(b"123456789"), 0xf4);
+        assert_eq!(crc8(&[]), 0);
+    }
+
+    #[test]
+    fn v1_blob_decodes_unchanged() {
+        let config = ChargerConfig::recommended(2000, Variant::Max77975);
+        assert_eq!(ChargerConfig::from_bytes(&V1_BLOB), Ok(config));
+        assert_eq!(config.to_bytes(), V1_BLOB);
+        // A whole EEPROM page decodes the same
+        let mut page = [0xff; 32];
+        page[..11].copy_from_slice(&V1_BLOB);
+        assert_eq!(ChargerConfig::from_bytes(&page), Ok(config));
+    }
+
+    #[test]
+    fn every_truncation_is_detected() {
+        for len in 0..ChargerConfig::ENCODED_LEN {
+            let res = ChargerConfig::from_bytes(&V1_BLOB[..len]);
+            assert_eq!(res, Err(DecodeError::Truncated), "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn every_single_bit_error_is_detected() {
+        // Flipping bits of the version byte changes the version instead
+        for (i, bit) in
+            (1..ChargerConfig::ENCODED_LEN).flat_map(|i| (0..8).map(move |bit| (i, bit)))
+        {
+            let mut corrupt = V1_BLOB;
+            corrupt[i] ^= 1 << bit;
+            let res = ChargerConfig::from_bytes(&corrupt);
+            assert_eq!(res, Err(DecodeError::Checksum), "byte {i} bit {bit}");
+        }
+    }
+
+    #[test]
+    fn unknown_versions_are_refused() {
+        for version in [0, 2, 0xff] {
+            let mut blob = V1_BLOB;
+            blob[0] = version;
+            let res = ChargerConfig::from_bytes(&blob);
+            assert_eq!(res, Err(DecodeError::UnsupportedVersion(version)));
+        }
+    }
+
+    #[test]
+    fn fields_never_encoded_are_refused() {
+        let [version, il0, il1, cc0, cc1, sys0, sys1, _, irqs, _, _] = V1_BLOB;
+        let contents = [version, il0, il1, cc0, cc1, sys0, sys1, 0x02, irqs, 0x05];
+        let res = ChargerConfig::from_bytes(&with_crc(contents));
+        assert_eq!(res, Err(DecodeError::InvalidField));
+        // 0x01 is a reserved mode code, 0x15 has bits outside the mode field
+        for mode in [0x01, 0x15] {
+            let contents = [version, il0, il1, cc0, cc1, sys0, sys1, 0, irqs, mode];
+            let res = ChargerConfig::from_bytes(&with_crc(contents));
+            assert_eq!(res, Err(DecodeError::InvalidField), "mode 0x{mode:02x}");
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn configs_round_trip(
+            chgin_ilim_ma: u16,
+            fast_charge_current_ma: u16,
+            sys_ilim_ma: u16,
+            sys_ilim_recycle: bool,
+            irqs: u8,
+            mode in any_field::<Mode>(),
+        ) {
+            let config = ChargerConfig {
+                chgin_ilim_ma,
+                fast_charge_current_ma,
+                sys_ilim_ma,
+                sys_ilim_recycle,
+                charger_irqs: ChargerInterrupts::from_bytes([irqs]),
+                mode,
+            };
+            let bytes = config.to_bytes();
+            prop_assert_eq!(bytes[0], ChargerConfig::ENCODING_VERSION);
+            prop_assert_eq!(ChargerConfig::from_bytes(&bytes), Ok(config));
+        }
+    }
+}
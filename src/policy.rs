@@ -0,0 +1,303 @@
+//! Cross-field safety rules for configurations and profiles.
+//!
+//! The setters check each value against its programmable range, but some combinations of valid
+//! values are unsafe. A [`SafetyPolicy`] encodes such rules; [`ChargerConfig::check_policy`]
+//! and [`ChargeProfile::check_policy`] report the first rule broken as a [`PolicyViolation`]:
+//!
+//! ```
+//! use max7797x_driver::{ChargerConfig, PolicyViolation, SafetyPolicy, Variant};
+//!
+//! let mut config = ChargerConfig::recommended(8000, Variant::Max77975);
+//! config.sys_ilim_ma = 3000;
+//! let violation = config.check_policy(&SafetyPolicy::DEFAULT).unwrap_err();
+//! assert_eq!(violation.fields(), ["sys_ilim_ma", "fast_charge_current_ma"]);
+//! ```
+//!
+//! The driver enforces no policy unless one is set with [`Charger::with_safety_policy`], e.g.
+//! `Some(SafetyPolicy::DEFAULT)`, after which [`Charger::apply_config`] and
+//! [`Charger::apply_charge_profile`] refuse what it forbids.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{ChargeProfile, Charger, ChargerConfig, ChargerConfig13, Error, FastChargeTimer};
+
+/// The cross-field rules checked by [`ChargerConfig::check_policy`] and
+/// [`ChargeProfile::check_policy`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SafetyPolicy {
+    /// Require the Vsys current limit to be at least the fast-charge current, so the charge
+    /// current can not trip the Vsys limit and shut the system down
+    pub sys_ilim_covers_charge_current: bool,
+    /// The highest termination voltage in mV allowed with both the fast-charge timer and JEITA
+    /// disabled, or `None` to allow any
+    ///
+    /// High-voltage cells charged without either have nothing to stop a charge that does not
+    /// terminate or runs outside the temperature window.
+    pub max_unsupervised_termination_mv: Option<u16>,
+}
+
+impl SafetyPolicy {
+    /// The recommended policy
+    ///
+    /// The Vsys current limit must cover the fast-charge current, and termination voltages
+    /// above the 4400 mV of common high-voltage cells need the fast-charge timer or JEITA.
+    pub const DEFAULT: Self = SafetyPolicy {
+        sys_ilim_covers_charge_current: true,
+        max_unsupervised_termination_mv: Some(4400),
+    };
+
+    /// A policy that allows everything
+    pub const PERMISSIVE: Self = SafetyPolicy {
+        sys_ilim_covers_charge_current: false,
+        max_unsupervised_termination_mv: None,
+    };
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A [`SafetyPolicy`] rule broken by a configuration or profile
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum PolicyViolation {
+    /// The Vsys current limit is below the fast-charge current, see
+    /// [`SafetyPolicy::sys_ilim_covers_charge_current`]
+    SysIlimBelowChargeCurrent {
+        /// The Vsys current limit in mA
+        sys_ilim_ma: u16,
+        /// The fast-charge current in mA
+        fast_charge_current_ma: u16,
+    },
+    /// The termination voltage is too high to charge with both the fast-charge timer and
+    /// JEITA disabled, see [`SafetyPolicy::max_unsupervised_termination_mv`]
+    UnsupervisedHighVoltage {
+        /// The termination voltage in mV
+        termination_mv: u16,
+        /// The highest termination voltage allowed in mV
+        max_mv: u16,
+    },
+}
+
+impl PolicyViolation {
+    /// The names of the settings involved
+    pub const fn fields(&self) -> &'static [&'static str] {
+        match self {
+            PolicyViolation::SysIlimBelowChargeCurrent { .. } => {
+                &["sys_ilim_ma", "fast_charge_current_ma"]
+            }
+            PolicyViolation::UnsupervisedHighVoltage { .. } => {
+                &["termination_mv", "fast_charge_timer", "jeita_enable"]
+            }
+        }
+    }
+
+    /// A description of the rule broken
+    pub const fn rule(&self) -> &'static str {
+        match self {
+            PolicyViolation::SysIlimBelowChargeCurrent { .. } => {
+                "the Vsys current limit must be at least the fast-charge current"
+            }
+            PolicyViolation::UnsupervisedHighVoltage { .. } => {
+                "a high termination voltage needs the fast-charge timer or JEITA"
+            }
+        }
+    }
+}
+
+impl ChargerConfig {
+    /// Check the configuration against the rules of `policy`.
+    pub fn check_policy(&self, policy: &SafetyPolicy) -> Result<(), PolicyViolation> {
+        if policy.sys_ilim_covers_charge_current && self.sys_ilim_ma < self.fast_charge_current_ma {
+            return Err(PolicyViolation::SysIlimBelowChargeCurrent {
+                sys_ilim_ma: self.sys_ilim_ma,
+                fast_charge_current_ma: self.fast_charge_current_ma,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl ChargeProfile {
+    /// Check the profile against the rules of `policy`, with JEITA control enabled or not.
+    pub fn check_policy(
+        &self,
+        policy: &SafetyPolicy,
+        jeita_enabled: bool,
+    ) -> Result<(), PolicyViolation> {
+        if let Some(max_mv) = policy.max_unsupervised_termination_mv {
+            let unsupervised =
+                self.fast_charge_timer() == FastChargeTimer::Disabled && !jeita_enabled;
+            if unsupervised && self.termination_mv() > max_mv {
+                return Err(PolicyViolation::UnsupervisedHighVoltage {
+                    termination_mv: self.termination_mv(),
+                    max_mv,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Enforce `policy` when applying configurations and profiles, or no policy with `None`.
+    ///
+    /// [`apply_config`](Self::apply_config) and
+    /// [`apply_charge_profile`](Self::apply_charge_profile) fail with
+    /// [`Error::PolicyViolation`] before writing anything if the policy is broken. The profile
+    /// is checked against the JEITA setting read from `CHARGER_CONFIG_13`. Defaults to `None`.
+    pub fn with_safety_policy(mut self, policy: Option<SafetyPolicy>) -> Self {
+        self.safety_policy = policy;
+        self
+    }
+
+    /// The enforced safety policy
+    pub fn safety_policy(&self) -> Option<SafetyPolicy> {
+        self.safety_policy
+    }
+
+    /// Check `profile` against the enforced policy, reading the JEITA setting if needed.
+    pub(crate) async fn enforce_profile_policy(
+        &mut self,
+        profile: &ChargeProfile,
+    ) -> Result<(), Error<D::Error>> {
+        let Some(policy) = self.safety_policy else {
+            return Ok(());
+        };
+        let jeita_enabled = if policy.max_unsupervised_termination_mv.is_some() {
+            self.read::<ChargerConfig13>().await?.jeita_enable()
+        } else {
+            false
+        };
+        profile
+            .check_policy(&policy, jeita_enabled)
+            .map_err(Error::PolicyViolation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use super::*;
+    use crate::testing::{lock, read, read_burst, run, unlock, write_burst};
+    use crate::{Reg, Variant};
+
+    fn config(sys_ilim_ma: u16, fast_charge_current_ma: u16) -> ChargerConfig {
+        ChargerConfig {
+            sys_ilim_ma,
+            fast_charge_current_ma,
+            ..ChargerConfig::recommended(2000, Variant::Max77975)
+        }
+    }
+
+    fn profile(termination_mv: u16, fast_charge_timer: FastChargeTimer) -> Option<ChargeProfile> {
+        Some(ChargeProfile::new(1000, termination_mv)?.with_fast_charge_timer(fast_charge_timer))
+    }
+
+    #[test]
+    fn sys_ilim_below_charge_current_is_refused() {
+        let res = config(3000, 3500).check_policy(&SafetyPolicy::DEFAULT);
+        assert_eq!(
+            res,
+            Err(PolicyViolation::SysIlimBelowChargeCurrent {
+                sys_ilim_ma: 3000,
+                fast_charge_current_ma: 3500,
+            })
+        );
+        assert_eq!(
+            config(3500, 3500).check_policy(&SafetyPolicy::DEFAULT),
+            Ok(())
+        );
+        assert_eq!(
+            config(3000, 3500).check_policy(&SafetyPolicy::PERMISSIVE),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn unsupervised_high_voltage_is_refused() {
+        let violation = PolicyViolation::UnsupervisedHighVoltage {
+            termination_mv: 4450,
+            max_mv: 4400,
+        };
+        let policy = SafetyPolicy::DEFAULT;
+        let high = profile(4450, FastChargeTimer::Disabled).unwrap();
+        assert_eq!(high.check_policy(&policy, false), Err(violation));
+        // Either the timer or JEITA supervises the charge
+        assert_eq!(high.check_policy(&policy, true), Ok(()));
+        let timed = profile(4450, FastChargeTimer::Hours5).unwrap();
+        assert_eq!(timed.check_policy(&policy, false), Ok(()));
+        let standard = profile(4400, FastChargeTimer::Disabled).unwrap();
+        assert_eq!(standard.check_policy(&policy, false), Ok(()));
+        assert_eq!(high.check_policy(&SafetyPolicy::PERMISSIVE, false), Ok(()));
+    }
+
+    #[test]
+    fn no_policy_is_enforced_by_default() {
+        let high = profile(4450, FastChargeTimer::Disabled).unwrap();
+        let mut expectations = vec![unlock(), read_burst(Reg::CHARGER_CONFIG_1, &[0; 4])];
+        expectations.extend(write_burst(
+            Reg::CHARGER_CONFIG_1,
+            &[0x10, 0x14, 0x19, 0x69],
+        ));
+        expectations.push(lock());
+        let res = run(&expectations, async |charger| {
+            assert_eq!(charger.safety_policy(), None);
+            charger.apply_charge_profile(&high).await
+        });
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn enforced_policy_refuses_a_config_before_writing() {
+        let res = run(&[], async |charger| {
+            charger.safety_policy = Some(SafetyPolicy::DEFAULT);
+            charger.apply_config(&config(3000, 3500)).await
+        });
+        assert!(matches!(
+            res,
+            Err(Error::PolicyViolation(
+                PolicyViolation::SysIlimBelowChargeCurrent { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn enforced_policy_reads_the_jeita_setting() {
+        let high = profile(4450, FastChargeTimer::Disabled).unwrap();
+        let res = run(
+            &[unlock(), read(Reg::CHARGER_CONFIG_13, 0x00), lock()],
+            async |charger| {
+                charger.safety_policy = Some(SafetyPolicy::DEFAULT);
+                charger.apply_charge_profile(&high).await
+            },
+        );
+        assert!(matches!(
+            res,
+            Err(Error::PolicyViolation(
+                PolicyViolation::UnsupervisedHighVoltage { .. }
+            ))
+        ));
+
+        let mut expectations = vec![
+            unlock(),
+            read(Reg::CHARGER_CONFIG_13, 0x01),
+            read_burst(Reg::CHARGER_CONFIG_1, &[0; 4]),
+        ];
+        expectations.extend(write_burst(
+            Reg::CHARGER_CONFIG_1,
+            &[0x10, 0x14, 0x19, 0x69],
+        ));
+        expectations.push(lock());
+        let res = run(&expectations, async |charger| {
+            charger.safety_policy = Some(SafetyPolicy::DEFAULT);
+            charger.apply_charge_profile(&high).await
+        });
+        assert_eq!(res, Ok(()));
+    }
+}
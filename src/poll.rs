@@ -0,0 +1,265 @@
+//! Poll-driven operations for superloop firmware without an async executor.
+//!
+//! Each `start_*` method returns an [`NbOperation`] which performs the same bus transactions as
+//! the corresponding async method. Pin the operation and call [`NbOperation::poll`] from the main
+//! loop until it stops returning [`nb::Error::WouldBlock`]:
+//!
+//! ```no_run
+//! # use embedded_hal_async::i2c::I2c;
+//! # use max7797x_driver::{Charger, Details, Error};
+//! # fn example<D: I2c>(
+//! #     charger: &mut Charger<D>,
+//! #     mut do_other_work: impl FnMut(),
+//! # ) -> Result<Details, Error<D::Error>> {
+//! let mut op = core::pin::pin!(charger.start_charger_details());
+//! let details = loop {
+//!     match op.as_mut().poll() {
+//!         Err(nb::Error::WouldBlock) => do_other_work(),
+//!         Err(nb::Error::Other(err)) => break Err(err),
+//!         Ok(details) => break Ok(details),
+//!     }
+//! };
+//! # details
+//! # }
+//! ```
+//!
+//! The underlying [`I2c`] implementation must return `Pending` rather than block while a
+//! transfer is in progress for the main loop to make progress in between polls.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{Charger, ChargerStatus, Details, Error, Mode};
+
+/// An in-progress charger operation, driven to completion with [`NbOperation::poll`].
+pub struct NbOperation<F> {
+    fut: F,
+    done: bool,
+}
+
+impl<F, T, E> NbOperation<F>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    /// Wrap a driver future so it can be polled without an executor.
+    pub fn new(fut: F) -> Self {
+        NbOperation { fut, done: false }
+    }
+
+    /// Advance the operation.
+    ///
+    /// Returns [`nb::Error::WouldBlock`] until the operation completes. Once a result has been
+    /// returned the operation is finished and further polls return `WouldBlock`.
+    pub fn poll(self: Pin<&mut Self>) -> nb::Result<T, E> {
+        // SAFETY: `fut` is structurally pinned; it is never moved out of `self` and
+        // `NbOperation` has no `Drop` impl.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.done {
+            return Err(nb::Error::WouldBlock);
+        }
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+        match fut.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(res) => {
+                this.done = true;
+                res.map_err(nb::Error::Other)
+            }
+            Poll::Pending => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    /// Whether the operation has completed.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Start [`Charger::charger_status`] as a poll-driven operation.
+    pub fn start_charger_status(
+        &mut self,
+    ) -> NbOperation<impl Future<Output = Result<ChargerStatus, Error<D::Error>>> + '_> {
+        NbOperation::new(self.charger_status())
+    }
+
+    /// Start [`Charger::charger_details`] as a poll-driven operation.
+    pub fn start_charger_details(
+        &mut self,
+    ) -> NbOperation<impl Future<Output = Result<Details, Error<D::Error>>> + '_> {
+        NbOperation::new(self.charger_details())
+    }
+
+    /// Start [`Charger::set_mode`] as a poll-driven operation.
+    pub fn start_set_mode(
+        &mut self,
+        mode: Mode,
+    ) -> NbOperation<impl Future<Output = Result<(), Error<D::Error>>> + '_> {
+        NbOperation::new(self.set_mode(mode))
+    }
+
+    /// Start [`Charger::set_chgin_ilim`] as a poll-driven operation.
+    pub fn start_set_chgin_ilim(
+        &mut self,
+        milliamps: u16,
+    ) -> NbOperation<impl Future<Output = Result<(), Error<D::Error>>> + '_> {
+        NbOperation::new(self.set_chgin_ilim(milliamps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::poll_fn;
+
+    use embedded_hal_async::i2c::{ErrorKind, ErrorType, Operation};
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+
+    use super::*;
+    use crate::testing::{read, read_burst, write};
+    use crate::{ChargerDetails, ChgIn, Reg};
+
+    /// A mock bus on which every operation is pending for `stalls` polls before it completes
+    struct Stalling {
+        mock: Mock,
+        stalls: usize,
+    }
+
+    impl Stalling {
+        async fn stall(&self) {
+            let mut remaining = self.stalls;
+            poll_fn(|_| {
+                if remaining == 0 {
+                    Poll::Ready(())
+                } else {
+                    remaining = remaining.saturating_sub(1);
+                    Poll::Pending
+                }
+            })
+            .await
+        }
+    }
+
+    impl ErrorType for Stalling {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for Stalling {
+        async fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), ErrorKind> {
+            self.stall().await;
+            self.mock.transaction(address, operations).await
+        }
+
+        async fn write_read(
+            &mut self,
+            address: u8,
+            write: &[u8],
+            read: &mut [u8],
+        ) -> Result<(), ErrorKind> {
+            self.stall().await;
+            self.mock.write_read(address, write, read).await
+        }
+
+        async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), ErrorKind> {
+            self.stall().await;
+            self.mock.write(address, write).await
+        }
+    }
+
+    fn charger(expectations: &[Transaction], stalls: usize) -> Charger<Stalling> {
+        Charger::new(Stalling {
+            mock: Mock::new(expectations),
+            stalls,
+        })
+    }
+
+    /// Poll `op` until it completes, returning the result and the number of `WouldBlock`s
+    fn poll_to_completion<F, T, E>(op: Pin<&mut NbOperation<F>>) -> (Result<T, E>, usize)
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let mut op = op;
+        for blocked in 0..100 {
+            match op.as_mut().poll() {
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(err)) => return (Err(err), blocked),
+                Ok(val) => return (Ok(val), blocked),
+            }
+        }
+        panic!("operation did not complete");
+    }
+
+    #[test]
+    fn charger_details_blocks_while_bus_is_busy() {
+        let mut charger = charger(
+            &[read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x01, 0x00])],
+            3,
+        );
+        {
+            let mut op = core::pin::pin!(charger.start_charger_details());
+            assert!(!op.is_done());
+            let (res, blocked) = poll_to_completion(op.as_mut());
+            assert_eq!(blocked, 3);
+            let details = res.unwrap();
+            assert_eq!(details.chgin(), ChgIn::Valid);
+            assert_eq!(details.charger(), ChargerDetails::ConstantCurrent);
+
+            // Completed operations keep returning WouldBlock
+            assert!(op.is_done());
+            assert!(matches!(op.as_mut().poll(), Err(nb::Error::WouldBlock)));
+        }
+        charger.release().mock.done();
+    }
+
+    #[test]
+    fn set_mode_blocks_on_each_transfer() {
+        let mut charger = charger(
+            &[
+                read(Reg::CHARGER_CONFIG_0, 0x10),
+                write(Reg::CHARGER_CONFIG_0, 0x15),
+            ],
+            2,
+        );
+        let (res, blocked) =
+            poll_to_completion(core::pin::pin!(charger.start_set_mode(Mode::Charge)));
+        assert_eq!(res, Ok(()));
+        assert_eq!(blocked, 4);
+        charger.release().mock.done();
+    }
+
+    #[test]
+    fn set_chgin_ilim_completes_without_stalls() {
+        let mut charger = charger(
+            &[
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                write(Reg::CHARGER_CONFIG_9, 0x09),
+            ],
+            0,
+        );
+        let (res, blocked) = poll_to_completion(core::pin::pin!(charger.start_set_chgin_ilim(500)));
+        assert_eq!(res, Ok(()));
+        assert_eq!(blocked, 0);
+        charger.release().mock.done();
+    }
+
+    #[test]
+    fn charger_status_reports_bus_errors() {
+        let mut charger = charger(
+            &[Transaction::write_read(
+                crate::DEFAULT_ADDRESS,
+                std::vec![Reg::CHARGER_INTERRUPT.address()],
+                std::vec![0; 3],
+            )
+            .with_error(ErrorKind::Other)],
+            1,
+        );
+        let (res, blocked) = poll_to_completion(core::pin::pin!(charger.start_charger_status()));
+        assert_eq!(res, Err(Error::I2c(ErrorKind::Other)));
+        assert_eq!(blocked, 1);
+        charger.release().mock.done();
+    }
+}
@@ -0,0 +1,580 @@
+//! Battery charge profiles.
+
+use embedded_hal_async::i2c::I2c;
+use modular_bitfield::BitfieldSpecifier;
+
+use crate::{conv, Charger, Error, ProtectedSession, Reg, Variant};
+
+/// The lowest programmable top-off current
+const MIN_TOP_OFF_MA: u16 = 100;
+/// The highest programmable top-off current
+const MAX_TOP_OFF_MA: u16 = MIN_TOP_OFF_MA + 50 * 7;
+/// The longest programmable top-off time
+const MAX_TOP_OFF_MIN: u8 = 70;
+
+/// The fast-charge safety timer (FCHGTIME)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[bits = 3]
+pub enum FastChargeTimer {
+    /// The timer is disabled
+    Disabled,
+    /// 3 hours
+    Hours3,
+    /// 4 hours
+    Hours4,
+    /// 5 hours
+    Hours5,
+    /// 6 hours
+    Hours6,
+    /// 7 hours
+    Hours7,
+    /// 8 hours
+    Hours8,
+    /// 10 hours
+    Hours10,
+}
+
+/// How far the battery must drop below the termination voltage to start a new charge cycle
+/// after [`ChargerDetails::Done`](crate::ChargerDetails::Done) (CHG_RSTRT)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[bits = 2]
+pub enum RechargeThreshold {
+    /// 100 mV below the termination voltage
+    Mv100,
+    /// 150 mV below the termination voltage
+    Mv150,
+    /// 200 mV below the termination voltage
+    Mv200,
+    /// Never restart automatically
+    Disabled,
+}
+
+/// Create a [`ChargeProfile`] that is checked at compile time.
+///
+/// Takes the arguments of [`ChargeProfile::checked`]: the [`Variant`](crate::Variant), the
+/// fast-charge current and termination voltage, and the top-off current and time. The profile
+/// is built in a `const` block, so an invalid profile fails to compile with the
+/// [`ProfileError`] message, and a valid one costs nothing at run time:
+///
+/// ```
+/// use max7797x_driver::{charge_profile, ChargeProfile, FastChargeTimer, Variant};
+///
+/// static PROFILES: [ChargeProfile; 2] = [
+///     charge_profile!(Variant::Max77975, 1500, 4200, 150, 30),
+///     charge_profile!(Variant::Max77975, 3000, 4350, 300, 20)
+///         .with_fast_charge_timer(FastChargeTimer::Hours3),
+/// ];
+/// # assert_eq!(PROFILES[1].termination_mv(), 4350);
+/// ```
+///
+/// ```compile_fail
+/// use max7797x_driver::{charge_profile, ChargeProfile, Variant};
+///
+/// // The MAX77975 charges at up to 3500 mA
+/// static PACK: ChargeProfile = charge_profile!(Variant::Max77975, 6500, 4200, 150, 30);
+/// ```
+#[macro_export]
+macro_rules! charge_profile {
+    ($variant:expr, $current_ma:expr, $termination_mv:expr, $top_off_ma:expr, $top_off_min:expr $(,)?) => {
+        const {
+            match $crate::ChargeProfile::checked(
+                $variant,
+                $current_ma,
+                $termination_mv,
+                $top_off_ma,
+                $top_off_min,
+            ) {
+                Ok(profile) => profile,
+                Err(err) => panic!("{}", err.message()),
+            }
+        }
+    };
+}
+
+/// Why a [`ChargeProfile`] is invalid, see [`ChargeProfile::validate`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum ProfileError {
+    /// The termination voltage is outside 3400 mV to 4670 mV
+    TerminationVoltage,
+    /// The fast-charge current exceeds the maximum of the part
+    FastChargeCurrent,
+    /// The top-off current is outside 100 mA to 450 mA or exceeds the fast-charge current
+    TopOffCurrent,
+    /// The top-off time exceeds 70 minutes
+    TopOffTime,
+}
+
+impl ProfileError {
+    /// A description of the error
+    pub const fn message(self) -> &'static str {
+        match self {
+            ProfileError::TerminationVoltage => "termination voltage outside 3400 mV to 4670 mV",
+            ProfileError::FastChargeCurrent => "fast-charge current above the maximum of the part",
+            ProfileError::TopOffCurrent => {
+                "top-off current outside 100 mA to 450 mA or above the fast-charge current"
+            }
+            ProfileError::TopOffTime => "top-off time above 70 minutes",
+        }
+    }
+}
+
+/// The charge settings for one battery
+///
+/// The constructors are `const` and validate the settings against each other and the
+/// programmable ranges, so profiles can be kept in constant tables:
+///
+/// ```
+/// # use max7797x_driver::ChargeProfile;
+/// const PACK_A: ChargeProfile = match ChargeProfile::new(1500, 4200) {
+///     Some(profile) => profile,
+///     None => panic!("invalid charge profile"),
+/// };
+/// ```
+///
+/// The fast-charge current is checked against the part when the profile is applied with
+/// [`Charger::apply_charge_profile`]. To check a profile against the part at compile time,
+/// build it with [`charge_profile!`](crate::charge_profile).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChargeProfile {
+    fast_charge_current_ma: u16,
+    termination_mv: u16,
+    top_off_current_ma: u16,
+    top_off_time_min: u8,
+    recharge: RechargeThreshold,
+    fast_charge_timer: FastChargeTimer,
+}
+
+impl ChargeProfile {
+    /// Create a profile charging at `fast_charge_current_ma` up to `termination_mv`.
+    ///
+    /// The termination voltage is rounded down to a 10 mV step and must be between 3400 mV and
+    /// 4670 mV. The other settings default to a 150 mA top-off current for 30 minutes, a 150 mV
+    /// recharge threshold and a 5 hour fast-charge timer. Returns `None` if a setting is out of
+    /// range or the top-off current exceeds the fast-charge current.
+    pub const fn new(fast_charge_current_ma: u16, termination_mv: u16) -> Option<Self> {
+        let termination_mv = match conv::chg_cv_prm_code(termination_mv) {
+            Some(code) => conv::chg_cv_prm_mv(code),
+            None => return None,
+        };
+        ChargeProfile {
+            fast_charge_current_ma,
+            termination_mv,
+            top_off_current_ma: 150,
+            top_off_time_min: 30,
+            recharge: RechargeThreshold::Mv150,
+            fast_charge_timer: FastChargeTimer::Hours5,
+        }
+        .validated()
+    }
+
+    /// Set the top-off current threshold and time.
+    ///
+    /// The current is rounded down to a 50 mA step between 100 mA and 450 mA and the time to a
+    /// 10 minute step up to 70 minutes. Returns `None` if a setting is out of range or the
+    /// current exceeds the fast-charge current.
+    pub const fn with_top_off(self, current_ma: u16, time_min: u8) -> Option<Self> {
+        if current_ma < MIN_TOP_OFF_MA || current_ma > MAX_TOP_OFF_MA || time_min > MAX_TOP_OFF_MIN
+        {
+            return None;
+        }
+        ChargeProfile {
+            top_off_current_ma: current_ma
+                .saturating_sub(current_ma.saturating_sub(MIN_TOP_OFF_MA) % 50),
+            top_off_time_min: time_min.saturating_sub(time_min % 10),
+            ..self
+        }
+        .validated()
+    }
+
+    /// Create a profile for `variant` with the given top-off settings, checking it with
+    /// [`validate`](Self::validate).
+    ///
+    /// The settings are rounded like [`new`](Self::new) and [`with_top_off`](Self::with_top_off).
+    /// Unlike those, the fast-charge current is also checked against the part and the error
+    /// says which setting is invalid. Use [`charge_profile!`](crate::charge_profile) to check a
+    /// profile at compile time.
+    pub const fn checked(
+        variant: Variant,
+        fast_charge_current_ma: u16,
+        termination_mv: u16,
+        top_off_current_ma: u16,
+        top_off_time_min: u8,
+    ) -> Result<Self, ProfileError> {
+        let termination_mv = match conv::chg_cv_prm_code(termination_mv) {
+            Some(code) => conv::chg_cv_prm_mv(code),
+            None => termination_mv,
+        };
+        let top_off_current_ma = if top_off_current_ma >= MIN_TOP_OFF_MA {
+            top_off_current_ma
+                .saturating_sub(top_off_current_ma.saturating_sub(MIN_TOP_OFF_MA) % 50)
+        } else {
+            top_off_current_ma
+        };
+        let profile = ChargeProfile {
+            fast_charge_current_ma,
+            termination_mv,
+            top_off_current_ma,
+            top_off_time_min: top_off_time_min.saturating_sub(top_off_time_min % 10),
+            recharge: RechargeThreshold::Mv150,
+            fast_charge_timer: FastChargeTimer::Hours5,
+        };
+        match profile.validate(variant) {
+            Ok(()) => Ok(profile),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Check the settings against each other, the programmable ranges and `variant`.
+    ///
+    /// Profiles built with the constructors always pass except for the fast-charge current,
+    /// which depends on the part; deserialized profiles may not. This is the check done by
+    /// [`Charger::apply_charge_profile`].
+    pub const fn validate(&self, variant: Variant) -> Result<(), ProfileError> {
+        if conv::chg_cv_prm_code(self.termination_mv).is_none() {
+            return Err(ProfileError::TerminationVoltage);
+        }
+        if conv::chg_cc_code(self.fast_charge_current_ma, variant).is_none() {
+            return Err(ProfileError::FastChargeCurrent);
+        }
+        if self.top_off_current_ma < MIN_TOP_OFF_MA
+            || self.top_off_current_ma > MAX_TOP_OFF_MA
+            || self.top_off_current_ma > self.fast_charge_current_ma
+        {
+            return Err(ProfileError::TopOffCurrent);
+        }
+        if self.top_off_time_min > MAX_TOP_OFF_MIN {
+            return Err(ProfileError::TopOffTime);
+        }
+        Ok(())
+    }
+
+    /// Set the recharge threshold.
+    pub const fn with_recharge(self, recharge: RechargeThreshold) -> Self {
+        ChargeProfile { recharge, ..self }
+    }
+
+    /// Set the fast-charge safety timer.
+    pub const fn with_fast_charge_timer(self, fast_charge_timer: FastChargeTimer) -> Self {
+        ChargeProfile {
+            fast_charge_timer,
+            ..self
+        }
+    }
+
+    const fn validated(self) -> Option<Self> {
+        if self.top_off_current_ma > self.fast_charge_current_ma {
+            None
+        } else {
+            Some(self)
+        }
+    }
+
+    /// The fast-charge current in mA
+    pub const fn fast_charge_current_ma(&self) -> u16 {
+        self.fast_charge_current_ma
+    }
+
+    /// The termination voltage in mV
+    pub const fn termination_mv(&self) -> u16 {
+        self.termination_mv
+    }
+
+    /// The top-off current threshold in mA
+    pub const fn top_off_current_ma(&self) -> u16 {
+        self.top_off_current_ma
+    }
+
+    /// The top-off time in minutes
+    pub const fn top_off_time_min(&self) -> u8 {
+        self.top_off_time_min
+    }
+
+    /// The recharge threshold
+    pub const fn recharge(&self) -> RechargeThreshold {
+        self.recharge
+    }
+
+    /// The fast-charge safety timer
+    pub const fn fast_charge_timer(&self) -> FastChargeTimer {
+        self.fast_charge_timer
+    }
+
+    /// The register fields of the profile on `variant`, see [`ProfileImage`].
+    ///
+    /// Returns `None` if the profile fails [`validate`](Self::validate) on `variant`.
+    pub const fn image(&self, variant: Variant) -> Option<ProfileImage> {
+        if self.validate(variant).is_err() {
+            return None;
+        }
+        let Some(chg_cc) = conv::chg_cc_code(self.fast_charge_current_ma, variant) else {
+            return None;
+        };
+        let Some(chg_cv_prm) = conv::chg_cv_prm_code(self.termination_mv) else {
+            return None;
+        };
+        let to_ith = (self.top_off_current_ma.saturating_sub(MIN_TOP_OFF_MA) / 50) as u8;
+        let to_time = self.top_off_time_min / 10;
+        Some(ProfileImage {
+            variant,
+            values: [
+                self.fast_charge_timer as u8 | (self.recharge as u8) << 4,
+                chg_cc,
+                to_ith | to_time << 3,
+                chg_cv_prm,
+            ],
+        })
+    }
+}
+
+/// The fields of `CHARGER_CONFIG_1` through `CHARGER_CONFIG_4` set by a [`ChargeProfile`]
+const PROFILE_MASKS: [u8; 4] = [0x37, 0x7f, 0x3f, 0x7f];
+
+/// The register fields of a [`ChargeProfile`] for one part, precomputed by
+/// [`ChargeProfile::image`]
+///
+/// The image holds the values of the FCHGTIME, CHG_RSTRT, CHG_CC, TO_ITH, TO_TIME and
+/// CHG_CV_PRM fields of `CHARGER_CONFIG_1` through `CHARGER_CONFIG_4`. The other bits are
+/// kept as read when the image is applied with [`Charger::apply_profile_image`]. Images can be
+/// computed in constant tables, so nothing is converted at run time:
+///
+/// ```
+/// use max7797x_driver::{ChargeProfile, FastChargeTimer, ProfileImage, Variant};
+///
+/// const fn image(current_ma: u16, termination_mv: u16) -> ProfileImage {
+///     match ChargeProfile::new(current_ma, termination_mv) {
+///         Some(profile) => profile
+///             .with_fast_charge_timer(FastChargeTimer::Hours8)
+///             .image(Variant::Max77975)
+///             .expect("current too high for the part"),
+///         None => panic!("invalid charge profile"),
+///     }
+/// }
+///
+/// static PROFILES: [ProfileImage; 4] =
+///     [image(500, 4200), image(1000, 4200), image(1500, 4350), image(2000, 4400)];
+///
+/// assert_eq!(PROFILES[1].values(), [0x16, 0x14, 0x19, 0x50]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct ProfileImage {
+    variant: Variant,
+    values: [u8; 4],
+}
+
+impl ProfileImage {
+    /// The part the image was computed for
+    pub const fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// The values of the profile fields in `CHARGER_CONFIG_1` through `CHARGER_CONFIG_4`, with
+    /// the other bits cleared
+    pub const fn values(&self) -> [u8; 4] {
+        self.values
+    }
+
+    /// The bits of `CHARGER_CONFIG_1` through `CHARGER_CONFIG_4` set by the image
+    pub const fn masks(&self) -> [u8; 4] {
+        PROFILE_MASKS
+    }
+
+    /// Apply the image to the current values of `CHARGER_CONFIG_1` through `CHARGER_CONFIG_4`.
+    const fn update(&self, regs: &mut [u8; 4]) {
+        let [r1, r2, r3, r4] = *regs;
+        let [m1, m2, m3, m4] = PROFILE_MASKS;
+        let [v1, v2, v3, v4] = self.values;
+        *regs = [r1 & !m1 | v1, r2 & !m2 | v2, r3 & !m3 | v3, r4 & !m4 | v4];
+    }
+}
+
+impl<D: I2c> ProtectedSession<'_, D> {
+    /// Apply a [`ChargeProfile`].
+    ///
+    /// See [`Charger::apply_charge_profile`].
+    pub async fn apply_charge_profile(
+        &mut self,
+        profile: &ChargeProfile,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self.charger, "apply_charge_profile", {
+            profile
+                .validate(self.charger.variant)
+                .map_err(Error::InvalidProfile)?;
+            self.charger.enforce_profile_policy(profile).await?;
+            let image = profile
+                .image(self.charger.variant)
+                .ok_or(Error::InvalidValue)?;
+            self.apply_profile_image(&image).await
+        })
+    }
+
+    /// Apply a [`ProfileImage`].
+    ///
+    /// See [`Charger::apply_profile_image`].
+    pub async fn apply_profile_image(
+        &mut self,
+        image: &ProfileImage,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self.charger, "apply_profile_image", {
+            if image.variant != self.charger.variant {
+                return Err(Error::InvalidValue);
+            }
+            let mut regs = [0; 4];
+            self.charger
+                .read_buf(Reg::CHARGER_CONFIG_1, &mut regs)
+                .await?;
+            image.update(&mut regs);
+            self.charger.write_buf(Reg::CHARGER_CONFIG_1, &regs).await
+        })
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Apply a [`ChargeProfile`].
+    ///
+    /// `CHARGER_CONFIG_1` through `CHARGER_CONFIG_4` are read and written back in one burst each
+    /// within a single [`ProtectedSession`]. Fails with [`Error::InvalidProfile`] if the profile
+    /// fails [`ChargeProfile::validate`] on this part, e.g. if the fast-charge current exceeds
+    /// its maximum, and with [`Error::PolicyViolation`] if it breaks the
+    /// [safety policy](Self::with_safety_policy).
+    pub async fn apply_charge_profile(
+        &mut self,
+        profile: &ChargeProfile,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "apply_charge_profile", {
+            self.protected(async |session| session.apply_charge_profile(profile).await)
+                .await
+        })
+    }
+
+    /// Apply a [`ProfileImage`] computed for this part.
+    ///
+    /// Like [`apply_charge_profile`](Self::apply_charge_profile), without converting the
+    /// settings. Fails with [`Error::InvalidValue`] if the image was computed for another
+    /// [`Variant`].
+    pub async fn apply_profile_image(
+        &mut self,
+        image: &ProfileImage,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "apply_profile_image", {
+            self.protected(async |session| session.apply_profile_image(image).await)
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use super::*;
+    use crate::testing::{lock, read_burst, run, run_variant, unlock, write_burst};
+    use crate::{ChargerConfig, ChargerInterrupts, ChargerIrqSource, Mode};
+
+    /// A per-SKU table, validated when it is compiled
+    static PROFILES: [ChargeProfile; 4] = [
+        charge_profile!(Variant::Max77975, 500, 4200, 100, 10),
+        charge_profile!(Variant::Max77975, 1000, 4200, 150, 30),
+        charge_profile!(Variant::Max77975, 2000, 4350, 200, 30)
+            .with_fast_charge_timer(FastChargeTimer::Hours3),
+        charge_profile!(Variant::Max77975, 3500, 4400, 300, 60)
+            .with_recharge(RechargeThreshold::Mv200),
+    ];
+
+    /// The register images of [`PROFILES`], converted when they are compiled
+    static IMAGES: [ProfileImage; 4] = {
+        let mut images = [PROFILES[0].image(Variant::Max77975).unwrap(); 4];
+        let mut i = 1;
+        while i < images.len() {
+            images[i] = PROFILES[i].image(Variant::Max77975).unwrap();
+            i += 1;
+        }
+        images
+    };
+
+    static CONFIG: ChargerConfig = ChargerConfig {
+        chgin_ilim_ma: 1500,
+        fast_charge_current_ma: 1000,
+        sys_ilim_ma: 3000,
+        sys_ilim_recycle: false,
+        charger_irqs: ChargerInterrupts::from_sources(&[ChargerIrqSource::Chgin]),
+        mode: Mode::Charge,
+    };
+
+    /// Register codes converted at compile time
+    const CODES: [Option<u8>; 4] = [
+        Some(conv::chgin_ilim_code(1500)),
+        conv::chg_cc_code(1000, Variant::Max77975),
+        conv::chg_cv_prm_code(4200),
+        conv::vbypset_code(5000),
+    ];
+
+    const _: () = assert!(PROFILES[3].termination_mv() == 4400);
+    const _: () = assert!(IMAGES[1].values()[1] == 0x14);
+
+    #[test]
+    fn const_profiles_hold_the_requested_settings() {
+        assert_eq!(PROFILES[2].fast_charge_current_ma(), 2000);
+        assert_eq!(PROFILES[2].fast_charge_timer(), FastChargeTimer::Hours3);
+        assert_eq!(
+            (
+                PROFILES[0].top_off_current_ma(),
+                PROFILES[0].top_off_time_min()
+            ),
+            (100, 10)
+        );
+        assert_eq!(PROFILES[3].recharge(), RechargeThreshold::Mv200);
+        assert_eq!(CONFIG.charger_irqs.bits(), 0x40);
+        assert_eq!(CODES, [Some(0x1d), Some(0x14), Some(0x50), Some(0x64)]);
+    }
+
+    #[test]
+    fn const_images_match_the_profiles() {
+        let values = IMAGES.map(|image| image.values());
+        assert_eq!(
+            values,
+            [
+                [0x13, 0x0a, 0x08, 0x50],
+                [0x13, 0x14, 0x19, 0x50],
+                [0x11, 0x28, 0x1a, 0x5f],
+                [0x23, 0x46, 0x34, 0x64],
+            ]
+        );
+        for (profile, image) in PROFILES.iter().zip(&IMAGES) {
+            assert_eq!(profile.image(Variant::Max77975).as_ref(), Some(image));
+            assert_eq!(image.variant(), Variant::Max77975);
+        }
+    }
+
+    #[test]
+    fn image_keeps_the_other_bits() {
+        let mut expectations = vec![
+            unlock(),
+            read_burst(Reg::CHARGER_CONFIG_1, &[0xc8, 0x80, 0xc0, 0x80]),
+        ];
+        expectations.extend(write_burst(
+            Reg::CHARGER_CONFIG_1,
+            &[0xd9, 0xa8, 0xda, 0xdf],
+        ));
+        expectations.push(lock());
+        let res = run(&expectations, async |charger| {
+            charger.apply_profile_image(&IMAGES[2]).await
+        });
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn image_for_another_part_is_rejected() {
+        let res = run_variant(Variant::Max77986, &[unlock(), lock()], async |charger| {
+            charger.apply_profile_image(&IMAGES[0]).await
+        });
+        assert_eq!(res, Err(Error::InvalidValue));
+    }
+}
@@ -0,0 +1,368 @@
+//! Coarse charge progress for user interfaces.
+//!
+//! A [`ProgressTracker`] owned by the application is fed by [`Charger::charge_progress`] on each
+//! poll and records when the charger moved between [`ChargePhase`]s:
+//!
+//! ```no_run
+//! # use embedded_hal_async::delay::DelayNs;
+//! # use embedded_hal_async::i2c::I2c;
+//! # use max7797x_driver::{ChargePhase, Charger, Error, ProgressTracker};
+//! # async fn example<D: I2c>(
+//! #     charger: &mut Charger<D>,
+//! #     delay: &mut impl DelayNs,
+//! #     now_ms: impl Fn() -> u64,
+//! #     show: impl Fn(ChargePhase, u64),
+//! # ) -> Result<(), Error<D::Error>> {
+//! let mut tracker = ProgressTracker::<8>::new();
+//! loop {
+//!     let phase = charger.charge_progress(&mut tracker, now_ms()).await?;
+//!     show(phase, tracker.time_in_phase());
+//!     delay.delay_ms(1000).await;
+//! }
+//! # }
+//! ```
+//!
+//! Timestamps are supplied by the caller and only need to be monotonic, so polls may be skipped
+//! or irregular: a transition is stamped with the first poll that observed it. Near the CC/CV
+//! threshold the charger may alternate between the two, so a move between
+//! [`ConstantCurrent`](ChargePhase::ConstantCurrent) and
+//! [`ConstantVoltage`](ChargePhase::ConstantVoltage) is only recorded once it has lasted
+//! [`ProgressTracker::CC_CV_DEBOUNCE_MS`].
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{ChargeStateClass, Charger, ChargerDetails, Error, FaultKind, SuspendReason};
+
+/// The phase of a charge cycle, see [`ProgressTracker`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum ChargePhase {
+    /// The charger is off or the input is invalid
+    Idle,
+    /// Dead-battery or low-battery prequalification
+    Prequalification,
+    /// Fast-charge constant current
+    ConstantCurrent,
+    /// Fast-charge constant voltage
+    ConstantVoltage,
+    /// Top-off
+    TopOff,
+    /// The charge cycle finished
+    Done,
+    /// Charging is suspended until the condition clears
+    Suspended(SuspendReason),
+    /// Charging stopped because of a fault
+    Fault(FaultKind),
+}
+
+impl From<ChargerDetails> for ChargePhase {
+    fn from(details: ChargerDetails) -> Self {
+        match details {
+            ChargerDetails::Prequalification => ChargePhase::Prequalification,
+            ChargerDetails::ConstantCurrent => ChargePhase::ConstantCurrent,
+            ChargerDetails::ConstantVoltage => ChargePhase::ConstantVoltage,
+            ChargerDetails::TopOff => ChargePhase::TopOff,
+            _ => match details.classify() {
+                ChargeStateClass::Done => ChargePhase::Done,
+                ChargeStateClass::Suspended(reason) => ChargePhase::Suspended(reason),
+                ChargeStateClass::Fault(kind) => ChargePhase::Fault(kind),
+                ChargeStateClass::Charging | ChargeStateClass::Idle => ChargePhase::Idle,
+            },
+        }
+    }
+}
+
+/// A phase change recorded by [`ProgressTracker`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct PhaseTransition {
+    /// The phase entered
+    pub phase: ChargePhase,
+    /// The timestamp of the first poll that observed the phase in ms
+    pub at_ms: u64,
+}
+
+/// The charge phases seen by [`Charger::charge_progress`], keeping the last `N` transitions
+#[derive(Debug, Clone)]
+pub struct ProgressTracker<const N: usize = 8> {
+    history: [PhaseTransition; N],
+    len: usize,
+    /// The timestamp of the last poll
+    last_ms: Option<u64>,
+    /// The other side of a CC/CV move and when it was first seen, until it is debounced
+    pending: Option<PhaseTransition>,
+}
+
+impl<const N: usize> Default for ProgressTracker<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ProgressTracker<N> {
+    /// How long a move between constant current and constant voltage must last to be recorded
+    /// in ms
+    pub const CC_CV_DEBOUNCE_MS: u64 = 5_000;
+
+    /// A tracker that has not seen a poll yet.
+    pub const fn new() -> Self {
+        ProgressTracker {
+            history: [PhaseTransition {
+                phase: ChargePhase::Idle,
+                at_ms: 0,
+            }; N],
+            len: 0,
+            last_ms: None,
+            pending: None,
+        }
+    }
+
+    /// The current phase, or `None` before the first poll
+    pub fn current_phase(&self) -> Option<ChargePhase> {
+        self.phase_history()
+            .last()
+            .map(|transition| transition.phase)
+    }
+
+    /// The time between entering the current phase and the last poll in ms
+    pub fn time_in_phase(&self) -> u64 {
+        match (self.phase_history().last(), self.last_ms) {
+            (Some(entered), Some(last_ms)) => last_ms.saturating_sub(entered.at_ms),
+            _ => 0,
+        }
+    }
+
+    /// The recorded transitions, oldest first, ending with the current phase
+    ///
+    /// The first entry is the phase seen by the first poll. Once `N` transitions are recorded,
+    /// the oldest is dropped for each new one.
+    pub fn phase_history(&self) -> &[PhaseTransition] {
+        &self.history[..self.len]
+    }
+
+    /// Forget the history, as if no poll had been seen.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Feed the phase observed at `now_ms` and return the current phase.
+    pub fn update(&mut self, observed: ChargePhase, now_ms: u64) -> ChargePhase {
+        let now_ms = self.last_ms.map_or(now_ms, |last_ms| now_ms.max(last_ms));
+        self.last_ms = Some(now_ms);
+        let Some(current) = self.current_phase() else {
+            self.push(observed, now_ms);
+            return observed;
+        };
+        if observed == current {
+            self.pending = None;
+        } else if is_cc_cv_move(current, observed) {
+            let pending = match self.pending {
+                Some(pending) if pending.phase == observed => pending,
+                _ => PhaseTransition {
+                    phase: observed,
+                    at_ms: now_ms,
+                },
+            };
+            if now_ms.saturating_sub(pending.at_ms) >= Self::CC_CV_DEBOUNCE_MS {
+                self.pending = None;
+                self.push(observed, pending.at_ms);
+            } else {
+                self.pending = Some(pending);
+            }
+        } else {
+            self.pending = None;
+            self.push(observed, now_ms);
+        }
+        self.current_phase().unwrap_or(observed)
+    }
+
+    fn push(&mut self, phase: ChargePhase, at_ms: u64) {
+        if N == 0 {
+            return;
+        }
+        if self.len == N {
+            self.history.copy_within(1.., 0);
+            self.len = self.len.saturating_sub(1);
+        }
+        if let Some(entry) = self.history.get_mut(self.len) {
+            *entry = PhaseTransition { phase, at_ms };
+            self.len = self.len.saturating_add(1);
+        }
+    }
+}
+
+fn is_cc_cv_move(from: ChargePhase, to: ChargePhase) -> bool {
+    matches!(
+        (from, to),
+        (ChargePhase::ConstantCurrent, ChargePhase::ConstantVoltage)
+            | (ChargePhase::ConstantVoltage, ChargePhase::ConstantCurrent)
+    )
+}
+
+impl<D: I2c> Charger<D> {
+    /// Sample the charger state into `tracker`, stamped with `now_ms`, and return the current
+    /// phase.
+    ///
+    /// Only `CHARGER_DETAILS_1` is read, see [`charger_state`](Self::charger_state). A
+    /// timestamp earlier than the previous one is treated as equal to it.
+    pub async fn charge_progress<const N: usize>(
+        &mut self,
+        tracker: &mut ProgressTracker<N>,
+        now_ms: u64,
+    ) -> Result<ChargePhase, Error<D::Error>> {
+        traced!(self, "charge_progress", {
+            let state = self.charger_state().await?;
+            Ok(tracker.update(state.into(), now_ms))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read, run};
+    use crate::Reg;
+
+    fn at(phase: ChargePhase, at_ms: u64) -> PhaseTransition {
+        PhaseTransition { phase, at_ms }
+    }
+
+    #[test]
+    fn time_running_backwards_does_not_record_a_move() {
+        let mut tracker = ProgressTracker::<4>::new();
+        tracker.update(ChargePhase::ConstantCurrent, 10_000);
+        tracker.update(ChargePhase::ConstantVoltage, 20_000);
+        let phase = tracker.update(ChargePhase::ConstantVoltage, 0);
+        assert_eq!(phase, ChargePhase::ConstantCurrent);
+        assert_eq!(tracker.phase_history().len(), 1);
+    }
+
+    #[test]
+    fn zero_capacity_records_nothing() {
+        let mut tracker = ProgressTracker::<0>::new();
+        assert_eq!(
+            tracker.update(ChargePhase::Done, u64::MAX),
+            ChargePhase::Done
+        );
+        assert_eq!(tracker.current_phase(), None);
+        assert_eq!(tracker.time_in_phase(), 0);
+    }
+
+    #[test]
+    fn cc_cv_done_cycle_is_recorded() {
+        let mut tracker = ProgressTracker::<8>::new();
+        assert_eq!(tracker.current_phase(), None);
+        for (now_ms, observed, current, time_in_phase) in [
+            (
+                0,
+                ChargePhase::ConstantCurrent,
+                ChargePhase::ConstantCurrent,
+                0,
+            ),
+            (
+                60_000,
+                ChargePhase::ConstantCurrent,
+                ChargePhase::ConstantCurrent,
+                60_000,
+            ),
+            // The move to CV is recorded once it lasted the debounce time, stamped when first
+            // seen
+            (
+                61_000,
+                ChargePhase::ConstantVoltage,
+                ChargePhase::ConstantCurrent,
+                61_000,
+            ),
+            (
+                66_000,
+                ChargePhase::ConstantVoltage,
+                ChargePhase::ConstantVoltage,
+                5_000,
+            ),
+            (
+                90_000,
+                ChargePhase::ConstantVoltage,
+                ChargePhase::ConstantVoltage,
+                29_000,
+            ),
+            // Other moves are recorded at once
+            (91_000, ChargePhase::Done, ChargePhase::Done, 0),
+            (95_000, ChargePhase::Done, ChargePhase::Done, 4_000),
+        ] {
+            assert_eq!(tracker.update(observed, now_ms), current, "{now_ms}");
+            assert_eq!(tracker.time_in_phase(), time_in_phase, "{now_ms}");
+        }
+        assert_eq!(
+            tracker.phase_history(),
+            [
+                at(ChargePhase::ConstantCurrent, 0),
+                at(ChargePhase::ConstantVoltage, 61_000),
+                at(ChargePhase::Done, 91_000),
+            ]
+        );
+
+        tracker.reset();
+        assert_eq!(tracker.current_phase(), None);
+        assert_eq!(tracker.time_in_phase(), 0);
+    }
+
+    #[test]
+    fn cc_cv_chatter_is_debounced() {
+        let mut tracker = ProgressTracker::<8>::new();
+        tracker.update(ChargePhase::ConstantCurrent, 0);
+        // Each return to CC restarts the debounce
+        for now_ms in (1_000..20_000).step_by(2_000) {
+            tracker.update(ChargePhase::ConstantVoltage, now_ms);
+            tracker.update(ChargePhase::ConstantCurrent, now_ms + 1_000);
+        }
+        assert_eq!(
+            tracker.phase_history(),
+            [at(ChargePhase::ConstantCurrent, 0)]
+        );
+        assert_eq!(tracker.time_in_phase(), 20_000);
+    }
+
+    #[test]
+    fn full_history_drops_the_oldest_transition() {
+        let mut tracker = ProgressTracker::<2>::new();
+        tracker.update(ChargePhase::Prequalification, 0);
+        tracker.update(ChargePhase::ConstantCurrent, 1_000);
+        tracker.update(ChargePhase::TopOff, 2_000);
+        assert_eq!(
+            tracker.phase_history(),
+            [
+                at(ChargePhase::ConstantCurrent, 1_000),
+                at(ChargePhase::TopOff, 2_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn charge_progress_reads_the_charger_state() {
+        let transitions = run(
+            &[
+                read(Reg::CHARGER_DETAILS_1, ChargerDetails::Off.code()),
+                read(
+                    Reg::CHARGER_DETAILS_1,
+                    ChargerDetails::ConstantCurrent.code(),
+                ),
+                read(Reg::CHARGER_DETAILS_1, ChargerDetails::TimerFault.code()),
+            ],
+            async |charger| {
+                let mut tracker = ProgressTracker::<4>::new();
+                for now_ms in [0, 1_000, 2_000] {
+                    charger.charge_progress(&mut tracker, now_ms).await?;
+                }
+                Ok::<_, Error<embedded_hal_async::i2c::ErrorKind>>(tracker.phase_history().to_vec())
+            },
+        );
+        assert_eq!(
+            transitions,
+            Ok(std::vec![
+                at(ChargePhase::Idle, 0),
+                at(ChargePhase::ConstantCurrent, 1_000),
+                at(ChargePhase::Fault(FaultKind::Timer), 2_000),
+            ])
+        );
+    }
+}
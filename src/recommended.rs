@@ -0,0 +1,223 @@
+//! A conservative baseline configuration for bring-up.
+//!
+//! [`Charger::init`] applies the defaults below for a single-cell Li-ion battery, so a new
+//! board charges safely before it is tuned. Each default is a constant of this module, with
+//! the reason it was chosen:
+//!
+//! ```
+//! use max7797x_driver::{recommended, ChargeProfile, ChargerConfig, FastChargeTimer, Mode, Variant};
+//!
+//! let config = ChargerConfig::recommended(2000, Variant::Max77975);
+//! assert_eq!(config.chgin_ilim_ma, recommended::CHGIN_ILIM_MA);
+//! assert_eq!(config.fast_charge_current_ma, 1000);
+//! assert_eq!(config.sys_ilim_ma, 10_000);
+//! assert_eq!(config.charger_irqs.bits(), 0x58);
+//! assert_eq!(config.mode, Mode::Charge);
+//!
+//! let profile = ChargeProfile::recommended(2000, Variant::Max77975).unwrap();
+//! assert_eq!(profile.termination_mv(), 4200);
+//! assert_eq!(profile.fast_charge_timer(), FastChargeTimer::Hours5);
+//! assert_eq!((profile.top_off_current_ma(), profile.top_off_time_min()), (150, 30));
+//! ```
+//!
+//! The values are part of the API: changing one is a breaking change.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{
+    ChargeProfile, Charger, ChargerConfig, ChargerInterrupts, ChargerIrqSource, Error,
+    FastChargeTimer, Mode, RechargeThreshold, Variant,
+};
+
+/// The CHGIN input current limit in mA.
+///
+/// A USB 2.0 port may supply 500 mA, so this is safe with any source until the adapter has been
+/// identified and the limit raised.
+pub const CHGIN_ILIM_MA: u16 = 500;
+
+/// The fast-charge current as a fraction of the capacity: the current is the capacity in mAh
+/// divided by this, i.e. 0.5 C.
+///
+/// Half the capacity is within the standard charge rate of common Li-ion cells and keeps the
+/// cell and the charger cool.
+pub const CHARGE_RATE_DIVISOR: u16 = 2;
+
+/// The termination voltage in mV.
+///
+/// The standard charge voltage of Li-ion cells with a graphite anode. Cells rated for a higher
+/// voltage still charge safely, only to less than their full capacity.
+pub const TERMINATION_MV: u16 = 4200;
+
+/// The fast-charge safety timer.
+///
+/// A 0.5 C charge takes about 2 to 3 hours, so 5 hours leaves margin for a deeply discharged
+/// cell while stopping a charge that never terminates, e.g. on a damaged cell.
+pub const FAST_CHARGE_TIMER: FastChargeTimer = FastChargeTimer::Hours5;
+
+/// The recharge threshold.
+///
+/// 150 mV below the termination voltage restarts charging after a moderate self-discharge
+/// without cycling the cell on every small load.
+pub const RECHARGE: RechargeThreshold = RechargeThreshold::Mv150;
+
+/// The Vsys current limit in mA.
+///
+/// The system load is not known to the driver, so the highest setting is used to avoid browning
+/// out the system. The current drawn from the input and into the battery is bounded by the
+/// other defaults.
+pub const SYS_ILIM_MA: u16 = 10_000;
+
+/// The charger interrupts to enable.
+///
+/// The input, the charger state and the battery state are what an application has to react to.
+/// The other sources report regulation loops that change often and rarely need attention.
+pub const CHARGER_IRQS: ChargerInterrupts = ChargerInterrupts::from_sources(&[
+    ChargerIrqSource::Chgin,
+    ChargerIrqSource::Charger,
+    ChargerIrqSource::Battery,
+]);
+
+/// Whether the charger watchdog is enabled.
+///
+/// The watchdog suspends charging unless the host kicks it every
+/// [`WATCHDOG_TIMEOUT_MS`](crate::WATCHDOG_TIMEOUT_MS), which a new application may not do
+/// yet. Enable it once the host runs a [`watchdog_task`](Charger::watchdog_task).
+pub const WATCHDOG_ENABLE: bool = false;
+
+/// The charger mode.
+///
+/// Charge the battery whenever a valid input is attached.
+pub const MODE: Mode = Mode::Charge;
+
+/// The recommended fast-charge current for a battery of `battery_capacity_mah` on `variant`,
+/// limited to the maximum of the part.
+const fn fast_charge_current_ma(battery_capacity_mah: u16, variant: Variant) -> u16 {
+    let current = battery_capacity_mah / CHARGE_RATE_DIVISOR;
+    let max = variant.max_fast_charge_current();
+    if current > max {
+        max
+    } else {
+        current
+    }
+}
+
+impl ChargerConfig {
+    /// The recommended configuration for a battery of `battery_capacity_mah` on `variant`, see
+    /// the [`recommended`](crate::recommended) module.
+    ///
+    /// The fast-charge current is limited to the maximum of the part.
+    pub const fn recommended(battery_capacity_mah: u16, variant: Variant) -> Self {
+        ChargerConfig {
+            chgin_ilim_ma: CHGIN_ILIM_MA,
+            fast_charge_current_ma: fast_charge_current_ma(battery_capacity_mah, variant),
+            sys_ilim_ma: SYS_ILIM_MA,
+            sys_ilim_recycle: false,
+            charger_irqs: CHARGER_IRQS,
+            mode: MODE,
+        }
+    }
+}
+
+impl ChargeProfile {
+    /// The recommended profile for a battery of `battery_capacity_mah` on `variant`, see the
+    /// [`recommended`](crate::recommended) module.
+    ///
+    /// The top-off settings are those of [`ChargeProfile::new`]. Returns `None` if the battery
+    /// is too small for the default top-off current, i.e. below 300 mAh.
+    pub const fn recommended(battery_capacity_mah: u16, variant: Variant) -> Option<Self> {
+        match ChargeProfile::new(
+            fast_charge_current_ma(battery_capacity_mah, variant),
+            TERMINATION_MV,
+        ) {
+            Some(profile) => Some(
+                profile
+                    .with_recharge(RECHARGE)
+                    .with_fast_charge_timer(FAST_CHARGE_TIMER),
+            ),
+            None => None,
+        }
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Apply the recommended baseline for a battery of `battery_capacity_mah`, see the
+    /// [`recommended`](crate::recommended) module.
+    ///
+    /// The watchdog is disabled first, then the [`ChargeProfile::recommended`] profile and the
+    /// [`ChargerConfig::recommended`] configuration are applied, so the mode is written last.
+    /// Fails with [`Error::InvalidValue`] if the battery is below 300 mAh.
+    pub async fn init(&mut self, battery_capacity_mah: u16) -> Result<(), Error<D::Error>> {
+        traced!(self, "init", {
+            let profile = ChargeProfile::recommended(battery_capacity_mah, self.variant)
+                .ok_or(Error::InvalidValue)?;
+            let config0 = self.charger_config0().await?;
+            self.set_charger_config0(config0.with_watchdog_enable(WATCHDOG_ENABLE))
+                .await?;
+            self.apply_charge_profile(&profile).await?;
+            self.apply_config(&ChargerConfig::recommended(
+                battery_capacity_mah,
+                self.variant,
+            ))
+            .await
+        })
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use super::*;
+    use crate::sim::Simulator;
+    use crate::testing::{block_on, run};
+    use crate::Reg;
+
+    /// The `CHARGER_CONFIG_0` to `CHARGER_CONFIG_13` image left by `init(battery_capacity_mah)`
+    fn init_image(variant: Variant, battery_capacity_mah: u16) -> ([u8; 14], u8) {
+        let sim = Simulator::new(variant);
+        let mut charger = Charger::new_with_variant(sim.device(), variant);
+        assert_eq!(block_on(charger.init(battery_capacity_mah)), Ok(()));
+        let image = core::array::from_fn(|i| {
+            let offset = u8::try_from(i).unwrap_or(u8::MAX);
+            sim.register(Reg::CHARGER_CONFIG_0.address().saturating_add(offset))
+        });
+        (image, sim.register(Reg::CHARGER_INTERRUPT_MASK.address()))
+    }
+
+    #[test]
+    fn init_register_image_is_locked() {
+        for variant in [Variant::Max77975, Variant::Max77986] {
+            let (image, irq_mask) = init_image(variant, 2000);
+            #[rustfmt::skip]
+            assert_eq!(
+                image,
+                [
+                    0x05, // CHG_CNFG_00: charge mode, watchdog off
+                    0x13, // CHG_CNFG_01: 5 h safety timer, 150 mV recharge
+                    0x14, // CHG_CNFG_02: 1000 mA fast charge (0.5 C)
+                    0x19, // CHG_CNFG_03: 150 mA top-off for 30 min
+                    0x50, // CHG_CNFG_04: 4200 mV termination
+                    0x0f, // CHG_CNFG_05: 10 A Vsys limit
+                    0x00, 0x00, 0x00,
+                    0x09, // CHG_CNFG_09: 500 mA CHGIN limit
+                    0x00, 0x00, 0x00, 0x02,
+                ],
+                "{variant:?}"
+            );
+            // CHGIN, charger and battery interrupts unmasked
+            assert_eq!(irq_mask, 0xa7, "{variant:?}");
+            assert_eq!(!irq_mask, CHARGER_IRQS.bits());
+        }
+    }
+
+    #[test]
+    fn init_limits_the_charge_current_to_the_part() {
+        let (image, _) = init_image(Variant::Max77975, 10_000);
+        // 3500 mA, the maximum of the MAX77975
+        assert_eq!(image[2], 0x46);
+    }
+
+    #[test]
+    fn init_rejects_a_battery_too_small_for_top_off() {
+        let res = run(&[], async |charger| charger.init(299).await);
+        assert_eq!(res, Err(Error::InvalidValue));
+    }
+}
@@ -0,0 +1,113 @@
+//! Retrying transient I2C errors.
+//!
+//! [`Charger::with_retry`] wraps the bus in a [`Retry`], which repeats a failed transaction
+//! according to a [`RetryPolicy`] before the error reaches the driver:
+//!
+//! ```no_run
+//! # use embedded_hal_async::delay::DelayNs;
+//! # use embedded_hal_async::i2c::I2c;
+//! # use max7797x_driver::retry::RetryPolicy;
+//! # use max7797x_driver::Charger;
+//! # fn example<D: I2c>(i2c: D, delay: impl DelayNs) {
+//! let policy = RetryPolicy { attempts: 3, backoff_us: 100 };
+//! let charger = Charger::new(i2c).with_retry(policy, delay);
+//! # }
+//! ```
+//!
+//! Every register access of the driver is a single transaction, so a retry repeats exactly one
+//! read or write. In particular the CHGPROT unlock and lock are separate transactions, each
+//! retried on its own, so a retried unlock is still followed by exactly one lock. Retrying a
+//! read of the read-to-clear interrupt registers after a failure during the data phase may
+//! lose the flags cleared by the failed attempt.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+
+use crate::Charger;
+
+/// How [`Retry`] repeats failed transactions
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryPolicy {
+    /// The number of attempts for each transaction, including the first. Zero is treated as one.
+    pub attempts: u8,
+    /// The delay between attempts in µs
+    pub backoff_us: u32,
+}
+
+/// An I2C bus that retries failed transactions, see [`Charger::with_retry`]
+pub struct Retry<D, T> {
+    i2c_dev: D,
+    delay: T,
+    policy: RetryPolicy,
+}
+
+impl<D, T> Retry<D, T> {
+    /// Wrap `i2c_dev`, waiting on `delay` between attempts.
+    pub fn new(i2c_dev: D, delay: T, policy: RetryPolicy) -> Self {
+        Retry {
+            i2c_dev,
+            delay,
+            policy,
+        }
+    }
+
+    /// The retry policy
+    pub fn policy(&self) -> RetryPolicy {
+        self.policy
+    }
+
+    /// Release the bus and the delay.
+    pub fn release(self) -> (D, T) {
+        (self.i2c_dev, self.delay)
+    }
+}
+
+impl<D: ErrorType, T> ErrorType for Retry<D, T> {
+    type Error = D::Error;
+}
+
+impl<D: I2c, T: DelayNs> I2c for Retry<D, T> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut attempt = 1;
+        loop {
+            match self.i2c_dev.transaction(address, operations).await {
+                Err(_) if attempt < self.policy.attempts => {
+                    attempt = attempt.saturating_add(1);
+                    if self.policy.backoff_us > 0 {
+                        self.delay.delay_us(self.policy.backoff_us).await;
+                    }
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Retry failed bus transactions according to `policy`, waiting on `delay` in between.
+    ///
+    /// Only the error of the last attempt is returned.
+    pub fn with_retry<T: DelayNs>(self, policy: RetryPolicy, delay: T) -> Charger<Retry<D, T>> {
+        Charger {
+            i2c_dev: Retry::new(self.i2c_dev, delay, policy),
+            bus_error: self.bus_error,
+            address: self.address,
+            variant: self.variant,
+            bypass: self.bypass,
+            suspended_mode: self.suspended_mode,
+            derating: self.derating,
+            read_voting: self.read_voting,
+            verify_writes: self.verify_writes,
+            cache: self.cache,
+            safety_policy: self.safety_policy,
+            #[cfg(feature = "trace")]
+            trace_op: self.trace_op,
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! A charger shared between tasks behind an async mutex.
+//!
+//! [`SharedCharger`] owns an [`embassy_sync`] mutex around a [`Charger`] and exposes its methods
+//! with `&self` receivers, so several tasks can hold a `&'static SharedCharger` and call it
+//! directly:
+//!
+//! ```no_run
+//! # use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+//! # use embedded_hal_async::i2c::I2c;
+//! # use max7797x_driver::shared::SharedCharger;
+//! # use max7797x_driver::{Charger, Error, Mode};
+//! # async fn example<D: I2c>(i2c: D) -> Result<(), Error<D::Error>> {
+//! let charger = SharedCharger::<NoopRawMutex, _>::new(Charger::new(i2c));
+//! // Place it in e.g. a `StaticCell` and hand `&charger` to each task, which can then call
+//! if charger.charger_details().await?.is_input_valid() {
+//!     charger.set_mode(Mode::Charge).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each method holds the lock for all of its transactions, so operations spanning several
+//! transactions, such as the protected writes, [`Charger::apply_config`] and
+//! [`Charger::full_status`], are not interleaved with calls from other tasks. Use
+//! [`SharedCharger::lock`] for methods that are not forwarded, or to keep the lock across
+//! several calls.
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+use embedded_hal_async::i2c::{ErrorType, I2c};
+
+use crate::{
+    BatteryStatus, BypassNodeDetails, ChargeProfile, Charger, ChargerConfig, ChargerConfig0,
+    ChargerDetails, ChargerInterrupts, ChargerStatus, ChgIn, Details, Error, FullStatus,
+    IrqSummary, Mode, ProtectedSession, RegisterSpec, ThermistorDetails, TopInterrupts, Variant,
+};
+
+/// Forward `&mut self` methods of [`Charger`] as `&self` methods taking the lock.
+macro_rules! forward {
+    ($($(#[$attr:meta])* fn $name:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        $(
+            $(#[$attr])*
+            pub async fn $name(&self, $($arg: $ty),*) -> Result<$ret, Error<D::Error>> {
+                self.charger.lock().await.$name($($arg),*).await
+            }
+        )*
+    };
+}
+
+/// A [`Charger`] behind an async mutex, see the [module documentation](self)
+pub struct SharedCharger<M: RawMutex, D: ErrorType> {
+    charger: Mutex<M, Charger<D>>,
+    variant: Variant,
+}
+
+impl<M: RawMutex, D: I2c> SharedCharger<M, D> {
+    /// Share `charger` between tasks.
+    pub fn new(charger: Charger<D>) -> Self {
+        SharedCharger {
+            variant: charger.variant,
+            charger: Mutex::new(charger),
+        }
+    }
+
+    /// Release the charger.
+    pub fn into_inner(self) -> Charger<D> {
+        self.charger.into_inner()
+    }
+
+    /// The part the driver is configured for.
+    ///
+    /// Reflects the variant when the charger was shared; use [`lock`](Self::lock) to detect it
+    /// again.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Lock the charger until the returned guard is dropped.
+    pub async fn lock(&self) -> MutexGuard<'_, M, Charger<D>> {
+        self.charger.lock().await
+    }
+
+    /// Read a typed register.
+    ///
+    /// See [`Charger::read`].
+    pub async fn read<R: RegisterSpec>(&self) -> Result<R, Error<D::Error>> {
+        self.charger.lock().await.read().await
+    }
+
+    /// Write a typed register.
+    ///
+    /// See [`Charger::write`].
+    pub async fn write<R: RegisterSpec>(&self, val: R) -> Result<(), Error<D::Error>> {
+        self.charger.lock().await.write(val).await
+    }
+
+    /// Unlock the CHGPROT protected charger settings for the duration of `f`.
+    ///
+    /// The lock is held for the whole session. See [`Charger::protected`].
+    pub async fn protected<T, F>(&self, f: F) -> Result<T, Error<D::Error>>
+    where
+        F: AsyncFnOnce(&mut ProtectedSession<'_, D>) -> Result<T, Error<D::Error>>,
+    {
+        self.charger.lock().await.protected(f).await
+    }
+
+    forward! {
+        /// See [`Charger::set_sys_ilim`].
+        fn set_sys_ilim(milliamps: u16, recycle_en: bool) -> ();
+        /// See [`Charger::set_chgin_ilim`].
+        fn set_chgin_ilim(milliamps: u16) -> ();
+        /// See [`Charger::chgin_ilim`].
+        fn chgin_ilim() -> u16;
+        /// See [`Charger::set_regulation_temperature`].
+        fn set_regulation_temperature(celsius: u8) -> ();
+        /// See [`Charger::regulation_temperature`].
+        fn regulation_temperature() -> u8;
+        /// See [`Charger::set_fast_charge_current`].
+        fn set_fast_charge_current(milliamps: u16) -> ();
+        /// See [`Charger::fast_charge_current`].
+        fn fast_charge_current() -> u16;
+        /// See [`Charger::set_mode`].
+        fn set_mode(mode: Mode) -> ();
+        /// See [`Charger::mode`].
+        fn mode() -> Mode;
+        /// See [`Charger::set_qbatt`].
+        fn set_qbatt(enabled: bool) -> ();
+        /// See [`Charger::qbatt_enabled`].
+        fn qbatt_enabled() -> bool;
+        /// See [`Charger::set_standby`].
+        fn set_standby(enabled: bool) -> ();
+        /// See [`Charger::standby`].
+        fn standby() -> bool;
+        /// See [`Charger::set_remote_sense`].
+        fn set_remote_sense(enabled: bool) -> ();
+        /// See [`Charger::remote_sense`].
+        fn remote_sense() -> bool;
+        /// See [`Charger::suspend_charging`].
+        fn suspend_charging() -> ();
+        /// See [`Charger::resume_charging`].
+        fn resume_charging() -> ();
+        /// See [`Charger::charger_config0`].
+        fn charger_config0() -> ChargerConfig0;
+        /// See [`Charger::set_charger_config0`].
+        fn set_charger_config0(config: ChargerConfig0) -> ();
+        /// See [`Charger::enable_otg`].
+        fn enable_otg(current_limit_ma: u16, vbus_mv: u16) -> ();
+        /// See [`Charger::disable_otg`].
+        fn disable_otg() -> ();
+        /// See [`Charger::enable_boost`].
+        fn enable_boost(vbyp_mv: u16) -> ();
+        /// See [`Charger::disable_boost`].
+        fn disable_boost() -> ();
+        /// See [`Charger::set_charger_irq_mask`].
+        fn set_charger_irq_mask(irqs: ChargerInterrupts) -> ();
+        /// See [`Charger::enable_charger_irqs`].
+        fn enable_charger_irqs(irqs: ChargerInterrupts) -> ();
+        /// See [`Charger::disable_charger_irqs`].
+        fn disable_charger_irqs(irqs: ChargerInterrupts) -> ();
+        /// See [`Charger::set_top_irq_mask`].
+        fn set_top_irq_mask(irqs: TopInterrupts) -> ();
+        /// See [`Charger::top_irq_flags`].
+        fn top_irq_flags() -> TopInterrupts;
+        /// See [`Charger::charger_irq_flags`].
+        fn charger_irq_flags() -> ChargerInterrupts;
+        /// See [`Charger::irq_summary`].
+        fn irq_summary() -> IrqSummary;
+        /// See [`Charger::charger_status`].
+        fn charger_status() -> ChargerStatus;
+        /// See [`Charger::charger_status_peek`].
+        fn charger_status_peek() -> ChargerStatus;
+        /// See [`Charger::full_status`].
+        fn full_status() -> FullStatus;
+        /// See [`Charger::charger_details`].
+        fn charger_details() -> Details;
+        /// See [`Charger::chgin_status`].
+        fn chgin_status() -> ChgIn;
+        /// See [`Charger::battery_status`].
+        fn battery_status() -> BatteryStatus;
+        /// See [`Charger::charger_state`].
+        fn charger_state() -> ChargerDetails;
+        /// See [`Charger::thermistor_status`].
+        fn thermistor_status() -> ThermistorDetails;
+        /// See [`Charger::bypass_status`].
+        fn bypass_status() -> BypassNodeDetails;
+        /// See [`Charger::apply_config`].
+        fn apply_config(config: &ChargerConfig) -> ();
+        /// See [`Charger::verify_config`].
+        fn verify_config(config: &ChargerConfig) -> ();
+        /// See [`Charger::apply_charge_profile`].
+        fn apply_charge_profile(profile: &ChargeProfile) -> ();
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use core::cell::Cell;
+    use core::future::{poll_fn, Future};
+    use core::mem::replace;
+    use core::pin::pin;
+    use core::task::Poll;
+    use std::vec::Vec;
+
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embedded_hal_async::i2c::{ErrorType, Operation};
+
+    use super::*;
+    use crate::sim::Simulator;
+    use crate::testing::{block_on, poll_once};
+    use crate::{conv, ChargerConfig5, Reg};
+
+    /// How many calls each task makes
+    const CALLS: usize = 50;
+
+    /// Return to the executor once.
+    async fn yield_now() {
+        let mut yielded = false;
+        poll_fn(|_| {
+            if replace(&mut yielded, true) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+
+    /// A bus yielding to the executor before each transaction, so that every poll of a task
+    /// completes at most one transaction and the tasks interleave mid-call
+    struct Yielding<D>(D);
+
+    impl<D: ErrorType> ErrorType for Yielding<D> {
+        type Error = D::Error;
+    }
+
+    impl<D: I2c> I2c for Yielding<D> {
+        async fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), D::Error> {
+            yield_now().await;
+            self.0.transaction(address, operations).await
+        }
+    }
+
+    /// Run `a` and `b` to completion, polling them in turn. Returns whether both completed.
+    fn run_tasks(a: impl Future<Output = ()>, b: impl Future<Output = ()>) -> bool {
+        let (mut a, mut b) = (pin!(a), pin!(b));
+        let (mut a_done, mut b_done) = (false, false);
+        for _ in 0..100_000 {
+            a_done = a_done || poll_once(a.as_mut()).is_ready();
+            b_done = b_done || poll_once(b.as_mut()).is_ready();
+            if a_done && b_done {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn two_tasks_keep_protected_writes_atomic() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        let charger = SharedCharger::<NoopRawMutex, _>::new(Charger::new(Yielding(sim.device())));
+        let a_calls = Cell::new(0);
+        let mut seen_by_b = Vec::new();
+
+        let a = async {
+            for i in 0..CALLS {
+                let milliamps = if i % 2 == 0 { 1000 } else { 1500 };
+                charger.set_fast_charge_current(milliamps).await.unwrap();
+                a_calls.set(i + 1);
+                // Let the other task take the lock between calls
+                yield_now().await;
+            }
+        };
+        let b = async {
+            for i in 0..CALLS {
+                let milliamps = if i % 2 == 0 { 3000 } else { 5000 };
+                charger.set_sys_ilim(milliamps, false).await.unwrap();
+                let guard = charger.lock().await;
+                // No other call is in progress while the lock is held
+                assert_eq!(sim.register(Reg::CHARGER_CONFIG_6.address()) & 0x0c, 0);
+                drop(guard);
+                charger.full_status().await.unwrap();
+                seen_by_b.push(a_calls.get());
+            }
+        };
+        assert!(run_tasks(a, b), "tasks did not complete");
+
+        assert_eq!(sim.protection_violations(), 0);
+        assert!(seen_by_b.iter().any(|&calls| calls > 0 && calls < CALLS));
+        assert_eq!(block_on(charger.fast_charge_current()), Ok(1500));
+        let config5 = block_on(charger.read::<ChargerConfig5>()).unwrap();
+        assert_eq!(conv::sys_ilim_ma(config5.sys_ilim()), 5000);
+    }
+}
@@ -0,0 +1,362 @@
+//! Checked ship mode entry and cancellation, and automatic entry on a low battery.
+
+use embedded_hal_async::i2c::{Error as _, ErrorKind, I2c};
+
+use crate::{Charger, ChgIn, Details, Error, Mode, Reg};
+
+/// The ship mode request bit of `SHIP_MODE_CONTROL`
+pub(crate) const SHIP_MODE_REQUEST: u8 = 0x01;
+/// The factory-ship bit of `SHIP_MODE_CONTROL`, entering ship mode when the input is removed
+pub(crate) const FACTORY_SHIP: u8 = 0x02;
+
+/// Why ship mode was not entered by [`Charger::try_enter_ship_mode`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum ShipModeError {
+    /// A valid input is attached, so the charger would ignore the request
+    ChargerPresent,
+    /// The charger still responds but the request did not latch, e.g. because an input was
+    /// attached after it was checked
+    Rejected,
+}
+
+impl<D: I2c> Charger<D> {
+    /// Enter ship mode, failing instead of writing the request if a valid input is attached.
+    ///
+    /// Fails with [`ShipModeError::ChargerPresent`] if `CHARGER_DETAILS_0` reports a valid
+    /// input. With `verify`, `SHIP_MODE_CONTROL` is read back after the write: a charger that
+    /// no longer acknowledges its address has shut down, one that reads back the request bit
+    /// has latched it, and one that reads it back clear fails with
+    /// [`ShipModeError::Rejected`]. Any other bus error is returned as is.
+    pub async fn try_enter_ship_mode(&mut self, verify: bool) -> Result<(), Error<D::Error>> {
+        traced!(self, "try_enter_ship_mode", {
+            if self.chgin_status().await? == ChgIn::Valid {
+                return Err(Error::ShipMode(ShipModeError::ChargerPresent));
+            }
+            self.write_reg(Reg::SHIP_MODE_CONTROL, SHIP_MODE_REQUEST)
+                .await?;
+            if !verify {
+                return Ok(());
+            }
+            match self.read_reg(Reg::SHIP_MODE_CONTROL).await {
+                Ok(val) if val & SHIP_MODE_REQUEST != 0 => Ok(()),
+                Ok(_) => Err(Error::ShipMode(ShipModeError::Rejected)),
+                Err(Error::I2c(err)) if matches!(err.kind(), ErrorKind::NoAcknowledge(_)) => Ok(()),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    /// Cancel a pending ship mode request. Returns whether a request was pending.
+    ///
+    /// A request does not always shut the charger down at once, and can be cancelled while it
+    /// is pending:
+    ///
+    /// - while the charger waits out its ship mode entry delay after the request bit was
+    ///   written, and
+    /// - with the factory-ship bit set, until the input is removed.
+    ///
+    /// Once the charger has shut down it no longer acknowledges its address, so this fails
+    /// with a bus error, and only attaching a valid input wakes it. Attaching a valid input
+    /// also cancels a pending request.
+    ///
+    /// Clears the request and factory-ship bits of `SHIP_MODE_CONTROL`; nothing is written if
+    /// neither is set.
+    pub async fn cancel_ship_mode(&mut self) -> Result<bool, Error<D::Error>> {
+        traced!(self, "cancel_ship_mode", {
+            let val = self.read_reg(Reg::SHIP_MODE_CONTROL).await?;
+            if val & (SHIP_MODE_REQUEST | FACTORY_SHIP) == 0 {
+                return Ok(false);
+            }
+            self.write_reg(
+                Reg::SHIP_MODE_CONTROL,
+                val & !(SHIP_MODE_REQUEST | FACTORY_SHIP),
+            )
+            .await?;
+            Ok(true)
+        })
+    }
+
+    /// Whether a ship mode request or the factory-ship bit is latched in `SHIP_MODE_CONTROL`.
+    pub async fn ship_mode_pending(&mut self) -> Result<bool, Error<D::Error>> {
+        traced!(self, "ship_mode_pending", {
+            let val = self.read_reg(Reg::SHIP_MODE_CONTROL).await?;
+            Ok(val & (SHIP_MODE_REQUEST | FACTORY_SHIP) != 0)
+        })
+    }
+
+    /// Shut the device down to protect a critically low battery on the shelf. Returns whether
+    /// ship mode was entered.
+    ///
+    /// Acts only if `battery_low` is set and neither `details` nor a fresh read of
+    /// `CHARGER_DETAILS_0` report a valid input. The charger reports the battery voltage only
+    /// while a valid input is attached, and [`BatteryDetails::BatteryOnly`](crate::BatteryDetails::BatteryOnly)
+    /// otherwise, so `battery_low` has to come from elsewhere, e.g. a fuel gauge.
+    ///
+    /// All TOP and charger interrupts are masked, the mode is set to [`Mode::Off`] and ship
+    /// mode is entered with [`try_enter_ship_mode`](Self::try_enter_ship_mode), verified. If an
+    /// input is attached in the meantime and the request fails with [`Error::ShipMode`], the
+    /// previous interrupt masks and `CHARGER_CONFIG_0` are restored and `false` is returned.
+    pub async fn auto_ship_on_low_battery(
+        &mut self,
+        details: &Details,
+        battery_low: bool,
+    ) -> Result<bool, Error<D::Error>> {
+        traced!(self, "auto_ship_on_low_battery", {
+            if !battery_low
+                || details.chgin() == ChgIn::Valid
+                || self.chgin_status().await? == ChgIn::Valid
+            {
+                return Ok(false);
+            }
+            let top_mask = self.read_reg(Reg::TOP_INTERRUPT_MASK).await?;
+            let charger_mask = self.read_reg(Reg::CHARGER_INTERRUPT_MASK).await?;
+            let config0 = self.charger_config0().await?;
+            self.write_reg(Reg::TOP_INTERRUPT_MASK, 0xff).await?;
+            self.write_reg(Reg::CHARGER_INTERRUPT_MASK, 0xff).await?;
+            self.set_mode(Mode::Off).await?;
+            match self.try_enter_ship_mode(true).await {
+                Ok(()) => Ok(true),
+                Err(Error::ShipMode(_)) => {
+                    self.set_charger_config0(config0).await?;
+                    self.write_reg(Reg::CHARGER_INTERRUPT_MASK, charger_mask)
+                        .await?;
+                    self.write_reg(Reg::TOP_INTERRUPT_MASK, top_mask).await?;
+                    Ok(false)
+                }
+                Err(err) => Err(err),
+            }
+        })
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use core::time::Duration;
+
+    use embedded_hal_async::i2c::{ErrorType, Operation};
+
+    use super::*;
+    use crate::sim::{SimulatedCharger, Simulator};
+    use crate::testing::{block_on, read, run};
+    use crate::{BatteryDetails, Variant};
+
+    /// A charger on battery with some interrupts unmasked.
+    fn on_battery(sim: &Simulator) -> Charger<SimulatedCharger<'_>> {
+        sim.set_register(Reg::TOP_INTERRUPT_MASK.address(), 0xfb);
+        sim.set_register(Reg::CHARGER_INTERRUPT_MASK.address(), 0xa7);
+        Charger::new(sim.device())
+    }
+
+    /// Whether the masks, the mode and ship mode are as [`on_battery`] left them.
+    fn untouched(sim: &Simulator) -> bool {
+        sim.register(Reg::TOP_INTERRUPT_MASK.address()) == 0xfb
+            && sim.register(Reg::CHARGER_INTERRUPT_MASK.address()) == 0xa7
+            && sim.mode() == Some(Mode::Charge)
+            && sim.register(Reg::SHIP_MODE_CONTROL.address()) == 0
+            && !sim.in_ship_mode()
+    }
+
+    /// A bus attaching an input right after the ship mode request is written.
+    struct PlugAfterRequest<'a> {
+        sim: &'a Simulator,
+        dev: SimulatedCharger<'a>,
+    }
+
+    impl ErrorType for PlugAfterRequest<'_> {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for PlugAfterRequest<'_> {
+        async fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), ErrorKind> {
+            let request = matches!(operations, [Operation::Write([0x51, val])] if val & SHIP_MODE_REQUEST != 0);
+            self.dev.transaction(address, operations).await?;
+            if request {
+                self.sim.plug_charger();
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn low_battery_without_input_enters_ship_mode() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        let details = block_on(charger.charger_details()).unwrap();
+        assert_eq!(details.battery(), BatteryDetails::BatteryOnly);
+        assert_eq!(
+            block_on(charger.auto_ship_on_low_battery(&details, true)),
+            Ok(true)
+        );
+        assert!(sim.in_ship_mode());
+        // An input wakes the charger with its registers reset
+        sim.plug_charger();
+        assert_eq!(sim.register(Reg::CHARGER_INTERRUPT_MASK.address()), 0xff);
+    }
+
+    #[test]
+    fn pending_ship_mode_masks_the_interrupts_and_turns_off() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_ship_mode_delay(Duration::from_secs(1));
+        let mut charger = on_battery(&sim);
+        let details = block_on(charger.charger_details()).unwrap();
+        assert_eq!(
+            block_on(charger.auto_ship_on_low_battery(&details, true)),
+            Ok(true)
+        );
+        assert!(!sim.in_ship_mode());
+        assert_eq!(sim.register(Reg::TOP_INTERRUPT_MASK.address()), 0xff);
+        assert_eq!(sim.register(Reg::CHARGER_INTERRUPT_MASK.address()), 0xff);
+        assert_eq!(sim.mode(), Some(Mode::Off));
+        sim.step(Duration::from_secs(1));
+        assert!(sim.in_ship_mode());
+    }
+
+    #[test]
+    fn low_battery_with_input_never_ships() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        sim.set_battery_voltage(2500);
+        sim.plug_charger();
+        let details = block_on(charger.charger_details()).unwrap();
+        assert_eq!(details.chgin(), ChgIn::Valid);
+        assert_eq!(
+            block_on(charger.auto_ship_on_low_battery(&details, true)),
+            Ok(false)
+        );
+        assert!(untouched(&sim));
+
+        // Stale details without the input are checked against a fresh read
+        let stale = details.with_chgin(ChgIn::Undervoltage);
+        assert_eq!(
+            block_on(charger.auto_ship_on_low_battery(&stale, true)),
+            Ok(false)
+        );
+        assert!(untouched(&sim));
+    }
+
+    #[test]
+    fn healthy_battery_never_ships() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        let details = block_on(charger.charger_details()).unwrap();
+        assert_eq!(
+            block_on(charger.auto_ship_on_low_battery(&details, false)),
+            Ok(false)
+        );
+        assert!(untouched(&sim));
+    }
+
+    #[test]
+    fn input_attached_during_entry_restores_the_state() {
+        let sim = Simulator::new(Variant::Max77975);
+        on_battery(&sim);
+        let mut charger = Charger::new(PlugAfterRequest {
+            sim: &sim,
+            dev: sim.device(),
+        });
+        let details = block_on(charger.charger_details()).unwrap();
+        assert_eq!(
+            block_on(charger.auto_ship_on_low_battery(&details, true)),
+            Ok(false)
+        );
+        assert!(untouched(&sim));
+    }
+
+    #[test]
+    fn try_enter_ship_mode_refuses_with_an_input() {
+        // The request is never written
+        let res = run(&[read(Reg::CHARGER_DETAILS_0, 0x60)], async |charger| {
+            charger.try_enter_ship_mode(true).await
+        });
+        assert_eq!(res, Err(Error::ShipMode(ShipModeError::ChargerPresent)));
+
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        sim.plug_charger();
+        assert_eq!(
+            block_on(charger.try_enter_ship_mode(true)),
+            Err(Error::ShipMode(ShipModeError::ChargerPresent))
+        );
+        assert!(!sim.in_ship_mode());
+    }
+
+    #[test]
+    fn try_enter_ship_mode_shuts_down() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        // The read-back is not acknowledged once the charger has shut down
+        assert_eq!(block_on(charger.try_enter_ship_mode(true)), Ok(()));
+        assert!(sim.in_ship_mode());
+
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_ship_mode_delay(Duration::from_secs(1));
+        let mut charger = on_battery(&sim);
+        // The read-back finds the request latched
+        assert_eq!(block_on(charger.try_enter_ship_mode(true)), Ok(()));
+        assert!(!sim.in_ship_mode());
+        sim.step(Duration::from_secs(1));
+        assert!(sim.in_ship_mode());
+    }
+
+    #[test]
+    fn try_enter_ship_mode_detects_a_rejected_request() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_ship_mode_delay(Duration::from_secs(1));
+        let mut charger = Charger::new(PlugAfterRequest {
+            sim: &sim,
+            dev: sim.device(),
+        });
+        assert_eq!(
+            block_on(charger.try_enter_ship_mode(true)),
+            Err(Error::ShipMode(ShipModeError::Rejected))
+        );
+        sim.step(Duration::from_secs(1));
+        assert!(!sim.in_ship_mode());
+    }
+
+    #[test]
+    fn cancel_ship_mode_cancels_a_pending_request() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_ship_mode_delay(Duration::from_secs(1));
+        let mut charger = on_battery(&sim);
+        assert_eq!(block_on(charger.ship_mode_pending()), Ok(false));
+        block_on(charger.try_enter_ship_mode(false)).unwrap();
+        assert_eq!(block_on(charger.ship_mode_pending()), Ok(true));
+
+        assert_eq!(block_on(charger.cancel_ship_mode()), Ok(true));
+        assert_eq!(block_on(charger.ship_mode_pending()), Ok(false));
+        sim.step(Duration::from_secs(2));
+        assert!(!sim.in_ship_mode());
+        // Nothing is left to cancel
+        assert_eq!(block_on(charger.cancel_ship_mode()), Ok(false));
+    }
+
+    #[test]
+    fn cancel_ship_mode_clears_factory_ship() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        sim.plug_charger();
+        sim.set_register(Reg::SHIP_MODE_CONTROL.address(), FACTORY_SHIP);
+        assert_eq!(block_on(charger.ship_mode_pending()), Ok(true));
+
+        assert_eq!(block_on(charger.cancel_ship_mode()), Ok(true));
+        assert_eq!(block_on(charger.ship_mode_pending()), Ok(false));
+        assert_eq!(sim.register(Reg::SHIP_MODE_CONTROL.address()), 0);
+        sim.unplug_charger();
+        sim.step(Duration::from_millis(10));
+        assert!(!sim.in_ship_mode());
+    }
+
+    #[test]
+    fn cancel_ship_mode_without_a_request_writes_nothing() {
+        let res = run(&[read(Reg::SHIP_MODE_CONTROL, 0x00)], async |charger| {
+            charger.cancel_ship_mode().await
+        });
+        assert_eq!(res, Ok(false));
+    }
+}
@@ -0,0 +1,1683 @@
+//! An in-memory model of the charger for testing application code without hardware.
+//!
+//! A [`Simulator`] holds the register file and the external conditions (input source, battery
+//! and thermistor state) of one charger. [`Simulator::device`] returns a [`SimulatedCharger`]
+//! implementing [`I2c`], which can be handed to [`Charger`](crate::Charger) while the
+//! `Simulator` itself is used as the test handle:
+//!
+//! ```no_run
+//! # use max7797x_driver::sim::Simulator;
+//! # use max7797x_driver::{Charger, ChgIn, Variant};
+//! # async fn example() -> Result<(), max7797x_driver::Error<embedded_hal_async::i2c::ErrorKind>> {
+//! let sim = Simulator::new(Variant::Max77975);
+//! let mut charger = Charger::new(sim.device());
+//! sim.set_chgin(ChgIn::Valid);
+//! assert_eq!(charger.charger_details().await?.chgin(), ChgIn::Valid);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The model implements the reset defaults, read-to-clear interrupt registers, CHGPROT write
+//! protection, software reset, ship mode and the detail and interrupt changes caused by mode
+//! and input changes. It is not cycle accurate.
+//!
+//! A simple battery model evolves over virtual time with [`Simulator::step`]: while charging,
+//! the battery takes the programmed fast-charge current until it reaches the programmed
+//! regulation voltage, tapers in constant voltage until the current falls below the top-off
+//! threshold and finishes once the top-off timer expires.
+//!
+//! Faults can be injected through the handle to exercise error paths: NAKs on upcoming
+//! transactions ([`Simulator::nak_next`]), a corrupted register read
+//! ([`Simulator::corrupt_next_read`]), a bus stalled mid-transaction ([`Simulator::freeze_bus`])
+//! and flipped bits in the detail registers ([`Simulator::flip_detail_bits`]).
+//!
+//! Application code written against [`ChargerOps`] can use the simpler [`FakeCharger`], which
+//! keeps the settings in plain fields instead of modelling the registers.
+
+// The simulator is a host-side test double, not part of the driver's no-panic contract: its
+// battery model works on bounded virtual quantities and the register file is indexed by u8
+// addresses into a 256-byte array.
+#![allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use core::future::poll_fn;
+use core::task::{Poll, Waker};
+use core::time::Duration;
+
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+use crate::ship::{FACTORY_SHIP, SHIP_MODE_REQUEST};
+use crate::{
+    conv, BatteryDetails, BatterySense, BypassNodeDetails, ChargerConfig0, ChargerDetails,
+    ChargerInterrupts, ChargerOps, ChargerStatus, ChgIn, Details, Error, IrqSummary, Mode, Reg,
+    TemperatureRegulation, ThermistorDetails, TopInterrupts, Variant, DEFAULT_ADDRESS,
+    WATCHDOG_TIMEOUT_MS, WRITABLE_RANGES,
+};
+
+/// The register values after power-on or software reset, other than zero
+const RESET_VALUES: [(Reg, u8); 8] = [
+    (Reg::CHIP_REVISION, 0x01),
+    (Reg::TOP_INTERRUPT_MASK, 0xff),
+    (Reg::CHARGER_INTERRUPT_MASK, 0xff),
+    (Reg::CHARGER_CONFIG_0, Mode::Charge as u8),
+    (Reg::CHARGER_CONFIG_2, 0x0a),
+    (Reg::CHARGER_CONFIG_4, 0x50),
+    (Reg::CHARGER_CONFIG_9, 0x09),
+    (Reg::CHARGER_CONFIG_13, 0x02),
+];
+
+/// The registers that are only writable while CHGPROT is unlocked
+const PROTECTED: [(Reg, Reg); 2] = [
+    (Reg::CHARGER_CONFIG_1, Reg::CHARGER_CONFIG_5),
+    (Reg::CHARGER_CONFIG_7, Reg::CHARGER_CONFIG_7),
+];
+
+const SOFTWARE_RESET_KEY: u8 = 0xa5;
+
+/// Open circuit voltage of an empty battery
+const EMPTY_MV: u32 = 3000;
+/// Open circuit voltage of a full battery
+const FULL_MV: u32 = 4200;
+/// Below this voltage the charger uses the trickle current (VPQLB)
+const PREQUALIFICATION_MV: u32 = 3000;
+/// Below this voltage the battery is reported as low (VSYSMIN)
+const SYS_MIN_MV: u32 = 3400;
+/// Battery internal resistance
+const RESISTANCE_MILLIOHMS: u32 = 100;
+/// The longest interval integrated at once by [`Simulator::step`]
+const MAX_STEP_MS: u64 = 1000;
+
+/// The phase of the simulated charge cycle
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Phase {
+    Prequalification,
+    ConstantCurrent,
+    ConstantVoltage,
+    TopOff { elapsed_ms: u64 },
+    Done,
+    TimerFault,
+}
+
+/// The simulated battery
+struct Battery {
+    capacity_mah: u32,
+    charge_uah: u64,
+    load_ma: u32,
+    phase: Phase,
+}
+
+impl Battery {
+    /// The open circuit voltage, rising linearly with the state of charge
+    fn voltage_mv(&self) -> u32 {
+        let capacity_uah = self.capacity_mah as u64 * 1000;
+        let charge = self.charge_uah.min(capacity_uah);
+        EMPTY_MV + ((FULL_MV - EMPTY_MV) as u64 * charge / capacity_uah.max(1)) as u32
+    }
+
+    fn set_voltage_mv(&mut self, millivolts: u32) {
+        let millivolts = millivolts.clamp(EMPTY_MV, FULL_MV);
+        self.charge_uah = (millivolts - EMPTY_MV) as u64 * self.capacity_mah as u64 * 1000
+            / (FULL_MV - EMPTY_MV) as u64;
+    }
+
+    fn details(&self) -> BatteryDetails {
+        match self.voltage_mv() {
+            mv if mv < PREQUALIFICATION_MV => BatteryDetails::PrequalificationVoltage,
+            mv if mv < SYS_MIN_MV => BatteryDetails::LowVoltage,
+            _ => BatteryDetails::RegularVoltage,
+        }
+    }
+}
+
+fn in_ranges(ranges: &[(Reg, Reg)], reg: u8) -> bool {
+    ranges
+        .iter()
+        .any(|&(first, last)| (first.address()..=last.address()).contains(&reg))
+}
+
+/// The simulated charger state
+struct State {
+    regs: [u8; 0x100],
+    address: u8,
+    variant: Variant,
+    chgin: ChgIn,
+    battery: Battery,
+    battery_override: Option<BatteryDetails>,
+    thermistor: ThermistorDetails,
+    sense: BatterySense,
+    ship_mode: bool,
+    ship_delay_ms: u64,
+    ship_elapsed_ms: u64,
+    protection_violations: u32,
+    irq_waker: Option<Waker>,
+    naks: u32,
+    corrupt_read: Option<(u8, u8)>,
+    bus_frozen: bool,
+    bus_stalled: bool,
+    bus_waker: Option<Waker>,
+    detail_flips: [u8; 3],
+    adapter_limit_ma: Option<u32>,
+    watchdog_elapsed_ms: u64,
+    watchdog_expired: bool,
+}
+
+impl State {
+    fn reset(&mut self) {
+        self.regs = [0; 0x100];
+        self.watchdog_elapsed_ms = 0;
+        self.watchdog_expired = false;
+        self.ship_elapsed_ms = 0;
+        self.regs[Reg::CHIP_ID.address() as usize] = self.variant.chip_id();
+        for (reg, val) in RESET_VALUES {
+            self.regs[reg.address() as usize] = val;
+        }
+        let details = self.details().into_bytes();
+        self.regs
+            [Reg::CHARGER_DETAILS_0.address() as usize..=Reg::CHARGER_DETAILS_2.address() as usize]
+            .copy_from_slice(&details);
+        self.regs[Reg::CHARGER_INTERRUPT_STATUS.address() as usize] = self.status();
+    }
+
+    fn reg(&self, reg: Reg) -> u8 {
+        self.regs[reg.address() as usize]
+    }
+
+    fn config0(&self) -> ChargerConfig0 {
+        ChargerConfig0::from_bytes([self.reg(Reg::CHARGER_CONFIG_0)])
+    }
+
+    fn mode(&self) -> Option<Mode> {
+        self.config0().mode().ok()
+    }
+
+    fn charger_state(&self) -> ChargerDetails {
+        let config0 = self.config0();
+        if self.chgin != ChgIn::Valid
+            || self.mode() != Some(Mode::Charge)
+            || config0.standby_enable()
+        {
+            return ChargerDetails::Off;
+        }
+        if config0.disibs() {
+            return ChargerDetails::QBattDisabled;
+        }
+        if self.watchdog_expired {
+            return ChargerDetails::WatchdogTimer;
+        }
+        match self.thermistor() {
+            ThermistorDetails::Removed => ChargerDetails::ThermistorRemoval,
+            ThermistorDetails::Cold | ThermistorDetails::Hot => ChargerDetails::Jeita,
+            _ => match self.battery.phase {
+                Phase::Prequalification => ChargerDetails::Prequalification,
+                Phase::ConstantCurrent => ChargerDetails::ConstantCurrent,
+                Phase::ConstantVoltage => ChargerDetails::ConstantVoltage,
+                Phase::TopOff { .. } => ChargerDetails::TopOff,
+                Phase::Done => ChargerDetails::Done,
+                Phase::TimerFault => ChargerDetails::TimerFault,
+            },
+        }
+    }
+
+    /// The reported thermistor condition, taking THM_EN into account
+    fn thermistor(&self) -> ThermistorDetails {
+        if self.reg(Reg::CHARGER_CONFIG_13) & 0x02 == 0 {
+            ThermistorDetails::Disabled
+        } else {
+            self.thermistor
+        }
+    }
+
+    fn charging(&self) -> bool {
+        !matches!(
+            self.charger_state(),
+            ChargerDetails::Off
+                | ChargerDetails::QBattDisabled
+                | ChargerDetails::ThermistorRemoval
+                | ChargerDetails::Jeita
+        )
+    }
+
+    /// The programmed fast-charge current (CHG_CC)
+    fn fast_charge_current_ma(&self) -> u32 {
+        conv::chg_cc_ma(self.reg(Reg::CHARGER_CONFIG_2), self.variant) as u32
+    }
+
+    /// The programmed regulation voltage (CHG_CV_PRM)
+    fn regulation_mv(&self) -> u32 {
+        conv::chg_cv_prm_mv(self.reg(Reg::CHARGER_CONFIG_4)) as u32
+    }
+
+    /// The drop below the regulation voltage that restarts a finished charge (CHG_RSTRT)
+    fn restart_mv(&self) -> Option<u32> {
+        match (self.reg(Reg::CHARGER_CONFIG_1) >> 4) & 0x03 {
+            3 => None,
+            code => Some(100 + 50 * code as u32),
+        }
+    }
+
+    /// The prequalification current (ITRICKLE)
+    fn trickle_ma(&self) -> u32 {
+        100 + 100 * ((self.reg(Reg::CHARGER_CONFIG_5) >> 5) & 0x03) as u32
+    }
+
+    /// The top-off current threshold (TO_ITH)
+    fn top_off_ma(&self) -> u32 {
+        100 + 50 * (self.reg(Reg::CHARGER_CONFIG_3) & 0x07) as u32
+    }
+
+    /// The top-off timer (TO_TIME)
+    fn top_off_ms(&self) -> u64 {
+        10 * 60 * 1000 * ((self.reg(Reg::CHARGER_CONFIG_3) >> 3) & 0x07) as u64
+    }
+
+    /// Advance the battery model by `dt_ms`, which should be short.
+    /// Advance the watchdog timer by `dt_ms`.
+    fn step_watchdog(&mut self, dt_ms: u64) {
+        if !self.config0().watchdog_enable() {
+            self.watchdog_elapsed_ms = 0;
+            self.watchdog_expired = false;
+            return;
+        }
+        self.watchdog_elapsed_ms += dt_ms;
+        if self.watchdog_elapsed_ms >= WATCHDOG_TIMEOUT_MS as u64 {
+            self.watchdog_expired = true;
+        }
+    }
+
+    /// Enter ship mode once a request has waited out the entry delay, or with the factory-ship
+    /// bit set once no valid input is attached.
+    fn step_ship_mode(&mut self, dt_ms: u64) {
+        let control = self.regs[Reg::SHIP_MODE_CONTROL.address() as usize];
+        if control & SHIP_MODE_REQUEST != 0 {
+            self.ship_elapsed_ms += dt_ms;
+            if self.ship_elapsed_ms >= self.ship_delay_ms {
+                self.ship_mode = true;
+            }
+        } else if control & FACTORY_SHIP != 0 && self.chgin != ChgIn::Valid {
+            self.ship_mode = true;
+        }
+    }
+
+    fn step_battery(&mut self, dt_ms: u64) {
+        if !self.charging() {
+            let drain = self.battery.load_ma as u64 * dt_ms / 3600;
+            self.battery.charge_uah = self.battery.charge_uah.saturating_sub(drain);
+            return;
+        }
+        let voltage = self.battery.voltage_mv();
+        let regulation = self.regulation_mv();
+        let headroom_ma = regulation.saturating_sub(voltage) * 1000 / RESISTANCE_MILLIOHMS;
+        let current = match self.battery.phase {
+            Phase::TimerFault => 0,
+            Phase::Done
+                if self
+                    .restart_mv()
+                    .is_none_or(|restart| voltage + restart > regulation) =>
+            {
+                self.battery.phase = Phase::Done;
+                0
+            }
+            _ if voltage < PREQUALIFICATION_MV => {
+                self.battery.phase = Phase::Prequalification;
+                self.trickle_ma()
+            }
+            Phase::TopOff { elapsed_ms } => {
+                let elapsed_ms = elapsed_ms + dt_ms;
+                self.battery.phase = if elapsed_ms >= self.top_off_ms() {
+                    Phase::Done
+                } else {
+                    Phase::TopOff { elapsed_ms }
+                };
+                headroom_ma
+            }
+            _ if headroom_ma >= self.fast_charge_current_ma() => {
+                self.battery.phase = Phase::ConstantCurrent;
+                self.fast_charge_current_ma()
+            }
+            _ if headroom_ma > self.top_off_ma() => {
+                self.battery.phase = Phase::ConstantVoltage;
+                headroom_ma
+            }
+            _ => {
+                self.battery.phase = Phase::TopOff { elapsed_ms: 0 };
+                headroom_ma
+            }
+        };
+        let net = current as i64 - self.battery.load_ma as i64;
+        let delta = net.unsigned_abs() * dt_ms / 3600;
+        self.battery.charge_uah = if net >= 0 {
+            self.battery.charge_uah + delta
+        } else {
+            self.battery.charge_uah.saturating_sub(delta)
+        };
+    }
+
+    fn details(&self) -> Details {
+        let boost = matches!(self.mode(), Some(Mode::Boost | Mode::Otg))
+            && !self.config0().standby_enable();
+        let battery = if self.chgin == ChgIn::Valid {
+            self.battery_override.unwrap_or(match self.battery.phase {
+                Phase::TimerFault => BatteryDetails::TimerFault,
+                _ => self.battery.details(),
+            })
+        } else {
+            BatteryDetails::BatteryOnly
+        };
+        Details::new()
+            .with_sense(self.sense)
+            .with_chgin(self.chgin)
+            .with_charger(self.charger_state())
+            .with_battery(battery)
+            .with_temp(TemperatureRegulation::BelowThreshold)
+            .with_bypass(
+                BypassNodeDetails::new().with_boost_on(boost && self.chgin != ChgIn::Valid),
+            )
+            .with_thermistor(self.thermistor())
+    }
+
+    /// The `CHARGER_INTERRUPT_STATUS` register
+    fn status(&self) -> u8 {
+        let details = self.details();
+        ChargerStatus::new()
+            .with_bypass_ok(true)
+            .with_disqbat_ok(!self.config0().disibs())
+            .with_battery_ok(matches!(
+                details.battery(),
+                BatteryDetails::RegularVoltage | BatteryDetails::BatteryOnly
+            ))
+            .with_charger_ok(!matches!(
+                details.charger(),
+                ChargerDetails::TimerFault
+                    | ChargerDetails::QBattDisabled
+                    | ChargerDetails::HighTemperature
+                    | ChargerDetails::WatchdogTimer
+                    | ChargerDetails::Jeita
+                    | ChargerDetails::ThermistorRemoval
+                    | ChargerDetails::SuspendPin
+                    | ChargerDetails::Done
+            ))
+            .with_input_current_limit_ok(true)
+            .with_chgin_ok(details.chgin() == ChgIn::Valid)
+            .with_aicl_ok(!self.aicl_active())
+            .into_bytes()[0]
+    }
+
+    /// Whether the input voltage is being regulated because CHGIN_ILIM exceeds what the
+    /// adapter can supply
+    fn aicl_active(&self) -> bool {
+        let chgin_ilim_ma = conv::chgin_ilim_ma(self.reg(Reg::CHARGER_CONFIG_9)) as u32;
+        self.chgin == ChgIn::Valid
+            && self
+                .adapter_limit_ma
+                .is_some_and(|limit| chgin_ilim_ma > limit)
+    }
+
+    /// Recompute the status and detail registers, latching interrupt flags for changed sources.
+    fn update(&mut self) {
+        // Disabling the charger or removing the input ends the charge cycle and clears faults
+        if self.chgin != ChgIn::Valid || self.mode() != Some(Mode::Charge) {
+            self.battery.phase = Phase::ConstantCurrent;
+        }
+        let details_reg = Reg::CHARGER_DETAILS_0.address() as usize;
+        let old = Details::from_bytes([
+            self.regs[details_reg],
+            self.regs[details_reg + 1],
+            self.regs[details_reg + 2],
+        ]);
+        let old_status = self.reg(Reg::CHARGER_INTERRUPT_STATUS);
+        let mut new = self.details().into_bytes();
+        for (byte, flip) in new.iter_mut().zip(self.detail_flips) {
+            *byte ^= flip;
+        }
+        let new_status = self.status();
+        self.regs[details_reg..details_reg + 3].copy_from_slice(&new);
+        self.regs[Reg::CHARGER_INTERRUPT_STATUS.address() as usize] = new_status;
+
+        // Flipped bits may not decode, so compare the raw fields
+        let new = Details::from_bytes(new);
+        let changed_status = ChargerStatus::from_bytes([old_status ^ new_status]);
+        let flags = ChargerInterrupts::new()
+            .with_bypass_node(old.bypass_or_err() != new.bypass_or_err())
+            .with_disqbat(changed_status.disqbat_ok())
+            .with_battery(old.battery_or_err() != new.battery_or_err())
+            .with_charger(old.charger_or_err() != new.charger_or_err())
+            .with_chgin(old.chgin_or_err() != new.chgin_or_err())
+            .with_adaptive_input_current_loop(changed_status.aicl_ok());
+        self.regs[Reg::CHARGER_INTERRUPT.address() as usize] |= flags.into_bytes()[0];
+        self.notify();
+    }
+
+    /// Whether an unmasked interrupt is pending, i.e. the nIRQ pin is asserted
+    fn irq_asserted(&self) -> bool {
+        let top = self.reg(Reg::TOP_INTERRUPT) & !self.reg(Reg::TOP_INTERRUPT_MASK);
+        let charger = self.reg(Reg::CHARGER_INTERRUPT) & !self.reg(Reg::CHARGER_INTERRUPT_MASK);
+        top != 0 || charger != 0
+    }
+
+    fn notify(&mut self) {
+        if let Some(waker) = self.irq_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn read(&mut self, reg: u8) -> u8 {
+        let mut val = self.regs[reg as usize];
+        if let Some((corrupt_reg, mask)) = self.corrupt_read {
+            if corrupt_reg == reg {
+                val ^= mask;
+                self.corrupt_read = None;
+            }
+        }
+        if reg == Reg::TOP_INTERRUPT.address() || reg == Reg::CHARGER_INTERRUPT.address() {
+            self.regs[reg as usize] = 0;
+            self.notify();
+        }
+        val
+    }
+
+    /// Execute one I2C operation, tracking the auto-incrementing register `pointer`.
+    fn execute(&mut self, pointer: &mut Option<u8>, op: &mut Operation<'_>) {
+        match op {
+            Operation::Write(bytes) => {
+                for &byte in bytes.iter() {
+                    *pointer = match *pointer {
+                        None => Some(byte),
+                        Some(reg) => {
+                            self.write(reg, byte);
+                            Some(reg.wrapping_add(1))
+                        }
+                    };
+                }
+            }
+            Operation::Read(buf) => {
+                let mut reg = pointer.unwrap_or(0);
+                for byte in buf.iter_mut() {
+                    *byte = self.read(reg);
+                    reg = reg.wrapping_add(1);
+                }
+                *pointer = Some(reg);
+            }
+        }
+    }
+
+    fn write(&mut self, reg: u8, val: u8) {
+        if !in_ranges(&WRITABLE_RANGES, reg) {
+            return;
+        }
+        if in_ranges(&PROTECTED, reg) && self.reg(Reg::CHARGER_CONFIG_6) & 0x0c != 0x0c {
+            self.protection_violations += 1;
+            return;
+        }
+        match Reg::new(reg) {
+            Reg::SOFTWARE_RESET => {
+                if val == SOFTWARE_RESET_KEY {
+                    self.reset();
+                }
+            }
+            Reg::SHIP_MODE_CONTROL => {
+                let mut val = val & (SHIP_MODE_REQUEST | FACTORY_SHIP);
+                // A request is ignored while a valid input is attached
+                if self.chgin == ChgIn::Valid {
+                    val &= !SHIP_MODE_REQUEST;
+                }
+                self.regs[reg as usize] = val;
+                self.ship_elapsed_ms = 0;
+                self.step_ship_mode(0);
+            }
+            Reg::CHARGER_CONFIG_6 => {
+                // WDTCLR is self-clearing
+                self.regs[reg as usize] = val & 0xfc;
+                if val & 0x03 == 0x01 {
+                    self.watchdog_elapsed_ms = 0;
+                    self.watchdog_expired = false;
+                    self.update();
+                }
+            }
+            _ => {
+                self.regs[reg as usize] = val;
+                self.update();
+            }
+        }
+    }
+}
+
+/// A simulated charger and the handle used to control its environment.
+pub struct Simulator {
+    state: RefCell<State>,
+}
+
+impl Simulator {
+    /// Create a simulated `variant` at the [`DEFAULT_ADDRESS`], with no input attached.
+    pub fn new(variant: Variant) -> Self {
+        Self::new_with_address(variant, DEFAULT_ADDRESS)
+    }
+
+    /// Create a simulated `variant` responding at the 7-bit I2C `address`.
+    pub fn new_with_address(variant: Variant, address: u8) -> Self {
+        let mut state = State {
+            regs: [0; 0x100],
+            address,
+            variant,
+            chgin: ChgIn::Undervoltage,
+            battery: Battery {
+                capacity_mah: 3000,
+                charge_uah: 1_500_000,
+                load_ma: 0,
+                phase: Phase::ConstantCurrent,
+            },
+            battery_override: None,
+            thermistor: ThermistorDetails::Normal,
+            sense: BatterySense::Connected,
+            ship_mode: false,
+            ship_delay_ms: 0,
+            ship_elapsed_ms: 0,
+            protection_violations: 0,
+            irq_waker: None,
+            naks: 0,
+            corrupt_read: None,
+            bus_frozen: false,
+            bus_stalled: false,
+            bus_waker: None,
+            detail_flips: [0; 3],
+            adapter_limit_ma: None,
+            watchdog_elapsed_ms: 0,
+            watchdog_expired: false,
+        };
+        state.reset();
+        Simulator {
+            state: RefCell::new(state),
+        }
+    }
+
+    /// Get an I2C device connected to the simulated charger.
+    pub fn device(&self) -> SimulatedCharger<'_> {
+        SimulatedCharger { sim: self }
+    }
+
+    /// Get the simulated nIRQ pin.
+    pub fn irq_pin(&self) -> SimIrqPin<'_> {
+        SimIrqPin { sim: self }
+    }
+
+    /// Set the CHGIN input condition.
+    ///
+    /// Attaching a valid input wakes the charger from ship mode with its registers reset, or
+    /// cancels a pending ship mode request. Removing it enters ship mode if the factory-ship
+    /// bit is set.
+    pub fn set_chgin(&self, chgin: ChgIn) {
+        let mut state = self.state.borrow_mut();
+        state.chgin = chgin;
+        if chgin != ChgIn::Valid {
+            state.step_ship_mode(0);
+        } else if state.ship_mode {
+            state.ship_mode = false;
+            state.reset();
+        } else {
+            state.regs[Reg::SHIP_MODE_CONTROL.address() as usize] &= !SHIP_MODE_REQUEST;
+        }
+        state.update();
+    }
+
+    /// Attach a valid input source.
+    pub fn plug_charger(&self) {
+        self.set_chgin(ChgIn::Valid);
+    }
+
+    /// Remove the input source.
+    pub fn unplug_charger(&self) {
+        self.set_chgin(ChgIn::Undervoltage);
+    }
+
+    /// Override the battery condition reported while a valid input is present.
+    ///
+    /// `None` reports the condition derived from the battery voltage.
+    pub fn set_battery(&self, battery: Option<BatteryDetails>) {
+        let mut state = self.state.borrow_mut();
+        state.battery_override = battery;
+        state.update();
+    }
+
+    /// Set the battery capacity used by the charge model. The voltage is kept.
+    pub fn set_battery_capacity(&self, milliamp_hours: u32) {
+        let mut state = self.state.borrow_mut();
+        let voltage = state.battery.voltage_mv();
+        state.battery.capacity_mah = milliamp_hours.max(1);
+        state.battery.set_voltage_mv(voltage);
+    }
+
+    /// Set the open circuit battery voltage, clamped to the empty and full voltages of the model.
+    pub fn set_battery_voltage(&self, millivolts: u32) {
+        let mut state = self.state.borrow_mut();
+        state.battery.set_voltage_mv(millivolts);
+        state.update();
+    }
+
+    /// The open circuit battery voltage.
+    pub fn battery_voltage(&self) -> u32 {
+        self.state.borrow().battery.voltage_mv()
+    }
+
+    /// Expire the fast-charge timer, putting a running charge cycle into timer fault.
+    ///
+    /// The fault clears when the charger is disabled or the input is removed.
+    pub fn trigger_timer_fault(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.charging() {
+            state.battery.phase = Phase::TimerFault;
+            state.update();
+        }
+    }
+
+    /// Model a weak adapter that sags once the input current limit exceeds `milliamps`.
+    ///
+    /// While CHGIN_ILIM is above the limit the adaptive input current loop is reported active.
+    /// `None` models an adapter that can supply any current.
+    pub fn set_adapter_limit(&self, milliamps: Option<u32>) {
+        let mut state = self.state.borrow_mut();
+        state.adapter_limit_ma = milliamps;
+        state.update();
+    }
+
+    /// Set the system load, which discharges the battery while it is not being charged and
+    /// reduces the charge current while it is.
+    pub fn set_system_load(&self, milliamps: u32) {
+        self.state.borrow_mut().battery.load_ma = milliamps;
+    }
+
+    /// Advance virtual time by `dt`, updating the battery and charge cycle.
+    ///
+    /// The model is integrated in slices of at most a second, so a long step may pass through
+    /// several charge phases; their interrupt flags accumulate until read.
+    pub fn step(&self, dt: Duration) {
+        let mut state = self.state.borrow_mut();
+        let mut remaining = dt.as_millis().min(u64::MAX as u128) as u64;
+        while remaining > 0 {
+            let dt_ms = remaining.min(MAX_STEP_MS);
+            state.step_watchdog(dt_ms);
+            state.step_ship_mode(dt_ms);
+            state.step_battery(dt_ms);
+            state.update();
+            remaining -= dt_ms;
+        }
+    }
+
+    /// Set the thermistor condition.
+    pub fn set_thermistor(&self, thermistor: ThermistorDetails) {
+        let mut state = self.state.borrow_mut();
+        state.thermistor = thermistor;
+        state.update();
+    }
+
+    /// Set the remote battery sense condition.
+    pub fn set_sense(&self, sense: BatterySense) {
+        let mut state = self.state.borrow_mut();
+        state.sense = sense;
+        state.update();
+    }
+
+    /// Read a register without the side effects of an I2C read.
+    pub fn register(&self, reg: u8) -> u8 {
+        self.state.borrow().regs[reg as usize]
+    }
+
+    /// Overwrite a register, bypassing write protection and side effects.
+    pub fn set_register(&self, reg: u8, val: u8) {
+        self.state.borrow_mut().regs[reg as usize] = val;
+    }
+
+    /// The currently programmed charger mode, if valid.
+    pub fn mode(&self) -> Option<Mode> {
+        self.state.borrow().mode()
+    }
+
+    /// Set the delay between a ship mode request and entering ship mode, zero by default.
+    ///
+    /// The request stays latched in `SHIP_MODE_CONTROL` until the delay has passed in
+    /// [`step`](Self::step), so it can be cancelled in the meantime.
+    pub fn set_ship_mode_delay(&self, delay: Duration) {
+        self.state.borrow_mut().ship_delay_ms = delay.as_millis().min(u64::MAX as u128) as u64;
+    }
+
+    /// Whether the charger has entered ship mode.
+    ///
+    /// While in ship mode the charger does not respond on the bus.
+    pub fn in_ship_mode(&self) -> bool {
+        self.state.borrow().ship_mode
+    }
+
+    /// Whether the nIRQ pin is asserted.
+    pub fn irq_asserted(&self) -> bool {
+        self.state.borrow().irq_asserted()
+    }
+
+    /// The time since the watchdog was last kicked, while it is enabled.
+    pub fn watchdog_elapsed(&self) -> Duration {
+        Duration::from_millis(self.state.borrow().watchdog_elapsed_ms)
+    }
+
+    /// The number of writes to protected registers rejected because CHGPROT was locked.
+    pub fn protection_violations(&self) -> u32 {
+        self.state.borrow().protection_violations
+    }
+
+    /// Fail the next `count` transactions with a NAK on the address.
+    ///
+    /// Replaces any NAKs still pending from a previous call.
+    pub fn nak_next(&self, count: u32) {
+        self.state.borrow_mut().naks = count;
+    }
+
+    /// The number of injected NAKs not yet consumed by a transaction.
+    pub fn pending_naks(&self) -> u32 {
+        self.state.borrow().naks
+    }
+
+    /// Flip the bits in `mask` the next time `reg` is read over I2C.
+    ///
+    /// The register itself is not modified. Replaces any corruption still pending.
+    pub fn corrupt_next_read(&self, reg: u8, mask: u8) {
+        self.state.borrow_mut().corrupt_read = Some((reg, mask));
+    }
+
+    /// Whether a read corruption is still waiting to be consumed.
+    pub fn read_corruption_pending(&self) -> bool {
+        self.state.borrow().corrupt_read.is_some()
+    }
+
+    /// Stall the bus: transactions block after their current operation until
+    /// [`release_bus`](Self::release_bus) is called.
+    pub fn freeze_bus(&self) {
+        self.state.borrow_mut().bus_frozen = true;
+    }
+
+    /// Let stalled transactions continue.
+    pub fn release_bus(&self) {
+        let mut state = self.state.borrow_mut();
+        state.bus_frozen = false;
+        if let Some(waker) = state.bus_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Whether a transaction is currently blocked on a frozen bus.
+    pub fn bus_stalled(&self) -> bool {
+        self.state.borrow().bus_stalled
+    }
+
+    /// XOR `mask` into the reported `CHARGER_DETAILS_0..2` bytes until cleared.
+    ///
+    /// This can report conditions the model never produces by itself, including invalid bit
+    /// patterns. Changed fields raise the corresponding interrupt flags as usual.
+    pub fn flip_detail_bits(&self, mask: [u8; 3]) {
+        let mut state = self.state.borrow_mut();
+        state.detail_flips = mask;
+        state.update();
+    }
+
+    /// Stop flipping detail bits, restoring the modelled details.
+    pub fn clear_detail_flips(&self) {
+        self.flip_detail_bits([0; 3]);
+    }
+
+    /// The mask currently XORed into the detail registers.
+    pub fn detail_flips(&self) -> [u8; 3] {
+        self.state.borrow().detail_flips
+    }
+}
+
+/// An I2C device connected to a [`Simulator`]
+pub struct SimulatedCharger<'a> {
+    sim: &'a Simulator,
+}
+
+impl SimulatedCharger<'_> {
+    async fn wait_for_bus(&mut self) {
+        poll_fn(|cx| {
+            let mut state = self.sim.state.borrow_mut();
+            state.bus_stalled = state.bus_frozen;
+            if state.bus_frozen {
+                state.bus_waker = Some(cx.waker().clone());
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+}
+
+impl ErrorType for SimulatedCharger<'_> {
+    type Error = ErrorKind;
+}
+
+impl I2c for SimulatedCharger<'_> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        {
+            let mut state = self.sim.state.borrow_mut();
+            if address != state.address || state.ship_mode {
+                return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+            }
+            if state.naks > 0 {
+                state.naks -= 1;
+                return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+            }
+        }
+        let mut pointer = None;
+        for op in operations {
+            self.sim.state.borrow_mut().execute(&mut pointer, op);
+            self.wait_for_bus().await;
+        }
+        Ok(())
+    }
+}
+
+/// The nIRQ pin of a [`Simulator`]
+///
+/// The pin is low while an unmasked interrupt flag is pending.
+pub struct SimIrqPin<'a> {
+    sim: &'a Simulator,
+}
+
+impl SimIrqPin<'_> {
+    async fn wait_for_level(&mut self, asserted: bool) {
+        poll_fn(|cx| {
+            let mut state = self.sim.state.borrow_mut();
+            if state.irq_asserted() == asserted {
+                Poll::Ready(())
+            } else {
+                state.irq_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl embedded_hal::digital::ErrorType for SimIrqPin<'_> {
+    type Error = Infallible;
+}
+
+impl Wait for SimIrqPin<'_> {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_level(false).await;
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_level(true).await;
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_level(true).await;
+        self.wait_for_level(false).await;
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_level(false).await;
+        self.wait_for_level(true).await;
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        let asserted = self.sim.irq_asserted();
+        self.wait_for_level(!asserted).await;
+        Ok(())
+    }
+}
+
+/// A [`ChargerOps`] implementation keeping its state in plain fields, without a register model
+///
+/// Intended as a stand-in for [`Charger`](crate::Charger) in unit tests of application code,
+/// and as a reference for hand-rolled fakes. The setters round and validate their arguments
+/// like the driver does, so the fields hold the values the charger would report. Set
+/// `fail_next` to fail the next call with a bus error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FakeCharger {
+    /// The part being faked
+    pub variant: Variant,
+    /// The charger mode
+    pub mode: Mode,
+    /// The CHGIN input current limit in mA
+    pub chgin_ilim_ma: u16,
+    /// The fast-charge current in mA
+    pub fast_charge_current_ma: u16,
+    /// The Vsys current limit in mA
+    pub sys_ilim_ma: u16,
+    /// Whether Vsys is recycled after a Vsys current limit shutdown
+    pub sys_ilim_recycle: bool,
+    /// The status bits returned by [`ChargerOps::charger_status`]
+    pub status: ChargerStatus,
+    /// The details returned by [`ChargerOps::charger_details`]
+    pub details: Details,
+    /// The enabled charger interrupts
+    pub charger_irqs: ChargerInterrupts,
+    /// The enabled TOP interrupts
+    pub top_irqs: TopInterrupts,
+    /// The pending interrupt flags, cleared when read
+    pub pending: IrqSummary,
+    /// The error the next call fails with
+    pub fail_next: Option<ErrorKind>,
+}
+
+impl FakeCharger {
+    /// Create a fake `variant` with the reset defaults of the charger and no input attached.
+    pub fn new(variant: Variant) -> Self {
+        FakeCharger {
+            variant,
+            mode: Mode::Charge,
+            chgin_ilim_ma: conv::chgin_ilim_ma(0x09),
+            fast_charge_current_ma: conv::chg_cc_ma(0x0a, variant),
+            sys_ilim_ma: conv::sys_ilim_ma(0),
+            sys_ilim_recycle: false,
+            status: ChargerStatus::new(),
+            details: Details::new().with_chgin(ChgIn::Undervoltage),
+            charger_irqs: ChargerInterrupts::new(),
+            top_irqs: TopInterrupts::new(),
+            pending: IrqSummary {
+                top: TopInterrupts::new(),
+                charger: ChargerInterrupts::new(),
+            },
+            fail_next: None,
+        }
+    }
+
+    /// Take the injected error, if any.
+    fn check(&mut self) -> Result<(), Error<ErrorKind>> {
+        self.fail_next
+            .take()
+            .map_or(Ok(()), |err| Err(Error::I2c(err)))
+    }
+}
+
+impl ChargerOps for FakeCharger {
+    type Error = ErrorKind;
+
+    async fn set_mode(&mut self, mode: Mode) -> Result<(), Error<ErrorKind>> {
+        self.check()?;
+        self.mode = mode;
+        Ok(())
+    }
+
+    async fn mode(&mut self) -> Result<Mode, Error<ErrorKind>> {
+        self.check()?;
+        Ok(self.mode)
+    }
+
+    async fn set_chgin_ilim(&mut self, milliamps: u16) -> Result<(), Error<ErrorKind>> {
+        self.check()?;
+        self.chgin_ilim_ma = conv::chgin_ilim_ma(conv::chgin_ilim_code(milliamps));
+        Ok(())
+    }
+
+    async fn chgin_ilim(&mut self) -> Result<u16, Error<ErrorKind>> {
+        self.check()?;
+        Ok(self.chgin_ilim_ma)
+    }
+
+    async fn set_fast_charge_current(&mut self, milliamps: u16) -> Result<(), Error<ErrorKind>> {
+        self.check()?;
+        let code = conv::chg_cc_code(milliamps, self.variant).ok_or(Error::InvalidValue)?;
+        self.fast_charge_current_ma = conv::chg_cc_ma(code, self.variant);
+        Ok(())
+    }
+
+    async fn fast_charge_current(&mut self) -> Result<u16, Error<ErrorKind>> {
+        self.check()?;
+        Ok(self.fast_charge_current_ma)
+    }
+
+    async fn set_sys_ilim(
+        &mut self,
+        milliamps: u16,
+        recycle_en: bool,
+    ) -> Result<(), Error<ErrorKind>> {
+        self.check()?;
+        if recycle_en && !self.variant.has_sys_ilim_recycle() {
+            return Err(Error::Unsupported);
+        }
+        self.sys_ilim_ma = conv::sys_ilim_ma(conv::sys_ilim_code(milliamps));
+        self.sys_ilim_recycle = recycle_en;
+        Ok(())
+    }
+
+    async fn charger_status(&mut self) -> Result<ChargerStatus, Error<ErrorKind>> {
+        self.check()?;
+        self.pending.charger = ChargerInterrupts::new();
+        Ok(self.status)
+    }
+
+    async fn charger_details(&mut self) -> Result<Details, Error<ErrorKind>> {
+        self.check()?;
+        Ok(self.details)
+    }
+
+    async fn set_charger_irq_mask(
+        &mut self,
+        irqs: ChargerInterrupts,
+    ) -> Result<(), Error<ErrorKind>> {
+        self.check()?;
+        self.charger_irqs = irqs;
+        Ok(())
+    }
+
+    async fn set_top_irq_mask(&mut self, irqs: TopInterrupts) -> Result<(), Error<ErrorKind>> {
+        self.check()?;
+        self.top_irqs = irqs;
+        Ok(())
+    }
+
+    async fn irq_summary(&mut self) -> Result<IrqSummary, Error<ErrorKind>> {
+        self.check()?;
+        let pending = self.pending;
+        self.pending = IrqSummary {
+            top: TopInterrupts::new(),
+            charger: ChargerInterrupts::new(),
+        };
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use crate::testing::{block_on, poll_once, SimDelay};
+    use crate::{ChargeStateClass, Charger, ChargerConfig, ChargerIrqSource, Error};
+
+    const CONFIG: ChargerConfig = ChargerConfig {
+        chgin_ilim_ma: 1500,
+        fast_charge_current_ma: 1000,
+        sys_ilim_ma: 3500,
+        sys_ilim_recycle: true,
+        charger_irqs: ChargerInterrupts::from_bytes([0x40]),
+        mode: Mode::Charge,
+    };
+
+    #[test]
+    fn apply_config_programs_the_registers() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.apply_config(&CONFIG)).unwrap();
+        block_on(charger.verify_config(&CONFIG)).unwrap();
+
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+        assert_eq!(sim.register(Reg::CHARGER_CONFIG_9.address()) & 0x3f, 29);
+        assert_eq!(sim.register(Reg::CHARGER_CONFIG_2.address()) & 0x7f, 20);
+        assert_eq!(sim.register(Reg::CHARGER_CONFIG_5.address()) & 0x1f, 0x12);
+        assert_eq!(sim.register(Reg::CHARGER_INTERRUPT_MASK.address()), 0xbf);
+        // Protection was unlocked for the protected writes and locked again
+        assert_eq!(sim.protection_violations(), 0);
+        assert_eq!(sim.register(Reg::CHARGER_CONFIG_6.address()) & 0x0c, 0);
+    }
+
+    #[test]
+    fn charger_status_clears_the_flags() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_charger_irq_mask(ChargerInterrupts::from_bytes([0xff]))).unwrap();
+        sim.plug_charger();
+        assert!(sim.irq_asserted());
+        assert_ne!(sim.register(Reg::CHARGER_INTERRUPT.address()), 0);
+
+        let status = block_on(charger.charger_status()).unwrap();
+        assert!(status.chgin_ok());
+        assert_eq!(sim.register(Reg::CHARGER_INTERRUPT.address()), 0);
+        assert!(!sim.irq_asserted());
+    }
+
+    #[test]
+    fn protected_writes_need_chgprot_unlocked() {
+        let sim = Simulator::new(Variant::Max77975);
+        let before = sim.register(Reg::CHARGER_CONFIG_2.address());
+        let mut dev = sim.device();
+        block_on(dev.write(
+            DEFAULT_ADDRESS,
+            &[Reg::CHARGER_CONFIG_2.address(), before ^ 0x01],
+        ))
+        .unwrap();
+        assert_eq!(sim.register(Reg::CHARGER_CONFIG_2.address()), before);
+        assert_eq!(sim.protection_violations(), 1);
+
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_fast_charge_current(1000)).unwrap();
+        assert_eq!(sim.register(Reg::CHARGER_CONFIG_2.address()) & 0x7f, 20);
+        assert_eq!(sim.protection_violations(), 1);
+    }
+
+    #[test]
+    fn retry_absorbs_injected_naks() {
+        let sim = Simulator::new(Variant::Max77975);
+        let policy = RetryPolicy {
+            attempts: 3,
+            backoff_us: 100,
+        };
+        let mut charger = Charger::new(sim.device()).with_retry(policy, NoopDelay::new());
+        sim.nak_next(2);
+        assert!(block_on(charger.charger_details()).is_ok());
+        assert_eq!(sim.pending_naks(), 0);
+
+        sim.nak_next(4);
+        assert_eq!(
+            block_on(charger.charger_details()),
+            Err(Error::I2c(ErrorKind::NoAcknowledge(
+                NoAcknowledgeSource::Address
+            )))
+        );
+        assert_eq!(sim.pending_naks(), 1);
+    }
+
+    #[test]
+    fn protected_session_relocks_after_injected_nak() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        let res = block_on(charger.protected(async |session| {
+            assert_ne!(sim.register(Reg::CHARGER_CONFIG_6.address()) & 0x0c, 0);
+            sim.nak_next(1);
+            session.set_fast_charge_current(2000).await
+        }));
+        assert!(matches!(res, Err(Error::I2c(ErrorKind::NoAcknowledge(_)))));
+        assert_eq!(sim.pending_naks(), 0);
+        assert_eq!(sim.register(Reg::CHARGER_CONFIG_6.address()) & 0x0c, 0);
+    }
+
+    #[test]
+    fn corrupted_read_is_consumed_once() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        let mut charger = Charger::new(sim.device());
+        // CHGIN Valid (0b11) reads back as Overvoltage (0b10)
+        sim.corrupt_next_read(Reg::CHARGER_DETAILS_0.address(), 0x20);
+        assert!(sim.read_corruption_pending());
+        assert_eq!(block_on(charger.chgin_status()), Ok(ChgIn::Overvoltage));
+        assert!(!sim.read_corruption_pending());
+        assert_eq!(block_on(charger.chgin_status()), Ok(ChgIn::Valid));
+    }
+
+    #[test]
+    fn frozen_bus_stalls_until_released() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        sim.freeze_bus();
+        let mut read = core::pin::pin!(charger.charger_details());
+        assert!(poll_once(read.as_mut()).is_pending());
+        assert!(sim.bus_stalled());
+        assert!(poll_once(read.as_mut()).is_pending());
+        sim.release_bus();
+        assert!(matches!(poll_once(read.as_mut()), Poll::Ready(Ok(_))));
+        assert!(!sim.bus_stalled());
+    }
+
+    #[test]
+    fn flipped_detail_bits_raise_interrupts() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_charger_irq_mask(ChargerInterrupts::from_bytes([0xff]))).unwrap();
+        sim.plug_charger();
+        block_on(charger.charger_irq_flags()).unwrap();
+
+        // Report a battery timer fault: RegularVoltage (3) XOR 1 is TimerFault (2)
+        sim.flip_detail_bits([0x00, 0x10, 0x00]);
+        assert!(sim.irq_asserted());
+        let details = block_on(charger.charger_details()).unwrap();
+        assert_eq!(details.battery(), BatteryDetails::TimerFault);
+        assert!(block_on(charger.charger_irq_flags()).unwrap().battery());
+
+        sim.clear_detail_flips();
+        assert_eq!(sim.detail_flips(), [0; 3]);
+        let details = block_on(charger.charger_details()).unwrap();
+        assert_eq!(details.battery(), BatteryDetails::RegularVoltage);
+    }
+
+    #[test]
+    fn charge_profile_runs_to_done() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_capacity(1000);
+        sim.set_battery_voltage(3500);
+        let mut charger = Charger::new(sim.device());
+        let profile = crate::charge_profile!(Variant::Max77975, 1000, 4200, 150, 10);
+        block_on(charger.apply_charge_profile(&profile)).unwrap();
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        sim.plug_charger();
+
+        let mut states = Vec::new();
+        for _ in 0..24 * 60 {
+            let state = block_on(charger.charger_details()).unwrap().charger();
+            if states.last() != Some(&state) {
+                states.push(state);
+            }
+            if state == ChargerDetails::Done {
+                break;
+            }
+            sim.step(Duration::from_secs(60));
+        }
+        assert_eq!(
+            states,
+            [
+                ChargerDetails::ConstantCurrent,
+                ChargerDetails::ConstantVoltage,
+                ChargerDetails::TopOff,
+                ChargerDetails::Done,
+            ]
+        );
+        assert!(sim.battery_voltage() >= 4150);
+    }
+
+    /// A simulator charging a half-full battery in timer fault
+    fn timer_fault() -> Simulator {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(3800);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        sim.plug_charger();
+        sim.trigger_timer_fault();
+        sim
+    }
+
+    #[test]
+    fn clear_timer_fault_restarts_charging() {
+        let sim = timer_fault();
+        let mut charger = Charger::new(sim.device());
+        let details = block_on(charger.charger_details()).unwrap();
+        assert_eq!(details.charger(), ChargerDetails::TimerFault);
+        assert_eq!(details.battery(), BatteryDetails::TimerFault);
+
+        block_on(charger.clear_timer_fault_and_restart(SimDelay(&sim), 10)).unwrap();
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+        let state = block_on(charger.charger_state()).unwrap();
+        assert_eq!(state.classify(), ChargeStateClass::Charging);
+    }
+
+    #[test]
+    fn clear_timer_fault_requires_a_fault() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(3800);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        sim.plug_charger();
+
+        let res = block_on(charger.clear_timer_fault_and_restart(SimDelay(&sim), 10));
+        assert_eq!(
+            res,
+            Err(Error::UnexpectedState(ChargerDetails::ConstantCurrent))
+        );
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    #[test]
+    fn clear_timer_fault_fails_if_the_fault_persists() {
+        let sim = timer_fault();
+        let mut charger = Charger::new(sim.device());
+        // The mode toggle restarts the model in constant current; flip the reported state back
+        // to a timer fault (1 ^ 7 = 6), as a part refusing to restart would report
+        sim.flip_detail_bits([0, 0x07, 0]);
+        let res = block_on(charger.clear_timer_fault_and_restart(SimDelay(&sim), 10));
+        assert_eq!(res, Err(Error::UnexpectedState(ChargerDetails::TimerFault)));
+    }
+
+    #[test]
+    fn force_recharge_restarts_a_finished_cycle() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(4200);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        sim.plug_charger();
+        sim.step(Duration::from_secs(60 * 60));
+        assert_eq!(
+            block_on(charger.charger_state()).unwrap(),
+            ChargerDetails::Done
+        );
+
+        block_on(charger.force_recharge(SimDelay(&sim), 10)).unwrap();
+        let state = block_on(charger.charger_state()).unwrap();
+        assert_eq!(state.classify(), ChargeStateClass::Charging);
+    }
+
+    #[test]
+    fn force_recharge_gives_up_without_an_input() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+
+        let res = block_on(charger.force_recharge(SimDelay(&sim), 10));
+        assert_eq!(res, Err(Error::UnexpectedState(ChargerDetails::Off)));
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    /// A simulator charging with an expired watchdog
+    fn watchdog_expired() -> Simulator {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(3800);
+        let mut charger = Charger::new(sim.device());
+        block_on(
+            charger.set_charger_config0(
+                ChargerConfig0::new()
+                    .with_mode(Mode::Charge)
+                    .with_watchdog_enable(true),
+            ),
+        )
+        .unwrap();
+        sim.plug_charger();
+        sim.step(Duration::from_millis(WATCHDOG_TIMEOUT_MS.into()));
+        sim
+    }
+
+    #[test]
+    fn recover_from_watchdog_resumes_charging() {
+        let sim = watchdog_expired();
+        let mut charger = Charger::new(sim.device());
+        assert_eq!(
+            block_on(charger.charger_state()).unwrap(),
+            ChargerDetails::WatchdogTimer
+        );
+
+        let state =
+            block_on(charger.recover_from_watchdog(Mode::Charge, SimDelay(&sim), 10)).unwrap();
+        assert_eq!(state, ChargerDetails::ConstantCurrent);
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+        assert!(sim.watchdog_elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn recover_from_watchdog_requires_an_expired_watchdog() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        let res = block_on(charger.recover_from_watchdog(Mode::Charge, SimDelay(&sim), 10));
+        assert_eq!(res, Err(Error::UnexpectedState(ChargerDetails::Off)));
+    }
+
+    #[test]
+    fn recover_from_watchdog_fails_if_the_fault_persists() {
+        let sim = watchdog_expired();
+        let mut charger = Charger::new(sim.device());
+        // Polling no faster than the watchdog timeout lets it expire again before every poll
+        let res = block_on(charger.recover_from_watchdog(
+            Mode::Charge,
+            SimDelay(&sim),
+            WATCHDOG_TIMEOUT_MS,
+        ));
+        assert_eq!(
+            res,
+            Err(Error::UnexpectedState(ChargerDetails::WatchdogTimer))
+        );
+    }
+
+    /// A delay stepping the simulator by `stall_ms`, or by the requested time if `None`, that
+    /// never completes once `calls_left` delays have passed
+    struct TaskDelay<'a> {
+        sim: &'a Simulator,
+        stall_ms: Option<u32>,
+        calls_left: usize,
+    }
+
+    impl embedded_hal_async::delay::DelayNs for TaskDelay<'_> {
+        async fn delay_ns(&mut self, ns: u32) {
+            let Some(calls_left) = self.calls_left.checked_sub(1) else {
+                return core::future::pending().await;
+            };
+            self.calls_left = calls_left;
+            self.sim.step(match self.stall_ms {
+                Some(ms) => Duration::from_millis(ms.into()),
+                None => Duration::from_nanos(ns.into()),
+            });
+        }
+    }
+
+    /// A simulator charging with the watchdog enabled
+    fn watchdog_enabled() -> Simulator {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(3800);
+        let mut charger = Charger::new(sim.device());
+        block_on(
+            charger.set_charger_config0(
+                ChargerConfig0::new()
+                    .with_mode(Mode::Charge)
+                    .with_watchdog_enable(true),
+            ),
+        )
+        .unwrap();
+        sim.plug_charger();
+        sim
+    }
+
+    #[test]
+    fn watchdog_task_keeps_the_watchdog_alive() {
+        let sim = watchdog_enabled();
+        let mut charger = Charger::new(sim.device());
+        let delay = TaskDelay {
+            sim: &sim,
+            stall_ms: None,
+            calls_left: 10,
+        };
+        {
+            // Ten periods take five watchdog timeouts
+            let task = core::pin::pin!(charger.watchdog_task(delay, WATCHDOG_TIMEOUT_MS / 2));
+            assert!(poll_once(task).is_pending());
+        }
+
+        assert!(sim.watchdog_elapsed() < Duration::from_millis(WATCHDOG_TIMEOUT_MS.into()));
+        assert_eq!(
+            block_on(charger.charger_state()).unwrap(),
+            ChargerDetails::ConstantCurrent
+        );
+    }
+
+    #[test]
+    fn watchdog_task_reports_starvation() {
+        let sim = watchdog_enabled();
+        let mut charger = Charger::new(sim.device());
+        // Each 1 s period actually takes a whole watchdog timeout
+        let delay = TaskDelay {
+            sim: &sim,
+            stall_ms: Some(WATCHDOG_TIMEOUT_MS),
+            calls_left: 1,
+        };
+        let res = block_on(charger.watchdog_task(delay, 1000));
+        assert_eq!(
+            res,
+            Err(Error::UnexpectedState(ChargerDetails::WatchdogTimer))
+        );
+    }
+
+    #[test]
+    fn watchdog_task_rejects_bad_periods() {
+        let sim = watchdog_enabled();
+        let mut charger = Charger::new(sim.device());
+        for period_ms in [0, WATCHDOG_TIMEOUT_MS / 2 + 1, u32::MAX] {
+            let res = block_on(charger.watchdog_task(SimDelay(&sim), period_ms));
+            assert_eq!(res, Err(Error::InvalidValue), "{period_ms}");
+        }
+        // Nothing was kicked
+        sim.step(Duration::from_millis(WATCHDOG_TIMEOUT_MS.into()));
+        assert_eq!(
+            block_on(charger.charger_state()).unwrap(),
+            ChargerDetails::WatchdogTimer
+        );
+    }
+
+    /// A charger on battery, in charge mode waiting for an input.
+    fn on_battery(sim: &Simulator) -> Charger<SimulatedCharger<'_>> {
+        sim.set_battery_voltage(3800);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        charger
+    }
+
+    #[test]
+    fn boost_starts_and_restores_the_mode() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        block_on(charger.enable_boost_settled(5000, SimDelay(&sim), 100)).unwrap();
+        assert_eq!(sim.mode(), Some(Mode::Boost));
+        let details = block_on(charger.charger_details()).unwrap();
+        assert!(details.bypass().boost_on());
+
+        block_on(charger.disable_boost()).unwrap();
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    #[test]
+    fn boost_refuses_a_valid_input() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        sim.plug_charger();
+        let res = block_on(charger.enable_boost_settled(5000, SimDelay(&sim), 100));
+        assert_eq!(res, Err(Error::InputPresent));
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    #[test]
+    fn boost_that_does_not_start_restores_the_mode() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        // A converter parked in standby never switches, as with a battery too low to boost
+        block_on(charger.set_standby(true)).unwrap();
+        let res = block_on(charger.enable_boost_settled(5000, SimDelay(&sim), 100));
+        assert!(matches!(res, Err(Error::ModeNotSettled(details)) if !details.bypass().boost_on()));
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    #[test]
+    fn disabling_the_other_bypass_mode_fails() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        block_on(charger.enable_otg(1500, 5000)).unwrap();
+        let res = block_on(charger.disable_boost());
+        assert_eq!(res, Err(Error::BypassModeMismatch(Mode::Otg)));
+        assert_eq!(sim.mode(), Some(Mode::Otg));
+        block_on(charger.disable_otg()).unwrap();
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+
+        block_on(charger.enable_boost(5000)).unwrap();
+        let res = block_on(charger.disable_otg());
+        assert_eq!(res, Err(Error::BypassModeMismatch(Mode::Boost)));
+        assert_eq!(sim.mode(), Some(Mode::Boost));
+        block_on(charger.disable_boost()).unwrap();
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    #[test]
+    fn switching_bypass_modes_keeps_the_mode_to_restore() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        block_on(charger.enable_otg(1500, 5000)).unwrap();
+        block_on(charger.enable_boost(5000)).unwrap();
+        assert_eq!(sim.mode(), Some(Mode::Boost));
+        assert_eq!(
+            block_on(charger.disable_otg()),
+            Err(Error::BypassModeMismatch(Mode::Boost))
+        );
+        block_on(charger.disable_boost()).unwrap();
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    #[test]
+    fn disabled_qbatt_suspends_charging() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        sim.plug_charger();
+        block_on(charger.set_qbatt(false)).unwrap();
+        assert_eq!(block_on(charger.qbatt_enabled()), Ok(false));
+        let details = block_on(charger.charger_details()).unwrap();
+        assert_eq!(details.charger(), ChargerDetails::QBattDisabled);
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+
+        block_on(charger.set_qbatt(true)).unwrap();
+        let state = block_on(charger.charger_state()).unwrap();
+        assert_eq!(state.classify(), ChargeStateClass::Charging);
+    }
+
+    #[test]
+    fn wait_for_event_waits_for_a_new_event() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        block_on(
+            charger
+                .set_charger_irq_mask(ChargerInterrupts::from_sources(&[ChargerIrqSource::Chgin])),
+        )
+        .unwrap();
+        sim.plug_charger();
+        // The flags latched by the first insertion are cleared and not reported again
+        block_on(charger.irq_summary()).unwrap();
+        assert!(!sim.irq_asserted());
+
+        let mut pin = sim.irq_pin();
+        let mut wait = core::pin::pin!(charger.wait_for_event(&mut pin));
+        assert!(poll_once(wait.as_mut()).is_pending());
+        sim.unplug_charger();
+        let Poll::Ready(Ok(event)) = poll_once(wait.as_mut()) else {
+            panic!("event not reported");
+        };
+        assert!(event.charger.chgin());
+        assert_eq!(event.details.chgin(), ChgIn::Undervoltage);
+        assert!(!sim.irq_asserted());
+    }
+
+    #[test]
+    fn wait_for_event_returns_at_once_if_asserted() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        block_on(
+            charger
+                .set_charger_irq_mask(ChargerInterrupts::from_sources(&[ChargerIrqSource::Chgin])),
+        )
+        .unwrap();
+        sim.plug_charger();
+        let event = block_on(charger.wait_for_event(&mut sim.irq_pin())).unwrap();
+        assert!(event.charger.chgin());
+        assert_eq!(event.details.chgin(), ChgIn::Valid);
+    }
+
+    #[test]
+    fn suspend_records_the_mode_and_resume_restores_it() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        sim.plug_charger();
+        block_on(charger.suspend_charging()).unwrap();
+        assert_eq!(charger.suspended_mode, Some(Mode::Charge));
+        assert_eq!(sim.mode(), Some(Mode::Buck));
+        // Suspending again keeps the original mode
+        block_on(charger.suspend_charging()).unwrap();
+        assert_eq!(charger.suspended_mode, Some(Mode::Charge));
+
+        block_on(charger.resume_charging()).unwrap();
+        assert_eq!(charger.suspended_mode, None);
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    #[test]
+    fn resume_without_suspend_does_nothing() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        block_on(charger.set_mode(Mode::Buck)).unwrap();
+        block_on(charger.resume_charging()).unwrap();
+        assert_eq!(sim.mode(), Some(Mode::Buck));
+    }
+
+    #[test]
+    fn resume_needs_an_input() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = on_battery(&sim);
+        sim.plug_charger();
+        block_on(charger.suspend_charging()).unwrap();
+        sim.unplug_charger();
+        assert_eq!(block_on(charger.resume_charging()), Err(Error::NoInput));
+        assert_eq!(charger.suspended_mode, Some(Mode::Charge));
+        assert_eq!(sim.mode(), Some(Mode::Buck));
+
+        sim.plug_charger();
+        block_on(charger.resume_charging()).unwrap();
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+}
@@ -0,0 +1,240 @@
+//! Separate control and monitor handles for one charger.
+//!
+//! [`Charger::split`] divides a driver into a [`ChargerControl`], which keeps the full API, and a
+//! [`ChargerMonitor`], which can only read status and configuration. Each half owns its own
+//! device handle on the shared bus, so a UI task can poll the monitor without waiting for the
+//! task that owns charging policy:
+//!
+//! ```no_run
+//! # use embedded_hal_async::i2c::I2c;
+//! # use max7797x_driver::Charger;
+//! # fn example<D: I2c>(control_dev: D, monitor_dev: D) {
+//! // Two devices on the same bus, e.g. `embedded-hal-bus` `RefCellDevice`s
+//! let charger = Charger::new(control_dev);
+//! let (control, monitor) = charger.split(monitor_dev);
+//! # }
+//! ```
+//!
+//! The halves do not coordinate. The monitor can observe the charger in the middle of a
+//! multi-register update by the control half, e.g. between the CHGPROT unlock and the protected
+//! write, or between the bursts of [`Charger::apply_config`]. The monitor never clears the
+//! interrupt flags, so reading the flags stays with the control half.
+
+use core::ops::{Deref, DerefMut};
+
+use embedded_hal_async::i2c::{ErrorType, I2c};
+
+use crate::{
+    BatterySense, BatteryStatus, BypassNodeDetails, Charger, ChargerConfig0, ChargerDetails,
+    ChargerStatus, ChgIn, Details, Error, Mode, ReadVoting, RegisterSpec, ThermistorDetails,
+    Variant,
+};
+
+/// The control half of a split [`Charger`], see [`Charger::split`]
+///
+/// Dereferences to the [`Charger`], so every method remains available.
+pub struct ChargerControl<D: ErrorType> {
+    charger: Charger<D>,
+}
+
+/// The read-only half of a split [`Charger`], see [`Charger::split`]
+pub struct ChargerMonitor<D: ErrorType> {
+    charger: Charger<D>,
+}
+
+impl<D: I2c> Charger<D> {
+    /// Split the driver into a control and a monitor half.
+    ///
+    /// `monitor_dev` must address the same bus as the device the driver was created with,
+    /// e.g. a second `embedded-hal-bus` or embassy `I2cDevice` on a shared bus. The monitor uses
+    /// the address, variant and [read voting](Charger::with_read_voting) of this driver. It does
+    /// not share the shadow cache and does not verify writes, as it never writes.
+    pub fn split<D2: I2c>(self, monitor_dev: D2) -> (ChargerControl<D>, ChargerMonitor<D2>) {
+        let mut monitor =
+            Charger::new_with_address(monitor_dev, self.address).with_read_voting(self.read_voting);
+        monitor.variant = self.variant;
+        (
+            ChargerControl { charger: self },
+            ChargerMonitor { charger: monitor },
+        )
+    }
+}
+
+impl<D: ErrorType> ChargerControl<D> {
+    /// Rejoin the halves, returning the driver and the device of the monitor.
+    pub fn unsplit<D2: ErrorType>(self, monitor: ChargerMonitor<D2>) -> (Charger<D>, D2) {
+        (self.charger, monitor.charger.i2c_dev)
+    }
+}
+
+impl<D: ErrorType> Deref for ChargerControl<D> {
+    type Target = Charger<D>;
+
+    fn deref(&self) -> &Charger<D> {
+        &self.charger
+    }
+}
+
+impl<D: ErrorType> DerefMut for ChargerControl<D> {
+    fn deref_mut(&mut self) -> &mut Charger<D> {
+        &mut self.charger
+    }
+}
+
+impl<D: I2c> ChargerMonitor<D> {
+    /// The part the driver is configured for.
+    pub fn variant(&self) -> Variant {
+        self.charger.variant
+    }
+
+    /// The read voting policy
+    pub fn read_voting(&self) -> ReadVoting {
+        self.charger.read_voting
+    }
+
+    /// Read a typed register.
+    ///
+    /// See [`Charger::read`].
+    pub async fn read<R: RegisterSpec>(&mut self) -> Result<R, Error<D::Error>> {
+        self.charger.read().await
+    }
+
+    /// Returns the current status bits without clearing the charger interrupt flags.
+    ///
+    /// See [`Charger::charger_status_peek`].
+    pub async fn charger_status(&mut self) -> Result<ChargerStatus, Error<D::Error>> {
+        self.charger.charger_status_peek().await
+    }
+
+    /// Get the detailed status of the charger.
+    ///
+    /// See [`Charger::charger_details`].
+    pub async fn charger_details(&mut self) -> Result<Details, Error<D::Error>> {
+        self.charger.charger_details().await
+    }
+
+    /// Get the CHGIN input status.
+    ///
+    /// See [`Charger::chgin_status`].
+    pub async fn chgin_status(&mut self) -> Result<ChgIn, Error<D::Error>> {
+        self.charger.chgin_status().await
+    }
+
+    /// Get the remote battery sense status.
+    ///
+    /// See [`Charger::battery_sense`].
+    pub async fn battery_sense(&mut self) -> Result<BatterySense, Error<D::Error>> {
+        self.charger.battery_sense().await
+    }
+
+    /// Get the battery status and temperature regulation state.
+    ///
+    /// See [`Charger::battery_status`].
+    pub async fn battery_status(&mut self) -> Result<BatteryStatus, Error<D::Error>> {
+        self.charger.battery_status().await
+    }
+
+    /// Get the charger state.
+    ///
+    /// See [`Charger::charger_state`].
+    pub async fn charger_state(&mut self) -> Result<ChargerDetails, Error<D::Error>> {
+        self.charger.charger_state().await
+    }
+
+    /// Get the thermistor status.
+    ///
+    /// See [`Charger::thermistor_status`].
+    pub async fn thermistor_status(&mut self) -> Result<ThermistorDetails, Error<D::Error>> {
+        self.charger.thermistor_status().await
+    }
+
+    /// Get the bypass node status.
+    ///
+    /// See [`Charger::bypass_status`].
+    pub async fn bypass_status(&mut self) -> Result<BypassNodeDetails, Error<D::Error>> {
+        self.charger.bypass_status().await
+    }
+
+    /// Read the [`ChargerConfig0`] register.
+    pub async fn charger_config0(&mut self) -> Result<ChargerConfig0, Error<D::Error>> {
+        self.charger.charger_config0().await
+    }
+
+    /// Read the charger [`Mode`].
+    ///
+    /// See [`Charger::mode`].
+    pub async fn mode(&mut self) -> Result<Mode, Error<D::Error>> {
+        self.charger.mode().await
+    }
+
+    /// Returns the current limit for CHGIN in mA.
+    pub async fn chgin_ilim(&mut self) -> Result<u16, Error<D::Error>> {
+        self.charger.chgin_ilim().await
+    }
+
+    /// Returns the programmed fast-charge current in mA.
+    pub async fn fast_charge_current(&mut self) -> Result<u16, Error<D::Error>> {
+        self.charger.fast_charge_current().await
+    }
+
+    /// Returns the junction temperature regulation threshold (REGTEMP) in °C.
+    pub async fn regulation_temperature(&mut self) -> Result<u8, Error<D::Error>> {
+        self.charger.regulation_temperature().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+
+    use super::*;
+    use crate::testing::{block_on, read, read_burst, write};
+    use crate::{Reg, DEFAULT_ADDRESS};
+
+    #[test]
+    fn halves_use_their_own_devices() {
+        let control_dev = Mock::new(&[
+            read(Reg::CHARGER_CONFIG_0, 0x04),
+            write(Reg::CHARGER_CONFIG_0, 0x05),
+        ]);
+        let monitor_dev = Mock::new(&[
+            read(Reg::CHARGER_CONFIG_0, 0x05),
+            read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x01, 0x00]),
+        ]);
+        let (mut control, mut monitor) = Charger::new(control_dev).split(monitor_dev);
+
+        block_on(control.set_mode(Mode::Charge)).unwrap();
+        assert_eq!(block_on(monitor.mode()), Ok(Mode::Charge));
+        let details = block_on(monitor.charger_details()).unwrap();
+        assert_eq!(details.charger(), ChargerDetails::ConstantCurrent);
+
+        let (charger, mut monitor_dev) = control.unsplit(monitor);
+        charger.release().done();
+        monitor_dev.done();
+    }
+
+    #[test]
+    fn monitor_inherits_the_address_and_variant() {
+        let address = DEFAULT_ADDRESS.wrapping_add(1);
+        let control_dev = Mock::new(&[]);
+        // CHG_CC code 20 is 1000 mA on the MAX77976 but 500 mA on the MAX77985, read twice to
+        // vote
+        let chg_cc =
+            Transaction::write_read(address, vec![Reg::CHARGER_CONFIG_2.address()], vec![20]);
+        let monitor_dev = Mock::new(&[chg_cc.clone(), chg_cc]);
+        let charger =
+            Charger::new_with_address_and_variant(control_dev, address, Variant::Max77985)
+                .with_read_voting(ReadVoting::Double);
+        let (control, mut monitor) = charger.split(monitor_dev);
+
+        assert_eq!(monitor.variant(), Variant::Max77985);
+        assert_eq!(monitor.read_voting(), ReadVoting::Double);
+        assert_eq!(block_on(monitor.fast_charge_current()), Ok(500));
+
+        let (charger, mut monitor_dev) = control.unsplit(monitor);
+        charger.release().done();
+        monitor_dev.done();
+    }
+}
@@ -0,0 +1,278 @@
+//! Cumulative charge statistics for analytics.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{
+    BatteryDetails, ChargeStateClass, Charger, ChargerDetails, ChgIn, Details, Error, FaultKind,
+};
+
+/// Counters accumulated by [`Charger::update_stats`]
+///
+/// The counters are plain data owned by the application, so they can be persisted and
+/// restored, e.g. with `postcard` and the `serde` feature. Each counter counts the polls at
+/// which a condition started, so a condition lasting several polls is counted once. The first
+/// poll after creating or restoring the counters only records the current state; the last
+/// sample is not serialized, so timestamps may restart after a reboot.
+///
+/// Only `CHARGER_DETAILS_0` through `CHARGER_DETAILS_2` are read. The TOP faults
+/// ([`FaultKind::ThermalShutdown`], [`FaultKind::SysOvervoltage`] and
+/// [`FaultKind::SysUndervoltage`]) are only reported by interrupt flags and are not counted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChargeStats {
+    /// The charge cycles started, i.e. entries into a charging state
+    pub cycles_started: u32,
+    /// The charge cycles completed, i.e. entries into [`ChargerDetails::Done`]
+    pub cycles_completed: u32,
+    /// The valid inputs attached to CHGIN
+    pub input_insertions: u32,
+    /// The fast-charge timer faults ([`FaultKind::Timer`])
+    pub timer_faults: u32,
+    /// The watchdog faults ([`FaultKind::Watchdog`])
+    pub watchdog_faults: u32,
+    /// The junction overtemperature faults ([`FaultKind::OverTemperature`])
+    pub over_temperature_faults: u32,
+    /// The battery removals detected on the THM pin ([`FaultKind::ThermistorRemoved`])
+    pub thermistor_removals: u32,
+    /// The battery overvoltage faults ([`FaultKind::BatteryOvervoltage`])
+    pub battery_overvoltage_faults: u32,
+    /// The CHGIN overvoltage faults ([`FaultKind::InputOvervoltage`])
+    pub input_overvoltage_faults: u32,
+    /// The time spent in a charging state in ms
+    ///
+    /// The time between two polls is counted if the charger was charging at the first.
+    pub charging_ms: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    last: Option<(Details, u64)>,
+}
+
+impl ChargeStats {
+    /// Counters starting from zero.
+    pub const fn new() -> Self {
+        ChargeStats {
+            cycles_started: 0,
+            cycles_completed: 0,
+            input_insertions: 0,
+            timer_faults: 0,
+            watchdog_faults: 0,
+            over_temperature_faults: 0,
+            thermistor_removals: 0,
+            battery_overvoltage_faults: 0,
+            input_overvoltage_faults: 0,
+            charging_ms: 0,
+            last: None,
+        }
+    }
+
+    /// The count of faults of `kind`, zero for the faults that are not counted
+    pub fn faults(&self, kind: FaultKind) -> u32 {
+        match kind {
+            FaultKind::Timer => self.timer_faults,
+            FaultKind::Watchdog => self.watchdog_faults,
+            FaultKind::OverTemperature => self.over_temperature_faults,
+            FaultKind::ThermistorRemoved => self.thermistor_removals,
+            FaultKind::BatteryOvervoltage => self.battery_overvoltage_faults,
+            FaultKind::InputOvervoltage => self.input_overvoltage_faults,
+            FaultKind::ThermalShutdown | FaultKind::SysOvervoltage | FaultKind::SysUndervoltage => {
+                0
+            }
+        }
+    }
+
+    /// Update the counters from `details` sampled at `now_ms`.
+    ///
+    /// A timestamp earlier than the previous one counts no charging time.
+    pub fn update(&mut self, details: Details, now_ms: u64) {
+        let Some((last, last_ms)) = self.last.replace((details, now_ms)) else {
+            return;
+        };
+        let charging =
+            |details: Details| details.charger().classify() == ChargeStateClass::Charging;
+        let entered = |state: fn(Details) -> bool| state(details) && !state(last);
+
+        if charging(last) {
+            self.charging_ms = self
+                .charging_ms
+                .saturating_add(now_ms.saturating_sub(last_ms));
+        }
+        bump(&mut self.cycles_started, entered(charging));
+        bump(
+            &mut self.cycles_completed,
+            entered(|d| d.charger() == ChargerDetails::Done),
+        );
+        bump(
+            &mut self.input_insertions,
+            entered(|d| d.chgin() == ChgIn::Valid),
+        );
+        bump(
+            &mut self.timer_faults,
+            entered(|d| d.charger() == ChargerDetails::TimerFault),
+        );
+        bump(
+            &mut self.watchdog_faults,
+            entered(|d| d.charger() == ChargerDetails::WatchdogTimer),
+        );
+        bump(
+            &mut self.over_temperature_faults,
+            entered(|d| d.charger() == ChargerDetails::HighTemperature),
+        );
+        bump(
+            &mut self.thermistor_removals,
+            entered(|d| d.charger() == ChargerDetails::ThermistorRemoval),
+        );
+        bump(
+            &mut self.battery_overvoltage_faults,
+            entered(|d| d.battery() == BatteryDetails::Overvoltage),
+        );
+        bump(
+            &mut self.input_overvoltage_faults,
+            entered(|d| d.chgin() == ChgIn::Overvoltage),
+        );
+    }
+}
+
+fn bump(counter: &mut u32, entered: bool) {
+    if entered {
+        *counter = counter.saturating_add(1);
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Sample the charger details at `now_ms` and update `stats`, see [`ChargeStats`].
+    ///
+    /// The details are read in one transaction; pending interrupt flags are left alone.
+    pub async fn update_stats(
+        &mut self,
+        stats: &mut ChargeStats,
+        now_ms: u64,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "update_stats", {
+            let details = self.charger_details().await?;
+            stats.update(details, now_ms);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read_burst, run};
+    use crate::Reg;
+
+    fn details(chgin: ChgIn, charger: ChargerDetails) -> Details {
+        Details::new()
+            .with_chgin(chgin)
+            .with_charger(charger)
+            .with_battery(BatteryDetails::RegularVoltage)
+    }
+
+    /// Feed `script` to fresh counters, one sample per second.
+    fn replay(script: &[Details]) -> ChargeStats {
+        let mut stats = ChargeStats::new();
+        for (sample, &details) in (0u64..).zip(script) {
+            stats.update(details, sample.saturating_mul(1000));
+        }
+        stats
+    }
+
+    #[test]
+    fn first_sample_only_records_the_state() {
+        let stats = replay(&[details(ChgIn::Valid, ChargerDetails::ConstantCurrent)]);
+        assert_eq!(
+            ChargeStats {
+                last: None,
+                ..stats
+            },
+            ChargeStats::new()
+        );
+    }
+
+    #[test]
+    fn scripted_cycle_updates_every_counter_once() {
+        let off = details(ChgIn::Undervoltage, ChargerDetails::Off);
+        let cc = details(ChgIn::Valid, ChargerDetails::ConstantCurrent);
+        let cv = details(ChgIn::Valid, ChargerDetails::ConstantVoltage);
+        let done = details(ChgIn::Valid, ChargerDetails::Done);
+        let stats = replay(&[
+            off,
+            cc,
+            cc,
+            cv,
+            done,
+            done,
+            off,
+            details(ChgIn::Overvoltage, ChargerDetails::Off),
+            details(ChgIn::Overvoltage, ChargerDetails::Off),
+            cc,
+            details(ChgIn::Valid, ChargerDetails::TimerFault),
+            details(ChgIn::Valid, ChargerDetails::WatchdogTimer),
+            details(ChgIn::Valid, ChargerDetails::HighTemperature),
+            details(ChgIn::Valid, ChargerDetails::ThermistorRemoval),
+            cc.with_battery(BatteryDetails::Overvoltage),
+            cc.with_battery(BatteryDetails::Overvoltage),
+            off,
+        ]);
+        assert_eq!(stats.cycles_started, 3);
+        assert_eq!(stats.cycles_completed, 1);
+        assert_eq!(stats.input_insertions, 2);
+        assert_eq!(stats.timer_faults, 1);
+        assert_eq!(stats.watchdog_faults, 1);
+        assert_eq!(stats.over_temperature_faults, 1);
+        assert_eq!(stats.thermistor_removals, 1);
+        assert_eq!(stats.battery_overvoltage_faults, 1);
+        assert_eq!(stats.input_overvoltage_faults, 1);
+        // Charging from samples 1 to 4, 9 to 10 and 14 to 16
+        assert_eq!(stats.charging_ms, 3000 + 1000 + 2000);
+        assert_eq!(stats.faults(FaultKind::Timer), 1);
+        assert_eq!(stats.faults(FaultKind::InputOvervoltage), 1);
+        assert_eq!(stats.faults(FaultKind::ThermalShutdown), 0);
+    }
+
+    #[test]
+    fn time_running_backwards_counts_nothing() {
+        let cc = details(ChgIn::Valid, ChargerDetails::ConstantCurrent);
+        let mut stats = ChargeStats::new();
+        stats.update(cc, 5000);
+        stats.update(cc, 1000);
+        assert_eq!(stats.charging_ms, 0);
+        stats.update(cc, 1500);
+        assert_eq!(stats.charging_ms, 500);
+    }
+
+    #[test]
+    fn update_stats_reads_the_details_once() {
+        let mut stats = ChargeStats::new();
+        let res = run(
+            &[
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x00, 0x38, 0x00]),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x31, 0x00]),
+            ],
+            async |charger| {
+                charger.update_stats(&mut stats, 0).await?;
+                charger.update_stats(&mut stats, 1000).await
+            },
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(stats.input_insertions, 1);
+        assert_eq!(stats.cycles_started, 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn counters_persist_without_the_last_sample() {
+        let cc = details(ChgIn::Valid, ChargerDetails::ConstantCurrent);
+        let stats = replay(&[details(ChgIn::Undervoltage, ChargerDetails::Off), cc, cc]);
+        let mut buf = [0; 64];
+        let bytes = postcard::to_slice(&stats, &mut buf).unwrap();
+        let restored: ChargeStats = postcard::from_bytes(bytes).unwrap();
+        assert_eq!(
+            restored,
+            ChargeStats {
+                last: None,
+                ..stats
+            }
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! A charging supervisor implementing the common charging policy.
+//!
+//! [`run`] applies a [`ChargerConfig`], enables charging while a valid input is present, recovers
+//! from timer and watchdog faults and reports every change of [`State`] to a callback.
+
+use core::convert::Infallible;
+
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::I2c;
+
+use crate::{Charger, ChargerConfig, ChargerDetails, ChgIn, Details, Error, FaultKind, Mode};
+
+/// The charging policy applied by [`run`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Policy {
+    /// The configuration to apply. Its mode is used while a valid input is present.
+    pub config: ChargerConfig,
+    /// The mode to use while no valid input is present
+    pub idle_mode: Mode,
+    /// How many times to restart charging after a timer or watchdog fault before leaving the
+    /// charger in the fault state. The count is reset once charging progresses normally.
+    pub max_fault_retries: u8,
+}
+
+/// The charging state reported by the supervisor
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum State {
+    /// No valid input is present
+    NoInput,
+    /// A valid input is present but the charger is off
+    Idle,
+    /// The battery is charging in the given phase
+    Charging(ChargerDetails),
+    /// Charging completed
+    Done,
+    /// Charging is suspended for the given reason
+    Suspended(ChargerDetails),
+    /// The charger reported a fault
+    Fault(FaultKind),
+}
+
+impl State {
+    /// Derive the supervisor state from the charger details.
+    pub fn from_details(details: &Details) -> Self {
+        if details.chgin() != ChgIn::Valid {
+            return State::NoInput;
+        }
+        match details.charger() {
+            phase @ (ChargerDetails::Prequalification
+            | ChargerDetails::ConstantCurrent
+            | ChargerDetails::ConstantVoltage
+            | ChargerDetails::TopOff) => State::Charging(phase),
+            ChargerDetails::Done => State::Done,
+            ChargerDetails::TimerFault => State::Fault(FaultKind::Timer),
+            ChargerDetails::WatchdogTimer => State::Fault(FaultKind::Watchdog),
+            ChargerDetails::HighTemperature => State::Fault(FaultKind::OverTemperature),
+            ChargerDetails::ThermistorRemoval => State::Fault(FaultKind::ThermistorRemoved),
+            reason @ (ChargerDetails::QBattDisabled
+            | ChargerDetails::Jeita
+            | ChargerDetails::SuspendPin) => State::Suspended(reason),
+            _ => State::Idle,
+        }
+    }
+}
+
+/// Run the charging supervisor.
+///
+/// Applies `policy.config`, then waits on `irq_pin` for charger events and reacts to them:
+/// the configured mode is selected while a valid input is present and `policy.idle_mode`
+/// otherwise, and timer and watchdog faults are cleared by cycling the mode, up to
+/// `policy.max_fault_retries` times. `callback` is invoked with the previous and the new
+/// [`State`] on every transition.
+///
+/// This only returns on error. The supervisor can be cancelled by dropping the returned future;
+/// every mode change is a single register write, so the charger is always left in either the
+/// configured mode or `policy.idle_mode`.
+pub async fn run<D, P, F>(
+    charger: &mut Charger<D>,
+    irq_pin: &mut P,
+    policy: &Policy,
+    mut callback: F,
+) -> Result<Infallible, Error<D::Error>>
+where
+    D: I2c,
+    P: Wait,
+    F: FnMut(State, State),
+{
+    let config = ChargerConfig {
+        charger_irqs: policy
+            .config
+            .charger_irqs
+            .with_chgin(true)
+            .with_charger(true),
+        mode: policy.idle_mode,
+        ..policy.config
+    };
+    charger.apply_config(&config).await?;
+
+    let mut state = State::NoInput;
+    let mut mode = policy.idle_mode;
+    let mut retries = 0;
+    let mut details = charger.charger_details().await?;
+    loop {
+        let desired = if details.chgin() == ChgIn::Valid {
+            policy.config.mode
+        } else {
+            policy.idle_mode
+        };
+        if desired != mode {
+            charger.set_mode(desired).await?;
+            mode = desired;
+            details = charger.charger_details().await?;
+        }
+
+        let new_state = State::from_details(&details);
+        match new_state {
+            State::Fault(FaultKind::Timer | FaultKind::Watchdog)
+                if retries < policy.max_fault_retries =>
+            {
+                retries = retries.saturating_add(1);
+                charger.set_mode(Mode::Off).await?;
+                charger.set_mode(mode).await?;
+            }
+            State::Charging(_) | State::Done | State::NoInput => retries = 0,
+            _ => (),
+        }
+        if new_state != state {
+            callback(state, new_state);
+            state = new_state;
+        }
+
+        details = charger.wait_for_event(irq_pin).await?.details;
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use core::cell::RefCell;
+    use core::pin::pin;
+    use core::time::Duration;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::sim::Simulator;
+    use crate::testing::{block_on, poll_once};
+    use crate::{ChargerConfig3, ChargerInterrupts, Variant};
+
+    const POLICY: Policy = Policy {
+        config: ChargerConfig {
+            chgin_ilim_ma: 1500,
+            fast_charge_current_ma: 1000,
+            sys_ilim_ma: 3000,
+            sys_ilim_recycle: false,
+            charger_irqs: ChargerInterrupts::from_bytes([0x00]),
+            mode: Mode::Charge,
+        },
+        idle_mode: Mode::Buck,
+        max_fault_retries: 1,
+    };
+
+    /// A simulated charger with a 10 minute top-off, so the top-off state outlasts a step
+    fn charger(sim: &Simulator) -> Charger<crate::sim::SimulatedCharger<'_>> {
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.modify(|val: ChargerConfig3| val.with_to_time(1)))
+            .expect("simulated bus failed");
+        charger
+    }
+
+    #[test]
+    fn walks_a_full_charge_cycle() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = charger(&sim);
+        let mut irq_pin = sim.irq_pin();
+        let transitions = RefCell::new(Vec::new());
+        {
+            let mut supervisor = pin!(run(&mut charger, &mut irq_pin, &POLICY, |_, new| {
+                transitions.borrow_mut().push(new)
+            }));
+            assert!(poll_once(supervisor.as_mut()).is_pending());
+            assert_eq!(sim.mode(), Some(Mode::Buck));
+
+            sim.plug_charger();
+            for _ in 0..24 * 60 {
+                assert!(poll_once(supervisor.as_mut()).is_pending());
+                if transitions.borrow().last() == Some(&State::Done) {
+                    break;
+                }
+                sim.step(Duration::from_secs(60));
+            }
+        }
+        assert_eq!(
+            transitions.into_inner(),
+            [
+                State::Charging(ChargerDetails::ConstantCurrent),
+                State::Charging(ChargerDetails::ConstantVoltage),
+                State::Charging(ChargerDetails::TopOff),
+                State::Done,
+            ]
+        );
+        assert_eq!(sim.mode(), Some(Mode::Charge));
+    }
+
+    /// The transitions reported while charging through two timer faults
+    fn timer_fault_transitions(max_fault_retries: u8) -> (Vec<State>, Option<Mode>) {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = charger(&sim);
+        let mut irq_pin = sim.irq_pin();
+        let policy = Policy {
+            max_fault_retries,
+            ..POLICY
+        };
+        let transitions = RefCell::new(Vec::new());
+        {
+            let mut supervisor = pin!(run(&mut charger, &mut irq_pin, &policy, |_, new| {
+                transitions.borrow_mut().push(new)
+            }));
+            sim.plug_charger();
+            for _ in 0..3 {
+                assert!(poll_once(supervisor.as_mut()).is_pending());
+            }
+            for _ in 0..2 {
+                sim.trigger_timer_fault();
+                for _ in 0..3 {
+                    assert!(poll_once(supervisor.as_mut()).is_pending());
+                }
+            }
+        }
+        (transitions.into_inner(), sim.mode())
+    }
+
+    #[test]
+    fn recovers_from_timer_faults() {
+        // The retry count is reset once charging resumes, so each fault is retried
+        let (transitions, mode) = timer_fault_transitions(1);
+        assert_eq!(
+            transitions,
+            [
+                State::Charging(ChargerDetails::ConstantCurrent),
+                State::Fault(FaultKind::Timer),
+                State::Charging(ChargerDetails::ConstantCurrent),
+                State::Fault(FaultKind::Timer),
+                State::Charging(ChargerDetails::ConstantCurrent),
+            ]
+        );
+        assert_eq!(mode, Some(Mode::Charge));
+    }
+
+    #[test]
+    fn leaves_timer_fault_without_retries() {
+        let (transitions, mode) = timer_fault_transitions(0);
+        assert_eq!(
+            transitions,
+            [
+                State::Charging(ChargerDetails::ConstantCurrent),
+                State::Fault(FaultKind::Timer),
+            ]
+        );
+        assert_eq!(mode, Some(Mode::Charge));
+    }
+}
@@ -0,0 +1,92 @@
+//! Compact snapshots of the charger state for logging and streaming.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{
+    conv, Charger, ChargerConfig2, ChargerConfig9, ChargerStatus, Details, Error, Reg, RegisterSpec,
+};
+
+/// A snapshot of the charger state, captured by [`Charger::telemetry_snapshot`]
+///
+/// With the `serde` feature the snapshot serializes as [`Telemetry::VERSION`] followed by the
+/// fields in declaration order. The representation of a version never changes, so snapshots
+/// encoded with a compact format such as `postcard` can be decoded by any later release.
+/// Decoding a snapshot of another version fails.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Telemetry {
+    /// The caller-supplied timestamp
+    pub timestamp: u32,
+    /// The charger status bits
+    pub status: ChargerStatus,
+    /// The charger details
+    pub details: Details,
+    /// The CHGIN input current limit in mA
+    pub chgin_ilim_ma: u16,
+    /// The fast-charge current in mA
+    pub fast_charge_current_ma: u16,
+}
+
+impl Telemetry {
+    /// The version of the serialized representation
+    pub const VERSION: u8 = 1;
+}
+
+impl<D: I2c> Charger<D> {
+    /// Capture a [`Telemetry`] snapshot stamped with `timestamp`.
+    ///
+    /// `CHARGER_INTERRUPT_STATUS` through `CHARGER_CONFIG_9` are read in a single transaction.
+    /// The interrupt flags are not cleared.
+    pub async fn telemetry_snapshot(
+        &mut self,
+        timestamp: u32,
+    ) -> Result<Telemetry, Error<D::Error>> {
+        traced!(self, "telemetry_snapshot", {
+            let mut buf = [0; 14];
+            self.read_buf(Reg::CHARGER_INTERRUPT_STATUS, &mut buf)
+                .await?;
+            let cc = ChargerConfig2::from_byte(buf[6]).chg_cc();
+            let chgin_ilim = ChargerConfig9::from_byte(buf[13]).chgin_ilim();
+            Ok(Telemetry {
+                timestamp,
+                status: ChargerStatus::from_bytes([buf[0]]),
+                details: Details::from_bytes([buf[1], buf[2], buf[3]]),
+                chgin_ilim_ma: conv::chgin_ilim_ma(chgin_ilim),
+                fast_charge_current_ma: conv::chg_cc_ma(cc, self.variant),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read_burst, run};
+    use crate::{ChgIn, Variant};
+
+    #[test]
+    fn snapshot_is_a_single_burst_read() {
+        let cc = conv::chg_cc_code(1000, Variant::Max77975).unwrap();
+        let chgin_ilim = conv::chgin_ilim_code(1500);
+        let mut regs = [0; 14];
+        regs[..4].copy_from_slice(&[0x5b, 0x60, 0x31, 0x21]);
+        regs[6] = 0x80 | cc;
+        regs[13] = 0xc0 | chgin_ilim;
+        let telemetry = run(
+            &[read_burst(Reg::CHARGER_INTERRUPT_STATUS, &regs)],
+            async |charger| charger.telemetry_snapshot(123_456).await,
+        )
+        .unwrap();
+        assert_eq!(
+            telemetry,
+            Telemetry {
+                timestamp: 123_456,
+                status: ChargerStatus::from_bytes([0x5b]),
+                details: Details::from_bytes([0x60, 0x31, 0x21]),
+                chgin_ilim_ma: 1500,
+                fast_charge_current_ma: 1000,
+            }
+        );
+        assert_eq!(telemetry.details.chgin(), ChgIn::Valid);
+    }
+}
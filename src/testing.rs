@@ -0,0 +1,151 @@
+//! Helpers for the unit tests, which drive the driver against a mock I2C bus.
+
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+use std::vec;
+
+use embedded_hal_async::i2c::{ErrorKind, ErrorType, I2c, Operation};
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+use modular_bitfield::Specifier;
+use proptest::strategy::Strategy;
+
+use crate::{Charger, Reg, Variant, DEFAULT_ADDRESS};
+
+/// How often [`block_on`] polls a future before giving up on it.
+const MAX_POLLS: usize = 10_000;
+
+/// Run `fut` to completion on the current thread.
+///
+/// The mock bus completes every operation immediately, so a future that is still pending
+/// after [`MAX_POLLS`] polls never completes, and the test fails instead of hanging.
+pub(crate) fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+    for _ in 0..MAX_POLLS {
+        if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+            return out;
+        }
+    }
+    panic!("future still pending after {MAX_POLLS} polls");
+}
+
+/// Poll `fut` once, for futures that are driven alongside a simulation or never complete.
+#[cfg(feature = "sim")]
+pub(crate) fn poll_once<F: Future>(fut: core::pin::Pin<&mut F>) -> Poll<F::Output> {
+    fut.poll(&mut Context::from_waker(Waker::noop()))
+}
+
+/// Any value of the bitfield field type `T`, drawn from its valid bit patterns.
+pub(crate) fn any_field<T>() -> impl Strategy<Value = T::InOut>
+where
+    T: Specifier<Bytes = u8>,
+    T::InOut: core::fmt::Debug,
+{
+    let mask = u8::MAX >> 8usize.saturating_sub(T::BITS);
+    (0..=mask).prop_filter_map("invalid bit pattern", |raw| T::from_bytes(raw).ok())
+}
+
+/// A delay advancing the simulator's virtual time instead of waiting.
+#[cfg(feature = "sim")]
+pub(crate) struct SimDelay<'a>(pub(crate) &'a crate::sim::Simulator);
+
+#[cfg(feature = "sim")]
+impl embedded_hal_async::delay::DelayNs for SimDelay<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.step(core::time::Duration::from_nanos(ns.into()));
+    }
+}
+
+/// Run `f` on a charger at the [`DEFAULT_ADDRESS`] and check that exactly `expectations`
+/// happened on the bus.
+pub(crate) fn run<T>(
+    expectations: &[Transaction],
+    f: impl AsyncFnOnce(&mut Charger<Mock>) -> T,
+) -> T {
+    run_variant(Variant::default(), expectations, f)
+}
+
+/// Like [`run`], for a `variant` charger.
+pub(crate) fn run_variant<T>(
+    variant: Variant,
+    expectations: &[Transaction],
+    f: impl AsyncFnOnce(&mut Charger<Mock>) -> T,
+) -> T {
+    let mut charger = Charger::new_with_variant(Mock::new(expectations), variant);
+    let out = block_on(f(&mut charger));
+    charger.release().done();
+    out
+}
+
+/// A single register read returning `val`.
+pub(crate) fn read(reg: Reg, val: u8) -> Transaction {
+    read_burst(reg, &[val])
+}
+
+/// A burst read from `base` returning `vals`.
+pub(crate) fn read_burst(base: Reg, vals: &[u8]) -> Transaction {
+    Transaction::write_read(DEFAULT_ADDRESS, vec![base.address()], vals.to_vec())
+}
+
+/// A single register write of `val`.
+pub(crate) fn write(reg: Reg, val: u8) -> Transaction {
+    Transaction::write(DEFAULT_ADDRESS, vec![reg.address(), val])
+}
+
+/// A burst write of `vals` to consecutive registers from `base`.
+pub(crate) fn write_burst(base: Reg, vals: &[u8]) -> [Transaction; 4] {
+    [
+        Transaction::transaction_start(DEFAULT_ADDRESS),
+        Transaction::write(DEFAULT_ADDRESS, vec![base.address()]),
+        Transaction::write(DEFAULT_ADDRESS, vals.to_vec()),
+        Transaction::transaction_end(DEFAULT_ADDRESS),
+    ]
+}
+
+/// The write unlocking the CHGPROT protected registers.
+pub(crate) fn unlock() -> Transaction {
+    write(Reg::CHARGER_CONFIG_6, 0x0c)
+}
+
+/// The write locking the CHGPROT protected registers again.
+pub(crate) fn lock() -> Transaction {
+    write(Reg::CHARGER_CONFIG_6, 0x00)
+}
+
+/// A mock bus whose burst transactions fail with [`ErrorKind::Other`] without reaching the mock.
+///
+/// The mock can only inject errors into single operations, so this covers a failing burst
+/// write.
+pub(crate) struct FailingBursts(pub(crate) Mock);
+
+impl ErrorType for FailingBursts {
+    type Error = ErrorKind;
+}
+
+impl I2c for FailingBursts {
+    async fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), ErrorKind> {
+        self.0.read(address, read).await
+    }
+
+    async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), ErrorKind> {
+        self.0.write(address, write).await
+    }
+
+    async fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), ErrorKind> {
+        self.0.write_read(address, write, read).await
+    }
+
+    async fn transaction(
+        &mut self,
+        _address: u8,
+        _operations: &mut [Operation<'_>],
+    ) -> Result<(), ErrorKind> {
+        Err(ErrorKind::Other)
+    }
+}
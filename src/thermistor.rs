@@ -0,0 +1,823 @@
+//! Thermistor monitoring and JEITA control.
+
+use embedded_hal_async::i2c::I2c;
+use modular_bitfield::BitfieldSpecifier;
+
+use crate::{
+    conv, ChargeProfile, Charger, ChargerConfig13, ChargerConfig7, ChargerConfig8, Error,
+    ThermistorDetails,
+};
+
+/// The fast-charge current used in a JEITA zone, relative to the programmed current
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[bits = 2]
+pub enum JeitaCurrent {
+    /// The full fast-charge current
+    #[default]
+    Percent100,
+    /// 75% of the fast-charge current
+    Percent75,
+    /// 50% of the fast-charge current
+    Percent50,
+    /// 20% of the fast-charge current
+    Percent20,
+}
+
+/// The reduction of the termination voltage in a JEITA zone
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[bits = 2]
+pub enum JeitaVoltage {
+    /// The programmed termination voltage
+    #[default]
+    Unchanged,
+    /// 100 mV below the termination voltage
+    Mv100,
+    /// 150 mV below the termination voltage
+    Mv150,
+    /// 200 mV below the termination voltage
+    Mv200,
+}
+
+impl JeitaCurrent {
+    /// The fraction of the fast-charge current, in percent
+    pub const fn percent(self) -> u8 {
+        match self {
+            JeitaCurrent::Percent100 => 100,
+            JeitaCurrent::Percent75 => 75,
+            JeitaCurrent::Percent50 => 50,
+            JeitaCurrent::Percent20 => 20,
+        }
+    }
+}
+
+impl JeitaVoltage {
+    /// The reduction of the termination voltage, in mV
+    pub const fn reduction_mv(self) -> u16 {
+        match self {
+            JeitaVoltage::Unchanged => 0,
+            JeitaVoltage::Mv100 => 100,
+            JeitaVoltage::Mv150 => 150,
+            JeitaVoltage::Mv200 => 200,
+        }
+    }
+}
+
+/// Which device biases the thermistor (FGSRC)
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ThermistorBiasSource {
+    /// The charger drives the THM bias
+    #[default]
+    Charger,
+    /// An external fuel gauge drives the THM bias
+    FuelGauge,
+}
+
+/// The boundary between the [`Cold`](crate::ThermistorDetails::Cold) and
+/// [`Cool`](crate::ThermistorDetails::Cool) zones (T1)
+///
+/// The variants are named after the nominal temperature with a 10 kΩ, β = 3435 NTC biased
+/// through 10 kΩ.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[bits = 2]
+pub enum ColdThreshold {
+    /// -10 °C
+    Minus10C,
+    /// -5 °C
+    Minus5C,
+    /// 0 °C
+    #[default]
+    Plus0C,
+    /// 5 °C
+    Plus5C,
+}
+
+/// The boundary between the [`Cool`](crate::ThermistorDetails::Cool) and
+/// [`Normal`](crate::ThermistorDetails::Normal) zones (T2)
+///
+/// The variants are named after the nominal temperature with a 10 kΩ, β = 3435 NTC biased
+/// through 10 kΩ.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[bits = 2]
+pub enum CoolThreshold {
+    /// 0 °C
+    Plus0C,
+    /// 5 °C
+    Plus5C,
+    /// 10 °C
+    #[default]
+    Plus10C,
+    /// 15 °C
+    Plus15C,
+}
+
+/// The boundary between the [`Normal`](crate::ThermistorDetails::Normal) and
+/// [`Warm`](crate::ThermistorDetails::Warm) zones (T3)
+///
+/// The variants are named after the nominal temperature with a 10 kΩ, β = 3435 NTC biased
+/// through 10 kΩ.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[bits = 2]
+pub enum WarmThreshold {
+    /// 35 °C
+    Plus35C,
+    /// 40 °C
+    Plus40C,
+    /// 45 °C
+    #[default]
+    Plus45C,
+    /// 50 °C
+    Plus50C,
+}
+
+/// The boundary between the [`Warm`](crate::ThermistorDetails::Warm) and
+/// [`Hot`](crate::ThermistorDetails::Hot) zones (T4)
+///
+/// The variants are named after the nominal temperature with a 10 kΩ, β = 3435 NTC biased
+/// through 10 kΩ.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[bits = 2]
+pub enum HotThreshold {
+    /// 45 °C
+    Plus45C,
+    /// 50 °C
+    Plus50C,
+    /// 55 °C
+    Plus55C,
+    /// 60 °C
+    #[default]
+    Plus60C,
+}
+
+impl ColdThreshold {
+    /// The nominal temperature in °C
+    pub const fn nominal_celsius(self) -> i8 {
+        match self {
+            ColdThreshold::Minus10C => -10,
+            ColdThreshold::Minus5C => -5,
+            ColdThreshold::Plus0C => 0,
+            ColdThreshold::Plus5C => 5,
+        }
+    }
+}
+
+impl CoolThreshold {
+    /// The nominal temperature in °C
+    pub const fn nominal_celsius(self) -> i8 {
+        match self {
+            CoolThreshold::Plus0C => 0,
+            CoolThreshold::Plus5C => 5,
+            CoolThreshold::Plus10C => 10,
+            CoolThreshold::Plus15C => 15,
+        }
+    }
+}
+
+impl WarmThreshold {
+    /// The nominal temperature in °C
+    pub const fn nominal_celsius(self) -> i8 {
+        match self {
+            WarmThreshold::Plus35C => 35,
+            WarmThreshold::Plus40C => 40,
+            WarmThreshold::Plus45C => 45,
+            WarmThreshold::Plus50C => 50,
+        }
+    }
+}
+
+impl HotThreshold {
+    /// The nominal temperature in °C
+    pub const fn nominal_celsius(self) -> i8 {
+        match self {
+            HotThreshold::Plus45C => 45,
+            HotThreshold::Plus50C => 50,
+            HotThreshold::Plus55C => 55,
+            HotThreshold::Plus60C => 60,
+        }
+    }
+}
+
+/// The thermistor zone boundaries, see [`Charger::set_thermistor_thresholds`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThermistorThresholds {
+    /// The cold/cool boundary
+    pub cold: ColdThreshold,
+    /// The cool/normal boundary
+    pub cool: CoolThreshold,
+    /// The normal/warm boundary
+    pub warm: WarmThreshold,
+    /// The warm/hot boundary
+    pub hot: HotThreshold,
+}
+
+impl ThermistorThresholds {
+    /// Whether the boundaries are in increasing temperature order
+    pub const fn is_ordered(&self) -> bool {
+        self.cold.nominal_celsius() < self.cool.nominal_celsius()
+            && self.cool.nominal_celsius() < self.warm.nominal_celsius()
+            && self.warm.nominal_celsius() < self.hot.nominal_celsius()
+    }
+}
+
+/// The JEITA derating settings, see [`Charger::set_jeita`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JeitaConfig {
+    /// Enable JEITA control
+    pub enabled: bool,
+    /// The fast-charge current in the cool zone
+    pub cool_current: JeitaCurrent,
+    /// The fast-charge current in the warm zone
+    pub warm_current: JeitaCurrent,
+    /// The termination voltage reduction in the cool zone
+    pub cool_voltage: JeitaVoltage,
+    /// The termination voltage reduction in the warm zone
+    pub warm_voltage: JeitaVoltage,
+}
+
+impl<D: I2c> Charger<D> {
+    /// Configure JEITA control.
+    ///
+    /// With JEITA enabled the charger reduces the fast-charge current and termination voltage
+    /// in the [`ThermistorDetails::Cool`](crate::ThermistorDetails::Cool) and
+    /// [`ThermistorDetails::Warm`](crate::ThermistorDetails::Warm) zones as configured, and
+    /// suspends charging in the `Cold` and `Hot` zones. While charging is derated or suspended
+    /// the charger state reads [`ChargerDetails::Jeita`](crate::ChargerDetails::Jeita) and the
+    /// zone is reported in [`Details::thermistor`](crate::Details::thermistor). The zone
+    /// boundaries are set in `CHARGER_CONFIG_7` and are not changed here.
+    ///
+    /// `CHARGER_CONFIG_8` is written and the JEITA enable bit in `CHARGER_CONFIG_13` is updated
+    /// with a read-modify-write.
+    pub async fn set_jeita(&mut self, config: &JeitaConfig) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_jeita", {
+            let derating = ChargerConfig8::new()
+                .with_cool_current(config.cool_current)
+                .with_warm_current(config.warm_current)
+                .with_cool_voltage(config.cool_voltage)
+                .with_warm_voltage(config.warm_voltage);
+            self.write_typed(derating).await?;
+            self.modify_typed(|val: ChargerConfig13| val.with_jeita_enable(config.enabled))
+                .await
+        })
+    }
+
+    /// Enable or disable thermistor monitoring.
+    ///
+    /// Boards without a pack thermistor must disable monitoring, or the charger suspends with
+    /// [`ThermistorDetails::Removed`](crate::ThermistorDetails::Removed). Battery removal is
+    /// detected on the THM pin, so while monitoring is disabled
+    /// [`BatteryDetails::BatteryRemoved`](crate::BatteryDetails::BatteryRemoved) is never
+    /// reported and the thermistor status reads
+    /// [`ThermistorDetails::Disabled`](crate::ThermistorDetails::Disabled). JEITA control has no
+    /// temperature zone to act on either.
+    ///
+    /// Only the THM enable bit of `CHARGER_CONFIG_13` is modified.
+    pub async fn set_thermistor_monitoring(
+        &mut self,
+        enabled: bool,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_thermistor_monitoring", {
+            self.modify_typed(|val: ChargerConfig13| val.with_thermistor_enable(enabled))
+                .await
+        })
+    }
+
+    /// Whether thermistor monitoring is enabled.
+    pub async fn thermistor_monitoring(&mut self) -> Result<bool, Error<D::Error>> {
+        traced!(self, "thermistor_monitoring", {
+            Ok(self.read::<ChargerConfig13>().await?.thermistor_enable())
+        })
+    }
+
+    /// Select which device biases the thermistor.
+    ///
+    /// With [`ThermistorBiasSource::FuelGauge`] the charger only measures the THM pin while the
+    /// gauge drives the bias. When the gauge is not biasing the thermistor the pin reads as open,
+    /// so battery removal may be reported or missed depending on the gauge's bias schedule.
+    ///
+    /// Only the FGSRC bit of `CHARGER_CONFIG_13` is modified.
+    pub async fn set_thermistor_bias_source(
+        &mut self,
+        source: ThermistorBiasSource,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_thermistor_bias_source", {
+            let fuel_gauge = source == ThermistorBiasSource::FuelGauge;
+            self.modify_typed(|val: ChargerConfig13| val.with_fuel_gauge_bias(fuel_gauge))
+                .await
+        })
+    }
+
+    /// Read which device biases the thermistor.
+    pub async fn thermistor_bias_source(
+        &mut self,
+    ) -> Result<ThermistorBiasSource, Error<D::Error>> {
+        traced!(self, "thermistor_bias_source", {
+            Ok(
+                match self.read::<ChargerConfig13>().await?.fuel_gauge_bias() {
+                    false => ThermistorBiasSource::Charger,
+                    true => ThermistorBiasSource::FuelGauge,
+                },
+            )
+        })
+    }
+
+    /// Set the thermistor zone boundaries.
+    ///
+    /// Fails with [`Error::InvalidValue`] unless the boundaries are in increasing temperature
+    /// order. `CHARGER_CONFIG_7` is CHGPROT protected, so it is written in a
+    /// [`ProtectedSession`](crate::ProtectedSession).
+    pub async fn set_thermistor_thresholds(
+        &mut self,
+        thresholds: ThermistorThresholds,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_thermistor_thresholds", {
+            if !thresholds.is_ordered() {
+                return Err(Error::InvalidValue);
+            }
+            let val = ChargerConfig7::new()
+                .with_cold(thresholds.cold)
+                .with_cool(thresholds.cool)
+                .with_warm(thresholds.warm)
+                .with_hot(thresholds.hot);
+            self.write(val).await
+        })
+    }
+
+    /// Read the thermistor zone boundaries.
+    pub async fn thermistor_thresholds(&mut self) -> Result<ThermistorThresholds, Error<D::Error>> {
+        traced!(self, "thermistor_thresholds", {
+            let val = self.read::<ChargerConfig7>().await?;
+            Ok(ThermistorThresholds {
+                cold: val.cold(),
+                cool: val.cool(),
+                warm: val.warm(),
+                hot: val.hot(),
+            })
+        })
+    }
+
+    /// Read the JEITA configuration.
+    pub async fn jeita(&mut self) -> Result<JeitaConfig, Error<D::Error>> {
+        traced!(self, "jeita", {
+            let derating = self.read::<ChargerConfig8>().await?;
+            let enabled = self.read::<ChargerConfig13>().await?.jeita_enable();
+            Ok(JeitaConfig {
+                enabled,
+                cool_current: derating.cool_current(),
+                warm_current: derating.warm_current(),
+                cool_voltage: derating.cool_voltage(),
+                warm_voltage: derating.warm_voltage(),
+            })
+        })
+    }
+}
+
+/// The derating applied in software JEITA zones, see [`ThermistorDetails::recommended_action`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JeitaPolicy {
+    /// The fast-charge current in the cool zone
+    pub cool_current: JeitaCurrent,
+    /// The fast-charge current in the warm zone
+    pub warm_current: JeitaCurrent,
+    /// The termination voltage reduction in the cool zone
+    pub cool_voltage: JeitaVoltage,
+    /// The termination voltage reduction in the warm zone
+    pub warm_voltage: JeitaVoltage,
+}
+
+impl JeitaPolicy {
+    /// Half the fast-charge current when cool, 100 mV below the termination voltage when warm
+    pub const DEFAULT: JeitaPolicy = JeitaPolicy {
+        cool_current: JeitaCurrent::Percent50,
+        warm_current: JeitaCurrent::Percent100,
+        cool_voltage: JeitaVoltage::Unchanged,
+        warm_voltage: JeitaVoltage::Mv100,
+    };
+}
+
+impl Default for JeitaPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// What to do in a thermistor zone, see [`ThermistorDetails::recommended_action`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum JeitaAction {
+    /// Charge with the profile unchanged
+    ChargeNormal,
+    /// Charge with a reduced fast-charge current and termination voltage
+    ChargeReduced {
+        /// The fast-charge current in mA
+        cc_ma: u16,
+        /// The termination voltage in mV
+        cv_mv: u16,
+    },
+    /// Suspend charging, the battery is too cold or too hot
+    Suspend,
+    /// Suspend charging, the thermistor reports the battery removed
+    BatteryRemoved,
+    /// The temperature is not monitored, the application has to decide
+    Unmonitored,
+}
+
+impl ThermistorDetails {
+    /// The raw THM_DTLS code
+    pub const fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether the code is reserved
+    pub const fn is_reserved(self) -> bool {
+        matches!(self, ThermistorDetails::Reserved)
+    }
+
+    /// The software JEITA action for this zone with [`JeitaPolicy::DEFAULT`].
+    ///
+    /// See [`recommended_action_with`](Self::recommended_action_with).
+    pub fn recommended_action(&self, profile: &ChargeProfile) -> JeitaAction {
+        self.recommended_action_with(profile, &JeitaPolicy::DEFAULT)
+    }
+
+    /// The software JEITA action for this zone.
+    ///
+    /// The cool and warm zones derate `profile` as set in `policy`, or charge normally if the
+    /// policy leaves the zone unchanged. The reduced termination voltage is clamped to the
+    /// lowest programmable setting, 3400 mV. Cold, hot and reserved readings suspend charging.
+    pub fn recommended_action_with(
+        &self,
+        profile: &ChargeProfile,
+        policy: &JeitaPolicy,
+    ) -> JeitaAction {
+        let (current, voltage) = match self {
+            ThermistorDetails::Normal => return JeitaAction::ChargeNormal,
+            ThermistorDetails::Cool => (policy.cool_current, policy.cool_voltage),
+            ThermistorDetails::Warm => (policy.warm_current, policy.warm_voltage),
+            ThermistorDetails::Removed => return JeitaAction::BatteryRemoved,
+            ThermistorDetails::Disabled => return JeitaAction::Unmonitored,
+            ThermistorDetails::Cold | ThermistorDetails::Hot | ThermistorDetails::Reserved => {
+                return JeitaAction::Suspend
+            }
+        };
+        if current == JeitaCurrent::Percent100 && voltage == JeitaVoltage::Unchanged {
+            return JeitaAction::ChargeNormal;
+        }
+        JeitaAction::ChargeReduced {
+            cc_ma: ((profile.fast_charge_current_ma() as u32)
+                .saturating_mul(current.percent() as u32)
+                / 100) as u16,
+            cv_mv: profile
+                .termination_mv()
+                .saturating_sub(voltage.reduction_mv())
+                .max(conv::chg_cv_prm_mv(0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{lock, read, run, unlock, write};
+    use crate::Reg;
+
+    /// JEITA configurations with their `CHARGER_CONFIG_8` encoding
+    const JEITA: [(JeitaConfig, u8); 3] = [
+        (
+            JeitaConfig {
+                enabled: false,
+                cool_current: JeitaCurrent::Percent100,
+                warm_current: JeitaCurrent::Percent100,
+                cool_voltage: JeitaVoltage::Unchanged,
+                warm_voltage: JeitaVoltage::Unchanged,
+            },
+            0x00,
+        ),
+        (
+            JeitaConfig {
+                enabled: true,
+                cool_current: JeitaCurrent::Percent50,
+                warm_current: JeitaCurrent::Percent100,
+                cool_voltage: JeitaVoltage::Unchanged,
+                warm_voltage: JeitaVoltage::Mv100,
+            },
+            0x42,
+        ),
+        (
+            JeitaConfig {
+                enabled: true,
+                cool_current: JeitaCurrent::Percent20,
+                warm_current: JeitaCurrent::Percent75,
+                cool_voltage: JeitaVoltage::Mv150,
+                warm_voltage: JeitaVoltage::Mv200,
+            },
+            0xe7,
+        ),
+    ];
+
+    #[test]
+    fn set_jeita_encodes_the_derating() {
+        for (config, derating) in JEITA {
+            // THM_EN and FGSRC are set and must survive the JEITA_EN update
+            let config13 = 0x06 | config.enabled as u8;
+            let res = run(
+                &[
+                    write(Reg::CHARGER_CONFIG_8, derating),
+                    read(Reg::CHARGER_CONFIG_13, 0x06),
+                    write(Reg::CHARGER_CONFIG_13, config13),
+                ],
+                async |charger| charger.set_jeita(&config).await,
+            );
+            assert_eq!(res, Ok(()), "{config:?}");
+        }
+    }
+
+    #[test]
+    fn jeita_decodes_the_derating() {
+        for (config, derating) in JEITA {
+            let res = run(
+                &[
+                    read(Reg::CHARGER_CONFIG_8, derating),
+                    read(Reg::CHARGER_CONFIG_13, 0xf6 | config.enabled as u8),
+                ],
+                async |charger| charger.jeita().await,
+            );
+            assert_eq!(res, Ok(config));
+        }
+    }
+
+    #[test]
+    fn jeita_derating_codes() {
+        let currents = [
+            (JeitaCurrent::Percent100, 100),
+            (JeitaCurrent::Percent75, 75),
+            (JeitaCurrent::Percent50, 50),
+            (JeitaCurrent::Percent20, 20),
+        ];
+        for (code, (current, percent)) in (0u8..).zip(currents) {
+            assert_eq!(current.percent(), percent);
+            assert_eq!(ChargerConfig8::from_bytes([code]).cool_current(), current);
+        }
+        let voltages = [
+            (JeitaVoltage::Unchanged, 0),
+            (JeitaVoltage::Mv100, 100),
+            (JeitaVoltage::Mv150, 150),
+            (JeitaVoltage::Mv200, 200),
+        ];
+        for (code, (voltage, reduction)) in (0u8..).zip(voltages) {
+            assert_eq!(voltage.reduction_mv(), reduction);
+            assert_eq!(
+                ChargerConfig8::from_bytes([code << 6]).warm_voltage(),
+                voltage
+            );
+        }
+    }
+
+    #[test]
+    fn set_thermistor_monitoring_keeps_jeita_bits() {
+        for (enabled, before, after) in [(false, 0xff, 0xfd), (true, 0x05, 0x07)] {
+            let res = run(
+                &[
+                    read(Reg::CHARGER_CONFIG_13, before),
+                    write(Reg::CHARGER_CONFIG_13, after),
+                ],
+                async |charger| charger.set_thermistor_monitoring(enabled).await,
+            );
+            assert_eq!(res, Ok(()), "{enabled}");
+        }
+    }
+
+    #[test]
+    fn thermistor_monitoring_reads_thm_en() {
+        for (val, enabled) in [(0xfd, false), (0x02, true)] {
+            let res = run(&[read(Reg::CHARGER_CONFIG_13, val)], async |charger| {
+                charger.thermistor_monitoring().await
+            });
+            assert_eq!(res, Ok(enabled), "0x{val:02x}");
+        }
+    }
+
+    #[test]
+    fn thermistor_bias_source_encodes_fgsrc() {
+        let sources = [
+            (ThermistorBiasSource::Charger, 0xfb, 0xfb),
+            (ThermistorBiasSource::FuelGauge, 0x00, 0x04),
+        ];
+        for (source, before, after) in sources {
+            let res = run(
+                &[
+                    read(Reg::CHARGER_CONFIG_13, before),
+                    write(Reg::CHARGER_CONFIG_13, after),
+                ],
+                async |charger| charger.set_thermistor_bias_source(source).await,
+            );
+            assert_eq!(res, Ok(()), "{source:?}");
+            let res = run(&[read(Reg::CHARGER_CONFIG_13, after)], async |charger| {
+                charger.thermistor_bias_source().await
+            });
+            assert_eq!(res, Ok(source));
+        }
+    }
+
+    /// Thermistor boundaries with their `CHARGER_CONFIG_7` encoding
+    const THRESHOLDS: [(ThermistorThresholds, u8); 3] = [
+        (
+            ThermistorThresholds {
+                cold: ColdThreshold::Plus0C,
+                cool: CoolThreshold::Plus10C,
+                warm: WarmThreshold::Plus45C,
+                hot: HotThreshold::Plus60C,
+            },
+            0xea,
+        ),
+        (
+            ThermistorThresholds {
+                cold: ColdThreshold::Minus10C,
+                cool: CoolThreshold::Plus0C,
+                warm: WarmThreshold::Plus35C,
+                hot: HotThreshold::Plus45C,
+            },
+            0x00,
+        ),
+        (
+            ThermistorThresholds {
+                cold: ColdThreshold::Plus5C,
+                cool: CoolThreshold::Plus15C,
+                warm: WarmThreshold::Plus40C,
+                hot: HotThreshold::Plus55C,
+            },
+            0x9f,
+        ),
+    ];
+
+    #[test]
+    fn set_thermistor_thresholds_encodes_the_boundaries() {
+        for (thresholds, val) in THRESHOLDS {
+            let res = run(
+                &[unlock(), write(Reg::CHARGER_CONFIG_7, val), lock()],
+                async |charger| charger.set_thermistor_thresholds(thresholds).await,
+            );
+            assert_eq!(res, Ok(()), "{thresholds:?}");
+        }
+    }
+
+    #[test]
+    fn thermistor_thresholds_decodes_the_boundaries() {
+        for (thresholds, val) in THRESHOLDS {
+            let res = run(&[read(Reg::CHARGER_CONFIG_7, val)], async |charger| {
+                charger.thermistor_thresholds().await
+            });
+            assert_eq!(res, Ok(thresholds), "0x{val:02x}");
+        }
+    }
+
+    #[test]
+    fn set_thermistor_thresholds_rejects_unordered_boundaries() {
+        let ordered = THRESHOLDS[0].0;
+        let unordered = [
+            ThermistorThresholds {
+                cold: ColdThreshold::Plus5C,
+                cool: CoolThreshold::Plus5C,
+                ..ordered
+            },
+            ThermistorThresholds {
+                cold: ColdThreshold::Plus5C,
+                cool: CoolThreshold::Plus0C,
+                ..ordered
+            },
+            ThermistorThresholds {
+                warm: WarmThreshold::Plus50C,
+                hot: HotThreshold::Plus45C,
+                ..ordered
+            },
+        ];
+        for thresholds in unordered {
+            assert!(!thresholds.is_ordered(), "{thresholds:?}");
+            // No bus traffic is expected
+            let res = run(&[], async |charger| {
+                charger.set_thermistor_thresholds(thresholds).await
+            });
+            assert_eq!(res, Err(Error::InvalidValue), "{thresholds:?}");
+        }
+    }
+
+    const PROFILE: ChargeProfile =
+        crate::charge_profile!(crate::Variant::Max77975, 2000, 4200, 150, 30);
+
+    #[test]
+    fn recommended_action_covers_every_zone() {
+        let halved = JeitaAction::ChargeReduced {
+            cc_ma: 1000,
+            cv_mv: 4200,
+        };
+        let lowered = JeitaAction::ChargeReduced {
+            cc_ma: 2000,
+            cv_mv: 4100,
+        };
+        let table = [
+            (ThermistorDetails::Cold, JeitaAction::Suspend),
+            (ThermistorDetails::Cool, halved),
+            (ThermistorDetails::Normal, JeitaAction::ChargeNormal),
+            (ThermistorDetails::Warm, lowered),
+            (ThermistorDetails::Hot, JeitaAction::Suspend),
+            (ThermistorDetails::Removed, JeitaAction::BatteryRemoved),
+            (ThermistorDetails::Disabled, JeitaAction::Unmonitored),
+            (ThermistorDetails::Reserved, JeitaAction::Suspend),
+        ];
+        for (zone, action) in table {
+            assert_eq!(zone.recommended_action(&PROFILE), action, "{zone:?}");
+        }
+    }
+
+    #[test]
+    fn recommended_action_follows_the_policy() {
+        let policies = [
+            (
+                JeitaPolicy {
+                    cool_current: JeitaCurrent::Percent100,
+                    warm_current: JeitaCurrent::Percent100,
+                    cool_voltage: JeitaVoltage::Unchanged,
+                    warm_voltage: JeitaVoltage::Unchanged,
+                },
+                JeitaAction::ChargeNormal,
+                JeitaAction::ChargeNormal,
+            ),
+            (
+                JeitaPolicy {
+                    cool_current: JeitaCurrent::Percent20,
+                    warm_current: JeitaCurrent::Percent75,
+                    cool_voltage: JeitaVoltage::Mv150,
+                    warm_voltage: JeitaVoltage::Mv200,
+                },
+                JeitaAction::ChargeReduced {
+                    cc_ma: 400,
+                    cv_mv: 4050,
+                },
+                JeitaAction::ChargeReduced {
+                    cc_ma: 1500,
+                    cv_mv: 4000,
+                },
+            ),
+        ];
+        for (policy, cool, warm) in policies {
+            let action = |zone: ThermistorDetails| zone.recommended_action_with(&PROFILE, &policy);
+            assert_eq!(action(ThermistorDetails::Cool), cool, "{policy:?}");
+            assert_eq!(action(ThermistorDetails::Warm), warm, "{policy:?}");
+            assert_eq!(action(ThermistorDetails::Normal), JeitaAction::ChargeNormal);
+            assert_eq!(action(ThermistorDetails::Hot), JeitaAction::Suspend);
+        }
+    }
+
+    #[test]
+    fn recommended_voltage_stays_programmable() {
+        let policy = JeitaPolicy {
+            warm_voltage: JeitaVoltage::Mv200,
+            ..JeitaPolicy::DEFAULT
+        };
+        let profile = ChargeProfile::new(1000, 3450).unwrap();
+        assert_eq!(
+            ThermistorDetails::Warm.recommended_action_with(&profile, &policy),
+            JeitaAction::ChargeReduced {
+                cc_ma: 1000,
+                cv_mv: 3400,
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn recommended_voltage_saturates_for_deserialized_profiles() {
+        // Deserialized profiles are not validated, so the termination voltage can be anything
+        let profile: ChargeProfile = serde_json::from_str(
+            r#"{"fast_charge_current_ma":1000,"termination_mv":50,"top_off_current_ma":150,
+                "top_off_time_min":30,"recharge":"Mv150","fast_charge_timer":"Hours5"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            ThermistorDetails::Warm.recommended_action(&profile),
+            JeitaAction::ChargeReduced {
+                cc_ma: 1000,
+                cv_mv: 3400,
+            }
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Bounding bus transactions in time.
+//!
+//! [`Charger::with_timeout`] wraps the bus in a [`Timeout`], which races each transaction
+//! against a delay and abandons it once the delay expires, so a stuck bus (e.g. SDA held low)
+//! can not stall the caller forever:
+//!
+//! ```no_run
+//! # use embedded_hal_async::delay::DelayNs;
+//! # use embedded_hal_async::i2c::I2c;
+//! # use max7797x_driver::timeout::{TimeoutError, TimeoutPolicy};
+//! # use max7797x_driver::{Charger, Details, Error};
+//! # async fn example<D: I2c>(
+//! #     i2c: D,
+//! #     delay: impl DelayNs,
+//! #     recover_bus: impl Fn(),
+//! #     handle: impl Fn(Result<Details, Error<TimeoutError<D::Error>>>),
+//! # ) {
+//! let timeouts = TimeoutPolicy { read_us: 2_000, write_us: 5_000 };
+//! let mut charger = Charger::new(i2c).with_timeout(timeouts, delay);
+//! match charger.charger_details().await {
+//!     Err(Error::Timeout) => recover_bus(),
+//!     res => handle(res),
+//! }
+//! # }
+//! ```
+//!
+//! An expired transaction fails with [`Error::Timeout`] and its future is dropped, so
+//! the driver can be used again once the bus recovers. Whether an abandoned write reached the
+//! charger is unknown; invalidate the [shadow cache](Charger::invalidate_cache) if it is
+//! enabled. Drivers that are not wrapped pay nothing for this facility.
+
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::task::Poll;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::{self, ErrorType, I2c, Operation};
+
+use crate::{Charger, Error};
+
+/// The time limits applied by [`Timeout`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeoutPolicy {
+    /// The limit for transactions reading registers in µs
+    pub read_us: u32,
+    /// The limit for transactions only writing registers in µs
+    pub write_us: u32,
+}
+
+/// The error of a [`Timeout`] bus
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum TimeoutError<E> {
+    /// An error from the underlying bus
+    Bus(E),
+    /// The transaction did not complete within its time limit, reported by the driver as
+    /// [`Error::Timeout`]
+    Timeout,
+}
+
+impl<E: i2c::Error> i2c::Error for TimeoutError<E> {
+    fn kind(&self) -> i2c::ErrorKind {
+        match self {
+            TimeoutError::Bus(err) => err.kind(),
+            TimeoutError::Timeout => i2c::ErrorKind::Other,
+        }
+    }
+}
+
+/// An I2C bus that abandons transactions exceeding a time limit, see [`Charger::with_timeout`]
+pub struct Timeout<D, T> {
+    i2c_dev: D,
+    delay: T,
+    policy: TimeoutPolicy,
+}
+
+impl<D, T> Timeout<D, T> {
+    /// Wrap `i2c_dev`, timing transactions with `delay`.
+    pub fn new(i2c_dev: D, delay: T, policy: TimeoutPolicy) -> Self {
+        Timeout {
+            i2c_dev,
+            delay,
+            policy,
+        }
+    }
+
+    /// The timeout policy
+    pub fn policy(&self) -> TimeoutPolicy {
+        self.policy
+    }
+
+    /// Release the bus and the delay.
+    pub fn release(self) -> (D, T) {
+        (self.i2c_dev, self.delay)
+    }
+}
+
+impl<D: ErrorType, T> ErrorType for Timeout<D, T> {
+    type Error = TimeoutError<D::Error>;
+}
+
+impl<D: I2c, T: DelayNs> I2c for Timeout<D, T> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let reads = operations.iter().any(|op| matches!(op, Operation::Read(_)));
+        let limit_us = if reads {
+            self.policy.read_us
+        } else {
+            self.policy.write_us
+        };
+        let mut transfer = pin!(self.i2c_dev.transaction(address, operations));
+        let mut expiry = pin!(self.delay.delay_us(limit_us));
+        poll_fn(|cx| {
+            if let Poll::Ready(res) = transfer.as_mut().poll(cx) {
+                return Poll::Ready(res.map_err(TimeoutError::Bus));
+            }
+            expiry
+                .as_mut()
+                .poll(cx)
+                .map(|()| Err(TimeoutError::Timeout))
+        })
+        .await
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Fail bus transactions that exceed the limits of `policy`, timed with `delay`.
+    ///
+    /// Transactions that read registers are limited to `read_us` and the others to
+    /// `write_us`. An expired transaction fails with [`Error::Timeout`].
+    pub fn with_timeout<T: DelayNs>(
+        self,
+        policy: TimeoutPolicy,
+        delay: T,
+    ) -> Charger<Timeout<D, T>> {
+        Charger {
+            i2c_dev: Timeout::new(self.i2c_dev, delay, policy),
+            bus_error: timeout_error,
+            address: self.address,
+            variant: self.variant,
+            bypass: self.bypass,
+            suspended_mode: self.suspended_mode,
+            derating: self.derating,
+            read_voting: self.read_voting,
+            verify_writes: self.verify_writes,
+            cache: self.cache,
+            safety_policy: self.safety_policy,
+            #[cfg(feature = "trace")]
+            trace_op: self.trace_op,
+        }
+    }
+}
+
+/// Report an expired transaction as [`Error::Timeout`] rather than as a bus error.
+fn timeout_error<E>(err: TimeoutError<E>) -> Error<TimeoutError<E>> {
+    match err {
+        TimeoutError::Timeout => Error::Timeout,
+        err => Error::I2c(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use embedded_hal_async::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::i2c::Mock;
+
+    use super::*;
+    use crate::testing::{block_on, read, write};
+    use crate::{conv, ChgIn, Reg};
+
+    const POLICY: TimeoutPolicy = TimeoutPolicy {
+        read_us: 2_000,
+        write_us: 5_000,
+    };
+
+    /// A bus whose transactions with the indices in `stuck` never complete
+    ///
+    /// The others are passed to the mock as plain reads and writes, which is how the driver
+    /// issues them.
+    struct Stuck {
+        mock: Mock,
+        stuck: &'static [usize],
+        count: usize,
+    }
+
+    impl ErrorType for Stuck {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for Stuck {
+        async fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), ErrorKind> {
+            let index = self.count;
+            self.count = index.saturating_add(1);
+            if self.stuck.contains(&index) {
+                return core::future::pending().await;
+            }
+            match operations {
+                [Operation::Write(write)] => self.mock.write(address, write).await,
+                [Operation::Write(write), Operation::Read(read)] => {
+                    self.mock.write_read(address, write, read).await
+                }
+                _ => self.mock.transaction(address, operations).await,
+            }
+        }
+    }
+
+    /// A delay expiring at once, recording the requested delays
+    struct Recorder(Vec<u32>);
+
+    impl DelayNs for Recorder {
+        async fn delay_ns(&mut self, ns: u32) {
+            self.0.push(ns);
+        }
+    }
+
+    #[test]
+    fn stuck_transactions_time_out_and_the_driver_recovers() {
+        let mock = Mock::new(&[
+            read(Reg::CHARGER_DETAILS_0, 0x60),
+            read(Reg::CHARGER_CONFIG_9, 0x00),
+            read(Reg::CHARGER_CONFIG_9, 0x00),
+            write(Reg::CHARGER_CONFIG_9, conv::chgin_ilim_code(800)),
+        ]);
+        let bus = Stuck {
+            mock,
+            stuck: &[0, 3],
+            count: 0,
+        };
+        let mut charger = Charger::new(bus).with_timeout(POLICY, Recorder(Vec::new()));
+
+        assert_eq!(block_on(charger.chgin_status()), Err(Error::Timeout));
+        assert_eq!(block_on(charger.chgin_status()), Ok(ChgIn::Valid));
+        assert_eq!(block_on(charger.set_chgin_ilim(800)), Err(Error::Timeout));
+        assert_eq!(block_on(charger.set_chgin_ilim(800)), Ok(()));
+
+        let (mut bus, delay) = charger.release().release();
+        bus.mock.done();
+        // The delay only runs for transactions that do not complete at once
+        let limits: Vec<u32> = delay.0.iter().map(|ns| ns / 1000).collect();
+        assert_eq!(limits, [POLICY.read_us, POLICY.write_us]);
+    }
+
+    #[test]
+    fn bus_errors_are_not_timeouts() {
+        let mut mock = Mock::new(&[read(Reg::CHARGER_DETAILS_0, 0x60).with_error(ErrorKind::Bus)]);
+        let bus = Stuck {
+            mock: mock.clone(),
+            stuck: &[],
+            count: 0,
+        };
+        let mut charger = Charger::new(bus).with_timeout(POLICY, Recorder(Vec::new()));
+        assert_eq!(
+            block_on(charger.chgin_status()),
+            Err(Error::I2c(TimeoutError::Bus(ErrorKind::Bus)))
+        );
+        mock.done();
+    }
+}
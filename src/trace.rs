@@ -0,0 +1,138 @@
+//! Register-level tracing of bus transactions.
+//!
+//! With the `trace` feature, every register read and written is logged at trace level together
+//! with the public method that caused it, e.g.
+//!
+//! ```text
+//! max7797x: wr CHG_CNFG_09 <= 0x2a (set_chgin_ilim)
+//! ```
+//!
+//! Lines go through `log`, and also through `defmt` if the `defmt-03` feature is enabled.
+//! Methods called by other driver methods are attributed to the outermost call. Without the
+//! feature, the tracing compiles to nothing.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{Charger, Reg};
+
+/// Run `$body` with `$op` as the traced operation of `$charger`, unless it runs within an
+/// outer traced operation.
+#[cfg(feature = "trace")]
+macro_rules! traced {
+    ($charger:expr, $op:literal, $body:block) => {{
+        let outer = $charger.trace_op;
+        if outer.is_none() {
+            $charger.trace_op = Some($op);
+        }
+        let res = async $body.await;
+        $charger.trace_op = outer;
+        res
+    }};
+}
+
+/// Run `$body`; operations are only recorded with the `trace` feature.
+#[cfg(not(feature = "trace"))]
+macro_rules! traced {
+    ($charger:expr, $op:literal, $body:block) => {
+        $body
+    };
+}
+
+impl<D: I2c> Charger<D> {
+    /// Log the `vals` read from or written to the registers from `base`.
+    #[cfg_attr(not(feature = "trace"), allow(unused_variables))]
+    #[inline(always)]
+    pub(crate) fn trace_transfer(&self, write: bool, base: Reg, vals: &[u8]) {
+        #[cfg(feature = "trace")]
+        for (offset, &val) in vals.iter().enumerate() {
+            let name = Reg::new(base.address().wrapping_add(offset as u8)).name();
+            let (dir, arrow) = if write { ("wr", "<=") } else { ("rd", "=>") };
+            let op = self.trace_op.unwrap_or("-");
+            #[cfg(feature = "defmt-03")]
+            defmt::trace!(
+                "max7797x: {=str} {=str} {=str} {=u8:#04x} ({=str})",
+                dir,
+                name,
+                arrow,
+                val,
+                op
+            );
+            log::trace!("max7797x: {dir} {name} {arrow} {val:#04x} ({op})");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "trace"))]
+mod tests {
+    use std::string::{String, ToString};
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+    use std::thread::{self, ThreadId};
+    use std::vec::Vec;
+
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+
+    use super::*;
+    use crate::testing::{read, run, write};
+
+    /// A logger keeping the lines logged by each test thread
+    struct Capture(Mutex<Vec<(ThreadId, String)>>);
+
+    impl Capture {
+        /// The captured lines, also after a test failed while holding the lock
+        fn lines(&self) -> MutexGuard<'_, Vec<(ThreadId, String)>> {
+            self.0.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    impl Log for Capture {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= Level::Trace
+        }
+
+        fn log(&self, record: &Record) {
+            let line = (thread::current().id(), record.args().to_string());
+            self.lines().push(line);
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+
+    /// The lines logged by the current thread while running `f`.
+    fn capture(f: impl FnOnce()) -> Vec<String> {
+        // Other tests may have installed the logger already
+        let _ = log::set_logger(&CAPTURE);
+        log::set_max_level(LevelFilter::Trace);
+        let id = thread::current().id();
+        CAPTURE.lines().retain(|(thread, _)| *thread != id);
+        f();
+        let lines = CAPTURE.lines();
+        lines
+            .iter()
+            .filter(|(thread, _)| *thread == id)
+            .map(|(_, line)| line.clone())
+            .collect()
+    }
+
+    #[test]
+    fn setter_logs_its_transfers() {
+        let lines = capture(|| {
+            let res = run(
+                &[
+                    read(Reg::CHARGER_CONFIG_9, 0xc0),
+                    write(Reg::CHARGER_CONFIG_9, 0xd7),
+                ],
+                async |charger| charger.set_chgin_ilim(1234).await,
+            );
+            assert_eq!(res, Ok(()));
+        });
+        assert_eq!(
+            lines,
+            [
+                "max7797x: rd CHG_CNFG_09 => 0xc0 (set_chgin_ilim)",
+                "max7797x: wr CHG_CNFG_09 <= 0xd7 (set_chgin_ilim)",
+            ]
+        );
+    }
+}
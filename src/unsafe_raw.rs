@@ -0,0 +1,107 @@
+//! Raw register access for bring-up and debugging.
+//!
+//! These methods bypass the typed API: values are not validated, fields other than the intended
+//! one are not preserved and invariants the driver maintains, such as the OTG and suspend
+//! bookkeeping, can be broken. Writes still go through the driver's write path, so the shadow
+//! cache stays coherent and write verification applies.
+//!
+//! `CHARGER_CONFIG_6` holds both WDTCLR and CHGPROT. Writing it raw can leave the protected
+//! registers unlocked, or lock them in the middle of a [`ProtectedSession`](crate::ProtectedSession).
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{Charger, Error, Reg};
+
+impl<D: I2c> Charger<D> {
+    /// Read the register at address `reg`.
+    ///
+    /// Reading `TOP_INTERRUPT` or `CHARGER_INTERRUPT` clears the interrupt flags.
+    pub async fn read_register_raw(&mut self, reg: u8) -> Result<u8, Error<D::Error>> {
+        traced!(self, "read_register_raw", {
+            self.read_reg(Reg::new(reg)).await
+        })
+    }
+
+    /// Write `val` to the register at address `reg`.
+    ///
+    /// Writes to the CHGPROT protected registers are ignored by the charger unless protection is
+    /// unlocked; use [`write_register_raw_protected`](Self::write_register_raw_protected) for
+    /// those.
+    pub async fn write_register_raw(&mut self, reg: u8, val: u8) -> Result<(), Error<D::Error>> {
+        traced!(self, "write_register_raw", {
+            self.write_reg(Reg::new(reg), val).await
+        })
+    }
+
+    /// Write `val` to the register at address `reg` with CHGPROT unlocked.
+    ///
+    /// Protection is locked again afterwards, see [`protected`](Self::protected).
+    pub async fn write_register_raw_protected(
+        &mut self,
+        reg: u8,
+        val: u8,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "write_register_raw_protected", {
+            self.protected(async |session| session.charger.write_reg(Reg::new(reg), val).await)
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c::Mock;
+
+    use super::*;
+    use crate::testing::{block_on, lock, read, run, unlock, write};
+    use crate::Mode;
+
+    #[test]
+    fn read_register_raw_reads_any_address() {
+        let res = run(&[read(Reg::new(0x30), 0xa5)], async |charger| {
+            charger.read_register_raw(0x30).await
+        });
+        assert_eq!(res, Ok(0xa5));
+    }
+
+    #[test]
+    fn write_register_raw_writes_without_unlocking() {
+        let res = run(&[write(Reg::CHARGER_CONFIG_2, 0x7f)], async |charger| {
+            charger
+                .write_register_raw(Reg::CHARGER_CONFIG_2.address(), 0x7f)
+                .await
+        });
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn write_register_raw_protected_unlocks_and_locks() {
+        let res = run(
+            &[unlock(), write(Reg::CHARGER_CONFIG_2, 0x7f), lock()],
+            async |charger| {
+                charger
+                    .write_register_raw_protected(Reg::CHARGER_CONFIG_2.address(), 0x7f)
+                    .await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn write_register_raw_keeps_the_cache_coherent() {
+        // The mode change is written without reading CHARGER_CONFIG_0 back
+        let mock = Mock::new(&[
+            write(Reg::CHARGER_CONFIG_0, 0x35),
+            write(Reg::CHARGER_CONFIG_0, 0x34),
+        ]);
+        let mut charger = Charger::new(mock).with_shadow_cache(true);
+        block_on(async {
+            charger
+                .write_register_raw(Reg::CHARGER_CONFIG_0.address(), 0x35)
+                .await?;
+            charger.set_mode(Mode::Buck).await
+        })
+        .unwrap();
+        charger.release().done();
+    }
+}
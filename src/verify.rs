@@ -0,0 +1,137 @@
+//! Read-back verification of register writes.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{Charger, Error, Reg};
+
+/// Registers that do not read back what was written: read-to-clear, self-clearing or
+/// action registers
+const VERIFY_EXEMPT: [Reg; 5] = [
+    Reg::TOP_INTERRUPT,
+    Reg::CHARGER_INTERRUPT,
+    Reg::CHARGER_CONFIG_6,
+    Reg::SOFTWARE_RESET,
+    Reg::SHIP_MODE_CONTROL,
+];
+
+impl<D: I2c> Charger<D> {
+    /// Read back every register write and compare it to the value written.
+    ///
+    /// With verification enabled, a register that does not read back the value written fails
+    /// the write with [`Error::VerifyFailed`], e.g. a protected register written while CHGPROT
+    /// was locked. The interrupt flag registers, `CHARGER_CONFIG_6` (WDTCLR and CHGPROT),
+    /// `SOFTWARE_RESET` and `SHIP_MODE_CONTROL` are not read back, as they clear on read, clear
+    /// themselves or trigger an action. Each register is read back in its own transaction.
+    pub fn with_write_verification(mut self, enabled: bool) -> Self {
+        self.verify_writes = enabled;
+        self
+    }
+
+    /// Whether register writes are verified
+    pub fn write_verification(&self) -> bool {
+        self.verify_writes
+    }
+
+    /// Read back `vals.len()` registers from `base` after writing `vals`, if enabled.
+    pub(crate) async fn verify_write(
+        &mut self,
+        base: Reg,
+        vals: &[u8],
+    ) -> Result<(), Error<D::Error>> {
+        if !self.verify_writes {
+            return Ok(());
+        }
+        for (offset, &wrote) in vals.iter().enumerate() {
+            let reg = Reg::new(base.address().wrapping_add(offset as u8));
+            if VERIFY_EXEMPT.contains(&reg) {
+                continue;
+            }
+            let mut read = 0;
+            self.read_buf_once(reg, core::slice::from_mut(&mut read))
+                .await?;
+            if read != wrote {
+                self.invalidate_cache();
+                return Err(Error::VerifyFailed {
+                    reg: reg.address(),
+                    wrote,
+                    read,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ShadowCache;
+    use crate::testing::{read, run, write};
+    use crate::Mode;
+
+    #[test]
+    fn verified_write_reads_back() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_CONFIG_0, 0x04),
+                write(Reg::CHARGER_CONFIG_0, 0x05),
+                read(Reg::CHARGER_CONFIG_0, 0x05),
+            ],
+            async |charger| {
+                charger.verify_writes = true;
+                charger.set_mode(Mode::Charge).await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn mismatch_fails_and_invalidates_the_cache() {
+        let res = run(
+            &[
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                write(Reg::CHARGER_CONFIG_9, 0x09),
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                // The cache no longer trusts the value written
+                read(Reg::CHARGER_CONFIG_9, 0x00),
+                write(Reg::CHARGER_CONFIG_9, 0x09),
+                read(Reg::CHARGER_CONFIG_9, 0x09),
+            ],
+            async |charger| {
+                charger.verify_writes = true;
+                charger.cache = Some(ShadowCache::default());
+                let first = charger.set_chgin_ilim(500).await;
+                let second = charger.set_chgin_ilim(500).await;
+                (first, second)
+            },
+        );
+        assert_eq!(
+            res,
+            (
+                Err(Error::VerifyFailed {
+                    reg: Reg::CHARGER_CONFIG_9.address(),
+                    wrote: 0x09,
+                    read: 0x00,
+                }),
+                Ok(())
+            )
+        );
+    }
+
+    #[test]
+    fn exempt_registers_are_not_read_back() {
+        let res = run(
+            &[
+                write(Reg::SHIP_MODE_CONTROL, 0x01),
+                read(Reg::CHARGER_CONFIG_6, 0x00),
+                write(Reg::CHARGER_CONFIG_6, 0x01),
+            ],
+            async |charger| {
+                charger.verify_writes = true;
+                charger.enter_ship_mode().await?;
+                charger.kick_watchdog().await
+            },
+        );
+        assert_eq!(res, Ok(()));
+    }
+}
@@ -0,0 +1,227 @@
+//! Repeated reads to detect silently corrupted data on noisy buses.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::{Charger, Error, Reg};
+
+/// The number of bytes compared per repeated read
+const VOTE_CHUNK: usize = 16;
+
+/// How register reads are checked, see [`Charger::with_read_voting`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReadVoting {
+    /// Every register is read once
+    #[default]
+    Off,
+    /// Registers are read twice and must agree
+    Double,
+    /// Registers are read twice and, if they disagree, a third time to take the majority
+    Majority,
+}
+
+/// Whether reading `len` registers from `base` clears latched interrupt flags
+fn clears_on_read(base: Reg, len: usize) -> bool {
+    let range = base.address() as usize..(base.address() as usize).saturating_add(len);
+    range.contains(&(Reg::TOP_INTERRUPT.address() as usize))
+        || range.contains(&(Reg::CHARGER_INTERRUPT.address() as usize))
+}
+
+impl<D: I2c> Charger<D> {
+    /// Check register reads according to `voting`.
+    ///
+    /// With voting enabled, each read is repeated and compared, failing with
+    /// [`Error::ReadMismatch`] if the reads can not be reconciled. Reads that include
+    /// `TOP_INTERRUPT` or `CHARGER_INTERRUPT` are never repeated, since reading them clears the
+    /// interrupt flags. This applies to [`charger_status`](Self::charger_status),
+    /// [`full_status`](Self::full_status), the interrupt flag getters and
+    /// [`dump_registers`](Self::dump_registers).
+    pub fn with_read_voting(mut self, voting: ReadVoting) -> Self {
+        self.read_voting = voting;
+        self
+    }
+
+    /// The read voting policy
+    pub fn read_voting(&self) -> ReadVoting {
+        self.read_voting
+    }
+
+    /// Repeat a read of `buf.len()` registers from `base` that returned `buf`, according to the
+    /// read voting policy.
+    pub(crate) async fn vote_read(
+        &mut self,
+        base: Reg,
+        buf: &mut [u8],
+    ) -> Result<(), Error<D::Error>> {
+        if self.read_voting == ReadVoting::Off || clears_on_read(base, buf.len()) {
+            return Ok(());
+        }
+        let mut reg = base.address();
+        for chunk in buf.chunks_mut(VOTE_CHUNK) {
+            let mut second = [0; VOTE_CHUNK];
+            // The chunks are at most VOTE_CHUNK long, so these never fall back to empty slices
+            let second = second.get_mut(..chunk.len()).unwrap_or_default();
+            self.read_buf_once(Reg::new(reg), second).await?;
+            if chunk != second {
+                let mut third = [0; VOTE_CHUNK];
+                let third = third.get_mut(..chunk.len()).unwrap_or_default();
+                if self.read_voting == ReadVoting::Majority {
+                    self.read_buf_once(Reg::new(reg), third).await?;
+                }
+                let votes = chunk.iter_mut().zip(&*second).zip(&*third);
+                for (i, ((byte, &second), &third)) in votes.enumerate() {
+                    if *byte == second {
+                        continue;
+                    }
+                    if self.read_voting == ReadVoting::Majority && second == third {
+                        *byte = second;
+                    } else if self.read_voting != ReadVoting::Majority || *byte != third {
+                        return Err(Error::ReadMismatch {
+                            reg: reg.wrapping_add(i as u8),
+                            first: *byte,
+                            second,
+                        });
+                    }
+                }
+            }
+            reg = reg.wrapping_add(chunk.len() as u8);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use super::*;
+    use crate::testing::{read, read_burst, run};
+    use crate::RegisterDump;
+
+    #[test]
+    fn double_reads_must_agree() {
+        let res = run(
+            &[
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x04, 0x00]),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x05, 0x00]),
+            ],
+            async |charger| {
+                charger.read_voting = ReadVoting::Double;
+                charger.charger_details().await
+            },
+        );
+        assert_eq!(
+            res,
+            Err(Error::ReadMismatch {
+                reg: Reg::CHARGER_DETAILS_1.address(),
+                first: 0x04,
+                second: 0x05,
+            })
+        );
+    }
+
+    #[test]
+    fn double_reads_that_agree_pass() {
+        let res = run(
+            &[
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x04, 0x00]),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x04, 0x00]),
+            ],
+            async |charger| {
+                charger.read_voting = ReadVoting::Double;
+                charger.charger_details().await
+            },
+        );
+        assert_eq!(
+            res.map(|details| details.into_bytes()),
+            Ok([0x60, 0x04, 0x00])
+        );
+    }
+
+    #[test]
+    fn majority_recovers_a_corrupted_read() {
+        let res = run(
+            &[
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x0c, 0x00]),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x04, 0x00]),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x04, 0x00]),
+            ],
+            async |charger| {
+                charger.read_voting = ReadVoting::Majority;
+                charger.charger_details().await
+            },
+        );
+        assert_eq!(
+            res.map(|details| details.into_bytes()),
+            Ok([0x60, 0x04, 0x00])
+        );
+    }
+
+    #[test]
+    fn majority_fails_without_two_equal_reads() {
+        let res = run(
+            &[
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x0c, 0x00]),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x04, 0x00]),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x05, 0x00]),
+            ],
+            async |charger| {
+                charger.read_voting = ReadVoting::Majority;
+                charger.charger_details().await
+            },
+        );
+        assert_eq!(
+            res,
+            Err(Error::ReadMismatch {
+                reg: Reg::CHARGER_DETAILS_1.address(),
+                first: 0x0c,
+                second: 0x04,
+            })
+        );
+    }
+
+    #[test]
+    fn interrupt_reads_are_never_repeated() {
+        let res = run(
+            &[
+                read_burst(Reg::CHARGER_INTERRUPT, &[0x40, 0xff, 0x5f]),
+                read_burst(
+                    Reg::CHARGER_INTERRUPT,
+                    &[0x00, 0xff, 0x5f, 0x60, 0x04, 0x00],
+                ),
+                read(Reg::TOP_INTERRUPT, 0x04),
+                read(Reg::CHARGER_INTERRUPT, 0x08),
+            ],
+            async |charger| {
+                charger.read_voting = ReadVoting::Double;
+                charger.charger_status().await?;
+                charger.full_status().await?;
+                charger.irq_summary().await
+            },
+        );
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn dump_repeats_only_the_ranges_without_interrupt_flags() {
+        let chip = [0x3b, 0x01, 0x02, 0x00, 0xff, 0x00];
+        let charger_block = [0x00; 21];
+        let mut expectations = vec![
+            read_burst(Reg::CHIP_ID, &chip),
+            read_burst(Reg::CHARGER_INTERRUPT, &charger_block),
+        ];
+        for _ in 0..2 {
+            expectations.push(read(Reg::I2C_CONFIG, 0x00));
+        }
+        for _ in 0..2 {
+            expectations.push(read_burst(Reg::SOFTWARE_RESET, &[0x00, 0x00]));
+        }
+        let mut buf = [0; RegisterDump::LEN];
+        let res = run(&expectations, async |charger| {
+            charger.read_voting = ReadVoting::Majority;
+            charger.dump_registers(&mut buf).await.map(|_| ())
+        });
+        assert_eq!(res, Ok(()));
+    }
+}
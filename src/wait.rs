@@ -0,0 +1,452 @@
+//! Waiting for the charger to reach a state.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::I2c;
+
+use crate::{
+    ChargeStateClass, Charger, ChargerDetails, ChargerInterrupts, ChargerIrqSource, ChgIn, Details,
+    Error, FaultKind, Mode,
+};
+
+/// The interval at which [`Charger::wait_mode_settled`] polls the details in ms
+const MODE_SETTLE_POLL_MS: u32 = 1;
+
+/// How a charge cycle awaited with [`Charger::wait_for_charge_done`] ended
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum ChargeOutcome {
+    /// The charger reached [`ChargerDetails::Done`]
+    Done,
+    /// The charger stopped on a fault: [`FaultKind::Timer`], [`FaultKind::Watchdog`],
+    /// [`FaultKind::OverTemperature`] or [`FaultKind::ThermistorRemoved`]
+    Fault(FaultKind),
+    /// The CHGIN input stopped being valid
+    InputRemoved,
+}
+
+impl ChargeOutcome {
+    /// The outcome `details` represent, or `None` while the cycle is still running.
+    fn from_details(details: Details) -> Option<Self> {
+        match details.charger().classify() {
+            ChargeStateClass::Done => return Some(ChargeOutcome::Done),
+            ChargeStateClass::Fault(kind) => return Some(ChargeOutcome::Fault(kind)),
+            _ => {}
+        }
+        if details.charger() == ChargerDetails::ThermistorRemoval {
+            Some(ChargeOutcome::Fault(FaultKind::ThermistorRemoved))
+        } else if details.chgin() != ChgIn::Valid {
+            Some(ChargeOutcome::InputRemoved)
+        } else {
+            None
+        }
+    }
+}
+
+impl Mode {
+    /// Whether `details` are consistent with the charger running in this mode.
+    ///
+    /// | Mode                       | Settled when                                         |
+    /// |----------------------------|------------------------------------------------------|
+    /// | [`Off`](Mode::Off), [`Buck`](Mode::Buck) | the boost is off and the charger is not charging |
+    /// | [`Charge`](Mode::Charge)   | the boost is off and the charger is charging or done |
+    /// | [`Boost`](Mode::Boost), [`Otg`](Mode::Otg) | the boost is on                    |
+    ///
+    /// The charger is charging in the [`ChargeStateClass::Charging`] states.
+    pub fn is_settled(self, details: &Details) -> bool {
+        let boost_on = details.bypass().boost_on();
+        let class = details.charger().classify();
+        match self {
+            Mode::Off | Mode::Buck => !boost_on && class != ChargeStateClass::Charging,
+            Mode::Charge => {
+                !boost_on && matches!(class, ChargeStateClass::Charging | ChargeStateClass::Done)
+            }
+            Mode::Boost | Mode::Otg => boost_on,
+        }
+    }
+}
+
+impl<D: I2c> Charger<D> {
+    /// Poll the CHGIN input every `interval_ms` until it is valid.
+    ///
+    /// Returns [`ChgIn::Valid`] as soon as a valid input is seen, including on the first poll.
+    /// With `attempts`, gives up after that many polls and returns the last state, so the
+    /// caller can tell an undervoltage from an overvoltage input; `Some(0)` is treated as a
+    /// single poll. With `None`, polls until an input becomes valid.
+    ///
+    /// Only `CHARGER_DETAILS_0` is read, so pending interrupt flags are left alone.
+    pub async fn wait_for_chgin_valid(
+        &mut self,
+        mut poll: impl DelayNs,
+        interval_ms: u32,
+        attempts: Option<u32>,
+    ) -> Result<ChgIn, Error<D::Error>> {
+        traced!(self, "wait_for_chgin_valid", {
+            let mut remaining = attempts.map(|attempts| attempts.max(1));
+            loop {
+                let chgin = self.chgin_status().await?;
+                if chgin == ChgIn::Valid {
+                    return Ok(chgin);
+                }
+                if let Some(remaining) = &mut remaining {
+                    *remaining = remaining.saturating_sub(1);
+                    if *remaining == 0 {
+                        return Ok(chgin);
+                    }
+                }
+                poll.delay_ms(interval_ms).await;
+            }
+        })
+    }
+
+    /// Wait on the nIRQ pin until the CHGIN input is valid.
+    ///
+    /// The CHGIN interrupt is enabled and the TOP and charger interrupt flags are cleared
+    /// before the input is first checked, so an input attached before the call is never
+    /// missed. Each time `irq_pin` asserts, all pending flags are cleared again and the input is
+    /// checked. Other interrupt sources are consumed while waiting; to give up after a while,
+    /// race the returned future against a timer.
+    pub async fn wait_for_chgin_valid_irq<P: Wait>(
+        &mut self,
+        irq_pin: &mut P,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "wait_for_chgin_valid_irq", {
+            self.enable_charger_irqs(ChargerInterrupts::from_sources(&[ChargerIrqSource::Chgin]))
+                .await?;
+            loop {
+                self.irq_summary().await?;
+                if self.chgin_status().await? == ChgIn::Valid {
+                    return Ok(());
+                }
+                irq_pin.wait_for_low().await.map_err(|_| Error::Pin)?;
+            }
+        })
+    }
+
+    /// Poll the charger every `interval_ms` until the charge cycle ends.
+    ///
+    /// The cycle ends when the charger reaches [`ChargerDetails::Done`], enters one of the
+    /// faults listed on [`ChargeOutcome::Fault`] or loses its input. States that may clear on
+    /// their own, such as JEITA or the SUSPEND pin, keep the wait going. Each poll reads the
+    /// details in one transaction; pending interrupt flags are left alone.
+    pub async fn wait_for_charge_done(
+        &mut self,
+        mut poll: impl DelayNs,
+        interval_ms: u32,
+    ) -> Result<ChargeOutcome, Error<D::Error>> {
+        traced!(self, "wait_for_charge_done", {
+            loop {
+                if let Some(outcome) = ChargeOutcome::from_details(self.charger_details().await?) {
+                    return Ok(outcome);
+                }
+                poll.delay_ms(interval_ms).await;
+            }
+        })
+    }
+
+    /// Wait on the nIRQ pin until the charge cycle ends.
+    ///
+    /// Like [`wait_for_charge_done`](Self::wait_for_charge_done), but the details are only read
+    /// when `irq_pin` asserts. The charger and CHGIN interrupts are enabled and all pending
+    /// flags are cleared before the first check and whenever the pin asserts, so other
+    /// interrupt sources are consumed while waiting.
+    pub async fn wait_for_charge_done_irq<P: Wait>(
+        &mut self,
+        irq_pin: &mut P,
+    ) -> Result<ChargeOutcome, Error<D::Error>> {
+        traced!(self, "wait_for_charge_done_irq", {
+            self.enable_charger_irqs(ChargerInterrupts::from_sources(&[
+                ChargerIrqSource::Charger,
+                ChargerIrqSource::Chgin,
+            ]))
+            .await?;
+            loop {
+                self.irq_summary().await?;
+                if let Some(outcome) = ChargeOutcome::from_details(self.charger_details().await?) {
+                    return Ok(outcome);
+                }
+                irq_pin.wait_for_low().await.map_err(|_| Error::Pin)?;
+            }
+        })
+    }
+
+    /// Wait until the charger details are consistent with `expected`, see [`Mode::is_settled`].
+    ///
+    /// The details are polled every millisecond on `delay`, starting immediately, and returned
+    /// once they match. Fails with [`Error::ModeNotSettled`] holding the last details read if
+    /// they do not match within `timeout_ms`, e.g. because the boost refused to start on a low
+    /// battery. The mode itself is not written; call [`set_mode`](Self::set_mode) first.
+    pub async fn wait_mode_settled(
+        &mut self,
+        expected: Mode,
+        mut delay: impl DelayNs,
+        timeout_ms: u32,
+    ) -> Result<Details, Error<D::Error>> {
+        traced!(self, "wait_mode_settled", {
+            let mut elapsed_ms = 0;
+            loop {
+                let details = self.charger_details().await?;
+                if expected.is_settled(&details) {
+                    return Ok(details);
+                }
+                if elapsed_ms >= timeout_ms {
+                    return Err(Error::ModeNotSettled(details));
+                }
+                delay.delay_ms(MODE_SETTLE_POLL_MS).await;
+                elapsed_ms = elapsed_ms.saturating_add(MODE_SETTLE_POLL_MS);
+            }
+        })
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use core::pin::pin;
+    use core::task::Poll;
+    use core::time::Duration;
+
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+
+    use super::*;
+    use crate::sim::Simulator;
+    use crate::testing::{block_on, poll_once, read_burst, run, SimDelay};
+    use crate::{Reg, Variant};
+
+    /// A delay setting the CHGIN input to `chgin` once it has been awaited `after` times
+    struct ChangeAfter<'a> {
+        sim: &'a Simulator,
+        after: u32,
+        chgin: ChgIn,
+        waited: u32,
+    }
+
+    impl<'a> ChangeAfter<'a> {
+        fn new(sim: &'a Simulator, after: u32, chgin: ChgIn) -> Self {
+            ChangeAfter {
+                sim,
+                after,
+                chgin,
+                waited: 0,
+            }
+        }
+    }
+
+    impl DelayNs for ChangeAfter<'_> {
+        async fn delay_ns(&mut self, _ns: u32) {
+            self.waited = self.waited.saturating_add(1);
+            if self.waited == self.after {
+                self.sim.set_chgin(self.chgin);
+            }
+        }
+    }
+
+    #[test]
+    fn chgin_already_valid_returns_at_once() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        let mut charger = Charger::new(sim.device());
+        let mut delay = ChangeAfter::new(&sim, 1, ChgIn::Overvoltage);
+        let res = block_on(charger.wait_for_chgin_valid(&mut delay, 100, Some(5)));
+        assert_eq!(res, Ok(ChgIn::Valid));
+        assert_eq!(delay.waited, 0);
+    }
+
+    #[test]
+    fn chgin_becoming_valid_ends_the_wait() {
+        let sim = Simulator::new(Variant::Max77975);
+        let mut charger = Charger::new(sim.device());
+        let mut delay = ChangeAfter::new(&sim, 3, ChgIn::Valid);
+        let res = block_on(charger.wait_for_chgin_valid(&mut delay, 100, None));
+        assert_eq!(res, Ok(ChgIn::Valid));
+        assert_eq!(delay.waited, 3);
+    }
+
+    #[test]
+    fn chgin_wait_gives_up_with_the_last_state() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_chgin(ChgIn::Overvoltage);
+        let mut charger = Charger::new(sim.device());
+        let mut delay = ChangeAfter::new(&sim, u32::MAX, ChgIn::Valid);
+        let res = block_on(charger.wait_for_chgin_valid(&mut delay, 100, Some(3)));
+        assert_eq!(res, Ok(ChgIn::Overvoltage));
+        // Three polls with two intervals in between
+        assert_eq!(delay.waited, 2);
+
+        let res = block_on(charger.wait_for_chgin_valid(SimDelay(&sim), 100, Some(0)));
+        assert_eq!(res, Ok(ChgIn::Overvoltage));
+    }
+
+    #[test]
+    fn chgin_irq_wait_ignores_stale_flags() {
+        let sim = Simulator::new(Variant::Max77975);
+        // Leave a stale CHGIN flag behind
+        sim.plug_charger();
+        sim.unplug_charger();
+        let mut charger = Charger::new(sim.device());
+        let mut irq_pin = sim.irq_pin();
+        let mut wait = pin!(charger.wait_for_chgin_valid_irq(&mut irq_pin));
+
+        assert!(poll_once(wait.as_mut()).is_pending());
+        assert!(!sim.irq_asserted());
+        sim.plug_charger();
+        assert_eq!(poll_once(wait.as_mut()), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn chgin_irq_wait_sees_a_present_input() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.plug_charger();
+        let mut charger = Charger::new(sim.device());
+        let mut irq_pin = sim.irq_pin();
+        assert_eq!(
+            block_on(charger.wait_for_chgin_valid_irq(&mut irq_pin)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn charge_wait_runs_to_done() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(4200);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        sim.plug_charger();
+        let res = block_on(charger.wait_for_charge_done(SimDelay(&sim), 60_000));
+        assert_eq!(res, Ok(ChargeOutcome::Done));
+    }
+
+    #[test]
+    fn charge_wait_ends_on_each_fault() {
+        for (state, kind) in [
+            (ChargerDetails::TimerFault, FaultKind::Timer),
+            (ChargerDetails::WatchdogTimer, FaultKind::Watchdog),
+            (ChargerDetails::HighTemperature, FaultKind::OverTemperature),
+            (
+                ChargerDetails::ThermistorRemoval,
+                FaultKind::ThermistorRemoved,
+            ),
+        ] {
+            // Constant current, then JEITA, which may clear on its own, then the fault
+            let res = run(
+                &[
+                    read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x01, 0x00]),
+                    read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x0c, 0x00]),
+                    read_burst(Reg::CHARGER_DETAILS_0, &[0x60, state.code(), 0x00]),
+                ],
+                async |charger| charger.wait_for_charge_done(NoopDelay, 100).await,
+            );
+            assert_eq!(res, Ok(ChargeOutcome::Fault(kind)), "{state:?}");
+        }
+    }
+
+    #[test]
+    fn charge_wait_ends_when_the_input_is_removed() {
+        let res = run(
+            &[
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x60, 0x02, 0x00]),
+                read_burst(Reg::CHARGER_DETAILS_0, &[0x00, 0x08, 0x00]),
+            ],
+            async |charger| charger.wait_for_charge_done(NoopDelay, 100).await,
+        );
+        assert_eq!(res, Ok(ChargeOutcome::InputRemoved));
+    }
+
+    #[test]
+    fn charge_irq_wait_sees_a_timer_fault() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(3800);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        sim.plug_charger();
+        let mut irq_pin = sim.irq_pin();
+        let mut wait = pin!(charger.wait_for_charge_done_irq(&mut irq_pin));
+
+        assert!(poll_once(wait.as_mut()).is_pending());
+        sim.trigger_timer_fault();
+        assert_eq!(
+            poll_once(wait.as_mut()),
+            Poll::Ready(Ok(ChargeOutcome::Fault(FaultKind::Timer)))
+        );
+    }
+
+    #[test]
+    fn charge_irq_wait_sees_the_input_removed() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(3800);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        sim.plug_charger();
+        let mut irq_pin = sim.irq_pin();
+        let mut wait = pin!(charger.wait_for_charge_done_irq(&mut irq_pin));
+
+        assert!(poll_once(wait.as_mut()).is_pending());
+        sim.unplug_charger();
+        assert_eq!(
+            poll_once(wait.as_mut()),
+            Poll::Ready(Ok(ChargeOutcome::InputRemoved))
+        );
+    }
+
+    #[test]
+    fn charge_irq_wait_sees_done() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(4200);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Charge)).unwrap();
+        sim.plug_charger();
+        let mut irq_pin = sim.irq_pin();
+        let mut wait = pin!(charger.wait_for_charge_done_irq(&mut irq_pin));
+
+        assert!(poll_once(wait.as_mut()).is_pending());
+        sim.step(Duration::from_secs(60 * 60));
+        assert_eq!(
+            poll_once(wait.as_mut()),
+            Poll::Ready(Ok(ChargeOutcome::Done))
+        );
+    }
+
+    #[test]
+    fn settled_mode_returns_at_once() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(3800);
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Otg)).unwrap();
+        let mut delay = ChangeAfter::new(&sim, u32::MAX, ChgIn::Valid);
+        let details = block_on(charger.wait_mode_settled(Mode::Otg, &mut delay, 10)).unwrap();
+        assert!(details.bypass().boost_on());
+        assert_eq!(delay.waited, 0);
+    }
+
+    #[test]
+    fn mode_settling_later_ends_the_wait() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(3800);
+        sim.plug_charger();
+        let mut charger = Charger::new(sim.device());
+        block_on(charger.set_mode(Mode::Otg)).unwrap();
+        // The boost only starts once the input is gone
+        let mut delay = ChangeAfter::new(&sim, 2, ChgIn::Undervoltage);
+        let details = block_on(charger.wait_mode_settled(Mode::Otg, &mut delay, 10)).unwrap();
+        assert!(details.bypass().boost_on());
+        assert_eq!(delay.waited, 2);
+    }
+
+    #[test]
+    fn refused_mode_times_out() {
+        let sim = Simulator::new(Variant::Max77975);
+        sim.set_battery_voltage(3800);
+        let mut charger = Charger::new(sim.device());
+        // A converter in standby never starts the boost
+        block_on(charger.set_standby(true)).unwrap();
+        block_on(charger.set_mode(Mode::Otg)).unwrap();
+        let mut delay = ChangeAfter::new(&sim, u32::MAX, ChgIn::Valid);
+        let res = block_on(charger.wait_mode_settled(Mode::Otg, &mut delay, 3));
+        assert!(matches!(res, Err(Error::ModeNotSettled(details)) if !details.bypass().boost_on()));
+        // Polled at 0, 1, 2 and 3 ms
+        assert_eq!(delay.waited, 3);
+
+        let res = block_on(charger.wait_mode_settled(Mode::Otg, &mut delay, 0));
+        assert!(matches!(res, Err(Error::ModeNotSettled(_))));
+        assert_eq!(delay.waited, 3);
+    }
+}
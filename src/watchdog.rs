@@ -0,0 +1,162 @@
+//! The charger watchdog timer.
+
+use core::convert::Infallible;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+use modular_bitfield::BitfieldSpecifier;
+
+use crate::{Charger, ChargerConfig6, ChargerDetails, Error, Mode};
+
+/// The time after the last kick at which the watchdog expires, in ms
+pub const WATCHDOG_TIMEOUT_MS: u32 = 80_000;
+
+/// The number of times [`Charger::recover_from_watchdog`] polls the charger state before giving
+/// up
+pub const WATCHDOG_RECOVERY_POLLS: usize = 10;
+
+/// What happens when the watchdog expires, see [`Charger::set_watchdog_expiry_action`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, BitfieldSpecifier)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[bits = 1]
+pub enum WatchdogAction {
+    /// Suspend charging, the system stays powered from the input and battery
+    #[default]
+    SuspendCharging,
+    /// Suspend charging and open the QBATT switch, disconnecting the battery from the system
+    SystemOff,
+}
+
+impl<D: I2c> Charger<D> {
+    /// Restart the watchdog timer (WDTCLR).
+    ///
+    /// This also resumes charging after the watchdog expired.
+    pub async fn kick_watchdog(&mut self) -> Result<(), Error<D::Error>> {
+        traced!(self, "kick_watchdog", {
+            self.modify_typed(|val: ChargerConfig6| val.with_wdtclr(0b01))
+                .await
+        })
+    }
+
+    /// Set what happens when the watchdog expires.
+    ///
+    /// This is the [`ChargerConfig0::watchdog_action`](crate::ChargerConfig0::watchdog_action) bit, bit 7 of `CHARGER_CONFIG_0`. The
+    /// other fields of the register, including the mode, are left untouched.
+    pub async fn set_watchdog_expiry_action(
+        &mut self,
+        action: WatchdogAction,
+    ) -> Result<(), Error<D::Error>> {
+        traced!(self, "set_watchdog_expiry_action", {
+            let config0 = self.charger_config0().await?;
+            self.set_charger_config0(config0.with_watchdog_action(action))
+                .await
+        })
+    }
+
+    /// Returns what happens when the watchdog expires.
+    pub async fn watchdog_expiry_action(&mut self) -> Result<WatchdogAction, Error<D::Error>> {
+        traced!(self, "watchdog_expiry_action", {
+            self.charger_config0()
+                .await
+                .map(|config0| config0.watchdog_action())
+        })
+    }
+
+    /// Kick the watchdog every `period_ms` until an error occurs.
+    ///
+    /// The charger state is checked before each kick, as a kick would also clear an expired
+    /// watchdog. If the watchdog expired anyway, because the task was starved or the bus
+    /// stalled, this fails with
+    /// [`Error::UnexpectedState`]`(`[`ChargerDetails::WatchdogTimer`]`)`. Dropping the future
+    /// stops kicking, so the watchdog expires [`WATCHDOG_TIMEOUT_MS`] after the last kick unless
+    /// it is disabled.
+    ///
+    /// Fails with [`Error::InvalidValue`] if `period_ms` is zero or more than half the timeout.
+    pub async fn watchdog_task(
+        &mut self,
+        mut delay: impl DelayNs,
+        period_ms: u32,
+    ) -> Result<Infallible, Error<D::Error>> {
+        traced!(self, "watchdog_task", {
+            if period_ms == 0 || period_ms > WATCHDOG_TIMEOUT_MS / 2 {
+                return Err(Error::InvalidValue);
+            }
+            loop {
+                let state = self.charger_state().await?;
+                if state == ChargerDetails::WatchdogTimer {
+                    return Err(Error::UnexpectedState(state));
+                }
+                self.kick_watchdog().await?;
+                delay.delay_ms(period_ms).await;
+            }
+        })
+    }
+
+    /// Recover from an expired watchdog and switch to `desired_mode`.
+    ///
+    /// Fails with [`Error::UnexpectedState`] unless the charger reports
+    /// [`ChargerDetails::WatchdogTimer`]. The watchdog is then cleared with WDTCLR, the mode is
+    /// rewritten and the charger state is polled every `interval_ms`, up to
+    /// [`WATCHDOG_RECOVERY_POLLS`] times, until the fault is gone. Returns the state read after
+    /// recovery, or [`Error::UnexpectedState`]`(`[`ChargerDetails::WatchdogTimer`]`)` if the
+    /// fault persists.
+    pub async fn recover_from_watchdog(
+        &mut self,
+        desired_mode: Mode,
+        delay: impl DelayNs,
+        interval_ms: u32,
+    ) -> Result<ChargerDetails, Error<D::Error>> {
+        traced!(self, "recover_from_watchdog", {
+            let state = self.charger_state().await?;
+            if state != ChargerDetails::WatchdogTimer {
+                return Err(Error::UnexpectedState(state));
+            }
+            self.kick_watchdog().await?;
+            self.set_mode(desired_mode).await?;
+            self.poll_charger_state(delay, interval_ms, WATCHDOG_RECOVERY_POLLS, |state| {
+                state != ChargerDetails::WatchdogTimer
+            })
+            .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read, run, write};
+    use crate::Reg;
+
+    #[test]
+    fn set_watchdog_expiry_action_flips_only_its_bit() {
+        for (action, before, after) in [
+            (WatchdogAction::SystemOff, 0x7f, 0xff),
+            (WatchdogAction::SystemOff, 0x05, 0x85),
+            (WatchdogAction::SuspendCharging, 0xff, 0x7f),
+            (WatchdogAction::SuspendCharging, 0x85, 0x05),
+        ] {
+            let res = run(
+                &[
+                    read(Reg::CHARGER_CONFIG_0, before),
+                    write(Reg::CHARGER_CONFIG_0, after),
+                ],
+                async |charger| charger.set_watchdog_expiry_action(action).await,
+            );
+            assert_eq!(res, Ok(()), "{action:?} from 0x{before:02x}");
+        }
+    }
+
+    #[test]
+    fn watchdog_expiry_action_reads_bit_7() {
+        for (val, action) in [
+            (0x7f, WatchdogAction::SuspendCharging),
+            (0x85, WatchdogAction::SystemOff),
+        ] {
+            let res = run(&[read(Reg::CHARGER_CONFIG_0, val)], async |charger| {
+                charger.watchdog_expiry_action().await
+            });
+            assert_eq!(res, Ok(action), "0x{val:02x}");
+        }
+    }
+}
@@ -0,0 +1,16 @@
+//! Checks that [`charge_profile!`](max7797x_driver::charge_profile) accepts valid profiles and
+//! rejects invalid ones at compile time, and that a
+//! [`ChargerMonitor`](max7797x_driver::ChargerMonitor) cannot write.
+
+#[test]
+fn charge_profiles() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/valid_profile.rs");
+    t.compile_fail("tests/ui/fail_*.rs");
+}
+
+#[test]
+fn monitor_is_read_only() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/monitor_*.rs");
+}
@@ -0,0 +1,6 @@
+use max7797x_driver::{charge_profile, ChargeProfile, Variant};
+
+// The MAX77975 charges at up to 3500 mA
+static PROFILE: ChargeProfile = charge_profile!(Variant::Max77975, 6000, 4200, 150, 30);
+
+fn main() {}
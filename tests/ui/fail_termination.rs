@@ -0,0 +1,6 @@
+use max7797x_driver::{charge_profile, ChargeProfile, Variant};
+
+// The termination voltage is programmable up to 4670 mV
+static PROFILE: ChargeProfile = charge_profile!(Variant::Max77975, 1000, 4700, 150, 30);
+
+fn main() {}
@@ -0,0 +1,6 @@
+use max7797x_driver::{charge_profile, ChargeProfile, Variant};
+
+// The top-off current must not exceed the fast-charge current
+static PROFILE: ChargeProfile = charge_profile!(Variant::Max77975, 200, 4200, 300, 30);
+
+fn main() {}
@@ -0,0 +1,6 @@
+use max7797x_driver::{charge_profile, ChargeProfile, Variant};
+
+// The top-off time is programmable up to 70 minutes
+static PROFILE: ChargeProfile = charge_profile!(Variant::Max77975, 1000, 4200, 150, 80);
+
+fn main() {}
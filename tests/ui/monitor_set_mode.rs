@@ -0,0 +1,9 @@
+use embedded_hal_async::i2c::I2c;
+use max7797x_driver::{ChargerMonitor, Mode};
+
+// The monitor half can only read
+async fn turn_off<D: I2c>(monitor: &mut ChargerMonitor<D>) {
+    let _ = monitor.set_mode(Mode::Off).await;
+}
+
+fn main() {}
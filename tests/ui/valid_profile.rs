@@ -0,0 +1,12 @@
+use max7797x_driver::{charge_profile, ChargeProfile, FastChargeTimer, Variant};
+
+static PROFILES: [ChargeProfile; 2] = [
+    charge_profile!(Variant::Max77975, 3500, 4200, 150, 30),
+    charge_profile!(Variant::Max77986, 6000, 4350, 300, 20)
+        .with_fast_charge_timer(FastChargeTimer::Hours3),
+];
+
+fn main() {
+    assert_eq!(PROFILES[0].fast_charge_current_ma(), 3500);
+    assert_eq!(PROFILES[1].termination_mv(), 4350);
+}